@@ -0,0 +1,1010 @@
+use std::{collections::HashMap, sync::OnceLock};
+
+use crate::{commands::arguments::integer, resp::Resp};
+
+/// ACL categories, a command being in those of its bits.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Categories(u32);
+
+/// Category names in bit order, as `ACL CAT` lists them.
+pub const CATEGORY_NAMES: [&str; 21] = [
+    "keyspace",
+    "read",
+    "write",
+    "set",
+    "sortedset",
+    "list",
+    "hash",
+    "string",
+    "bitmap",
+    "hyperloglog",
+    "geo",
+    "stream",
+    "pubsub",
+    "admin",
+    "fast",
+    "slow",
+    "blocking",
+    "dangerous",
+    "connection",
+    "transaction",
+    "scripting",
+];
+
+impl Categories {
+    /// Single category named `name`.
+    pub fn named(name: &str) -> Option<Self> {
+        CATEGORY_NAMES
+            .iter()
+            .position(|category| category.eq_ignore_ascii_case(name))
+            .map(|bit| Self(1 << bit))
+    }
+
+    fn parse(names: &str) -> Self {
+        names.split_whitespace().fold(Self::default(), |all, name| {
+            Self(all.0 | Self::named(name).expect("known category").0)
+        })
+    }
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+/// Access a command needs to a key, matched against `%R~` and `%W~` patterns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl Access {
+    pub fn reads(self) -> bool {
+        matches!(self, Self::Read | Self::ReadWrite)
+    }
+
+    pub fn writes(self) -> bool {
+        matches!(self, Self::Write | Self::ReadWrite)
+    }
+}
+
+/// Where a command takes its keys, positions counting from its first argument after the
+/// command and subcommand names.
+#[derive(Debug, Clone, Copy)]
+pub enum KeySpec {
+    /// Every `step` argument from `first` to `last`, a negative `last` counting from the
+    /// end like Redis' key specs do.
+    Range {
+        first: usize,
+        last: isize,
+        step: usize,
+        access: Access,
+    },
+    /// As many keys as the `numkeys` argument at `index` says, following it.
+    Numkeys { index: usize, access: Access },
+    /// The first half of the arguments following `STREAMS`.
+    Streams { access: Access },
+}
+
+/// Arguments naming the channels of pub/sub commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelSpec {
+    None,
+    First,
+    All,
+    /// Patterns, which only patterns granted as is allow.
+    Patterns,
+}
+
+#[derive(Debug)]
+pub struct CommandSpec {
+    /// Lowercase name, `container|subcommand` for subcommands like Redis names them.
+    pub name: &'static str,
+    pub categories: Categories,
+    pub keys: &'static [KeySpec],
+    pub channels: ChannelSpec,
+    pub arity: i32,
+}
+
+impl CommandSpec {
+    /// Whether `argc` arguments, the command and subcommand names included, fit the arity.
+    pub fn accepts(&self, argc: usize) -> bool {
+        match self.arity {
+            arity if arity < 0 => argc >= arity.unsigned_abs() as usize,
+            arity => argc == arity as usize,
+        }
+    }
+
+    /// Keys `args` hold, along with the access needed to each.
+    pub fn keys<'a>(&self, args: &'a [Resp]) -> Vec<(&'a [u8], Access)> {
+        let key = |arg: &'a Resp| match arg {
+            Resp::BulkString(key) => Some(key.as_slice()),
+            _ => None,
+        };
+        let mut keys = vec![];
+        for spec in self.keys {
+            let (positions, access) = match *spec {
+                KeySpec::Range {
+                    first,
+                    last,
+                    step,
+                    access,
+                } => {
+                    let last = match last {
+                        last if last < 0 => args.len() as isize + last,
+                        last => last,
+                    };
+                    let positions: Vec<_> = (first as isize..=last)
+                        .step_by(step)
+                        .map(|position| position as usize)
+                        .collect();
+                    (positions, access)
+                }
+                KeySpec::Numkeys { index, access } => {
+                    let numkeys = args.get(index).and_then(|arg| integer(arg).ok());
+                    let first = index + 1;
+                    ((first..first + numkeys.unwrap_or(0usize)).collect(), access)
+                }
+                KeySpec::Streams { access } => {
+                    let streams = args.iter().position(|arg| {
+                        matches!(arg, Resp::BulkString(arg) if arg.eq_ignore_ascii_case(b"STREAMS"))
+                    });
+                    let positions = streams
+                        .map(|streams| {
+                            let count = (args.len() - streams - 1) / 2;
+                            (streams + 1..streams + 1 + count).collect()
+                        })
+                        .unwrap_or_default();
+                    (positions, access)
+                }
+            };
+            keys.extend(
+                positions
+                    .into_iter()
+                    .filter_map(|position| args.get(position).and_then(key))
+                    .map(|key| (key, access)),
+            );
+        }
+        keys
+    }
+
+    /// Channels, or patterns, `args` hold.
+    pub fn channels<'a>(&self, args: &'a [Resp]) -> Vec<&'a [u8]> {
+        let args = match self.channels {
+            ChannelSpec::None => &args[..0],
+            ChannelSpec::First => &args[..args.len().min(1)],
+            ChannelSpec::All | ChannelSpec::Patterns => args,
+        };
+        args.iter()
+            .filter_map(|arg| match arg {
+                Resp::BulkString(channel) => Some(channel.as_slice()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+const fn one(access: Access) -> KeySpec {
+    KeySpec::Range {
+        first: 0,
+        last: 0,
+        step: 1,
+        access,
+    }
+}
+
+const fn all(access: Access) -> KeySpec {
+    KeySpec::Range {
+        first: 0,
+        last: -1,
+        step: 1,
+        access,
+    }
+}
+
+const fn range(first: usize, last: isize, step: usize, access: Access) -> KeySpec {
+    KeySpec::Range {
+        first,
+        last,
+        step,
+        access,
+    }
+}
+
+use Access::{Read as R, ReadWrite as RW, Write as W};
+
+const NONE: &[KeySpec] = &[];
+const ONE_R: &[KeySpec] = &[one(R)];
+const ONE_W: &[KeySpec] = &[one(W)];
+const ALL_R: &[KeySpec] = &[all(R)];
+const ALL_W: &[KeySpec] = &[all(W)];
+const TWO_W: &[KeySpec] = &[range(0, 1, 1, W)];
+const STORE: &[KeySpec] = &[one(W), range(1, -1, 1, R)];
+const STORE_NUMKEYS: &[KeySpec] = &[
+    one(W),
+    KeySpec::Numkeys {
+        index: 1,
+        access: R,
+    },
+];
+
+/// Commands with their categories and where they take keys and channels from.
+const COMMANDS: &[(&str, &str, &[KeySpec], ChannelSpec)] = &[
+    // Connection and server.
+    ("ping", "fast connection", NONE, ChannelSpec::None),
+    ("echo", "fast connection", NONE, ChannelSpec::None),
+    ("hello", "fast connection", NONE, ChannelSpec::None),
+    ("auth", "fast connection", NONE, ChannelSpec::None),
+    ("select", "fast connection", NONE, ChannelSpec::None),
+    ("shutdown", "admin slow dangerous", NONE, ChannelSpec::None),
+    (
+        "config|get",
+        "admin slow dangerous",
+        NONE,
+        ChannelSpec::None,
+    ),
+    ("client|id", "slow connection", NONE, ChannelSpec::None),
+    ("client|info", "slow connection", NONE, ChannelSpec::None),
+    (
+        "client|list",
+        "admin slow dangerous connection",
+        NONE,
+        ChannelSpec::None,
+    ),
+    (
+        "client|kill",
+        "admin slow dangerous connection",
+        NONE,
+        ChannelSpec::None,
+    ),
+    ("client|setname", "slow connection", NONE, ChannelSpec::None),
+    ("client|getname", "slow connection", NONE, ChannelSpec::None),
+    (
+        "client|pause",
+        "admin slow dangerous connection",
+        NONE,
+        ChannelSpec::None,
+    ),
+    (
+        "client|unpause",
+        "admin slow dangerous connection",
+        NONE,
+        ChannelSpec::None,
+    ),
+    (
+        "client|no-evict",
+        "admin slow dangerous connection",
+        NONE,
+        ChannelSpec::None,
+    ),
+    ("client|reply", "slow connection", NONE, ChannelSpec::None),
+    (
+        "client|tracking",
+        "slow connection",
+        NONE,
+        ChannelSpec::None,
+    ),
+    ("client|caching", "slow connection", NONE, ChannelSpec::None),
+    (
+        "client|getredir",
+        "slow connection",
+        NONE,
+        ChannelSpec::None,
+    ),
+    (
+        "client|trackinginfo",
+        "slow connection",
+        NONE,
+        ChannelSpec::None,
+    ),
+    (
+        "acl|setuser",
+        "admin slow dangerous",
+        NONE,
+        ChannelSpec::None,
+    ),
+    (
+        "acl|getuser",
+        "admin slow dangerous",
+        NONE,
+        ChannelSpec::None,
+    ),
+    (
+        "acl|deluser",
+        "admin slow dangerous",
+        NONE,
+        ChannelSpec::None,
+    ),
+    ("acl|list", "admin slow dangerous", NONE, ChannelSpec::None),
+    ("acl|users", "admin slow dangerous", NONE, ChannelSpec::None),
+    ("acl|load", "admin slow dangerous", NONE, ChannelSpec::None),
+    ("acl|save", "admin slow dangerous", NONE, ChannelSpec::None),
+    ("acl|log", "admin slow dangerous", NONE, ChannelSpec::None),
+    ("acl|whoami", "slow", NONE, ChannelSpec::None),
+    ("acl|cat", "slow", NONE, ChannelSpec::None),
+    // Scripting.
+    (
+        "eval",
+        "slow scripting",
+        &[KeySpec::Numkeys {
+            index: 1,
+            access: RW,
+        }],
+        ChannelSpec::None,
+    ),
+    (
+        "evalsha",
+        "slow scripting",
+        &[KeySpec::Numkeys {
+            index: 1,
+            access: RW,
+        }],
+        ChannelSpec::None,
+    ),
+    (
+        "fcall",
+        "slow scripting",
+        &[KeySpec::Numkeys {
+            index: 1,
+            access: RW,
+        }],
+        ChannelSpec::None,
+    ),
+    (
+        "fcall_ro",
+        "slow scripting",
+        &[KeySpec::Numkeys {
+            index: 1,
+            access: R,
+        }],
+        ChannelSpec::None,
+    ),
+    ("script|load", "slow scripting", NONE, ChannelSpec::None),
+    ("script|exists", "slow scripting", NONE, ChannelSpec::None),
+    ("script|flush", "slow scripting", NONE, ChannelSpec::None),
+    ("script|kill", "slow scripting", NONE, ChannelSpec::None),
+    (
+        "function|load",
+        "write slow scripting",
+        NONE,
+        ChannelSpec::None,
+    ),
+    (
+        "function|delete",
+        "write slow scripting",
+        NONE,
+        ChannelSpec::None,
+    ),
+    (
+        "function|flush",
+        "write slow scripting",
+        NONE,
+        ChannelSpec::None,
+    ),
+    (
+        "function|restore",
+        "write slow scripting",
+        NONE,
+        ChannelSpec::None,
+    ),
+    ("function|list", "slow scripting", NONE, ChannelSpec::None),
+    ("function|dump", "slow scripting", NONE, ChannelSpec::None),
+    ("function|kill", "slow scripting", NONE, ChannelSpec::None),
+    // Keyspace.
+    ("del", "keyspace write slow", ALL_W, ChannelSpec::None),
+    ("unlink", "keyspace write fast", ALL_W, ChannelSpec::None),
+    ("exists", "keyspace read fast", ALL_R, ChannelSpec::None),
+    ("touch", "keyspace read fast", ALL_R, ChannelSpec::None),
+    ("scan", "keyspace read slow", NONE, ChannelSpec::None),
+    (
+        "keys",
+        "keyspace read slow dangerous",
+        NONE,
+        ChannelSpec::None,
+    ),
+    ("randomkey", "keyspace read slow", NONE, ChannelSpec::None),
+    ("rename", "keyspace write slow", TWO_W, ChannelSpec::None),
+    ("renamenx", "keyspace write fast", TWO_W, ChannelSpec::None),
+    ("dump", "keyspace read slow", ONE_R, ChannelSpec::None),
+    (
+        "restore",
+        "keyspace write slow dangerous",
+        ONE_W,
+        ChannelSpec::None,
+    ),
+    (
+        "object|encoding",
+        "keyspace read slow",
+        ONE_R,
+        ChannelSpec::None,
+    ),
+    (
+        "object|idletime",
+        "keyspace read slow",
+        ONE_R,
+        ChannelSpec::None,
+    ),
+    (
+        "object|freq",
+        "keyspace read slow",
+        ONE_R,
+        ChannelSpec::None,
+    ),
+    (
+        "object|refcount",
+        "keyspace read slow",
+        ONE_R,
+        ChannelSpec::None,
+    ),
+    (
+        "copy",
+        "keyspace write slow",
+        &[one(R), range(1, 1, 1, W)],
+        ChannelSpec::None,
+    ),
+    ("move", "keyspace write fast", ONE_W, ChannelSpec::None),
+    (
+        "swapdb",
+        "keyspace write fast dangerous",
+        NONE,
+        ChannelSpec::None,
+    ),
+    (
+        "flushdb",
+        "keyspace write slow dangerous",
+        NONE,
+        ChannelSpec::None,
+    ),
+    (
+        "flushall",
+        "keyspace write slow dangerous",
+        NONE,
+        ChannelSpec::None,
+    ),
+    ("dbsize", "keyspace read fast", NONE, ChannelSpec::None),
+    // Strings.
+    ("get", "read string fast", ONE_R, ChannelSpec::None),
+    ("set", "write string slow", ONE_W, ChannelSpec::None),
+    ("mget", "read string fast", ALL_R, ChannelSpec::None),
+    (
+        "mset",
+        "write string slow",
+        &[range(0, -1, 2, W)],
+        ChannelSpec::None,
+    ),
+    (
+        "msetnx",
+        "write string slow",
+        &[range(0, -1, 2, W)],
+        ChannelSpec::None,
+    ),
+    ("incr", "write string fast", ONE_W, ChannelSpec::None),
+    ("decr", "write string fast", ONE_W, ChannelSpec::None),
+    ("incrby", "write string fast", ONE_W, ChannelSpec::None),
+    ("decrby", "write string fast", ONE_W, ChannelSpec::None),
+    ("incrbyfloat", "write string fast", ONE_W, ChannelSpec::None),
+    ("append", "write string fast", ONE_W, ChannelSpec::None),
+    ("strlen", "read string fast", ONE_R, ChannelSpec::None),
+    ("getrange", "read string slow", ONE_R, ChannelSpec::None),
+    ("substr", "read string slow", ONE_R, ChannelSpec::None),
+    ("setrange", "write string slow", ONE_W, ChannelSpec::None),
+    ("getdel", "write string fast", ONE_W, ChannelSpec::None),
+    ("getex", "write string fast", ONE_W, ChannelSpec::None),
+    // Hashes.
+    ("hset", "write hash fast", ONE_W, ChannelSpec::None),
+    ("hmset", "write hash fast", ONE_W, ChannelSpec::None),
+    ("hsetnx", "write hash fast", ONE_W, ChannelSpec::None),
+    ("hdel", "write hash fast", ONE_W, ChannelSpec::None),
+    ("hincrby", "write hash fast", ONE_W, ChannelSpec::None),
+    ("hincrbyfloat", "write hash fast", ONE_W, ChannelSpec::None),
+    ("hget", "read hash fast", ONE_R, ChannelSpec::None),
+    ("hmget", "read hash fast", ONE_R, ChannelSpec::None),
+    ("hlen", "read hash fast", ONE_R, ChannelSpec::None),
+    ("hexists", "read hash fast", ONE_R, ChannelSpec::None),
+    ("hstrlen", "read hash fast", ONE_R, ChannelSpec::None),
+    ("hgetall", "read hash slow", ONE_R, ChannelSpec::None),
+    ("hkeys", "read hash slow", ONE_R, ChannelSpec::None),
+    ("hvals", "read hash slow", ONE_R, ChannelSpec::None),
+    ("hscan", "read hash slow", ONE_R, ChannelSpec::None),
+    ("hexpire", "write hash fast", ONE_W, ChannelSpec::None),
+    ("hpexpire", "write hash fast", ONE_W, ChannelSpec::None),
+    ("hexpireat", "write hash fast", ONE_W, ChannelSpec::None),
+    ("hpexpireat", "write hash fast", ONE_W, ChannelSpec::None),
+    ("hpersist", "write hash fast", ONE_W, ChannelSpec::None),
+    ("httl", "read hash fast", ONE_R, ChannelSpec::None),
+    ("hpttl", "read hash fast", ONE_R, ChannelSpec::None),
+    // Sets.
+    ("sadd", "write set fast", ONE_W, ChannelSpec::None),
+    ("srem", "write set fast", ONE_W, ChannelSpec::None),
+    ("spop", "write set fast", ONE_W, ChannelSpec::None),
+    ("smembers", "read set slow", ONE_R, ChannelSpec::None),
+    ("sismember", "read set fast", ONE_R, ChannelSpec::None),
+    ("smismember", "read set fast", ONE_R, ChannelSpec::None),
+    ("scard", "read set fast", ONE_R, ChannelSpec::None),
+    ("sinter", "read set slow", ALL_R, ChannelSpec::None),
+    ("sunion", "read set slow", ALL_R, ChannelSpec::None),
+    ("sdiff", "read set slow", ALL_R, ChannelSpec::None),
+    ("sinterstore", "write set slow", STORE, ChannelSpec::None),
+    ("sunionstore", "write set slow", STORE, ChannelSpec::None),
+    ("sdiffstore", "write set slow", STORE, ChannelSpec::None),
+    (
+        "sintercard",
+        "read set slow",
+        &[KeySpec::Numkeys {
+            index: 0,
+            access: R,
+        }],
+        ChannelSpec::None,
+    ),
+    ("srandmember", "read set slow", ONE_R, ChannelSpec::None),
+    ("smove", "write set fast", TWO_W, ChannelSpec::None),
+    ("sscan", "read set slow", ONE_R, ChannelSpec::None),
+    // Sorted sets.
+    ("zadd", "write sortedset fast", ONE_W, ChannelSpec::None),
+    ("zincrby", "write sortedset fast", ONE_W, ChannelSpec::None),
+    ("zrem", "write sortedset fast", ONE_W, ChannelSpec::None),
+    ("zcard", "read sortedset fast", ONE_R, ChannelSpec::None),
+    ("zcount", "read sortedset fast", ONE_R, ChannelSpec::None),
+    ("zscore", "read sortedset fast", ONE_R, ChannelSpec::None),
+    ("zrank", "read sortedset fast", ONE_R, ChannelSpec::None),
+    ("zrevrank", "read sortedset fast", ONE_R, ChannelSpec::None),
+    ("zrange", "read sortedset slow", ONE_R, ChannelSpec::None),
+    ("zrevrange", "read sortedset slow", ONE_R, ChannelSpec::None),
+    (
+        "zrangebyscore",
+        "read sortedset slow",
+        ONE_R,
+        ChannelSpec::None,
+    ),
+    (
+        "zrevrangebyscore",
+        "read sortedset slow",
+        ONE_R,
+        ChannelSpec::None,
+    ),
+    (
+        "zrangebylex",
+        "read sortedset slow",
+        ONE_R,
+        ChannelSpec::None,
+    ),
+    (
+        "zrevrangebylex",
+        "read sortedset slow",
+        ONE_R,
+        ChannelSpec::None,
+    ),
+    (
+        "zremrangebyrank",
+        "write sortedset slow",
+        ONE_W,
+        ChannelSpec::None,
+    ),
+    (
+        "zremrangebyscore",
+        "write sortedset slow",
+        ONE_W,
+        ChannelSpec::None,
+    ),
+    (
+        "zremrangebylex",
+        "write sortedset slow",
+        ONE_W,
+        ChannelSpec::None,
+    ),
+    ("zpopmin", "write sortedset fast", ONE_W, ChannelSpec::None),
+    ("zpopmax", "write sortedset fast", ONE_W, ChannelSpec::None),
+    (
+        "bzpopmin",
+        "write sortedset fast blocking",
+        &[range(0, -2, 1, W)],
+        ChannelSpec::None,
+    ),
+    (
+        "bzpopmax",
+        "write sortedset fast blocking",
+        &[range(0, -2, 1, W)],
+        ChannelSpec::None,
+    ),
+    (
+        "zunionstore",
+        "write sortedset slow",
+        STORE_NUMKEYS,
+        ChannelSpec::None,
+    ),
+    (
+        "zinterstore",
+        "write sortedset slow",
+        STORE_NUMKEYS,
+        ChannelSpec::None,
+    ),
+    ("zscan", "read sortedset slow", ONE_R, ChannelSpec::None),
+    // Streams.
+    ("xadd", "write stream fast", ONE_W, ChannelSpec::None),
+    ("xrange", "read stream slow", ONE_R, ChannelSpec::None),
+    ("xrevrange", "read stream slow", ONE_R, ChannelSpec::None),
+    ("xlen", "read stream fast", ONE_R, ChannelSpec::None),
+    ("xtrim", "write stream slow", ONE_W, ChannelSpec::None),
+    ("xdel", "write stream fast", ONE_W, ChannelSpec::None),
+    (
+        "xread",
+        "read stream slow blocking",
+        &[KeySpec::Streams { access: R }],
+        ChannelSpec::None,
+    ),
+    (
+        "xgroup|create",
+        "write stream slow",
+        ONE_W,
+        ChannelSpec::None,
+    ),
+    (
+        "xgroup|setid",
+        "write stream slow",
+        ONE_W,
+        ChannelSpec::None,
+    ),
+    (
+        "xgroup|destroy",
+        "write stream slow",
+        ONE_W,
+        ChannelSpec::None,
+    ),
+    (
+        "xgroup|createconsumer",
+        "write stream slow",
+        ONE_W,
+        ChannelSpec::None,
+    ),
+    (
+        "xgroup|delconsumer",
+        "write stream slow",
+        ONE_W,
+        ChannelSpec::None,
+    ),
+    (
+        "xreadgroup",
+        "write stream slow blocking",
+        &[KeySpec::Streams { access: W }],
+        ChannelSpec::None,
+    ),
+    ("xack", "write stream fast", ONE_W, ChannelSpec::None),
+    ("xpending", "read stream slow", ONE_R, ChannelSpec::None),
+    ("xclaim", "write stream fast", ONE_W, ChannelSpec::None),
+    ("xautoclaim", "write stream fast", ONE_W, ChannelSpec::None),
+    ("xinfo|stream", "read stream slow", ONE_R, ChannelSpec::None),
+    ("xinfo|groups", "read stream slow", ONE_R, ChannelSpec::None),
+    (
+        "xinfo|consumers",
+        "read stream slow",
+        ONE_R,
+        ChannelSpec::None,
+    ),
+    // Pub/sub.
+    ("subscribe", "pubsub slow", NONE, ChannelSpec::All),
+    ("unsubscribe", "pubsub slow", NONE, ChannelSpec::None),
+    ("psubscribe", "pubsub slow", NONE, ChannelSpec::Patterns),
+    ("punsubscribe", "pubsub slow", NONE, ChannelSpec::None),
+    ("ssubscribe", "pubsub slow", NONE, ChannelSpec::All),
+    ("sunsubscribe", "pubsub slow", NONE, ChannelSpec::None),
+    ("publish", "pubsub fast", NONE, ChannelSpec::First),
+    ("spublish", "pubsub fast", NONE, ChannelSpec::First),
+    ("pubsub|channels", "pubsub slow", NONE, ChannelSpec::None),
+    ("pubsub|numsub", "pubsub slow", NONE, ChannelSpec::None),
+    ("pubsub|numpat", "pubsub slow", NONE, ChannelSpec::None),
+    (
+        "pubsub|shardchannels",
+        "pubsub slow",
+        NONE,
+        ChannelSpec::None,
+    ),
+    ("pubsub|shardnumsub", "pubsub slow", NONE, ChannelSpec::None),
+    // Transactions.
+    ("multi", "fast transaction", NONE, ChannelSpec::None),
+    ("exec", "slow transaction", NONE, ChannelSpec::None),
+    ("discard", "fast transaction", NONE, ChannelSpec::None),
+    ("watch", "fast transaction", ALL_R, ChannelSpec::None),
+    ("unwatch", "fast transaction", NONE, ChannelSpec::None),
+];
+
+/// Arity of the commands as Redis counts it, the command and subcommand names included:
+/// the exact number of arguments when positive, the minimum when negative.
+const ARITIES: &[(&str, i32)] = &[
+    ("ping", -1),
+    ("echo", 2),
+    ("hello", -1),
+    ("auth", -2),
+    ("select", 2),
+    ("shutdown", -1),
+    ("config|get", -3),
+    ("client|id", 2),
+    ("client|info", 2),
+    ("client|list", -2),
+    ("client|kill", -3),
+    ("client|setname", 3),
+    ("client|getname", 2),
+    ("client|pause", -3),
+    ("client|unpause", 2),
+    ("client|no-evict", 3),
+    ("client|reply", 3),
+    ("client|tracking", -3),
+    ("client|caching", 3),
+    ("client|getredir", 2),
+    ("client|trackinginfo", 2),
+    ("acl|setuser", -3),
+    ("acl|getuser", 3),
+    ("acl|deluser", -3),
+    ("acl|list", 2),
+    ("acl|users", 2),
+    ("acl|load", 2),
+    ("acl|save", 2),
+    ("acl|log", -2),
+    ("acl|whoami", 2),
+    ("acl|cat", -2),
+    ("eval", -3),
+    ("evalsha", -3),
+    ("fcall", -3),
+    ("script|load", 3),
+    ("script|exists", -3),
+    ("script|flush", -2),
+    ("script|kill", 2),
+    ("function|load", -3),
+    ("function|delete", 3),
+    ("function|flush", -2),
+    ("function|restore", -3),
+    ("function|list", -2),
+    ("function|dump", 2),
+    ("function|kill", 2),
+    ("del", -2),
+    ("unlink", -2),
+    ("exists", -2),
+    ("touch", -2),
+    ("scan", -2),
+    ("keys", 2),
+    ("randomkey", 1),
+    ("rename", 3),
+    ("renamenx", 3),
+    ("dump", 2),
+    ("restore", -4),
+    ("object|encoding", 3),
+    ("object|idletime", 3),
+    ("object|freq", 3),
+    ("object|refcount", 3),
+    ("copy", -3),
+    ("move", 3),
+    ("swapdb", 3),
+    ("flushdb", -1),
+    ("flushall", -1),
+    ("dbsize", 1),
+    ("get", 2),
+    ("set", -3),
+    ("mget", -2),
+    ("mset", -3),
+    ("msetnx", -3),
+    ("incr", 2),
+    ("decr", 2),
+    ("incrby", 3),
+    ("decrby", 3),
+    ("incrbyfloat", 3),
+    ("append", 3),
+    ("strlen", 2),
+    ("getrange", 4),
+    ("substr", 4),
+    ("setrange", 4),
+    ("getdel", 2),
+    ("getex", -2),
+    ("hset", -4),
+    ("hmset", -4),
+    ("hsetnx", 4),
+    ("hdel", -3),
+    ("hincrby", 4),
+    ("hincrbyfloat", 4),
+    ("hget", 3),
+    ("hmget", -3),
+    ("hlen", 2),
+    ("hexists", 3),
+    ("hstrlen", 3),
+    ("hgetall", 2),
+    ("hkeys", 2),
+    ("hvals", 2),
+    ("hscan", -3),
+    ("hexpire", -6),
+    ("hpexpire", -6),
+    ("hexpireat", -6),
+    ("hpexpireat", -6),
+    ("hpersist", -5),
+    ("httl", -5),
+    ("hpttl", -5),
+    ("sadd", -3),
+    ("srem", -3),
+    ("spop", -2),
+    ("smembers", 2),
+    ("sismember", 3),
+    ("smismember", -3),
+    ("scard", 2),
+    ("sinter", -2),
+    ("sunion", -2),
+    ("sdiff", -2),
+    ("sinterstore", -3),
+    ("sunionstore", -3),
+    ("sdiffstore", -3),
+    ("sintercard", -3),
+    ("srandmember", -2),
+    ("smove", 4),
+    ("sscan", -3),
+    ("zadd", -4),
+    ("zincrby", 4),
+    ("zrem", -3),
+    ("zcard", 2),
+    ("zcount", 4),
+    ("zscore", 3),
+    ("zrank", -3),
+    ("zrevrank", -3),
+    ("zrange", -4),
+    ("zrevrange", -4),
+    ("zrangebyscore", -4),
+    ("zrevrangebyscore", -4),
+    ("zrangebylex", -4),
+    ("zrevrangebylex", -4),
+    ("zremrangebyrank", 4),
+    ("zremrangebyscore", 4),
+    ("zremrangebylex", 4),
+    ("zpopmin", -2),
+    ("zpopmax", -2),
+    ("bzpopmin", -3),
+    ("bzpopmax", -3),
+    ("zunionstore", -4),
+    ("zinterstore", -4),
+    ("zscan", -3),
+    ("xadd", -5),
+    ("xrange", -4),
+    ("xrevrange", -4),
+    ("xlen", 2),
+    ("xtrim", -4),
+    ("xdel", -3),
+    ("xread", -4),
+    ("xgroup|create", -5),
+    ("xgroup|setid", -5),
+    ("xgroup|destroy", 4),
+    ("xgroup|createconsumer", 5),
+    ("xgroup|delconsumer", 5),
+    ("xreadgroup", -7),
+    ("xack", -4),
+    ("xpending", -3),
+    ("xclaim", -6),
+    ("xautoclaim", -6),
+    ("xinfo|stream", -3),
+    ("xinfo|groups", 3),
+    ("xinfo|consumers", 4),
+    ("subscribe", -2),
+    ("unsubscribe", -1),
+    ("psubscribe", -2),
+    ("punsubscribe", -1),
+    ("ssubscribe", -2),
+    ("sunsubscribe", -1),
+    ("publish", 3),
+    ("spublish", 3),
+    ("pubsub|channels", -2),
+    ("pubsub|numsub", -2),
+    ("pubsub|numpat", 2),
+    ("pubsub|shardchannels", -2),
+    ("pubsub|shardnumsub", -2),
+    ("multi", 1),
+    ("exec", 1),
+    ("discard", 1),
+    ("watch", -2),
+    ("unwatch", 1),
+];
+
+fn table() -> &'static HashMap<&'static str, CommandSpec> {
+    static TABLE: OnceLock<HashMap<&'static str, CommandSpec>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let arities: HashMap<_, _> = ARITIES.iter().copied().collect();
+        COMMANDS
+            .iter()
+            .map(|(name, categories, keys, channels)| {
+                let spec = CommandSpec {
+                    name,
+                    categories: Categories::parse(categories),
+                    keys,
+                    channels: *channels,
+                    arity: arities.get(name).copied().unwrap_or(-1),
+                };
+                (*name, spec)
+            })
+            .collect()
+    })
+}
+
+/// Spec of the command named `name`, `container|subcommand` for subcommands.
+pub fn command_spec(name: &str) -> Option<&'static CommandSpec> {
+    table().get(name.to_lowercase().as_str())
+}
+
+/// Whether `name` is a command, or a container of subcommands like `config`.
+pub fn known_command(name: &str) -> bool {
+    let name = name.to_lowercase();
+    table().contains_key(name.as_str())
+        || table().keys().any(|command| {
+            command
+                .split_once('|')
+                .is_some_and(|(container, _)| container == name)
+        })
+}
+
+/// Name Redis reports for `command` sent with `args`, like `client|list` for subcommands.
+pub fn command_name(command: &str, args: &[Resp]) -> String {
+    let command = command.to_lowercase();
+    if let Some(Resp::BulkString(subcommand)) = args.first() {
+        let name = format!(
+            "{command}|{}",
+            String::from_utf8_lossy(subcommand).to_lowercase()
+        );
+        if table().contains_key(name.as_str()) {
+            return name;
+        }
+    }
+    command
+}
+
+/// Whether the command named `name` may change the dataset or be propagated, which
+/// `CLIENT PAUSE WRITE` holds back: write commands, scripts not flagged read-only and
+/// publishing.
+pub fn may_write(name: &str) -> bool {
+    let write = Categories::named("write").unwrap_or_default();
+    command_spec(name).is_some_and(|spec| spec.categories.contains(write))
+        || ["eval", "evalsha", "fcall", "publish", "spublish"].contains(&name)
+}
+
+/// Whether the command named `name` may block its client until another one writes.
+pub fn blocking(name: &str) -> bool {
+    let blocking = Categories::named("blocking").unwrap_or_default();
+    command_spec(name).is_some_and(|spec| spec.categories.contains(blocking))
+}
+
+/// Names of the commands in `category`, sorted.
+pub fn commands_in(category: Categories) -> Vec<&'static str> {
+    let mut names: Vec<_> = table()
+        .values()
+        .filter(|spec| spec.categories.contains(category))
+        .map(|spec| spec.name)
+        .collect();
+    names.sort_unstable();
+    names
+}
+
+#[cfg(test)]
+mod test {
+    use crate::commands::test_helpers::args;
+
+    use super::{command_spec, Access};
+
+    #[test]
+    fn should_find_keys_from_specs() {
+        let mset = command_spec("MSET").unwrap();
+        let mset_args = args(&["a", "1", "b", "2"]);
+        let keys = mset.keys(&mset_args);
+        assert_eq!(
+            keys,
+            vec![(&b"a"[..], Access::Write), (&b"b"[..], Access::Write)]
+        );
+
+        let zunionstore = command_spec("zunionstore").unwrap();
+        let zunionstore_args = args(&["dest", "2", "x", "y", "WEIGHTS", "1", "2"]);
+        let keys = zunionstore.keys(&zunionstore_args);
+        let names: Vec<_> = keys.iter().map(|(key, _)| *key).collect();
+        assert_eq!(names, vec![&b"dest"[..], b"x", b"y"]);
+        assert_eq!(keys[1].1, Access::Read);
+
+        let xread = command_spec("xread").unwrap();
+        let xread_args = args(&["COUNT", "1", "STREAMS", "s1", "s2", "0", "0"]);
+        let keys = xread.keys(&xread_args);
+        let names: Vec<_> = keys.iter().map(|(key, _)| *key).collect();
+        assert_eq!(names, vec![&b"s1"[..], b"s2"]);
+
+        let bzpopmin = command_spec("bzpopmin").unwrap();
+        assert_eq!(bzpopmin.keys(&args(&["a", "b", "0"])).len(), 2);
+        assert!(command_spec("config|get").is_some());
+    }
+}
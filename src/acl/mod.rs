@@ -0,0 +1,428 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    path::PathBuf,
+    sync::{Mutex, RwLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{commands::session::Session, config::AppConfig, errors::AppError, resp::Resp};
+
+use user::{merge_selectors, Denial, User};
+
+pub mod command_table;
+pub mod user;
+
+/// User connections start as, and `AUTH <password>` logs in as.
+pub const DEFAULT_USER: &str = "default";
+
+/// Commands clients may send before authenticating, whatever their permissions.
+pub const NO_AUTH_COMMANDS: [&str; 2] = ["AUTH", "HELLO"];
+
+/// Entries the ACL log keeps, like Redis' default `acllog-max-len`.
+const ACL_LOG_MAX_LEN: usize = 128;
+
+/// Denials alike within this many milliseconds count as a single log entry.
+const ACL_LOG_GROUPING_MS: u64 = 60_000;
+
+#[derive(Debug)]
+struct LogEntry {
+    count: u64,
+    reason: &'static str,
+    context: &'static str,
+    object: String,
+    username: String,
+    client_info: String,
+    entry_id: u64,
+    created: u64,
+    updated: u64,
+}
+
+impl LogEntry {
+    fn reply(&self, now: u64) -> Resp {
+        let age = now.saturating_sub(self.created) as f64 / 1000.0;
+        let fields = [
+            ("count", Resp::Integers(self.count as i64)),
+            ("reason", Resp::bulk_string_from_str(self.reason)),
+            ("context", Resp::bulk_string_from_str(self.context)),
+            ("object", Resp::bulk_string_from_str(&self.object)),
+            ("username", Resp::bulk_string_from_str(&self.username)),
+            (
+                "age-seconds",
+                Resp::bulk_string_from_str(&format!("{age:.3}")),
+            ),
+            ("client-info", Resp::bulk_string_from_str(&self.client_info)),
+            ("entry-id", Resp::Integers(self.entry_id as i64)),
+            ("timestamp-created", Resp::Integers(self.created as i64)),
+            (
+                "timestamp-last-updated",
+                Resp::Integers(self.updated as i64),
+            ),
+        ];
+        Resp::Array(
+            fields
+                .into_iter()
+                .flat_map(|(name, value)| [Resp::bulk_string_from_str(name), value])
+                .collect(),
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+struct AclLog {
+    /// Newest first.
+    entries: VecDeque<LogEntry>,
+    next_id: u64,
+}
+
+/// Users and the log of the commands they were denied, shared by every connection.
+#[derive(Debug)]
+pub struct Acl {
+    users: RwLock<BTreeMap<String, User>>,
+    log: Mutex<AclLog>,
+    /// File `ACL LOAD` and `ACL SAVE` read and write users from.
+    aclfile: Option<PathBuf>,
+}
+
+impl Default for Acl {
+    fn default() -> Self {
+        Self::new(None, None)
+    }
+}
+
+impl Acl {
+    /// Only the default user, which `requirepass` gives a password to.
+    pub fn new(requirepass: Option<&str>, aclfile: Option<PathBuf>) -> Self {
+        let users = [(DEFAULT_USER.to_owned(), default_user(requirepass))].into();
+        Self {
+            users: RwLock::new(users),
+            log: Mutex::default(),
+            aclfile,
+        }
+    }
+
+    /// Users of the config, read from its `aclfile` when there is one. A file that fails
+    /// to load is logged, leaving only the default user.
+    pub fn from_config(config: &AppConfig) -> Self {
+        let acl = Self::new(config.requirepass.as_deref(), config.aclfile.clone());
+        if acl.aclfile.as_ref().is_some_and(|path| path.exists()) {
+            if let Err(err) = acl.load() {
+                log::error!("Could not load the ACL file: {err}");
+            }
+        }
+        acl
+    }
+
+    /// Whether new connections are logged in as the default user without `AUTH`.
+    pub fn default_authenticated(&self) -> bool {
+        self.users
+            .read()
+            .unwrap()
+            .get(DEFAULT_USER)
+            .is_some_and(|user| user.enabled() && user.nopass())
+    }
+
+    /// Whether the default user takes any password, `AUTH <password>` then being an error.
+    pub fn default_nopass(&self) -> bool {
+        self.users
+            .read()
+            .unwrap()
+            .get(DEFAULT_USER)
+            .is_some_and(User::nopass)
+    }
+
+    /// Checks the credentials of `AUTH` and `HELLO AUTH`, logging failed attempts.
+    pub fn authenticate(
+        &self,
+        session: &Session,
+        user: &str,
+        password: &[u8],
+    ) -> Result<(), AppError> {
+        let valid = self
+            .users
+            .read()
+            .unwrap()
+            .get(user)
+            .is_some_and(|found| found.enabled() && found.accepts(password));
+        if !valid {
+            self.record(session, "auth", "AUTH".to_owned(), user);
+            return Err(AppError::WrongPass);
+        }
+        Ok(())
+    }
+
+    /// Checks that the session's user may run `name`, `command|subcommand` for subcommands,
+    /// on the keys and channels of `args`. Denials are logged.
+    pub fn check(&self, session: &Session, name: &str, args: &[Resp]) -> Result<(), AppError> {
+        let container = name.split('|').next().unwrap_or_default().to_uppercase();
+        if NO_AUTH_COMMANDS.contains(&container.as_str()) {
+            return Ok(());
+        }
+        let name = name.to_lowercase();
+        let denial = match self.users.read().unwrap().get(session.user()) {
+            Some(user) => user.check(&name, args),
+            None => Err(Denial::Command),
+        };
+        let (reason, object, err) = match denial {
+            Ok(()) => return Ok(()),
+            Err(Denial::Command) => (
+                "command",
+                name.clone(),
+                AppError::NoPermCommand(session.user().to_owned(), name),
+            ),
+            Err(Denial::Key(key)) => (
+                "key",
+                String::from_utf8_lossy(&key).into(),
+                AppError::NoPermKey,
+            ),
+            Err(Denial::Channel(channel)) => (
+                "channel",
+                String::from_utf8_lossy(&channel).into(),
+                AppError::NoPermChannel,
+            ),
+        };
+        self.record(session, reason, object, session.user());
+        Err(err)
+    }
+
+    fn record(&self, session: &Session, reason: &'static str, object: String, username: &str) {
+        let context = match (session.in_script(), session.in_transaction()) {
+            (true, _) => "lua",
+            (false, true) => "multi",
+            (false, false) => "toplevel",
+        };
+        let now = now_ms();
+        let mut log = self.log.lock().unwrap();
+        let similar = log.entries.iter_mut().find(|entry| {
+            entry.reason == reason
+                && entry.context == context
+                && entry.object == object
+                && entry.username == username
+                && now.saturating_sub(entry.updated) < ACL_LOG_GROUPING_MS
+        });
+        if let Some(entry) = similar {
+            entry.count += 1;
+            entry.updated = now;
+            return;
+        }
+        let entry_id = log.next_id;
+        log.next_id += 1;
+        log.entries.push_front(LogEntry {
+            count: 1,
+            reason,
+            context,
+            object,
+            username: username.to_owned(),
+            client_info: session.client_info(),
+            entry_id,
+            created: now,
+            updated: now,
+        });
+        log.entries.truncate(ACL_LOG_MAX_LEN);
+    }
+
+    /// The `count` newest log entries, all of them when not given.
+    pub fn log(&self, count: Option<usize>) -> Resp {
+        let now = now_ms();
+        let log = self.log.lock().unwrap();
+        Resp::Array(
+            log.entries
+                .iter()
+                .take(count.unwrap_or(usize::MAX))
+                .map(|entry| entry.reply(now))
+                .collect(),
+        )
+    }
+
+    pub fn reset_log(&self) {
+        self.log.lock().unwrap().entries.clear();
+    }
+
+    /// Applies `rules` to user `name`, creating it if needed.
+    pub fn set_user(&self, name: &str, rules: &[String]) -> Result<(), AppError> {
+        if name.contains([' ', '\0']) {
+            return Err(AppError::Generic(
+                "Usernames can't contain spaces or null characters".to_owned(),
+            ));
+        }
+        let mut users = self.users.write().unwrap();
+        let mut user = users.get(name).cloned().unwrap_or_else(|| User::new(name));
+        user.apply_rules(rules).map_err(AppError::Generic)?;
+        users.insert(name.to_owned(), user);
+        Ok(())
+    }
+
+    pub fn user_info(&self, name: &str) -> Option<Resp> {
+        self.users.read().unwrap().get(name).map(User::info)
+    }
+
+    /// Deletes users, returning the names of those that existed.
+    pub fn delete_users<'a>(&self, names: &'a [String]) -> Result<Vec<&'a str>, AppError> {
+        if names.iter().any(|name| name == DEFAULT_USER) {
+            return Err(AppError::Generic(
+                "The 'default' user cannot be removed".to_owned(),
+            ));
+        }
+        let mut users = self.users.write().unwrap();
+        Ok(names
+            .iter()
+            .map(String::as_str)
+            .filter(|name| users.remove(*name).is_some())
+            .collect())
+    }
+
+    pub fn usernames(&self) -> Vec<String> {
+        self.users.read().unwrap().keys().cloned().collect()
+    }
+
+    /// Rules of every user, as `ACL LIST` shows them and the ACL file holds them.
+    pub fn describe(&self) -> Vec<String> {
+        self.users
+            .read()
+            .unwrap()
+            .values()
+            .map(User::describe)
+            .collect()
+    }
+
+    fn aclfile(&self) -> Result<&PathBuf, AppError> {
+        self.aclfile.as_ref().ok_or(AppError::Generic(
+            "This Redis instance is not configured to use an ACL file. You may want to specify users via the ACL SETUSER command and then issue a CONFIG REWRITE (assuming you have a Redis configuration file set) in order to store users in the Redis configuration.".to_owned(),
+        ))
+    }
+
+    /// Replaces every user by those of the ACL file, leaving them untouched if any line
+    /// is invalid.
+    pub fn load(&self) -> Result<(), AppError> {
+        let path = self.aclfile()?;
+        let content = std::fs::read_to_string(path)?;
+        let mut users = BTreeMap::new();
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fail =
+                |err: &str| AppError::Generic(format!("{}:{}: {err}", path.display(), number + 1));
+            let words: Vec<String> = line.split_whitespace().map(str::to_owned).collect();
+            let (Some("user"), Some(name)) = (words.first().map(String::as_str), words.get(1))
+            else {
+                return Err(fail("should start with user keyword"));
+            };
+            if users.contains_key(name) {
+                return Err(fail(&format!("Duplicate user '{name}' found")));
+            }
+            let rules = merge_selectors(&words[2..]).map_err(|err| fail(&err))?;
+            let mut user = User::new(name);
+            user.apply_rules(&rules).map_err(|err| fail(&err))?;
+            users.insert(name.to_owned(), user);
+        }
+        users
+            .entry(DEFAULT_USER.to_owned())
+            .or_insert_with(|| default_user(None));
+        *self.users.write().unwrap() = users;
+        Ok(())
+    }
+
+    /// Writes every user to the ACL file, through a temporary file so that it is never
+    /// left half written.
+    pub fn save(&self) -> Result<(), AppError> {
+        let path = self.aclfile()?;
+        let mut content = self.describe().join("\n");
+        content.push('\n');
+        let temporary = path.with_extension("tmp");
+        std::fs::write(&temporary, content)?;
+        std::fs::rename(&temporary, path)?;
+        Ok(())
+    }
+}
+
+/// Default user granted everything, taking any password unless `requirepass` is set.
+fn default_user(requirepass: Option<&str>) -> User {
+    let mut user = User::new(DEFAULT_USER);
+    let password = match requirepass {
+        Some(password) => format!(">{password}"),
+        None => "nopass".to_owned(),
+    };
+    let rules = ["on", &password, "~*", "&*", "+@all"].map(str::to_owned);
+    user.apply_rules(&rules)
+        .expect("default user rules to apply");
+    user
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_millis() as u64)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{commands::session::Session, resp::Resp};
+
+    use super::Acl;
+
+    fn strings(strings: &[&str]) -> Vec<String> {
+        strings.iter().map(|string| string.to_string()).collect()
+    }
+
+    #[test]
+    fn should_log_denied_commands() {
+        let acl = Acl::default();
+        acl.set_user("alice", &strings(&["on", ">pw", "+get", "~cache:*"]))
+            .unwrap();
+        let mut session = Session::default();
+        session.set_user("alice".to_owned());
+        let key = [Resp::bulk_string_from_str("secret")];
+        for _ in 0..2 {
+            let err = acl.check(&session, "get", &key).unwrap_err();
+            assert_eq!(err.to_string(), "NOPERM No permissions to access a key");
+        }
+        let err = acl.check(&session, "config|get", &[]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "NOPERM User alice has no permissions to run the 'config|get' command"
+        );
+        assert!(acl.authenticate(&session, "alice", b"wrong").is_err());
+        assert!(acl.authenticate(&session, "alice", b"pw").is_ok());
+
+        let Resp::Array(entries) = acl.log(None) else {
+            panic!()
+        };
+        assert_eq!(entries.len(), 3);
+        let Resp::Array(newest) = &entries[0] else {
+            panic!()
+        };
+        assert_eq!(newest[3], Resp::bulk_string_from_str("auth"));
+        let Resp::Array(oldest) = &entries[2] else {
+            panic!()
+        };
+        assert_eq!(oldest[1], Resp::Integers(2));
+        assert_eq!(oldest[7], Resp::bulk_string_from_str("secret"));
+        acl.reset_log();
+        assert_eq!(acl.log(None), Resp::Array(vec![]));
+    }
+
+    #[test]
+    fn should_save_and_load_the_acl_file() {
+        let path = std::env::temp_dir().join(format!("users-{}.acl", std::process::id()));
+        let acl = Acl::new(Some("secret"), Some(path.clone()));
+        acl.set_user(
+            "bob",
+            &strings(&["on", "nopass", "%R~app:*", "(+set", "~tmp:*)"]),
+        )
+        .unwrap();
+        acl.save().unwrap();
+        let loaded = Acl::new(None, Some(path.clone()));
+        loaded.load().unwrap();
+        assert_eq!(loaded.describe(), acl.describe());
+        assert!(!loaded.default_authenticated());
+
+        std::fs::write(&path, "user carol on +nosuchcommand\n").unwrap();
+        let err = loaded.load().unwrap_err();
+        assert!(err
+            .to_string()
+            .contains(":1: Error in ACL SETUSER modifier '+nosuchcommand'"));
+        assert_eq!(loaded.usernames(), vec!["bob", "default"]);
+        std::fs::remove_file(path).unwrap();
+    }
+}
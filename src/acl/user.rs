@@ -0,0 +1,558 @@
+use std::collections::BTreeSet;
+
+use crate::{
+    helpers::{constant_time::constant_time_eq, glob::glob_match, sha256::sha256_hex},
+    resp::Resp,
+};
+
+use super::command_table::{command_spec, known_command, Access, Categories, ChannelSpec};
+
+/// Why a command was denied, reported by `NOPERM` replies and the ACL log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Denial {
+    Command,
+    Key(Vec<u8>),
+    Channel(Vec<u8>),
+}
+
+/// A `+` or `-` command rule, the last one matching a command deciding whether it runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum CommandRule {
+    Category(bool, String),
+    /// Command, or `command|subcommand`, lowercase.
+    Command(bool, String),
+}
+
+impl CommandRule {
+    fn allows(&self, name: &str, categories: Categories) -> Option<bool> {
+        match self {
+            Self::Category(allow, category) => Categories::named(category)
+                .filter(|category| categories.contains(*category))
+                .map(|_| *allow),
+            Self::Command(allow, command) => {
+                let container = name.split_once('|').map(|(container, _)| container);
+                (command == name || Some(command.as_str()) == container).then_some(*allow)
+            }
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Self::Category(allow, category) => format!("{}@{category}", sign(*allow)),
+            Self::Command(allow, command) => format!("{}{command}", sign(*allow)),
+        }
+    }
+}
+
+fn sign(allow: bool) -> char {
+    if allow {
+        '+'
+    } else {
+        '-'
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct KeyPattern {
+    pattern: Vec<u8>,
+    read: bool,
+    write: bool,
+}
+
+impl KeyPattern {
+    fn describe(&self) -> String {
+        let pattern = String::from_utf8_lossy(&self.pattern);
+        match (self.read, self.write) {
+            (true, false) => format!("%R~{pattern}"),
+            (false, true) => format!("%W~{pattern}"),
+            _ => format!("~{pattern}"),
+        }
+    }
+}
+
+/// Commands, keys and channels granted together: a command runs when the user's root
+/// selector or one of its other selectors grants all it touches.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Selector {
+    /// Whether rules start from every command, `+@all`, or none.
+    all_commands: bool,
+    rules: Vec<CommandRule>,
+    keys: Vec<KeyPattern>,
+    channels: Vec<Vec<u8>>,
+}
+
+impl Selector {
+    fn allows_command(&self, name: &str, categories: Categories) -> bool {
+        self.rules
+            .iter()
+            .rev()
+            .find_map(|rule| rule.allows(name, categories))
+            .unwrap_or(self.all_commands)
+    }
+
+    fn allows_key(&self, key: &[u8], access: Access) -> bool {
+        self.keys.iter().any(|pattern| {
+            (pattern.read || !access.reads())
+                && (pattern.write || !access.writes())
+                && glob_match(&pattern.pattern, key, false)
+        })
+    }
+
+    fn allows_channel(&self, channel: &[u8], literal: bool) -> bool {
+        self.channels.iter().any(|pattern| match literal {
+            true => pattern == b"*" || pattern == channel,
+            false => glob_match(pattern, channel, false),
+        })
+    }
+
+    /// Checks a command, named like `CommandSpec::name`, against this selector.
+    fn check(&self, name: &str, args: &[Resp]) -> Result<(), Denial> {
+        let spec = command_spec(name);
+        let categories = spec.map(|spec| spec.categories).unwrap_or_default();
+        if !self.allows_command(name, categories) {
+            return Err(Denial::Command);
+        }
+        let Some(spec) = spec else {
+            return Ok(());
+        };
+        if let Some((key, _)) = spec
+            .keys(args)
+            .into_iter()
+            .find(|(key, access)| !self.allows_key(key, *access))
+        {
+            return Err(Denial::Key(key.to_vec()));
+        }
+        let literal = spec.channels == ChannelSpec::Patterns;
+        if let Some(channel) = spec
+            .channels(args)
+            .into_iter()
+            .find(|channel| !self.allows_channel(channel, literal))
+        {
+            return Err(Denial::Channel(channel.to_vec()));
+        }
+        Ok(())
+    }
+
+    /// Applies a rule about commands, keys or channels, `None` meaning it is none of these.
+    fn apply(&mut self, rule: &str) -> Option<Result<(), String>> {
+        let lowercase = rule.to_lowercase();
+        let result = match lowercase.as_str() {
+            "allcommands" | "+@all" => {
+                self.all_commands = true;
+                self.rules.clear();
+                Ok(())
+            }
+            "nocommands" | "-@all" => {
+                self.all_commands = false;
+                self.rules.clear();
+                Ok(())
+            }
+            "allkeys" => {
+                self.keys = vec![KeyPattern {
+                    pattern: b"*".to_vec(),
+                    read: true,
+                    write: true,
+                }];
+                Ok(())
+            }
+            "resetkeys" => {
+                self.keys.clear();
+                Ok(())
+            }
+            "allchannels" => {
+                self.channels = vec![b"*".to_vec()];
+                Ok(())
+            }
+            "resetchannels" => {
+                self.channels.clear();
+                Ok(())
+            }
+            _ => match rule.as_bytes().first()? {
+                b'+' | b'-' => self.command_rule(&lowercase),
+                b'~' | b'%' => self.key_pattern(rule),
+                b'&' => {
+                    let pattern = rule.as_bytes()[1..].to_vec();
+                    if !self.channels.contains(&pattern) {
+                        self.channels.push(pattern);
+                    }
+                    Ok(())
+                }
+                _ => return None,
+            },
+        };
+        Some(result)
+    }
+
+    fn command_rule(&mut self, rule: &str) -> Result<(), String> {
+        let allow = rule.starts_with('+');
+        let rule = match rule[1..].strip_prefix('@') {
+            Some(category) if Categories::named(category).is_some() => {
+                CommandRule::Category(allow, category.to_owned())
+            }
+            None if known_command(&rule[1..]) => CommandRule::Command(allow, rule[1..].to_owned()),
+            _ => return Err("Unknown command or category name in ACL".to_owned()),
+        };
+        self.rules.retain(|existing| existing != &rule);
+        self.rules.push(rule);
+        Ok(())
+    }
+
+    fn key_pattern(&mut self, rule: &str) -> Result<(), String> {
+        let (read, write, pattern) = match rule.split_once('~') {
+            Some(("", pattern)) => (true, true, pattern),
+            Some((flags, pattern)) if flags.len() > 1 => {
+                let flags = flags[1..].to_uppercase();
+                if flags.is_empty() || !flags.chars().all(|flag| flag == 'R' || flag == 'W') {
+                    return Err("Syntax error".to_owned());
+                }
+                (flags.contains('R'), flags.contains('W'), pattern)
+            }
+            _ => return Err("Syntax error".to_owned()),
+        };
+        let pattern = KeyPattern {
+            pattern: pattern.as_bytes().to_vec(),
+            read,
+            write,
+        };
+        if !self.keys.contains(&pattern) {
+            self.keys.push(pattern);
+        }
+        Ok(())
+    }
+
+    fn describe_commands(&self) -> String {
+        let base = if self.all_commands { "+@all" } else { "-@all" };
+        std::iter::once(base.to_owned())
+            .chain(self.rules.iter().map(CommandRule::describe))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn describe_keys(&self) -> String {
+        self.keys
+            .iter()
+            .map(KeyPattern::describe)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn describe_channels(&self) -> String {
+        self.channels
+            .iter()
+            .map(|pattern| format!("&{}", String::from_utf8_lossy(pattern)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Rules recreating this selector, as `ACL LIST` shows them.
+    fn describe(&self) -> String {
+        let channels = match self.channels.as_slice() {
+            [all] if all == b"*" => "&*".to_owned(),
+            _ => ["resetchannels".to_owned(), self.describe_channels()].join(" "),
+        };
+        [self.describe_keys(), channels, self.describe_commands()]
+            .iter()
+            .filter(|part| !part.trim().is_empty())
+            .map(|part| part.trim())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// `commands`, `keys` and `channels` fields of `ACL GETUSER`.
+    fn fields(&self) -> Vec<(&'static str, Resp)> {
+        vec![
+            (
+                "commands",
+                Resp::bulk_string_from_str(&self.describe_commands()),
+            ),
+            ("keys", Resp::bulk_string_from_str(&self.describe_keys())),
+            (
+                "channels",
+                Resp::bulk_string_from_str(&self.describe_channels()),
+            ),
+        ]
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct User {
+    name: String,
+    enabled: bool,
+    nopass: bool,
+    /// SHA-256 of the passwords, hex encoded.
+    passwords: BTreeSet<String>,
+    root: Selector,
+    selectors: Vec<Selector>,
+}
+
+impl User {
+    /// A new user, disabled and granted nothing until rules say otherwise.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn nopass(&self) -> bool {
+        self.nopass
+    }
+
+    pub fn accepts(&self, password: &[u8]) -> bool {
+        let hash = sha256_hex(password);
+        self.nopass
+            || self
+                .passwords
+                .iter()
+                .any(|stored| constant_time_eq(stored.as_bytes(), hash.as_bytes()))
+    }
+
+    /// Checks a command against the root selector, then the others. The root selector's
+    /// denial is the one reported when none grants it.
+    pub fn check(&self, name: &str, args: &[Resp]) -> Result<(), Denial> {
+        let denial = match self.root.check(name, args) {
+            Ok(()) => return Ok(()),
+            Err(denial) => denial,
+        };
+        match self
+            .selectors
+            .iter()
+            .any(|selector| selector.check(name, args).is_ok())
+        {
+            true => Ok(()),
+            false => Err(denial),
+        }
+    }
+
+    /// Applies rules in order, `(...)` ones adding a selector. Nothing is changed unless
+    /// they all apply.
+    pub fn apply_rules(&mut self, rules: &[String]) -> Result<(), String> {
+        let mut user = self.clone();
+        for rule in merge_selectors(rules)? {
+            user.apply(&rule)
+                .map_err(|err| format!("Error in ACL SETUSER modifier '{rule}': {err}"))?;
+        }
+        *self = user;
+        Ok(())
+    }
+
+    fn apply(&mut self, rule: &str) -> Result<(), String> {
+        if let Some(selector) = rule
+            .strip_prefix('(')
+            .and_then(|rule| rule.strip_suffix(')'))
+        {
+            let mut added = Selector::default();
+            for rule in selector.split_whitespace() {
+                added
+                    .apply(rule)
+                    .unwrap_or(Err("Syntax error".to_owned()))?;
+            }
+            self.selectors.push(added);
+            return Ok(());
+        }
+        if let Some(result) = self.root.apply(rule) {
+            return result;
+        }
+        match rule.to_lowercase().as_str() {
+            "on" => self.enabled = true,
+            "off" => self.enabled = false,
+            "nopass" => {
+                self.nopass = true;
+                self.passwords.clear();
+            }
+            "resetpass" => {
+                self.nopass = false;
+                self.passwords.clear();
+            }
+            "clearselectors" => self.selectors.clear(),
+            "reset" => {
+                let name = std::mem::take(&mut self.name);
+                *self = Self::new(&name);
+            }
+            _ => match rule.as_bytes().first() {
+                Some(b'>') => {
+                    self.passwords.insert(sha256_hex(&rule.as_bytes()[1..]));
+                    self.nopass = false;
+                }
+                Some(b'<') => {
+                    if !self.passwords.remove(&sha256_hex(&rule.as_bytes()[1..])) {
+                        return Err("no such password".to_owned());
+                    }
+                }
+                Some(b'#') => {
+                    self.passwords.insert(password_hash(&rule[1..])?);
+                    self.nopass = false;
+                }
+                Some(b'!') => {
+                    if !self.passwords.remove(&password_hash(&rule[1..])?) {
+                        return Err("no such password".to_owned());
+                    }
+                }
+                _ => return Err("Syntax error".to_owned()),
+            },
+        }
+        Ok(())
+    }
+
+    /// Rules recreating the user, as `ACL LIST` and the ACL file hold them.
+    pub fn describe(&self) -> String {
+        let mut parts = vec![
+            format!("user {}", self.name),
+            if self.enabled { "on" } else { "off" }.to_owned(),
+        ];
+        if self.nopass {
+            parts.push("nopass".to_owned());
+        }
+        parts.extend(self.passwords.iter().map(|hash| format!("#{hash}")));
+        parts.push(self.root.describe());
+        parts.extend(
+            self.selectors
+                .iter()
+                .map(|selector| format!("({})", selector.describe())),
+        );
+        parts.join(" ")
+    }
+
+    /// Reply of `ACL GETUSER`.
+    pub fn info(&self) -> Resp {
+        let mut flags = vec![Resp::bulk_string_from_str(if self.enabled {
+            "on"
+        } else {
+            "off"
+        })];
+        if self.nopass {
+            flags.push(Resp::bulk_string_from_str("nopass"));
+        }
+        let passwords = self
+            .passwords
+            .iter()
+            .map(|hash| Resp::bulk_string_from_str(hash))
+            .collect();
+        let selectors = self
+            .selectors
+            .iter()
+            .map(|selector| flatten(selector.fields()))
+            .collect();
+        let mut fields = vec![
+            ("flags", Resp::Array(flags)),
+            ("passwords", Resp::Array(passwords)),
+        ];
+        fields.extend(self.root.fields());
+        fields.push(("selectors", Resp::Array(selectors)));
+        flatten(fields)
+    }
+}
+
+fn flatten(fields: Vec<(&'static str, Resp)>) -> Resp {
+    Resp::Array(
+        fields
+            .into_iter()
+            .flat_map(|(name, value)| [Resp::bulk_string_from_str(name), value])
+            .collect(),
+    )
+}
+
+fn password_hash(hash: &str) -> Result<String, String> {
+    match hash.len() == 64
+        && hash
+            .bytes()
+            .all(|byte| byte.is_ascii_digit() || (b'a'..=b'f').contains(&byte))
+    {
+        true => Ok(hash.to_owned()),
+        false => Err("The password hash must be exactly 64 characters and contain only lowercase hexadecimal characters".to_owned()),
+    }
+}
+
+/// Joins the rules of a selector split over several arguments, `(~key` `+get)` forming
+/// `(~key +get)`, like Redis does for both `ACL SETUSER` and ACL files.
+pub fn merge_selectors(rules: &[String]) -> Result<Vec<String>, String> {
+    let mut merged = vec![];
+    let mut selector: Option<String> = None;
+    for rule in rules {
+        match selector.as_mut() {
+            Some(open) => {
+                open.push(' ');
+                open.push_str(rule);
+            }
+            None if rule.starts_with('(') => selector = Some(rule.clone()),
+            None => merged.push(rule.clone()),
+        }
+        if selector.as_ref().is_some_and(|open| open.ends_with(')')) {
+            merged.extend(selector.take());
+        }
+    }
+    match selector {
+        Some(_) => Err("Unmatched parenthesis in acl selector starting at '('.".to_owned()),
+        None => Ok(merged),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::commands::test_helpers::args;
+
+    use super::{Denial, User};
+
+    fn rules(rules: &str) -> Vec<String> {
+        rules.split_whitespace().map(str::to_owned).collect()
+    }
+
+    #[test]
+    fn should_check_commands_keys_and_channels() {
+        let mut user = User::new("alice");
+        user.apply_rules(&rules("on >pw +@read -hget %R~cache:* ~own:* &news.*"))
+            .unwrap();
+        assert!(user.accepts(b"pw") && !user.accepts(b"other"));
+        assert_eq!(user.check("get", &args(&["cache:1"])), Ok(()));
+        assert_eq!(
+            user.check("hget", &args(&["own:1", "f"])),
+            Err(Denial::Command)
+        );
+        assert_eq!(
+            user.check("set", &args(&["own:1", "v"])),
+            Err(Denial::Command)
+        );
+        assert_eq!(
+            user.check("mget", &args(&["own:1", "secret"])),
+            Err(Denial::Key(b"secret".to_vec()))
+        );
+
+        user.apply_rules(&rules("+set +publish +psubscribe"))
+            .unwrap();
+        assert_eq!(
+            user.check("set", &args(&["cache:1", "v"])),
+            Err(Denial::Key(b"cache:1".to_vec()))
+        );
+        assert_eq!(user.check("publish", &args(&["news.tech", "hi"])), Ok(()));
+        assert_eq!(
+            user.check("psubscribe", &args(&["news.t*"])),
+            Err(Denial::Channel(b"news.t*".to_vec()))
+        );
+        assert_eq!(user.check("psubscribe", &args(&["news.*"])), Ok(()));
+    }
+
+    #[test]
+    fn should_grant_through_selectors() {
+        let mut user = User::new("bob");
+        user.apply_rules(&rules("on nopass +get ~app:* (+set ~tmp:*)"))
+            .unwrap();
+        assert_eq!(user.check("set", &args(&["tmp:1", "v"])), Ok(()));
+        assert_eq!(
+            user.check("set", &args(&["app:1", "v"])),
+            Err(Denial::Command)
+        );
+        assert_eq!(
+            user.describe(),
+            "user bob on nopass ~app:* resetchannels -@all +get (~tmp:* resetchannels -@all +set)"
+        );
+        let err = user.apply_rules(&rules("+nosuchcommand")).unwrap_err();
+        assert_eq!(
+            err,
+            "Error in ACL SETUSER modifier '+nosuchcommand': Unknown command or category name in ACL"
+        );
+    }
+}
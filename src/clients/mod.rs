@@ -0,0 +1,428 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use tokio::sync::Notify;
+
+use crate::{
+    commands::session::{ClientAddress, Session},
+    errors::AppError,
+};
+
+/// Snapshot of a connection, one line of `CLIENT LIST`.
+#[derive(Debug, Clone)]
+pub struct ClientInfo {
+    id: u64,
+    address: Option<ClientAddress>,
+    name: String,
+    connected: Instant,
+    last_interaction: Instant,
+    flags: String,
+    db: usize,
+    sub: usize,
+    ssub: usize,
+    multi: i64,
+    qbuf: usize,
+    omem: usize,
+    cmd: String,
+    user: String,
+    redir: i64,
+    resp: u8,
+}
+
+impl ClientInfo {
+    pub fn new(session: &Session) -> Self {
+        let now = Instant::now();
+        let mut flags = String::new();
+        if session.in_transaction() {
+            flags.push('x');
+        }
+        if session.subscribed() {
+            flags.push('P');
+        }
+        if session.address().is_some_and(ClientAddress::unix) {
+            flags.push('U');
+        }
+        if session.tracking().is_some() {
+            flags.push('t');
+        }
+        if session.no_evict() {
+            flags.push('e');
+        }
+        if flags.is_empty() {
+            flags.push('N');
+        }
+        let (sub, ssub) = session.subscriptions();
+        Self {
+            id: session.id(),
+            address: session.address().cloned(),
+            name: session.name().unwrap_or_default().to_owned(),
+            connected: session.connected().unwrap_or(now),
+            last_interaction: session.last_interaction().unwrap_or(now),
+            flags,
+            db: session.db,
+            sub,
+            ssub,
+            multi: match session.in_transaction() {
+                true => session.queued() as i64,
+                false => -1,
+            },
+            qbuf: 0,
+            omem: 0,
+            cmd: session.command().unwrap_or("NULL").to_owned(),
+            user: session.user().to_owned(),
+            redir: session
+                .tracking()
+                .map_or(-1, |tracking| tracking.redirect.unwrap_or(0) as i64),
+            resp: if session.resp3() { 3 } else { 2 },
+        }
+    }
+
+    /// Sets the bytes of commands read but not processed yet, and of replies waiting to be
+    /// written.
+    pub fn with_buffers(mut self, qbuf: usize, omem: usize) -> Self {
+        self.qbuf = qbuf;
+        self.omem = omem;
+        self
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn kind(&self) -> ClientType {
+        if self.sub + self.ssub > 0 {
+            ClientType::Pubsub
+        } else {
+            ClientType::Normal
+        }
+    }
+
+    fn addr(&self) -> String {
+        self.address
+            .as_ref()
+            .map(ClientAddress::addr)
+            .unwrap_or_default()
+    }
+
+    fn laddr(&self) -> String {
+        self.address
+            .as_ref()
+            .map(ClientAddress::laddr)
+            .unwrap_or_default()
+    }
+
+    fn age(&self) -> Duration {
+        self.connected.elapsed()
+    }
+}
+
+impl fmt::Display for ClientInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "id={} addr={} laddr={} name={} age={} idle={} flags={} db={} sub={} ssub={} multi={} qbuf={} omem={} cmd={} user={} redir={} resp={}",
+            self.id,
+            self.addr(),
+            self.laddr(),
+            self.name,
+            self.age().as_secs(),
+            self.last_interaction.elapsed().as_secs(),
+            self.flags,
+            self.db,
+            self.sub,
+            self.ssub,
+            self.multi,
+            self.qbuf,
+            self.omem,
+            self.cmd,
+            self.user,
+            self.redir,
+            self.resp,
+        )
+    }
+}
+
+/// `TYPE` of `CLIENT LIST` and `CLIENT KILL`. There are no replication links, so no
+/// client is ever a master or a replica.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientType {
+    Normal,
+    Master,
+    Replica,
+    Pubsub,
+}
+
+impl ClientType {
+    pub fn parse(name: &str) -> Result<Self, AppError> {
+        match name.to_lowercase().as_str() {
+            "normal" => Ok(Self::Normal),
+            "master" => Ok(Self::Master),
+            "replica" | "slave" => Ok(Self::Replica),
+            "pubsub" => Ok(Self::Pubsub),
+            _ => Err(AppError::Generic(format!("Unknown client type '{name}'"))),
+        }
+    }
+}
+
+/// Filters of `CLIENT KILL`, a client having to match all of those set.
+#[derive(Debug, Default)]
+pub struct KillFilter {
+    pub id: Option<u64>,
+    pub addr: Option<String>,
+    pub laddr: Option<String>,
+    pub user: Option<String>,
+    pub kind: Option<ClientType>,
+    /// Kills clients connected for longer than this many seconds.
+    pub maxage: Option<u64>,
+    /// Client left alone, the one sending `CLIENT KILL` unless `SKIPME no`.
+    pub skip: Option<u64>,
+}
+
+impl KillFilter {
+    fn matches(&self, client: &ClientInfo) -> bool {
+        self.id.is_none_or(|id| id == client.id)
+            && self.addr.as_ref().is_none_or(|addr| *addr == client.addr())
+            && self
+                .laddr
+                .as_ref()
+                .is_none_or(|laddr| *laddr == client.laddr())
+            && self.user.as_ref().is_none_or(|user| *user == client.user)
+            && self.kind.is_none_or(|kind| kind == client.kind())
+            && self
+                .maxage
+                .is_none_or(|maxage| client.age().as_secs() > maxage)
+            && self.skip != Some(client.id)
+    }
+}
+
+#[derive(Debug)]
+struct Client {
+    info: ClientInfo,
+    /// Notified to make the connection close.
+    kill: Arc<Notify>,
+}
+
+/// `CLIENT PAUSE` in effect.
+#[derive(Debug, Clone, Copy)]
+struct Pause {
+    until: Instant,
+    /// Pausing every command, not only the ones that may write.
+    all: bool,
+}
+
+/// Connected clients, updated by their connections after every command, and the
+/// `CLIENT PAUSE` holding them back.
+#[derive(Debug, Default)]
+pub struct ClientTable {
+    clients: Mutex<BTreeMap<u64, Client>>,
+    pause: Mutex<Option<Pause>>,
+    unpaused: Notify,
+}
+
+/// Entry of a connection in the [`ClientTable`], removed once dropped so that a
+/// connection task ending in any way, panics included, frees its slot.
+#[derive(Debug)]
+pub struct Connected {
+    clients: Arc<ClientTable>,
+    id: u64,
+    kill: Arc<Notify>,
+}
+
+impl Connected {
+    /// Notified to make the connection close.
+    pub fn kill(&self) -> &Notify {
+        &self.kill
+    }
+}
+
+impl Drop for Connected {
+    fn drop(&mut self) {
+        self.clients.disconnect(self.id);
+    }
+}
+
+impl ClientTable {
+    /// Adds the client of `session`, until the returned entry is dropped, unless
+    /// `maxclients` are already connected.
+    pub fn connect(
+        self: &Arc<Self>,
+        session: &Session,
+        maxclients: usize,
+    ) -> Result<Connected, AppError> {
+        let kill = Arc::new(Notify::new());
+        let client = Client {
+            info: ClientInfo::new(session),
+            kill: kill.clone(),
+        };
+        let mut clients = self.clients.lock().unwrap();
+        if clients.len() >= maxclients {
+            return Err(AppError::MaxClients);
+        }
+        clients.insert(session.id(), client);
+        Ok(Connected {
+            clients: self.clone(),
+            id: session.id(),
+            kill,
+        })
+    }
+
+    /// Number of connected clients.
+    pub fn len(&self) -> usize {
+        self.clients.lock().unwrap().len()
+    }
+
+    fn disconnect(&self, id: u64) {
+        self.clients.lock().unwrap().remove(&id);
+    }
+
+    /// Replaces what is known of the client of `info`, unless it was killed.
+    pub fn update(&self, info: ClientInfo) {
+        if let Some(client) = self.clients.lock().unwrap().get_mut(&info.id) {
+            client.info = info;
+        }
+    }
+
+    /// Updates the client of `session` from it, keeping its last known buffer sizes.
+    pub fn refresh(&self, session: &Session) {
+        if let Some(client) = self.clients.lock().unwrap().get_mut(&session.id()) {
+            let (qbuf, omem) = (client.info.qbuf, client.info.omem);
+            client.info = ClientInfo::new(session).with_buffers(qbuf, omem);
+        }
+    }
+
+    /// Clients of type `kind` among `ids`, all of them when unset, by increasing id.
+    pub fn list(&self, kind: Option<ClientType>, ids: &[u64]) -> Vec<ClientInfo> {
+        self.clients
+            .lock()
+            .unwrap()
+            .values()
+            .map(|client| &client.info)
+            .filter(|info| kind.is_none_or(|kind| kind == info.kind()))
+            .filter(|info| ids.is_empty() || ids.contains(&info.id))
+            .cloned()
+            .collect()
+    }
+
+    /// Disconnects the clients matching `filter`, returning how many there were.
+    pub fn kill(&self, filter: &KillFilter) -> usize {
+        let mut clients = self.clients.lock().unwrap();
+        let killed: Vec<u64> = clients
+            .values()
+            .filter(|client| filter.matches(&client.info))
+            .map(|client| client.info.id)
+            .collect();
+        for id in &killed {
+            if let Some(client) = clients.remove(id) {
+                client.kill.notify_one();
+            }
+        }
+        killed.len()
+    }
+
+    /// Holds back commands for `timeout`, only those that may write unless `all`. A pause
+    /// already in effect is only ever extended or made stricter.
+    pub fn pause(&self, timeout: Duration, all: bool) {
+        let until = Instant::now() + timeout;
+        let mut pause = self.pause.lock().unwrap();
+        *pause = Some(match *pause {
+            Some(current) if current.until > Instant::now() => Pause {
+                until: current.until.max(until),
+                all: current.all || all,
+            },
+            _ => Pause { until, all },
+        });
+    }
+
+    pub fn unpause(&self) {
+        *self.pause.lock().unwrap() = None;
+        self.unpaused.notify_waiters();
+    }
+
+    /// Waits for the pause holding back a command to end, `write` telling whether the
+    /// command may write.
+    pub async fn wait_unpaused(&self, write: bool) {
+        loop {
+            let unpaused = self.unpaused.notified();
+            let until = match *self.pause.lock().unwrap() {
+                Some(pause) if (pause.all || write) && pause.until > Instant::now() => pause.until,
+                _ => return,
+            };
+            tokio::select! {
+                _ = tokio::time::sleep_until(until.into()) => (),
+                _ = unpaused => (),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{sync::Arc, time::Duration};
+
+    use crate::{
+        commands::session::Session,
+        data_management::pubsub::{push_channel, OutputBufferLimits},
+    };
+
+    use super::{ClientTable, ClientType, KillFilter};
+
+    fn session(id: u64) -> Session {
+        let (push, _) = push_channel(id, OutputBufferLimits::default());
+        Session::with_push(push)
+    }
+
+    #[test]
+    fn should_list_and_kill_clients() {
+        let clients = Arc::<ClientTable>::default();
+        let mut first = session(1);
+        first.set_name(Some("worker".to_owned()));
+        let _first = clients.connect(&first, 3).unwrap();
+        let second = clients.connect(&session(2), 3).unwrap();
+        assert!(clients.connect(&session(3), 2).is_err());
+
+        let listed = clients.list(None, &[]);
+        assert_eq!(listed.len(), 2);
+        assert!(listed[0].to_string().contains(" name=worker "));
+        assert_eq!(clients.list(Some(ClientType::Pubsub), &[]).len(), 0);
+        assert_eq!(clients.list(None, &[2])[0].id(), 2);
+
+        let filter = KillFilter {
+            kind: Some(ClientType::Normal),
+            skip: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(clients.kill(&filter), 1);
+        assert!(futures::FutureExt::now_or_never(second.kill().notified()).is_some());
+        assert_eq!(clients.list(None, &[]).len(), 1);
+    }
+
+    #[test]
+    fn should_disconnect_clients_once_dropped() {
+        let clients = Arc::<ClientTable>::default();
+        let connected = clients.connect(&session(1), 1).unwrap();
+        assert_eq!(clients.len(), 1);
+        let panicked = std::thread::spawn(move || {
+            let _connected = connected;
+            panic!("connection task panicked");
+        });
+        assert!(panicked.join().is_err());
+        assert_eq!(clients.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn should_hold_back_writes_while_paused() {
+        let clients = ClientTable::default();
+        clients.pause(Duration::from_secs(10), false);
+        let read = tokio::time::timeout(Duration::from_millis(50), clients.wait_unpaused(false));
+        assert!(read.await.is_ok());
+        let write = tokio::time::timeout(Duration::from_millis(50), clients.wait_unpaused(true));
+        assert!(write.await.is_err());
+        clients.unpause();
+        let write = tokio::time::timeout(Duration::from_millis(50), clients.wait_unpaused(true));
+        assert!(write.await.is_ok());
+    }
+}
@@ -0,0 +1,224 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::{
+    acl::{
+        command_table::{commands_in, Categories, CATEGORY_NAMES},
+        Acl,
+    },
+    clients::{ClientTable, KillFilter},
+    errors::AppError,
+    resp::Resp,
+};
+
+use super::{
+    arguments::{bulk_string, check_arity, integer},
+    command_registry::CommandHandler,
+    session::Session,
+};
+
+pub const ACL_COMMAND_NAMES: [&str; 10] = [
+    "ACL SETUSER",
+    "ACL GETUSER",
+    "ACL DELUSER",
+    "ACL LIST",
+    "ACL USERS",
+    "ACL WHOAMI",
+    "ACL CAT",
+    "ACL LOG",
+    "ACL LOAD",
+    "ACL SAVE",
+];
+
+#[derive(Debug)]
+pub struct AclCommandHandler {
+    name: &'static str,
+    acl: Arc<Acl>,
+    /// Connections of deleted users, which get disconnected.
+    clients: Arc<ClientTable>,
+}
+
+impl AclCommandHandler {
+    pub fn new(name: &'static str, acl: Arc<Acl>, clients: Arc<ClientTable>) -> Self {
+        Self { name, acl, clients }
+    }
+}
+
+fn strings(args: &[Resp]) -> Result<Vec<String>, AppError> {
+    args.iter()
+        .map(|arg| Ok(String::from_utf8_lossy(&bulk_string(arg)?).into_owned()))
+        .collect()
+}
+
+fn bulk_strings(strings: Vec<impl AsRef<str>>) -> Resp {
+    Resp::Array(
+        strings
+            .iter()
+            .map(|string| Resp::bulk_string_from_str(string.as_ref()))
+            .collect(),
+    )
+}
+
+#[async_trait]
+impl CommandHandler for AclCommandHandler {
+    async fn handle(&self, session: &mut Session, args: &[Resp]) -> Result<Resp, AppError> {
+        let ok = || Resp::simple_string_from_str("OK");
+        let reply = match self.name {
+            "ACL SETUSER" => {
+                check_arity(self.name, args, 1, true)?;
+                let mut args = strings(args)?;
+                let rules = args.split_off(1);
+                self.acl.set_user(&args[0], &rules)?;
+                ok()
+            }
+            "ACL GETUSER" => {
+                check_arity(self.name, args, 1, false)?;
+                let name = String::from_utf8_lossy(&bulk_string(&args[0])?).into_owned();
+                self.acl.user_info(&name).unwrap_or(Resp::NullArray)
+            }
+            "ACL DELUSER" => {
+                check_arity(self.name, args, 1, true)?;
+                let names = strings(args)?;
+                let deleted = self.acl.delete_users(&names)?;
+                for user in &deleted {
+                    self.clients.kill(&KillFilter {
+                        user: Some(user.to_string()),
+                        ..Default::default()
+                    });
+                }
+                Resp::Integers(deleted.len() as i64)
+            }
+            "ACL LIST" => {
+                check_arity(self.name, args, 0, false)?;
+                bulk_strings(self.acl.describe())
+            }
+            "ACL USERS" => {
+                check_arity(self.name, args, 0, false)?;
+                bulk_strings(self.acl.usernames())
+            }
+            "ACL WHOAMI" => {
+                check_arity(self.name, args, 0, false)?;
+                Resp::bulk_string_from_str(session.user())
+            }
+            "ACL CAT" => match args {
+                [] => bulk_strings(CATEGORY_NAMES.to_vec()),
+                [category] => {
+                    let name = String::from_utf8_lossy(&bulk_string(category)?).into_owned();
+                    let category = Categories::named(&name)
+                        .ok_or(AppError::Generic(format!("Unknown category '{name}'")))?;
+                    bulk_strings(commands_in(category))
+                }
+                _ => return Err(AppError::Syntax),
+            },
+            "ACL LOG" => match args {
+                [] => self.acl.log(None),
+                [option] if bulk_string(option)?.eq_ignore_ascii_case(b"RESET") => {
+                    self.acl.reset_log();
+                    ok()
+                }
+                [count] => {
+                    let count: i64 = integer(count)?;
+                    let count = usize::try_from(count).map_err(|_| {
+                        AppError::Generic("value is out of range, must be positive".to_owned())
+                    })?;
+                    self.acl.log(Some(count))
+                }
+                _ => return Err(AppError::Syntax),
+            },
+            "ACL LOAD" => {
+                check_arity(self.name, args, 0, false)?;
+                self.acl.load()?;
+                ok()
+            }
+            "ACL SAVE" => {
+                check_arity(self.name, args, 0, false)?;
+                self.acl.save()?;
+                ok()
+            }
+            _ => return Err(AppError::UnknownCommand(self.name.to_owned())),
+        };
+        Ok(reply)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::{
+        acl::Acl,
+        clients::ClientTable,
+        commands::{command_registry::CommandHandler, session::Session, test_helpers::args},
+        data_management::pubsub::{push_channel, OutputBufferLimits},
+        resp::Resp,
+    };
+
+    use super::AclCommandHandler;
+
+    #[tokio::test]
+    async fn should_manage_users() {
+        let acl = Arc::new(Acl::default());
+        let clients = Arc::<ClientTable>::default();
+        let handler = |name| AclCommandHandler::new(name, acl.clone(), clients.clone());
+        let mut session = Session::default();
+        handler("ACL SETUSER")
+            .handle(&mut session, &args(&["alice", "on", "+@read", "~cache:*"]))
+            .await
+            .unwrap();
+        let list = handler("ACL LIST").handle(&mut session, &[]).await.unwrap();
+        assert_eq!(
+            list,
+            Resp::Array(vec![
+                Resp::bulk_string_from_str("user alice on ~cache:* resetchannels -@all +@read"),
+                Resp::bulk_string_from_str("user default on nopass ~* &* +@all"),
+            ])
+        );
+        let Resp::Array(info) = handler("ACL GETUSER")
+            .handle(&mut session, &args(&["alice"]))
+            .await
+            .unwrap()
+        else {
+            panic!()
+        };
+        assert_eq!(info[5], Resp::bulk_string_from_str("-@all +@read"));
+        let deleted = handler("ACL DELUSER")
+            .handle(&mut session, &args(&["alice", "bob"]))
+            .await
+            .unwrap();
+        assert_eq!(deleted, Resp::Integers(1));
+        let err = handler("ACL DELUSER")
+            .handle(&mut session, &args(&["default"]))
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "ERR The 'default' user cannot be removed");
+    }
+
+    #[tokio::test]
+    async fn should_disconnect_clients_of_deleted_users() {
+        let acl = Arc::new(Acl::default());
+        let clients = Arc::<ClientTable>::default();
+        let handler = |name| AclCommandHandler::new(name, acl.clone(), clients.clone());
+        let session = |id, user: &str| {
+            let (push, _) = push_channel(id, OutputBufferLimits::default());
+            let mut session = Session::with_push(push);
+            session.set_user(user.to_owned());
+            session
+        };
+        let mut admin = session(1, "default");
+        handler("ACL SETUSER")
+            .handle(&mut admin, &args(&["alice", "on", "nopass", "+@all"]))
+            .await
+            .unwrap();
+        let _admin = clients.connect(&admin, 2).unwrap();
+        let alice = clients.connect(&session(2, "alice"), 2).unwrap();
+
+        handler("ACL DELUSER")
+            .handle(&mut admin, &args(&["alice"]))
+            .await
+            .unwrap();
+        assert!(futures::FutureExt::now_or_never(alice.kill().notified()).is_some());
+        assert_eq!(clients.list(None, &[])[0].id(), 1);
+        assert_eq!(clients.len(), 1);
+    }
+}
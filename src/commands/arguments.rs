@@ -0,0 +1,140 @@
+use std::str::FromStr;
+
+use crate::{
+    data_management::dict::ScanArgs, errors::AppError, helpers::number::parse_float, resp::Resp,
+};
+
+pub fn bulk_string(arg: &Resp) -> Result<Vec<u8>, AppError> {
+    match arg {
+        Resp::BulkString(bytes) => Ok(bytes.to_owned()),
+        _ => Err(AppError::InvalidArgType("bulk string".to_owned())),
+    }
+}
+
+/// Keys are stored under their serialized RESP representation.
+pub fn key(arg: &Resp) -> Result<Vec<u8>, AppError> {
+    if !arg.is_bulk_string() {
+        return Err(AppError::InvalidArgType("bulk string".to_owned()));
+    }
+    Ok(arg.to_owned().serialize()?)
+}
+
+pub fn keyword(arg: &Resp) -> Result<String, AppError> {
+    let bytes = bulk_string(arg)?;
+    Ok(String::from_utf8_lossy(&bytes).to_uppercase())
+}
+
+pub fn integer<T: FromStr>(arg: &Resp) -> Result<T, AppError> {
+    let bytes = bulk_string(arg)?;
+    std::str::from_utf8(&bytes)
+        .ok()
+        .and_then(|int| int.parse().ok())
+        .ok_or(AppError::NotAnInteger)
+}
+
+pub fn float(arg: &Resp) -> Result<f64, AppError> {
+    let bytes = bulk_string(arg)?;
+    parse_float(&bytes).ok_or(AppError::NotAFloat)
+}
+
+pub fn check_arity(
+    command: &str,
+    args: &[Resp],
+    expected: usize,
+    at_least: bool,
+) -> Result<(), AppError> {
+    let valid = if at_least {
+        args.len() >= expected
+    } else {
+        args.len() == expected
+    };
+    if !valid {
+        return Err(AppError::InvalidArgLength(
+            command.to_owned(),
+            expected.to_string(),
+            args.len().to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// `cursor [MATCH pattern] [COUNT count]` of the `SCAN` family, handing any other option
+/// to `extra` which returns false when it does not know it either.
+pub fn scan_args<'a>(
+    cursor: &Resp,
+    options: &'a [Resp],
+    mut extra: impl FnMut(&str, &mut std::slice::Iter<'a, Resp>) -> Result<bool, AppError>,
+) -> Result<ScanArgs, AppError> {
+    let mut scan = ScanArgs {
+        cursor: integer(cursor).map_err(|_| AppError::Generic("invalid cursor".to_owned()))?,
+        ..Default::default()
+    };
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        match keyword(option)?.as_str() {
+            "MATCH" => scan.pattern = Some(bulk_string(options.next().ok_or(AppError::Syntax)?)?),
+            "COUNT" => {
+                scan.count = integer(options.next().ok_or(AppError::Syntax)?)?;
+                if scan.count < 1 {
+                    return Err(AppError::Syntax);
+                }
+            }
+            option => {
+                if !extra(option, &mut options)? {
+                    return Err(AppError::Syntax);
+                }
+            }
+        }
+    }
+    Ok(scan)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_parse_integer_argument() {
+        assert_eq!(
+            integer::<i64>(&Resp::bulk_string_from_str("-42")).unwrap(),
+            -42
+        );
+        assert!(integer::<i64>(&Resp::bulk_string_from_str("4.2")).is_err());
+    }
+
+    #[test]
+    fn should_parse_float_argument() {
+        assert_eq!(float(&Resp::bulk_string_from_str("1.5")).unwrap(), 1.5);
+        assert_eq!(
+            float(&Resp::bulk_string_from_str("-inf")).unwrap(),
+            f64::NEG_INFINITY
+        );
+        assert!(float(&Resp::bulk_string_from_str("nan")).is_err());
+    }
+
+    #[test]
+    fn should_parse_scan_options() {
+        let options = [
+            Resp::bulk_string_from_str("count"),
+            Resp::bulk_string_from_str("5"),
+            Resp::bulk_string_from_str("NOVALUES"),
+        ];
+        let mut novalues = false;
+        let scan = scan_args(&Resp::bulk_string_from_str("12"), &options, |option, _| {
+            novalues = option == "NOVALUES";
+            Ok(novalues)
+        })
+        .unwrap();
+        assert_eq!((scan.cursor, scan.count, novalues), (12, 5, true));
+        let result = scan_args(&Resp::bulk_string_from_str("-1"), &[], |_, _| Ok(false));
+        assert_eq!(result.unwrap_err().to_string(), "ERR invalid cursor");
+    }
+
+    #[test]
+    fn should_check_arity() {
+        let args = [Resp::bulk_string_from_str("key")];
+        assert!(check_arity("HGET", &args, 2, false).is_err());
+        assert!(check_arity("HLEN", &args, 1, false).is_ok());
+        assert!(check_arity("HDEL", &args, 1, true).is_ok());
+    }
+}
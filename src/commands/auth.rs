@@ -0,0 +1,127 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::{
+    acl::{Acl, DEFAULT_USER},
+    errors::AppError,
+    resp::Resp,
+};
+
+use super::{
+    arguments::{bulk_string, check_arity},
+    command_registry::CommandHandler,
+    session::Session,
+};
+
+pub const AUTH_COMMAND_NAME: &str = "AUTH";
+
+/// `AUTH [username] password`, `AUTH <password>` logging in as the default user.
+#[derive(Debug)]
+pub struct AuthCommand {
+    acl: Arc<Acl>,
+}
+
+impl AuthCommand {
+    pub fn new(acl: Arc<Acl>) -> Self {
+        Self { acl }
+    }
+}
+
+/// Logs the session in as `user` if the password matches, for `AUTH` and `HELLO AUTH`.
+pub fn log_in(
+    acl: &Acl,
+    session: &mut Session,
+    user: &Resp,
+    password: &Resp,
+) -> Result<(), AppError> {
+    let user = String::from_utf8_lossy(&bulk_string(user)?).into_owned();
+    acl.authenticate(session, &user, &bulk_string(password)?)?;
+    session.set_user(user);
+    session.set_authenticated(true);
+    Ok(())
+}
+
+#[async_trait]
+impl CommandHandler for AuthCommand {
+    async fn handle(&self, session: &mut Session, args: &[Resp]) -> Result<Resp, AppError> {
+        check_arity(AUTH_COMMAND_NAME, args, 1, true)?;
+        match args {
+            [_] if self.acl.default_nopass() => {
+                return Err(AppError::Generic(
+                    "AUTH <password> called without any password configured for the default user. Are you sure your configuration is correct?".to_owned(),
+                ));
+            }
+            [password] => log_in(
+                &self.acl,
+                session,
+                &Resp::bulk_string_from_str(DEFAULT_USER),
+                password,
+            )?,
+            [user, password] => log_in(&self.acl, session, user, password)?,
+            _ => return Err(AppError::Syntax),
+        }
+        Ok(Resp::simple_string_from_str("OK"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::{
+        acl::Acl,
+        commands::{command_registry::CommandHandler, session::Session, test_helpers::args},
+        resp::Resp,
+    };
+
+    use super::AuthCommand;
+
+    #[tokio::test]
+    async fn should_authenticate_with_requirepass() {
+        let handler = AuthCommand::new(Arc::new(Acl::new(Some("secret"), None)));
+        let mut session = Session::default();
+        session.set_authenticated(false);
+        let err = handler
+            .handle(&mut session, &args(&["wrong"]))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "WRONGPASS invalid username-password pair or user is disabled."
+        );
+        assert!(handler
+            .handle(&mut session, &args(&["other", "secret"]))
+            .await
+            .is_err());
+        assert!(!session.authenticated());
+        let reply = handler
+            .handle(&mut session, &args(&["default", "secret"]))
+            .await
+            .unwrap();
+        assert_eq!(reply, Resp::simple_string_from_str("OK"));
+        assert!(session.authenticated());
+
+        let err = AuthCommand::new(Arc::default())
+            .handle(&mut session, &args(&["secret"]))
+            .await
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("ERR AUTH <password> called without"));
+    }
+
+    #[tokio::test]
+    async fn should_log_in_as_acl_user() {
+        let acl = Arc::new(Acl::default());
+        acl.set_user("alice", &["on".to_owned(), ">pw".to_owned()])
+            .unwrap();
+        let handler = AuthCommand::new(acl);
+        let mut session = Session::default();
+        handler
+            .handle(&mut session, &args(&["alice", "pw"]))
+            .await
+            .unwrap();
+        assert_eq!(session.user(), "alice");
+    }
+}
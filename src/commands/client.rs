@@ -0,0 +1,520 @@
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use tokio::sync::mpsc::Sender;
+
+use crate::{
+    clients::{ClientTable, ClientType, KillFilter},
+    data_management::{
+        message::{send_message, DataChannelMessage, DataRequest, TrackingMessage},
+        tracking::{TrackingCommand, TrackingMode, TrackingOptions},
+    },
+    errors::AppError,
+    resp::Resp,
+};
+
+use super::{
+    arguments::{bulk_string, check_arity, integer, keyword},
+    command_registry::CommandHandler,
+    session::{ReplyMode, Session},
+};
+
+pub const CLIENT_COMMAND_NAMES: [&str; 5] = [
+    "CLIENT ID",
+    "CLIENT TRACKING",
+    "CLIENT CACHING",
+    "CLIENT GETREDIR",
+    "CLIENT TRACKINGINFO",
+];
+
+#[derive(Debug)]
+pub struct ClientCommandHandler {
+    name: &'static str,
+    data_sender: Arc<Sender<DataChannelMessage>>,
+}
+
+impl ClientCommandHandler {
+    pub fn new(name: &'static str, data_sender: Arc<Sender<DataChannelMessage>>) -> Self {
+        Self { name, data_sender }
+    }
+
+    /// `ON|OFF [REDIRECT id] [PREFIX prefix ...] [BCAST] [OPTIN] [OPTOUT] [NOLOOP]`, the
+    /// options being `None` for `OFF`.
+    fn tracking_options(args: &[Resp]) -> Result<Option<TrackingOptions>, AppError> {
+        let on = match keyword(&args[0])?.as_str() {
+            "ON" => true,
+            "OFF" => false,
+            _ => return Err(AppError::Syntax),
+        };
+        let mut options = TrackingOptions::default();
+        let (mut optin, mut optout) = (false, false);
+        let mut args = args[1..].iter();
+        while let Some(arg) = args.next() {
+            match keyword(arg)?.as_str() {
+                "REDIRECT" => {
+                    let id = integer(args.next().ok_or(AppError::Syntax)?)?;
+                    options.redirect = Some(id);
+                }
+                "PREFIX" => {
+                    let prefix = bulk_string(args.next().ok_or(AppError::Syntax)?)?;
+                    options.prefixes.push(prefix);
+                }
+                "BCAST" => options.bcast = true,
+                "OPTIN" => optin = true,
+                "OPTOUT" => optout = true,
+                "NOLOOP" => options.noloop = true,
+                _ => return Err(AppError::Syntax),
+            }
+        }
+        if !on {
+            return Ok(None);
+        }
+        if !options.prefixes.is_empty() && !options.bcast {
+            return Err(AppError::Generic(
+                "PREFIX option requires BCAST mode to be enabled".to_owned(),
+            ));
+        }
+        options.mode = match (optin, optout) {
+            (true, true) => {
+                return Err(AppError::Generic(
+                    "You can't use both OPTIN and OPTOUT".to_owned(),
+                ))
+            }
+            (true, false) => TrackingMode::OptIn,
+            (false, true) => TrackingMode::OptOut,
+            (false, false) => TrackingMode::Always,
+        };
+        if options.bcast && options.mode != TrackingMode::Always {
+            return Err(AppError::Generic(
+                "OPTIN and OPTOUT are not compatible with BCAST".to_owned(),
+            ));
+        }
+        Ok(Some(options))
+    }
+
+    async fn tracking(&self, session: &mut Session, args: &[Resp]) -> Result<Resp, AppError> {
+        check_arity(self.name, args, 1, true)?;
+        let options = Self::tracking_options(args)?;
+        if let (Some(current), Some(options)) = (session.tracking(), &options) {
+            if current.bcast != options.bcast || current.mode != options.mode {
+                return Err(AppError::Generic(
+                    "You can't switch tracking mode before disabling tracking for this client, and then re-enabling it with a different mode.".to_owned(),
+                ));
+            }
+        }
+        let client = session.push().cloned().ok_or(AppError::Generic(
+            "client tracking is not available".to_owned(),
+        ))?;
+        let command = match &options {
+            Some(options) => TrackingCommand::On {
+                client,
+                resp3: session.resp3(),
+                options: options.clone(),
+            },
+            None => TrackingCommand::Off(client.id()),
+        };
+        let reply = send_message(&self.data_sender, session.db, |sender| {
+            DataRequest::Tracking(TrackingMessage::new(command, sender))
+        })
+        .await?;
+        if let Resp::SimpleString(_) = reply {
+            session.set_tracking(options);
+        }
+        Ok(reply)
+    }
+
+    fn caching(session: &mut Session, args: &[Resp]) -> Result<Resp, AppError> {
+        let mode = session.tracking().map(|tracking| tracking.mode);
+        match (keyword(&args[0])?.as_str(), mode) {
+            ("YES", Some(TrackingMode::OptIn)) => session.set_caching(true),
+            ("NO", Some(TrackingMode::OptOut)) => session.set_caching(false),
+            ("YES" | "NO", Some(TrackingMode::OptIn | TrackingMode::OptOut)) => {
+                return Err(AppError::Generic(
+                    "CLIENT CACHING YES is only valid in OPTIN mode, and NO in OPTOUT mode"
+                        .to_owned(),
+                ))
+            }
+            ("YES" | "NO", _) => {
+                return Err(AppError::Generic(
+                    "CLIENT CACHING can be called only when the client is in tracking mode with OPTIN or OPTOUT mode enabled".to_owned(),
+                ))
+            }
+            _ => return Err(AppError::Syntax),
+        }
+        Ok(Resp::simple_string_from_str("OK"))
+    }
+
+    fn tracking_info(session: &Session) -> Resp {
+        let mut flags = vec![];
+        let (redirect, prefixes) = match session.tracking() {
+            None => {
+                flags.push("off");
+                (-1, vec![])
+            }
+            Some(tracking) => {
+                flags.push("on");
+                if tracking.bcast {
+                    flags.push("bcast");
+                }
+                match tracking.mode {
+                    TrackingMode::OptIn => flags.push("optin"),
+                    TrackingMode::OptOut => flags.push("optout"),
+                    TrackingMode::Always => (),
+                }
+                match session.caching() {
+                    Some(true) => flags.push("caching-yes"),
+                    Some(false) => flags.push("caching-no"),
+                    None => (),
+                }
+                if tracking.noloop {
+                    flags.push("noloop");
+                }
+                let redirect = tracking.redirect.map_or(-1, |id| id as i64);
+                (redirect, tracking.prefixes.clone())
+            }
+        };
+        Resp::Array(vec![
+            Resp::bulk_string_from_str("flags"),
+            Resp::Array(flags.into_iter().map(Resp::bulk_string_from_str).collect()),
+            Resp::bulk_string_from_str("redirect"),
+            Resp::Integers(redirect),
+            Resp::bulk_string_from_str("prefixes"),
+            Resp::Array(prefixes.into_iter().map(Resp::BulkString).collect()),
+        ])
+    }
+}
+
+#[async_trait]
+impl CommandHandler for ClientCommandHandler {
+    async fn handle(&self, session: &mut Session, args: &[Resp]) -> Result<Resp, AppError> {
+        match self.name {
+            "CLIENT TRACKING" => self.tracking(session, args).await,
+            "CLIENT CACHING" => {
+                check_arity(self.name, args, 1, false)?;
+                Self::caching(session, args)
+            }
+            "CLIENT ID" => {
+                check_arity(self.name, args, 0, false)?;
+                Ok(Resp::Integers(session.id() as i64))
+            }
+            "CLIENT GETREDIR" => {
+                check_arity(self.name, args, 0, false)?;
+                let redirect = session
+                    .tracking()
+                    .map_or(-1, |tracking| tracking.redirect.unwrap_or(0) as i64);
+                Ok(Resp::Integers(redirect))
+            }
+            "CLIENT TRACKINGINFO" => {
+                check_arity(self.name, args, 0, false)?;
+                Ok(Self::tracking_info(session))
+            }
+            _ => Err(AppError::UnknownCommand(self.name.to_owned())),
+        }
+    }
+}
+
+pub const CLIENT_CONNECTION_COMMAND_NAMES: [&str; 9] = [
+    "CLIENT INFO",
+    "CLIENT LIST",
+    "CLIENT KILL",
+    "CLIENT SETNAME",
+    "CLIENT GETNAME",
+    "CLIENT PAUSE",
+    "CLIENT UNPAUSE",
+    "CLIENT NO-EVICT",
+    "CLIENT REPLY",
+];
+
+/// `CLIENT` subcommands about the connections themselves, going through the client table.
+#[derive(Debug)]
+pub struct ClientConnectionCommandHandler {
+    name: &'static str,
+    clients: Arc<ClientTable>,
+}
+
+impl ClientConnectionCommandHandler {
+    pub fn new(name: &'static str, clients: Arc<ClientTable>) -> Self {
+        Self { name, clients }
+    }
+
+    fn lines(lines: impl IntoIterator<Item = String>) -> Resp {
+        let lines: String = lines.into_iter().map(|line| line + "\n").collect();
+        Resp::bulk_string_from_str(&lines)
+    }
+
+    /// `[TYPE type] [ID id ...]`
+    fn list(&self, args: &[Resp]) -> Result<Resp, AppError> {
+        let (mut kind, mut ids) = (None, vec![]);
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match keyword(arg)?.as_str() {
+                "TYPE" => {
+                    let name = bulk_string(args.next().ok_or(AppError::Syntax)?)?;
+                    kind = Some(ClientType::parse(&String::from_utf8_lossy(&name))?);
+                }
+                "ID" => {
+                    for id in args.by_ref() {
+                        ids.push(
+                            integer(id)
+                                .map_err(|_| AppError::Generic("Invalid client ID".to_owned()))?,
+                        );
+                    }
+                    if ids.is_empty() {
+                        return Err(AppError::Syntax);
+                    }
+                }
+                _ => return Err(AppError::Syntax),
+            }
+        }
+        let clients = self.clients.list(kind, &ids);
+        Ok(Self::lines(clients.iter().map(ToString::to_string)))
+    }
+
+    /// `addr:port`, or `[ID id] [ADDR addr] [LADDR addr] [USER user] [TYPE type]
+    /// [MAXAGE seconds] [SKIPME yes|no]` replying how many clients were killed.
+    fn kill(&self, session: &Session, args: &[Resp]) -> Result<Resp, AppError> {
+        if let [addr] = args {
+            let filter = KillFilter {
+                addr: Some(String::from_utf8_lossy(&bulk_string(addr)?).into_owned()),
+                ..Default::default()
+            };
+            return match self.clients.kill(&filter) {
+                0 => Err(AppError::Generic("No such client".to_owned())),
+                _ => Ok(Resp::simple_string_from_str("OK")),
+            };
+        }
+        if !args.len().is_multiple_of(2) {
+            return Err(AppError::Syntax);
+        }
+        let mut filter = KillFilter {
+            skip: Some(session.id()),
+            ..Default::default()
+        };
+        for pair in args.chunks(2) {
+            let value = String::from_utf8_lossy(&bulk_string(&pair[1])?).into_owned();
+            match keyword(&pair[0])?.as_str() {
+                "ID" => {
+                    let id = value.parse().map_err(|_| {
+                        AppError::Generic("client-id should be greater than 0".to_owned())
+                    })?;
+                    filter.id = Some(id);
+                }
+                "ADDR" => filter.addr = Some(value),
+                "LADDR" => filter.laddr = Some(value),
+                "USER" => filter.user = Some(value),
+                "TYPE" => filter.kind = Some(ClientType::parse(&value)?),
+                "MAXAGE" => filter.maxage = Some(integer(&pair[1])?),
+                "SKIPME" => {
+                    filter.skip = match value.to_lowercase().as_str() {
+                        "yes" => Some(session.id()),
+                        "no" => None,
+                        _ => return Err(AppError::Syntax),
+                    }
+                }
+                _ => return Err(AppError::Syntax),
+            }
+        }
+        Ok(Resp::Integers(self.clients.kill(&filter) as i64))
+    }
+
+    fn set_name(session: &mut Session, name: &Resp) -> Result<Resp, AppError> {
+        let name = bulk_string(name)?;
+        if name.iter().any(|byte| !(b'!'..=b'~').contains(byte)) {
+            return Err(AppError::Generic(
+                "Client names cannot contain spaces, newlines or special characters.".to_owned(),
+            ));
+        }
+        let name = String::from_utf8_lossy(&name).into_owned();
+        session.set_name(Some(name).filter(|name| !name.is_empty()));
+        Ok(Resp::simple_string_from_str("OK"))
+    }
+
+    /// `timeout [WRITE|ALL]`, the timeout being in milliseconds.
+    fn pause(&self, args: &[Resp]) -> Result<Resp, AppError> {
+        let timeout: u64 = integer(&args[0]).map_err(|_| {
+            AppError::Generic("timeout is not an integer or out of range".to_owned())
+        })?;
+        let all = match args.get(1).map(keyword).transpose()?.as_deref() {
+            None | Some("ALL") => true,
+            Some("WRITE") => false,
+            Some(_) => return Err(AppError::Syntax),
+        };
+        if args.len() > 2 {
+            return Err(AppError::Syntax);
+        }
+        self.clients.pause(Duration::from_millis(timeout), all);
+        Ok(Resp::simple_string_from_str("OK"))
+    }
+}
+
+#[async_trait]
+impl CommandHandler for ClientConnectionCommandHandler {
+    async fn handle(&self, session: &mut Session, args: &[Resp]) -> Result<Resp, AppError> {
+        let ok = || Resp::simple_string_from_str("OK");
+        match self.name {
+            "CLIENT INFO" => {
+                check_arity(self.name, args, 0, false)?;
+                Ok(Self::lines([session.client_info()]))
+            }
+            "CLIENT LIST" => {
+                self.clients.refresh(session);
+                self.list(args)
+            }
+            "CLIENT KILL" => {
+                check_arity(self.name, args, 1, true)?;
+                self.kill(session, args)
+            }
+            "CLIENT SETNAME" => {
+                check_arity(self.name, args, 1, false)?;
+                Self::set_name(session, &args[0])
+            }
+            "CLIENT GETNAME" => {
+                check_arity(self.name, args, 0, false)?;
+                Ok(session
+                    .name()
+                    .map_or(Resp::null_bulk_string(), Resp::bulk_string_from_str))
+            }
+            "CLIENT PAUSE" => {
+                check_arity(self.name, args, 1, true)?;
+                self.pause(args)
+            }
+            "CLIENT UNPAUSE" => {
+                check_arity(self.name, args, 0, false)?;
+                self.clients.unpause();
+                Ok(ok())
+            }
+            "CLIENT NO-EVICT" => {
+                check_arity(self.name, args, 1, false)?;
+                match keyword(&args[0])?.as_str() {
+                    "ON" => session.set_no_evict(true),
+                    "OFF" => session.set_no_evict(false),
+                    _ => return Err(AppError::Syntax),
+                }
+                Ok(ok())
+            }
+            "CLIENT REPLY" => {
+                check_arity(self.name, args, 1, false)?;
+                let mode = match keyword(&args[0])?.as_str() {
+                    "ON" => ReplyMode::On,
+                    "OFF" => ReplyMode::Off,
+                    "SKIP" => ReplyMode::Skip,
+                    _ => return Err(AppError::Syntax),
+                };
+                session.set_reply_mode(mode);
+                Ok(ok())
+            }
+            _ => Err(AppError::UnknownCommand(self.name.to_owned())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        commands::{command_registry::CommandHandler, session::Session, test_helpers::args},
+        data_management::{
+            message::{DataChannelMessage, DataRequest, ResponseChannelMessage},
+            pubsub::{push_channel, OutputBufferLimits},
+            tracking::{TrackingCommand, TrackingMode},
+        },
+        resp::Resp,
+    };
+
+    use super::{ClientCommandHandler, ClientConnectionCommandHandler};
+
+    #[tokio::test]
+    async fn should_enable_tracking_and_accept_caching_in_optin_mode() {
+        let (sender, mut receiver) = tokio::sync::mpsc::channel::<DataChannelMessage>(1000);
+        let task = tokio::spawn(async move {
+            let Some(DataRequest::Tracking(message)) =
+                receiver.recv().await.map(|message| message.request)
+            else {
+                panic!()
+            };
+            message
+                .sender
+                .send(ResponseChannelMessage(Resp::simple_string_from_str("OK")))
+                .unwrap();
+            message.command
+        });
+        let (push, _pushes) = push_channel(7, OutputBufferLimits::default());
+        let mut session = Session::with_push(push);
+        let tracking = ClientCommandHandler::new("CLIENT TRACKING", sender.clone().into());
+        tracking
+            .handle(&mut session, &args(&["on", "optin", "redirect", "3"]))
+            .await
+            .unwrap();
+        match task.await.unwrap() {
+            TrackingCommand::On {
+                client, options, ..
+            } => {
+                assert_eq!(client.id(), 7);
+                assert_eq!(options.redirect, Some(3));
+                assert_eq!(options.mode, TrackingMode::OptIn);
+            }
+            _ => panic!(),
+        }
+
+        let caching = ClientCommandHandler::new("CLIENT CACHING", sender.clone().into());
+        assert!(caching.handle(&mut session, &args(&["no"])).await.is_err());
+        caching.handle(&mut session, &args(&["yes"])).await.unwrap();
+        let getredir = ClientCommandHandler::new("CLIENT GETREDIR", sender.into());
+        let result = getredir.handle(&mut session, &[]).await;
+        assert_eq!(result.unwrap(), Resp::Integers(3));
+    }
+
+    #[tokio::test]
+    async fn should_reject_prefixes_without_bcast() {
+        let (sender, _receiver) = tokio::sync::mpsc::channel(1000);
+        let tracking = ClientCommandHandler::new("CLIENT TRACKING", sender.into());
+        let result = tracking
+            .handle(&mut Session::default(), &args(&["on", "prefix", "user:"]))
+            .await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "ERR PREFIX option requires BCAST mode to be enabled"
+        );
+    }
+
+    #[tokio::test]
+    async fn should_name_client_and_list_it() {
+        let clients = std::sync::Arc::<crate::clients::ClientTable>::default();
+        let handler = |name| ClientConnectionCommandHandler::new(name, clients.clone());
+        let (push, _pushes) = push_channel(4, OutputBufferLimits::default());
+        let mut session = Session::with_push(push);
+        let _connected = clients.connect(&session, 1).unwrap();
+        let setname = handler("CLIENT SETNAME");
+        assert!(setname.handle(&mut session, &args(&["a b"])).await.is_err());
+        setname
+            .handle(&mut session, &args(&["worker"]))
+            .await
+            .unwrap();
+        let name = handler("CLIENT GETNAME").handle(&mut session, &[]).await;
+        assert_eq!(name.unwrap(), Resp::bulk_string_from_str("worker"));
+
+        let Resp::BulkString(list) = handler("CLIENT LIST")
+            .handle(&mut session, &args(&["ID", "4"]))
+            .await
+            .unwrap()
+        else {
+            panic!()
+        };
+        let list = String::from_utf8(list).unwrap();
+        assert!(list.starts_with("id=4 "));
+        assert!(list.contains(" name=worker "));
+        assert!(list.ends_with('\n'));
+    }
+
+    #[tokio::test]
+    async fn should_list_no_client_as_empty_string() {
+        let clients = std::sync::Arc::<crate::clients::ClientTable>::default();
+        let (push, _pushes) = push_channel(4, OutputBufferLimits::default());
+        let mut session = Session::with_push(push);
+        let _connected = clients.connect(&session, 1).unwrap();
+        let list = ClientConnectionCommandHandler::new("CLIENT LIST", clients.clone())
+            .handle(&mut session, &args(&["TYPE", "pubsub"]))
+            .await
+            .unwrap();
+        assert_eq!(list.serialize().unwrap(), b"$0\r\n\r\n");
+    }
+}
@@ -1,58 +1,189 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
 use async_trait::async_trait;
 use mockall::automock;
 
-use crate::{errors::AppError, resp::Resp};
+use crate::{
+    acl::{command_table::command_spec, Acl},
+    errors::AppError,
+    resp::Resp,
+};
+
+use super::session::{queued, QueuedCommand, Session};
+
+/// Commands driving a transaction, run right away instead of being queued.
+const TRANSACTION_CONTROL: [&str; 4] = ["MULTI", "EXEC", "DISCARD", "WATCH"];
+
+/// Commands a connection subscribed to channels or patterns may still send.
+const SUBSCRIBED_COMMANDS: [&str; 7] = [
+    "SUBSCRIBE",
+    "UNSUBSCRIBE",
+    "PSUBSCRIBE",
+    "PUNSUBSCRIBE",
+    "SSUBSCRIBE",
+    "SUNSUBSCRIBE",
+    "PING",
+];
 
 #[async_trait]
 #[automock]
 pub trait CommandHandler: std::fmt::Debug + Send + Sync {
-    async fn handle(&self, args: &[Resp]) -> Result<Resp, AppError>;
+    async fn handle(&self, session: &mut Session, args: &[Resp]) -> Result<Resp, AppError>;
 }
 
 #[derive(Debug)]
 pub struct CommandRegistry {
     registry: HashMap<String, Box<dyn CommandHandler>>,
+    acl: Option<Arc<Acl>>,
+}
+
+impl Default for CommandRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl CommandRegistry {
     pub fn new() -> Self {
         let registry = HashMap::new();
-        Self { registry }
+        Self {
+            registry,
+            acl: None,
+        }
+    }
+
+    /// Checks every command against the session user's permissions before it runs.
+    pub fn with_acl(mut self, acl: Arc<Acl>) -> Self {
+        self.acl = Some(acl);
+        self
     }
 
     pub fn register(&mut self, arg: &str, command_handler: Box<dyn CommandHandler>) {
         self.registry.insert(arg.to_uppercase(), command_handler);
     }
 
-    pub fn command_handler(&self, arg: &str) -> Result<&Box<dyn CommandHandler>, AppError> {
+    pub fn command_handler(&self, arg: &str) -> Result<&dyn CommandHandler, AppError> {
         self.registry
             .get(arg.to_uppercase().as_str())
+            .map(|handler| handler.as_ref())
             .ok_or(AppError::UnknownCommand(arg.to_owned()))
     }
 
-    pub async fn command_with_args(&self, command: &str, args: &[Resp]) -> Result<Resp, AppError> {
-        let handler = self.command_handler(command)?;
-        handler.handle(args).await
+    /// Subcommand families (`XGROUP CREATE`, `CONFIG GET`, ...) are registered under
+    /// `"COMMAND SUBCOMMAND"` and tried before the bare command name.
+    pub async fn command_with_args(
+        &self,
+        session: &mut Session,
+        command: &str,
+        args: &[Resp],
+    ) -> Result<Resp, AppError> {
+        if let Some(Resp::BulkString(subcommand)) = args.first() {
+            let subcommand = String::from_utf8_lossy(subcommand);
+            let registered = format!("{command} {subcommand}");
+            if self.command_handler(&registered).is_ok() {
+                let acl_name = format!("{command}|{subcommand}");
+                return self
+                    .dispatch(session, command, &acl_name, &registered, &args[1..])
+                    .await;
+            }
+        }
+        self.dispatch(session, command, command, command, args)
+            .await
+    }
+
+    pub async fn no_args_command(
+        &self,
+        session: &mut Session,
+        command: &str,
+    ) -> Result<Resp, AppError> {
+        self.dispatch(session, command, command, command, &[]).await
+    }
+
+    /// Runs the handler registered under `registered`, or queues it with its arguments
+    /// when the session is in a transaction, to run when `EXEC` is. A command rejected
+    /// while queuing makes `EXEC` fail. Known commands are checked against the ACL under
+    /// `acl_name` (`"command|subcommand"` for families).
+    async fn dispatch(
+        &self,
+        session: &mut Session,
+        command: &str,
+        acl_name: &str,
+        registered: &str,
+        args: &[Resp],
+    ) -> Result<Resp, AppError> {
+        let name = command.to_uppercase();
+        if session.subscribed() && !SUBSCRIBED_COMMANDS.contains(&name.as_str()) {
+            return Err(AppError::Generic(format!(
+                "Can't execute '{}': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT / RESET are allowed in this context",
+                command.to_lowercase()
+            )));
+        }
+        let handler = self.command_handler(registered);
+        if let (Some(acl), Ok(_)) = (&self.acl, &handler) {
+            if let Err(err) = acl.check(session, acl_name, args) {
+                if session.in_transaction() {
+                    session.abort();
+                }
+                return Err(err);
+            }
+        }
+        if name == "EXEC" {
+            self.run_queued(session).await;
+        }
+        if !session.in_transaction() || TRANSACTION_CONTROL.contains(&name.as_str()) {
+            return handler?.handle(session, args).await;
+        }
+        if let Err(err) = handler {
+            session.abort();
+            return Err(err);
+        }
+        let argc = args.len() + if acl_name.contains('|') { 2 } else { 1 };
+        if command_spec(acl_name).is_some_and(|spec| !spec.accepts(argc)) {
+            session.abort();
+            return Err(AppError::WrongArity(acl_name.to_lowercase()));
+        }
+        session.queue_command(registered, args);
+        Ok(queued())
     }
 
-    pub async fn no_args_command(&self, command: &str) -> Result<Resp, AppError> {
-        let handler = self.command_handler(command)?;
-        handler.handle(&[]).await
+    /// Runs the commands queued by the transaction of `session`, in order, right before
+    /// `EXEC` ships them: those reaching the data worker queue their request, the others
+    /// have their reply, errors included, queued instead.
+    async fn run_queued(&self, session: &mut Session) {
+        for QueuedCommand { command, args } in session.take_queued_commands() {
+            let queued_before = session.queued();
+            let reply = match self.command_handler(&command) {
+                Ok(handler) => handler.handle(session, &args).await,
+                Err(err) => Err(err),
+            };
+            match reply {
+                Ok(_) if session.queued() > queued_before => (),
+                Ok(reply) => session.queue_reply(reply),
+                Err(err) => session.queue_reply(err.into()),
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::{CommandRegistry, MockCommandHandler};
+    use crate::{
+        commands::{
+            session::{queued, Session},
+            test_helpers::args,
+            transaction::{TransactionCommandHandler, TRANSACTION_COMMAND_NAMES},
+        },
+        data_management::message::{DataChannelMessage, DataRequest, ResponseChannelMessage},
+        resp::Resp,
+    };
 
     #[test]
     fn should_register_command() {
         let mut registry = CommandRegistry::new();
         let command_handler = MockCommandHandler::new();
         registry.register("PING", Box::new(command_handler));
-        assert!(registry.registry.get("PING").is_some())
+        assert!(registry.registry.contains_key("PING"))
     }
 
     #[test]
@@ -73,6 +204,98 @@ mod test {
         assert!(handler.is_err())
     }
 
+    #[tokio::test]
+    async fn should_dispatch_subcommand() {
+        let mut registry = CommandRegistry::new();
+        let mut command_handler = MockCommandHandler::new();
+        command_handler
+            .expect_handle()
+            .withf(|_, args| args == [Resp::bulk_string_from_str("dir")])
+            .returning(|_, _| Box::pin(async { Ok(Resp::Integers(1)) }));
+        registry.register("CONFIG GET", Box::new(command_handler));
+        let args = [
+            Resp::bulk_string_from_str("get"),
+            Resp::bulk_string_from_str("dir"),
+        ];
+        let result = registry
+            .command_with_args(&mut Session::default(), "config", &args)
+            .await;
+        assert_eq!(result.unwrap(), Resp::Integers(1))
+    }
+
+    #[tokio::test]
+    async fn should_run_queued_commands_on_exec_only() {
+        let (sender, mut receiver) = tokio::sync::mpsc::channel::<DataChannelMessage>(1000);
+        tokio::spawn(async move {
+            while let Some(message) = receiver.recv().await {
+                if let DataRequest::Exec(exec) = message.request {
+                    let reply = Resp::simple_string_from_str("OK");
+                    exec.sender.send(ResponseChannelMessage(reply)).unwrap();
+                }
+            }
+        });
+        let mut registry = CommandRegistry::new();
+        for name in TRANSACTION_COMMAND_NAMES {
+            let handler = TransactionCommandHandler::new(name, sender.clone().into());
+            registry.register(name, Box::new(handler));
+        }
+        let mut setname = MockCommandHandler::new();
+        setname
+            .expect_handle()
+            .withf(|_, args| args == [Resp::bulk_string_from_str("x")])
+            .times(1)
+            .returning(|_, _| Box::pin(async { Ok(Resp::simple_string_from_str("OK")) }));
+        registry.register("CLIENT SETNAME", Box::new(setname));
+        let mut session = Session::default();
+        let setname = args(&["setname", "x"]);
+
+        for last in ["DISCARD", "EXEC"] {
+            registry
+                .no_args_command(&mut session, "MULTI")
+                .await
+                .unwrap();
+            let result = registry
+                .command_with_args(&mut session, "CLIENT", &setname)
+                .await;
+            assert_eq!(result.unwrap(), queued());
+            assert_eq!(session.queued(), 1);
+            let result = registry.no_args_command(&mut session, last).await.unwrap();
+            if last == "EXEC" {
+                let ok = Resp::simple_string_from_str("OK");
+                assert_eq!(result, Resp::Array(vec![ok]));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn should_abort_transaction_on_wrong_arity() {
+        let (sender, _receiver) = tokio::sync::mpsc::channel::<DataChannelMessage>(1);
+        let mut registry = CommandRegistry::new();
+        for name in TRANSACTION_COMMAND_NAMES {
+            let handler = TransactionCommandHandler::new(name, sender.clone().into());
+            registry.register(name, Box::new(handler));
+        }
+        let mut set = MockCommandHandler::new();
+        set.expect_handle().never();
+        registry.register("SET", Box::new(set));
+        let mut session = Session::default();
+
+        registry
+            .no_args_command(&mut session, "MULTI")
+            .await
+            .unwrap();
+        let result = registry
+            .command_with_args(&mut session, "SET", &args(&["a"]))
+            .await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "ERR wrong number of arguments for 'set' command"
+        );
+        assert_eq!(session.queued(), 0);
+        let result = registry.no_args_command(&mut session, "EXEC").await;
+        assert!(result.unwrap_err().to_string().starts_with("EXECABORT"));
+    }
+
     #[test]
     fn shoudl_return_command_case_insensitive() {
         let mut registry = CommandRegistry::new();
@@ -0,0 +1,529 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::sync::mpsc::Sender;
+
+use crate::{
+    data_management::{
+        blocking::BlockingCommand,
+        consumer_group::{
+            ClaimOptions, ConsumerGroupCommand, GroupStreams, PendingFilter, ReadGroupFrom,
+        },
+        message::{BlockingMessage, DataChannelMessage, DataRequest, StreamMessage},
+        stream::{StreamCommand, StreamId},
+    },
+    errors::AppError,
+    resp::Resp,
+};
+
+use super::{
+    arguments::{bulk_string, check_arity, integer, key, keyword},
+    command_registry::CommandHandler,
+    session::Session,
+    stream::{block_timeout, range_bound, read_from, split_streams, stream_id},
+};
+
+pub const CONSUMER_GROUP_COMMAND_NAMES: [&str; 13] = [
+    "XGROUP CREATE",
+    "XGROUP SETID",
+    "XGROUP DESTROY",
+    "XGROUP CREATECONSUMER",
+    "XGROUP DELCONSUMER",
+    "XREADGROUP",
+    "XACK",
+    "XPENDING",
+    "XCLAIM",
+    "XAUTOCLAIM",
+    "XINFO STREAM",
+    "XINFO GROUPS",
+    "XINFO CONSUMERS",
+];
+
+#[derive(Debug)]
+pub struct ConsumerGroupCommandHandler {
+    name: &'static str,
+    data_sender: Arc<Sender<DataChannelMessage>>,
+}
+
+impl ConsumerGroupCommandHandler {
+    pub fn new(name: &'static str, data_sender: Arc<Sender<DataChannelMessage>>) -> Self {
+        Self { name, data_sender }
+    }
+
+    /// Trailing `ENTRIESREAD entries-read` of `XGROUP CREATE` and `XGROUP SETID`.
+    fn entries_read(options: &[Resp]) -> Result<Option<u64>, AppError> {
+        match options {
+            [] => Ok(None),
+            [option, value] if keyword(option)? == "ENTRIESREAD" => Ok(Some(integer(value)?)),
+            _ => Err(AppError::Syntax),
+        }
+    }
+
+    /// `XREADGROUP GROUP group consumer [COUNT count] [BLOCK milliseconds] [NOACK]
+    /// STREAMS key [key ...] id [id ...]`, returning the `BLOCK` timeout when the read
+    /// may block.
+    fn read_group(
+        &self,
+        args: &[Resp],
+    ) -> Result<(ConsumerGroupCommand, Option<Option<std::time::Duration>>), AppError> {
+        check_arity(self.name, args, 6, true)?;
+        if keyword(&args[0])? != "GROUP" {
+            return Err(AppError::Syntax);
+        }
+        let (group, consumer) = (bulk_string(&args[1])?, bulk_string(&args[2])?);
+        let (mut count, mut block, mut noack) = (None, None, false);
+        let mut position = 3;
+        loop {
+            let option = args.get(position).ok_or(AppError::Syntax)?;
+            let value = args.get(position + 1).ok_or(AppError::Syntax);
+            match keyword(option)?.as_str() {
+                "COUNT" => count = Some(integer::<usize>(value?)?),
+                "BLOCK" => block = Some(block_timeout(value?)?),
+                "NOACK" => {
+                    noack = true;
+                    position += 1;
+                    continue;
+                }
+                "STREAMS" => break,
+                _ => return Err(AppError::Syntax),
+            }
+            position += 2;
+        }
+        let (keys, ids) = split_streams(self.name, &args[position + 1..])?;
+        let streams: GroupStreams = keys
+            .iter()
+            .zip(ids)
+            .map(|(stream_key, id)| {
+                let from = match bulk_string(id)?.as_slice() {
+                    b">" => ReadGroupFrom::New,
+                    _ => ReadGroupFrom::History(stream_id(id, 0)?),
+                };
+                Ok((key(stream_key)?, from))
+            })
+            .collect::<Result<_, AppError>>()?;
+        // Replaying the pending history never blocks.
+        let only_new = streams.iter().all(|(_, from)| *from == ReadGroupFrom::New);
+        let command = ConsumerGroupCommand::ReadGroup {
+            group,
+            consumer,
+            streams,
+            count,
+            noack,
+        };
+        Ok((command, block.filter(|_| only_new)))
+    }
+
+    fn claim(&self, args: &[Resp]) -> Result<ConsumerGroupCommand, AppError> {
+        check_arity(self.name, args, 5, true)?;
+        let mut position = 4;
+        let mut ids = vec![];
+        while let Some(id) = args.get(position) {
+            let Ok(id) = stream_id(id, 0) else {
+                break;
+            };
+            ids.push(id);
+            position += 1;
+        }
+        if ids.is_empty() {
+            return Err(AppError::InvalidStreamId);
+        }
+        let mut options = ClaimOptions::default();
+        let mut rest = args[position..].iter();
+        while let Some(option) = rest.next() {
+            let mut value = || rest.next().ok_or(AppError::Syntax);
+            match keyword(option)?.as_str() {
+                "IDLE" => options.idle = Some(integer(value()?)?),
+                "TIME" => options.time = Some(integer(value()?)?),
+                "RETRYCOUNT" => options.retry_count = Some(integer(value()?)?),
+                "LASTID" => options.last_id = Some(stream_id(value()?, 0)?),
+                "FORCE" => options.force = true,
+                "JUSTID" => options.justid = true,
+                _ => return Err(AppError::Syntax),
+            }
+        }
+        Ok(ConsumerGroupCommand::Claim {
+            key: key(&args[0])?,
+            group: bulk_string(&args[1])?,
+            consumer: bulk_string(&args[2])?,
+            min_idle: integer(&args[3])?,
+            ids,
+            options,
+        })
+    }
+
+    fn auto_claim(&self, args: &[Resp]) -> Result<ConsumerGroupCommand, AppError> {
+        check_arity(self.name, args, 5, true)?;
+        let (mut count, mut justid) = (100, false);
+        let mut options = args[5..].iter();
+        while let Some(option) = options.next() {
+            match keyword(option)?.as_str() {
+                "COUNT" => {
+                    count = integer::<usize>(options.next().ok_or(AppError::Syntax)?)?;
+                    if count == 0 {
+                        return Err(AppError::Generic("COUNT must be > 0".to_owned()));
+                    }
+                }
+                "JUSTID" => justid = true,
+                _ => return Err(AppError::Syntax),
+            }
+        }
+        Ok(ConsumerGroupCommand::AutoClaim {
+            key: key(&args[0])?,
+            group: bulk_string(&args[1])?,
+            consumer: bulk_string(&args[2])?,
+            min_idle: integer(&args[3])?,
+            start: range_bound(&args[4], true)?,
+            count,
+            justid,
+        })
+    }
+
+    fn pending(&self, args: &[Resp]) -> Result<ConsumerGroupCommand, AppError> {
+        check_arity(self.name, args, 2, true)?;
+        let mut rest = &args[2..];
+        let mut min_idle = None;
+        if let Some(option) = rest.first() {
+            if keyword(option)? == "IDLE" {
+                min_idle = Some(integer(rest.get(1).ok_or(AppError::Syntax)?)?);
+                rest = &rest[2..];
+            }
+        }
+        let filter = match rest {
+            [] if min_idle.is_none() => None,
+            [start, end, count, consumer @ ..] if consumer.len() <= 1 => Some(PendingFilter {
+                min_idle,
+                start: range_bound(start, true)?,
+                end: range_bound(end, false)?,
+                count: integer::<i64>(count)?.max(0) as usize,
+                consumer: consumer.first().map(bulk_string).transpose()?,
+            }),
+            _ => return Err(AppError::Syntax),
+        };
+        Ok(ConsumerGroupCommand::Pending {
+            key: key(&args[0])?,
+            group: bulk_string(&args[1])?,
+            filter,
+        })
+    }
+
+    fn parse(&self, args: &[Resp]) -> Result<ConsumerGroupCommand, AppError> {
+        check_arity(self.name, args, 1, true)?;
+        let command = match self.name {
+            "XGROUP CREATE" => {
+                check_arity(self.name, args, 3, true)?;
+                let mkstream = args
+                    .get(3)
+                    .map(keyword)
+                    .transpose()?
+                    .is_some_and(|option| option == "MKSTREAM");
+                let options = &args[if mkstream { 4 } else { 3 }..];
+                ConsumerGroupCommand::Create {
+                    key: key(&args[0])?,
+                    group: bulk_string(&args[1])?,
+                    start: read_from(&args[2])?,
+                    mkstream,
+                    entries_read: Self::entries_read(options)?,
+                }
+            }
+            "XGROUP SETID" => {
+                check_arity(self.name, args, 3, true)?;
+                ConsumerGroupCommand::SetId {
+                    key: key(&args[0])?,
+                    group: bulk_string(&args[1])?,
+                    start: read_from(&args[2])?,
+                    entries_read: Self::entries_read(&args[3..])?,
+                }
+            }
+            "XGROUP DESTROY" => {
+                check_arity(self.name, args, 2, false)?;
+                ConsumerGroupCommand::Destroy {
+                    key: key(&args[0])?,
+                    group: bulk_string(&args[1])?,
+                }
+            }
+            "XGROUP CREATECONSUMER" | "XGROUP DELCONSUMER" => {
+                check_arity(self.name, args, 3, false)?;
+                let (key, group, consumer) = (
+                    key(&args[0])?,
+                    bulk_string(&args[1])?,
+                    bulk_string(&args[2])?,
+                );
+                if self.name == "XGROUP CREATECONSUMER" {
+                    ConsumerGroupCommand::CreateConsumer {
+                        key,
+                        group,
+                        consumer,
+                    }
+                } else {
+                    ConsumerGroupCommand::DelConsumer {
+                        key,
+                        group,
+                        consumer,
+                    }
+                }
+            }
+            "XACK" => {
+                check_arity(self.name, args, 3, true)?;
+                let ids = args[2..]
+                    .iter()
+                    .map(|id| stream_id(id, 0))
+                    .collect::<Result<Vec<StreamId>, _>>()?;
+                ConsumerGroupCommand::Ack {
+                    key: key(&args[0])?,
+                    group: bulk_string(&args[1])?,
+                    ids,
+                }
+            }
+            "XPENDING" => self.pending(args)?,
+            "XCLAIM" => self.claim(args)?,
+            "XAUTOCLAIM" => self.auto_claim(args)?,
+            "XINFO STREAM" => {
+                // `FULL [COUNT count]` is accepted but the summary form is always returned.
+                if args.len() > 1 && keyword(&args[1])? != "FULL" {
+                    return Err(AppError::Syntax);
+                }
+                ConsumerGroupCommand::InfoStream(key(&args[0])?)
+            }
+            "XINFO GROUPS" => {
+                check_arity(self.name, args, 1, false)?;
+                ConsumerGroupCommand::InfoGroups(key(&args[0])?)
+            }
+            "XINFO CONSUMERS" => {
+                check_arity(self.name, args, 2, false)?;
+                ConsumerGroupCommand::InfoConsumers {
+                    key: key(&args[0])?,
+                    group: bulk_string(&args[1])?,
+                }
+            }
+            _ => return Err(AppError::UnknownCommand(self.name.to_owned())),
+        };
+        Ok(command)
+    }
+}
+
+#[async_trait]
+impl CommandHandler for ConsumerGroupCommandHandler {
+    async fn handle(&self, session: &mut Session, args: &[Resp]) -> Result<Resp, AppError> {
+        let command = match self.name {
+            "XREADGROUP" => match self.read_group(args)? {
+                (
+                    ConsumerGroupCommand::ReadGroup {
+                        group,
+                        consumer,
+                        streams,
+                        count,
+                        noack,
+                    },
+                    Some(timeout),
+                ) => {
+                    let command = BlockingCommand::XReadGroup {
+                        group,
+                        consumer,
+                        streams,
+                        count,
+                        noack,
+                    };
+                    return session
+                        .send(&self.data_sender, |sender| {
+                            DataRequest::Blocking(BlockingMessage::new(command, timeout, sender))
+                        })
+                        .await;
+                }
+                (command, _) => command,
+            },
+            _ => self.parse(args)?,
+        };
+        session
+            .send(&self.data_sender, |sender| {
+                DataRequest::Stream(StreamMessage::new(StreamCommand::Group(command), sender))
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use crate::{
+        commands::{
+            command_registry::CommandHandler,
+            session::Session,
+            test_helpers::{args, key},
+        },
+        data_management::{
+            blocking::BlockingCommand,
+            consumer_group::{ClaimOptions, ConsumerGroupCommand, PendingFilter, ReadGroupFrom},
+            message::{DataRequest, ResponseChannelMessage},
+            stream::{ReadFrom, StreamCommand, StreamId},
+        },
+        resp::Resp,
+    };
+
+    use super::ConsumerGroupCommandHandler;
+
+    async fn parsed_command(name: &'static str, arguments: &[&str]) -> ConsumerGroupCommand {
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(1000);
+        let handler = ConsumerGroupCommandHandler::new(name, sender.into());
+        let task = tokio::spawn(async move {
+            match receiver.recv().await.map(|message| message.request) {
+                Some(DataRequest::Stream(message)) => {
+                    message
+                        .sender
+                        .send(ResponseChannelMessage(Resp::Integers(0)))
+                        .unwrap();
+                    match message.command {
+                        StreamCommand::Group(command) => command,
+                        _ => panic!(),
+                    }
+                }
+                _ => panic!(),
+            }
+        });
+        handler
+            .handle(&mut Session::default(), &args(arguments))
+            .await
+            .unwrap();
+        task.await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_parse_group_creation() {
+        let command = parsed_command(
+            "XGROUP CREATE",
+            &["events", "workers", "$", "MKSTREAM", "ENTRIESREAD", "3"],
+        )
+        .await;
+        let expected = ConsumerGroupCommand::Create {
+            key: key("events"),
+            group: b"workers".to_vec(),
+            start: ReadFrom::Last,
+            mkstream: true,
+            entries_read: Some(3),
+        };
+        assert_eq!(command, expected);
+    }
+
+    #[tokio::test]
+    async fn should_parse_history_read_without_blocking() {
+        let command = parsed_command(
+            "XREADGROUP",
+            &[
+                "GROUP", "workers", "alice", "BLOCK", "100", "NOACK", "STREAMS", "events", "0",
+            ],
+        )
+        .await;
+        let expected = ConsumerGroupCommand::ReadGroup {
+            group: b"workers".to_vec(),
+            consumer: b"alice".to_vec(),
+            streams: vec![(key("events"), ReadGroupFrom::History(StreamId::MIN))],
+            count: None,
+            noack: true,
+        };
+        assert_eq!(command, expected);
+    }
+
+    #[tokio::test]
+    async fn should_block_when_reading_new_entries() {
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(1000);
+        let handler = ConsumerGroupCommandHandler::new("XREADGROUP", sender.into());
+        let task = tokio::spawn(async move {
+            match receiver.recv().await.map(|message| message.request) {
+                Some(DataRequest::Blocking(message)) => {
+                    message
+                        .sender
+                        .send(ResponseChannelMessage(Resp::NullArray))
+                        .unwrap();
+                    (message.command, message.timeout)
+                }
+                _ => panic!(),
+            }
+        });
+        let arguments = args(&[
+            "GROUP", "workers", "alice", "COUNT", "2", "BLOCK", "50", "STREAMS", "events", ">",
+        ]);
+        assert_eq!(
+            handler
+                .handle(&mut Session::default(), &arguments)
+                .await
+                .unwrap(),
+            Resp::NullArray
+        );
+        let (command, timeout) = task.await.unwrap();
+        let expected = BlockingCommand::XReadGroup {
+            group: b"workers".to_vec(),
+            consumer: b"alice".to_vec(),
+            streams: vec![(key("events"), ReadGroupFrom::New)],
+            count: Some(2),
+            noack: false,
+        };
+        assert_eq!(command, expected);
+        assert_eq!(timeout, Some(Duration::from_millis(50)));
+    }
+
+    #[tokio::test]
+    async fn should_parse_extended_pending_form() {
+        let command = parsed_command(
+            "XPENDING",
+            &["events", "workers", "IDLE", "1000", "-", "+", "10", "alice"],
+        )
+        .await;
+        let expected = ConsumerGroupCommand::Pending {
+            key: key("events"),
+            group: b"workers".to_vec(),
+            filter: Some(PendingFilter {
+                min_idle: Some(1000),
+                start: StreamId::MIN,
+                end: StreamId::MAX,
+                count: 10,
+                consumer: Some(b"alice".to_vec()),
+            }),
+        };
+        assert_eq!(command, expected);
+    }
+
+    #[tokio::test]
+    async fn should_parse_claim_options_after_ids() {
+        let command = parsed_command(
+            "XCLAIM",
+            &[
+                "events",
+                "workers",
+                "bob",
+                "500",
+                "1-1",
+                "2-0",
+                "RETRYCOUNT",
+                "4",
+                "JUSTID",
+            ],
+        )
+        .await;
+        let expected = ConsumerGroupCommand::Claim {
+            key: key("events"),
+            group: b"workers".to_vec(),
+            consumer: b"bob".to_vec(),
+            min_idle: 500,
+            ids: vec![StreamId::new(1, 1), StreamId::new(2, 0)],
+            options: ClaimOptions {
+                retry_count: Some(4),
+                justid: true,
+                ..Default::default()
+            },
+        };
+        assert_eq!(command, expected);
+    }
+
+    #[tokio::test]
+    async fn should_throw_error_on_zero_autoclaim_count() {
+        let (sender, _) = tokio::sync::mpsc::channel(1000);
+        let handler = ConsumerGroupCommandHandler::new("XAUTOCLAIM", sender.into());
+        let result = handler
+            .handle(
+                &mut Session::default(),
+                &args(&["events", "workers", "bob", "0", "0", "COUNT", "0"]),
+            )
+            .await;
+        assert_eq!(result.unwrap_err().to_string(), "ERR COUNT must be > 0");
+    }
+}
@@ -0,0 +1,199 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::sync::mpsc::Sender;
+
+use crate::{
+    data_management::{
+        database::DatabaseCommand,
+        message::{DataChannelMessage, DataRequest, DatabaseMessage},
+    },
+    errors::AppError,
+    resp::Resp,
+};
+
+use super::{
+    arguments::{check_arity, integer, key, keyword},
+    command_registry::CommandHandler,
+    session::Session,
+};
+
+pub const DATABASE_COMMAND_NAMES: [&str; 7] = [
+    "SELECT", "SWAPDB", "FLUSHDB", "FLUSHALL", "DBSIZE", "COPY", "MOVE",
+];
+
+#[derive(Debug)]
+pub struct DatabaseCommandHandler {
+    name: &'static str,
+    data_sender: Arc<Sender<DataChannelMessage>>,
+    databases: usize,
+}
+
+impl DatabaseCommandHandler {
+    pub fn new(
+        name: &'static str,
+        data_sender: Arc<Sender<DataChannelMessage>>,
+        databases: usize,
+    ) -> Self {
+        Self {
+            name,
+            data_sender,
+            databases,
+        }
+    }
+
+    fn db_index(&self, arg: &Resp) -> Result<usize, AppError> {
+        let db = integer::<i64>(arg)?;
+        usize::try_from(db)
+            .ok()
+            .filter(|db| *db < self.databases)
+            .ok_or(AppError::Generic("DB index is out of range".to_owned()))
+    }
+
+    /// Optional `ASYNC | SYNC` of `FLUSHDB` and `FLUSHALL`, flushing synchronously by
+    /// default.
+    fn lazy_flush(&self, args: &[Resp]) -> Result<bool, AppError> {
+        match args {
+            [] => Ok(false),
+            [mode] => match keyword(mode)?.as_str() {
+                "ASYNC" => Ok(true),
+                "SYNC" => Ok(false),
+                _ => Err(AppError::Syntax),
+            },
+            _ => Err(AppError::Syntax),
+        }
+    }
+
+    /// `source destination [DB destination-db] [REPLACE]`.
+    fn copy(&self, args: &[Resp]) -> Result<DatabaseCommand, AppError> {
+        check_arity(self.name, args, 2, true)?;
+        let (mut db, mut replace) = (None, false);
+        let mut options = args[2..].iter();
+        while let Some(option) = options.next() {
+            match keyword(option)?.as_str() {
+                "DB" => db = Some(self.db_index(options.next().ok_or(AppError::Syntax)?)?),
+                "REPLACE" => replace = true,
+                _ => return Err(AppError::Syntax),
+            }
+        }
+        Ok(DatabaseCommand::Copy {
+            source: key(&args[0])?,
+            destination: key(&args[1])?,
+            db,
+            replace,
+        })
+    }
+
+    fn parse(&self, args: &[Resp]) -> Result<DatabaseCommand, AppError> {
+        let command = match self.name {
+            "SWAPDB" => {
+                check_arity(self.name, args, 2, false)?;
+                let index = |arg, position| {
+                    integer::<usize>(arg)
+                        .map_err(|_| AppError::Generic(format!("invalid {position} DB index")))
+                };
+                let (first, second) = (index(&args[0], "first")?, index(&args[1], "second")?);
+                DatabaseCommand::SwapDb(first, second)
+            }
+            "FLUSHDB" => DatabaseCommand::FlushDb {
+                lazy: self.lazy_flush(args)?,
+            },
+            "FLUSHALL" => DatabaseCommand::FlushAll {
+                lazy: self.lazy_flush(args)?,
+            },
+            "DBSIZE" => {
+                check_arity(self.name, args, 0, false)?;
+                DatabaseCommand::DbSize
+            }
+            "COPY" => self.copy(args)?,
+            "MOVE" => {
+                check_arity(self.name, args, 2, false)?;
+                DatabaseCommand::Move {
+                    key: key(&args[0])?,
+                    db: self.db_index(&args[1])?,
+                }
+            }
+            _ => return Err(AppError::UnknownCommand(self.name.to_owned())),
+        };
+        Ok(command)
+    }
+}
+
+#[async_trait]
+impl CommandHandler for DatabaseCommandHandler {
+    async fn handle(&self, session: &mut Session, args: &[Resp]) -> Result<Resp, AppError> {
+        if self.name == "SELECT" {
+            check_arity(self.name, args, 1, false)?;
+            session.db = self.db_index(&args[0])?;
+            return Ok(Resp::simple_string_from_str("OK"));
+        }
+        let command = self.parse(args)?;
+        session
+            .send(&self.data_sender, |sender| {
+                DataRequest::Database(DatabaseMessage::new(command, sender))
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        commands::{command_registry::CommandHandler, session::Session, test_helpers::args},
+        data_management::{
+            database::DatabaseCommand,
+            message::{DataChannelMessage, DataRequest, ResponseChannelMessage},
+        },
+        resp::Resp,
+    };
+
+    use super::DatabaseCommandHandler;
+
+    #[tokio::test]
+    async fn should_select_database_for_the_session() {
+        let (sender, _) = tokio::sync::mpsc::channel(1000);
+        let handler = DatabaseCommandHandler::new("SELECT", sender.into(), 16);
+        let mut session = Session::default();
+        let result = handler.handle(&mut session, &args(&["15"])).await;
+        assert_eq!(result.unwrap(), Resp::simple_string_from_str("OK"));
+        assert_eq!(session.db, 15);
+        let result = handler.handle(&mut session, &args(&["16"])).await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "ERR DB index is out of range"
+        );
+        assert_eq!(session.db, 15);
+    }
+
+    #[tokio::test]
+    async fn should_send_selected_database_with_the_command() {
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(1000);
+        let handler = DatabaseCommandHandler::new("FLUSHDB", sender.into(), 16);
+        let task = tokio::spawn(async move {
+            match receiver.recv().await {
+                Some(DataChannelMessage {
+                    db,
+                    request: DataRequest::Database(message),
+                    ..
+                }) => {
+                    message
+                        .sender
+                        .send(ResponseChannelMessage(Resp::Integers(0)))
+                        .unwrap();
+                    (db, message.command)
+                }
+                _ => panic!(),
+            }
+        });
+        let mut session = Session::default();
+        session.db = 3;
+        handler
+            .handle(&mut session, &args(&["async"]))
+            .await
+            .unwrap();
+        assert_eq!(
+            task.await.unwrap(),
+            (3, DatabaseCommand::FlushDb { lazy: true })
+        );
+    }
+}
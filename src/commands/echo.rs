@@ -2,7 +2,7 @@ use async_trait::async_trait;
 
 use crate::errors::AppError;
 
-use super::command_registry::CommandHandler;
+use super::{command_registry::CommandHandler, session::Session};
 
 pub const ECHO_COMMAND_NAME: &str = "ECHO";
 
@@ -11,6 +11,12 @@ pub struct EchoCommand {
     args: String,
 }
 
+impl Default for EchoCommand {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl EchoCommand {
     pub fn new() -> Self {
         Self {
@@ -21,7 +27,11 @@ impl EchoCommand {
 
 #[async_trait]
 impl CommandHandler for EchoCommand {
-    async fn handle(&self, args: &[crate::resp::Resp]) -> Result<crate::resp::Resp, AppError> {
+    async fn handle(
+        &self,
+        _session: &mut Session,
+        args: &[crate::resp::Resp],
+    ) -> Result<crate::resp::Resp, AppError> {
         if args.len() > 1 {
             return Err(AppError::InvalidArgLength(
                 ECHO_COMMAND_NAME.to_owned(),
@@ -35,7 +45,10 @@ impl CommandHandler for EchoCommand {
 
 #[cfg(test)]
 mod test {
-    use crate::{commands::command_registry::CommandHandler, resp::Resp};
+    use crate::{
+        commands::{command_registry::CommandHandler, session::Session},
+        resp::Resp,
+    };
 
     use super::EchoCommand;
 
@@ -43,7 +56,10 @@ mod test {
     async fn should_reply_to_echo() {
         let handler = EchoCommand::new();
         let result = handler
-            .handle(&[Resp::BulkString(b"HELLO WORLD".to_vec())])
+            .handle(
+                &mut Session::default(),
+                &[Resp::BulkString(b"HELLO WORLD".to_vec())],
+            )
             .await;
         assert_eq!(result.unwrap(), Resp::BulkString(b"HELLO WORLD".to_vec()))
     }
@@ -51,10 +67,13 @@ mod test {
     async fn should_throw_error_for_invalid_arg_lenght() {
         let handler = EchoCommand::new();
         let result = handler
-            .handle(&[
-                Resp::BulkString(b"HELLO WORLD".to_vec()),
-                Resp::BulkString(b"HELLO WORLD".to_vec()),
-            ])
+            .handle(
+                &mut Session::default(),
+                &[
+                    Resp::BulkString(b"HELLO WORLD".to_vec()),
+                    Resp::BulkString(b"HELLO WORLD".to_vec()),
+                ],
+            )
             .await;
         assert!(result.is_err())
     }
@@ -0,0 +1,185 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::sync::mpsc::Sender;
+
+use crate::{
+    data_management::{
+        function::{FunctionCommand, RestorePolicy},
+        message::{DataChannelMessage, DataRequest, FunctionMessage},
+    },
+    errors::AppError,
+    resp::Resp,
+};
+
+use super::{
+    arguments::{bulk_string, check_arity, keyword},
+    command_registry::CommandHandler,
+    scripting::keys_and_args,
+    session::Session,
+};
+
+pub const FUNCTION_COMMAND_NAMES: [&str; 8] = [
+    "FUNCTION LOAD",
+    "FUNCTION LIST",
+    "FUNCTION DELETE",
+    "FUNCTION FLUSH",
+    "FUNCTION DUMP",
+    "FUNCTION RESTORE",
+    "FCALL",
+    "FCALL_RO",
+];
+
+#[derive(Debug)]
+pub struct FunctionCommandHandler {
+    name: &'static str,
+    data_sender: Arc<Sender<DataChannelMessage>>,
+}
+
+impl FunctionCommandHandler {
+    pub fn new(name: &'static str, data_sender: Arc<Sender<DataChannelMessage>>) -> Self {
+        Self { name, data_sender }
+    }
+
+    /// `[LIBRARYNAME pattern] [WITHCODE]`
+    fn list(args: &[Resp]) -> Result<FunctionCommand, AppError> {
+        let (mut pattern, mut with_code) = (None, false);
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match keyword(arg)?.as_str() {
+                "LIBRARYNAME" if pattern.is_none() => {
+                    pattern = Some(bulk_string(args.next().ok_or(AppError::Syntax)?)?);
+                }
+                "WITHCODE" if !with_code => with_code = true,
+                _ => return Err(AppError::Syntax),
+            }
+        }
+        Ok(FunctionCommand::List { pattern, with_code })
+    }
+
+    fn parse(&self, args: &[Resp]) -> Result<FunctionCommand, AppError> {
+        let command = match self.name {
+            "FUNCTION LOAD" => {
+                check_arity(self.name, args, 1, true)?;
+                let replace = match args {
+                    [_] => false,
+                    [option, _] if keyword(option)? == "REPLACE" => true,
+                    _ => return Err(AppError::Syntax),
+                };
+                FunctionCommand::Load {
+                    code: bulk_string(&args[args.len() - 1])?,
+                    replace,
+                }
+            }
+            "FUNCTION LIST" => Self::list(args)?,
+            "FUNCTION DELETE" => {
+                check_arity(self.name, args, 1, false)?;
+                FunctionCommand::Delete(String::from_utf8_lossy(&bulk_string(&args[0])?).into())
+            }
+            "FUNCTION FLUSH" => {
+                // Flushing is cheap enough for `ASYNC` to run synchronously too.
+                match args {
+                    [] => (),
+                    [mode] if matches!(keyword(mode)?.as_str(), "ASYNC" | "SYNC") => (),
+                    _ => return Err(AppError::Syntax),
+                }
+                FunctionCommand::Flush
+            }
+            "FUNCTION DUMP" => {
+                check_arity(self.name, args, 0, false)?;
+                FunctionCommand::Dump
+            }
+            "FUNCTION RESTORE" => {
+                check_arity(self.name, args, 1, true)?;
+                let policy = match &args[1..] {
+                    [] => RestorePolicy::Append,
+                    [policy] => match keyword(policy)?.as_str() {
+                        "APPEND" => RestorePolicy::Append,
+                        "REPLACE" => RestorePolicy::Replace,
+                        "FLUSH" => RestorePolicy::Flush,
+                        _ => {
+                            return Err(AppError::Generic(
+                                "Wrong restore policy given, value should be either FLUSH, APPEND or REPLACE.".to_owned(),
+                            ))
+                        }
+                    },
+                    _ => return Err(AppError::Syntax),
+                };
+                FunctionCommand::Restore {
+                    payload: bulk_string(&args[0])?,
+                    policy,
+                }
+            }
+            "FCALL" | "FCALL_RO" => {
+                check_arity(self.name, args, 2, true)?;
+                let name = String::from_utf8_lossy(&bulk_string(&args[0])?).into();
+                let (keys, call_args) = keys_and_args(&args[1..])?;
+                FunctionCommand::Call {
+                    name,
+                    keys,
+                    args: call_args,
+                    read_only: self.name == "FCALL_RO",
+                }
+            }
+            _ => return Err(AppError::UnknownCommand(self.name.to_owned())),
+        };
+        Ok(command)
+    }
+}
+
+#[async_trait]
+impl CommandHandler for FunctionCommandHandler {
+    async fn handle(&self, session: &mut Session, args: &[Resp]) -> Result<Resp, AppError> {
+        let command = self.parse(args)?;
+        let user = session.user().to_owned();
+        session
+            .send(&self.data_sender, |sender| {
+                DataRequest::Function(FunctionMessage::new(command, user, sender))
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        commands::test_helpers::args,
+        data_management::function::{FunctionCommand, RestorePolicy},
+    };
+
+    use super::FunctionCommandHandler;
+
+    #[test]
+    fn should_parse_function_options() {
+        let (sender, _receiver) = tokio::sync::mpsc::channel(1);
+        let load = FunctionCommandHandler::new("FUNCTION LOAD", sender.clone().into());
+        let command = load.parse(&args(&["replace", "#!lua name=lib"])).unwrap();
+        assert!(matches!(
+            command,
+            FunctionCommand::Load { replace: true, .. }
+        ));
+        let restore = FunctionCommandHandler::new("FUNCTION RESTORE", sender.clone().into());
+        let command = restore.parse(&args(&["payload", "flush"])).unwrap();
+        assert!(matches!(
+            command,
+            FunctionCommand::Restore {
+                policy: RestorePolicy::Flush,
+                ..
+            }
+        ));
+        let fcall = FunctionCommandHandler::new("FCALL_RO", sender.into());
+        match fcall.parse(&args(&["peek", "1", "key"])).unwrap() {
+            FunctionCommand::Call {
+                name,
+                keys,
+                read_only,
+                ..
+            } => {
+                assert_eq!(name, "peek");
+                assert_eq!(keys, vec![b"key".to_vec()]);
+                assert!(read_only);
+            }
+            _ => panic!(),
+        }
+    }
+}
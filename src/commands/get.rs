@@ -1,16 +1,15 @@
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use futures::TryFutureExt;
 use tokio::sync::mpsc::Sender;
 
 use crate::{
-    data_management::message::{DataChannelMessage, GetMessage, MessageChannelError},
+    data_management::message::{DataChannelMessage, DataRequest, GetMessage},
     errors::AppError,
     resp::Resp,
 };
 
-use super::command_registry::CommandHandler;
+use super::{command_registry::CommandHandler, session::Session};
 
 pub const GET_COMMAND_NAME: &str = "GET";
 
@@ -29,8 +28,8 @@ impl GetCommandHandler {
 
 #[async_trait]
 impl CommandHandler for GetCommandHandler {
-    async fn handle(&self, args: &[Resp]) -> Result<Resp, AppError> {
-        if args.len() < 1 {
+    async fn handle(&self, session: &mut Session, args: &[Resp]) -> Result<Resp, AppError> {
+        if args.is_empty() {
             return Err(AppError::InvalidArgLength(
                 GET_COMMAND_NAME.to_owned(),
                 "1".to_owned(),
@@ -38,24 +37,20 @@ impl CommandHandler for GetCommandHandler {
             ));
         }
 
-        let (sender, receiver) = tokio::sync::oneshot::channel();
-        let message = GetMessage::new(args[0].to_owned().serialize()?, sender);
-
-        self.data_sender
-            .send(DataChannelMessage::Get(message))
-            .map_err(MessageChannelError::from)
-            .await?;
-
-        let reply = receiver.await.map_err(MessageChannelError::from)?;
-        Ok(reply.0)
+        let key = args[0].to_owned().serialize()?;
+        session
+            .send(&self.data_sender, |sender| {
+                DataRequest::Get(GetMessage::new(key, sender))
+            })
+            .await
     }
 }
 
 #[cfg(test)]
 mod test {
     use crate::{
-        commands::{command_registry::CommandHandler, get::GET_COMMAND_NAME},
-        data_management::message::{DataChannelMessage, ResponseChannelMessage},
+        commands::{command_registry::CommandHandler, get::GET_COMMAND_NAME, session::Session},
+        data_management::message::{DataRequest, ResponseChannelMessage},
         errors::AppError,
         resp::Resp,
     };
@@ -68,9 +63,9 @@ mod test {
         let handler = GetCommandHandler::new(sender.into());
 
         tokio::spawn(async move {
-            if let Some(message) = receiver.recv().await {
+            if let Some(message) = receiver.recv().await.map(|message| message.request) {
                 match message {
-                    DataChannelMessage::Get(message) => message
+                    DataRequest::Get(message) => message
                         .sender
                         .send(ResponseChannelMessage(Resp::bulk_string_from_str("world")))
                         .unwrap(),
@@ -79,7 +74,12 @@ mod test {
             };
         });
 
-        let result = handler.handle(&[Resp::bulk_string_from_str("HELLO")]).await;
+        let result = handler
+            .handle(
+                &mut Session::default(),
+                &[Resp::bulk_string_from_str("HELLO")],
+            )
+            .await;
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), Resp::bulk_string_from_str("world"));
     }
@@ -89,7 +89,7 @@ mod test {
         let (sender, _) = tokio::sync::mpsc::channel(1000);
         let handler = GetCommandHandler::new(sender.into());
 
-        let result = handler.handle(&[]).await;
+        let result = handler.handle(&mut Session::default(), &[]).await;
         assert!(result.is_err());
         assert_eq!(
             result.unwrap_err().to_string(),
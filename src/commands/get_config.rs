@@ -4,7 +4,7 @@ use async_trait::async_trait;
 
 use crate::{config::ConfigField, errors::AppError, resp::Resp};
 
-use super::command_registry::CommandHandler;
+use super::{command_registry::CommandHandler, session::Session};
 
 pub const GET_CONFIG_COMMAND_NAME: &str = "CONFIG GET";
 
@@ -21,8 +21,8 @@ impl GetConfigCommandHandler {
 
 #[async_trait]
 impl CommandHandler for GetConfigCommandHandler {
-    async fn handle(&self, args: &[Resp]) -> Result<Resp, AppError> {
-        if args.len() < 1 {
+    async fn handle(&self, _session: &mut Session, args: &[Resp]) -> Result<Resp, AppError> {
+        if args.is_empty() {
             return Err(AppError::InvalidArgLength(
                 GET_CONFIG_COMMAND_NAME.to_owned(),
                 "1".to_owned(),
@@ -53,7 +53,10 @@ mod tests {
         let handler = GetConfigCommandHandler::new((&config).into());
         let arg = Resp::bulk_string_from_str("dbfilename");
 
-        let result = handler.handle(&[arg]).await.unwrap();
+        let result = handler
+            .handle(&mut Session::default(), &[arg])
+            .await
+            .unwrap();
         assert_eq!(
             result,
             Resp::Array(
@@ -72,7 +75,7 @@ mod tests {
         let handler = GetConfigCommandHandler::new((&config).into());
         let arg = Resp::simple_string_from_str("HELLO");
 
-        let result = handler.handle(&[arg]).await;
+        let result = handler.handle(&mut Session::default(), &[arg]).await;
         assert_eq!(
             result.unwrap_err().to_string(),
             AppError::InvalidArgType("bulk string".to_owned()).to_string()
@@ -84,7 +87,7 @@ mod tests {
         let config = AppConfig::parse_from(["config", "--dbfilename", "redis.rdb"]);
         let handler = GetConfigCommandHandler::new((&config).into());
 
-        let result = handler.handle(&[]).await;
+        let result = handler.handle(&mut Session::default(), &[]).await;
         assert_eq!(
             result.unwrap_err().to_string(),
             AppError::InvalidArgLength(
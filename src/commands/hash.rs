@@ -0,0 +1,288 @@
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use tokio::sync::mpsc::Sender;
+
+use crate::{
+    data_management::{
+        datastore::ExpireCondition,
+        hash::{FieldValues, HashCommand},
+        message::{DataChannelMessage, DataRequest, HashMessage},
+    },
+    errors::AppError,
+    resp::Resp,
+};
+
+use super::{
+    arguments::{bulk_string, check_arity, float, integer, key, keyword, scan_args},
+    command_registry::CommandHandler,
+    session::Session,
+};
+
+pub const HASH_COMMAND_NAMES: [&str; 22] = [
+    "HSET",
+    "HMSET",
+    "HSETNX",
+    "HGET",
+    "HMGET",
+    "HDEL",
+    "HGETALL",
+    "HKEYS",
+    "HVALS",
+    "HLEN",
+    "HEXISTS",
+    "HSTRLEN",
+    "HINCRBY",
+    "HINCRBYFLOAT",
+    "HSCAN",
+    "HEXPIRE",
+    "HPEXPIRE",
+    "HEXPIREAT",
+    "HPEXPIREAT",
+    "HTTL",
+    "HPTTL",
+    "HPERSIST",
+];
+
+#[derive(Debug)]
+pub struct HashCommandHandler {
+    name: &'static str,
+    data_sender: Arc<Sender<DataChannelMessage>>,
+}
+
+impl HashCommandHandler {
+    pub fn new(name: &'static str, data_sender: Arc<Sender<DataChannelMessage>>) -> Self {
+        Self { name, data_sender }
+    }
+
+    fn field_values(&self, args: &[Resp]) -> Result<FieldValues, AppError> {
+        if !args.len().is_multiple_of(2) {
+            return Err(AppError::InvalidArgLength(
+                self.name.to_owned(),
+                "field value pairs".to_owned(),
+                args.len().to_string(),
+            ));
+        }
+        args.chunks(2)
+            .map(|pair| Ok((bulk_string(&pair[0])?, bulk_string(&pair[1])?)))
+            .collect()
+    }
+
+    /// Parses the trailing `FIELDS numfields field [field ...]` block of the field TTL commands.
+    fn fields_block(&self, args: &[Resp]) -> Result<Vec<Vec<u8>>, AppError> {
+        check_arity(self.name, args, 3, true)?;
+        if keyword(&args[0])? != "FIELDS" {
+            return Err(AppError::Generic(
+                "Mandatory argument FIELDS is missing or not at the right position".to_owned(),
+            ));
+        }
+        let numfields = integer::<usize>(&args[1])?;
+        if numfields != args.len() - 2 {
+            return Err(AppError::Generic(
+                "The `numfields` parameter must match the number of arguments".to_owned(),
+            ));
+        }
+        args[2..].iter().map(bulk_string).collect()
+    }
+
+    fn expire_at(&self, arg: &Resp) -> Result<SystemTime, AppError> {
+        let time = integer::<i64>(arg)?;
+        if time < 0 {
+            return Err(AppError::InvalidExpireTime(self.name.to_lowercase()));
+        }
+        let time = time as u64;
+        Ok(match self.name {
+            "HEXPIRE" => SystemTime::now() + Duration::from_secs(time),
+            "HPEXPIRE" => SystemTime::now() + Duration::from_millis(time),
+            "HEXPIREAT" => UNIX_EPOCH + Duration::from_secs(time),
+            _ => UNIX_EPOCH + Duration::from_millis(time),
+        })
+    }
+
+    fn parse(&self, args: &[Resp]) -> Result<(Vec<u8>, HashCommand), AppError> {
+        check_arity(self.name, args, 1, true)?;
+        let key = key(&args[0])?;
+        let args = &args[1..];
+
+        let command = match self.name {
+            "HSET" | "HMSET" => {
+                check_arity(self.name, args, 2, true)?;
+                let fields = self.field_values(args)?;
+                if self.name == "HSET" {
+                    HashCommand::Set(fields)
+                } else {
+                    HashCommand::MSet(fields)
+                }
+            }
+            "HSETNX" => {
+                check_arity(self.name, args, 2, false)?;
+                HashCommand::SetNx(bulk_string(&args[0])?, bulk_string(&args[1])?)
+            }
+            "HGET" | "HEXISTS" | "HSTRLEN" => {
+                check_arity(self.name, args, 1, false)?;
+                let field = bulk_string(&args[0])?;
+                match self.name {
+                    "HGET" => HashCommand::Get(field),
+                    "HEXISTS" => HashCommand::Exists(field),
+                    _ => HashCommand::StrLen(field),
+                }
+            }
+            "HMGET" | "HDEL" => {
+                check_arity(self.name, args, 1, true)?;
+                let fields = args.iter().map(bulk_string).collect::<Result<_, _>>()?;
+                if self.name == "HMGET" {
+                    HashCommand::MGet(fields)
+                } else {
+                    HashCommand::Del(fields)
+                }
+            }
+            "HGETALL" | "HKEYS" | "HVALS" | "HLEN" => {
+                check_arity(self.name, args, 0, false)?;
+                match self.name {
+                    "HGETALL" => HashCommand::GetAll,
+                    "HKEYS" => HashCommand::Keys,
+                    "HVALS" => HashCommand::Vals,
+                    _ => HashCommand::Len,
+                }
+            }
+            "HINCRBY" => {
+                check_arity(self.name, args, 2, false)?;
+                HashCommand::IncrBy(bulk_string(&args[0])?, integer(&args[1])?)
+            }
+            "HINCRBYFLOAT" => {
+                check_arity(self.name, args, 2, false)?;
+                HashCommand::IncrByFloat(bulk_string(&args[0])?, float(&args[1])?)
+            }
+            "HSCAN" => {
+                check_arity(self.name, args, 1, true)?;
+                let mut novalues = false;
+                let scan = scan_args(&args[0], &args[1..], |option, _| {
+                    novalues |= option == "NOVALUES";
+                    Ok(option == "NOVALUES")
+                })?;
+                HashCommand::Scan { scan, novalues }
+            }
+            "HEXPIRE" | "HPEXPIRE" | "HEXPIREAT" | "HPEXPIREAT" => {
+                check_arity(self.name, args, 4, true)?;
+                let at = self.expire_at(&args[0])?;
+                let (condition, rest) = match keyword(&args[1])?.as_str() {
+                    "NX" => (Some(ExpireCondition::Nx), &args[2..]),
+                    "XX" => (Some(ExpireCondition::Xx), &args[2..]),
+                    "GT" => (Some(ExpireCondition::Gt), &args[2..]),
+                    "LT" => (Some(ExpireCondition::Lt), &args[2..]),
+                    _ => (None, &args[1..]),
+                };
+                HashCommand::Expire {
+                    at,
+                    condition,
+                    fields: self.fields_block(rest)?,
+                }
+            }
+            "HTTL" | "HPTTL" => HashCommand::Ttl {
+                millis: self.name == "HPTTL",
+                fields: self.fields_block(args)?,
+            },
+            "HPERSIST" => HashCommand::Persist(self.fields_block(args)?),
+            _ => return Err(AppError::UnknownCommand(self.name.to_owned())),
+        };
+        Ok((key, command))
+    }
+}
+
+#[async_trait]
+impl CommandHandler for HashCommandHandler {
+    async fn handle(&self, session: &mut Session, args: &[Resp]) -> Result<Resp, AppError> {
+        let (key, command) = self.parse(args)?;
+        session
+            .send(&self.data_sender, |sender| {
+                DataRequest::Hash(HashMessage::new(key, command, sender))
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        commands::{command_registry::CommandHandler, session::Session, test_helpers::args},
+        data_management::{
+            hash::HashCommand,
+            message::{DataRequest, ResponseChannelMessage},
+        },
+        errors::AppError,
+        resp::Resp,
+    };
+
+    use super::HashCommandHandler;
+
+    #[tokio::test]
+    async fn should_send_hash_command() {
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(1000);
+        let handler = HashCommandHandler::new("HSET", sender.into());
+        tokio::spawn(async move {
+            if let Some(DataRequest::Hash(message)) =
+                receiver.recv().await.map(|message| message.request)
+            {
+                assert_eq!(
+                    message.command,
+                    HashCommand::Set(vec![(b"field".to_vec(), b"value".to_vec())])
+                );
+                message
+                    .sender
+                    .send(ResponseChannelMessage(Resp::Integers(1)))
+                    .unwrap();
+            } else {
+                panic!()
+            }
+        });
+        let result = handler
+            .handle(&mut Session::default(), &args(&["key", "field", "value"]))
+            .await;
+        assert_eq!(result.unwrap(), Resp::Integers(1));
+    }
+
+    #[tokio::test]
+    async fn should_throw_error_on_odd_field_values() {
+        let (sender, _) = tokio::sync::mpsc::channel(1000);
+        let handler = HashCommandHandler::new("HSET", sender.into());
+        let result = handler
+            .handle(&mut Session::default(), &args(&["key", "field"]))
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn should_throw_error_on_numfields_mismatch() {
+        let (sender, _) = tokio::sync::mpsc::channel(1000);
+        let handler = HashCommandHandler::new("HEXPIRE", sender.into());
+        let result = handler
+            .handle(
+                &mut Session::default(),
+                &args(&["key", "10", "FIELDS", "2", "field"]),
+            )
+            .await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "ERR The `numfields` parameter must match the number of arguments"
+        );
+    }
+
+    #[tokio::test]
+    async fn should_throw_error_on_negative_expire_time() {
+        let (sender, _) = tokio::sync::mpsc::channel(1000);
+        let handler = HashCommandHandler::new("HEXPIRE", sender.into());
+        let result = handler
+            .handle(
+                &mut Session::default(),
+                &args(&["key", "-1", "FIELDS", "1", "field"]),
+            )
+            .await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            AppError::InvalidExpireTime("hexpire".to_owned()).to_string()
+        );
+    }
+}
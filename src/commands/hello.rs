@@ -0,0 +1,143 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::{acl::Acl, errors::AppError, resp::Resp};
+
+use super::{
+    arguments::{bulk_string, integer, keyword},
+    auth::log_in,
+    command_registry::CommandHandler,
+    session::Session,
+};
+
+pub const HELLO_COMMAND_NAME: &str = "HELLO";
+
+/// Version reported to clients, some of them gating features like RESP3 on it.
+const REDIS_VERSION: &str = "7.2.0";
+
+/// `HELLO [protover [AUTH username password]]`, switching the connection between RESP2
+/// and RESP3, and authenticating it at the same time if asked to.
+#[derive(Debug)]
+pub struct HelloCommand {
+    acl: Arc<Acl>,
+}
+
+impl HelloCommand {
+    pub fn new(acl: Arc<Acl>) -> Self {
+        Self { acl }
+    }
+
+    fn reply(session: &Session) -> Resp {
+        let protocol = if session.resp3() { 3 } else { 2 };
+        let fields = [
+            ("server", Resp::bulk_string_from_str("redis")),
+            ("version", Resp::bulk_string_from_str(REDIS_VERSION)),
+            ("proto", Resp::Integers(protocol)),
+            ("id", Resp::Integers(session.id() as i64)),
+            ("mode", Resp::bulk_string_from_str("standalone")),
+            ("role", Resp::bulk_string_from_str("master")),
+            ("modules", Resp::Array(vec![])),
+        ]
+        .map(|(name, value)| (Resp::bulk_string_from_str(name), value));
+        if session.resp3() {
+            return Resp::Map(fields.into());
+        }
+        Resp::Array(
+            fields
+                .into_iter()
+                .flat_map(|(name, value)| [name, value])
+                .collect(),
+        )
+    }
+}
+
+#[async_trait]
+impl CommandHandler for HelloCommand {
+    async fn handle(&self, session: &mut Session, args: &[Resp]) -> Result<Resp, AppError> {
+        let mut resp3 = session.resp3();
+        if let Some((protocol, options)) = args.split_first() {
+            resp3 = match integer::<i64>(protocol) {
+                Ok(2) => false,
+                Ok(3) => true,
+                Ok(_) => return Err(AppError::NoProto),
+                Err(_) => {
+                    return Err(AppError::Generic(
+                        "Protocol version is not an integer or out of range".to_owned(),
+                    ))
+                }
+            };
+            let mut options = options.iter();
+            while let Some(option) = options.next() {
+                match (keyword(option)?.as_str(), options.next(), options.next()) {
+                    ("AUTH", Some(user), Some(password)) => {
+                        log_in(&self.acl, session, user, password)?
+                    }
+                    _ => {
+                        return Err(AppError::Generic(format!(
+                            "Syntax error in HELLO option '{}'",
+                            String::from_utf8_lossy(&bulk_string(option)?)
+                        )))
+                    }
+                }
+            }
+        }
+        if !session.authenticated() {
+            return Err(AppError::HelloNoAuth);
+        }
+        session.set_resp3(resp3);
+        Ok(Self::reply(session))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::{
+        acl::Acl,
+        commands::{command_registry::CommandHandler, session::Session},
+        resp::Resp,
+    };
+
+    use super::HelloCommand;
+
+    #[tokio::test]
+    async fn should_switch_protocol() {
+        let mut session = Session::default();
+        let reply = HelloCommand::new(Arc::default())
+            .handle(&mut session, &[Resp::bulk_string_from_str("3")])
+            .await
+            .unwrap();
+        assert!(session.resp3());
+        let Resp::Map(fields) = reply else { panic!() };
+        assert!(fields.contains(&(Resp::bulk_string_from_str("proto"), Resp::Integers(3))));
+
+        let result = HelloCommand::new(Arc::default())
+            .handle(&mut session, &[Resp::bulk_string_from_str("4")])
+            .await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "NOPROTO unsupported protocol version"
+        );
+        assert!(session.resp3());
+    }
+
+    #[tokio::test]
+    async fn should_authenticate_with_auth_option() {
+        let handler = HelloCommand::new(Arc::new(Acl::new(Some("secret"), None)));
+        let mut session = Session::default();
+        session.set_authenticated(false);
+        let args =
+            |password: &str| ["2", "AUTH", "default", password].map(Resp::bulk_string_from_str);
+        let result = handler.handle(&mut session, &args("secret")[..1]).await;
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .starts_with("NOAUTH HELLO must"));
+        let result = handler.handle(&mut session, &args("wrong")).await;
+        assert!(result.unwrap_err().to_string().starts_with("WRONGPASS"));
+        handler.handle(&mut session, &args("secret")).await.unwrap();
+        assert!(session.authenticated());
+    }
+}
@@ -0,0 +1,264 @@
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use tokio::sync::mpsc::Sender;
+
+use crate::{
+    data_management::{
+        keyspace::{KeyValues, KeyspaceCommand, ObjectField, RestoreArgs},
+        message::{DataChannelMessage, DataRequest, KeyspaceMessage},
+    },
+    errors::AppError,
+    resp::Resp,
+};
+
+use super::{
+    arguments::{bulk_string, check_arity, integer, key, keyword, scan_args},
+    command_registry::CommandHandler,
+    session::Session,
+};
+
+pub const KEYSPACE_COMMAND_NAMES: [&str; 18] = [
+    "MGET",
+    "MSET",
+    "MSETNX",
+    "DEL",
+    "UNLINK",
+    "EXISTS",
+    "TOUCH",
+    "SCAN",
+    "KEYS",
+    "RANDOMKEY",
+    "RENAME",
+    "RENAMENX",
+    "DUMP",
+    "RESTORE",
+    "OBJECT ENCODING",
+    "OBJECT IDLETIME",
+    "OBJECT FREQ",
+    "OBJECT REFCOUNT",
+];
+
+#[derive(Debug)]
+pub struct KeyspaceCommandHandler {
+    name: &'static str,
+    data_sender: Arc<Sender<DataChannelMessage>>,
+}
+
+impl KeyspaceCommandHandler {
+    pub fn new(name: &'static str, data_sender: Arc<Sender<DataChannelMessage>>) -> Self {
+        Self { name, data_sender }
+    }
+
+    /// `key value [key value ...]`, values being stored serialized like `SET` does.
+    fn key_values(&self, args: &[Resp]) -> Result<KeyValues, AppError> {
+        if !args.len().is_multiple_of(2) {
+            return Err(AppError::InvalidArgLength(
+                self.name.to_owned(),
+                (args.len() + 1).to_string(),
+                args.len().to_string(),
+            ));
+        }
+        args.chunks(2)
+            .map(|pair| {
+                if !pair[1].is_bulk_string() {
+                    return Err(AppError::InvalidArgType("bulk string".to_owned()));
+                }
+                Ok((key(&pair[0])?, pair[1].to_owned().serialize()?))
+            })
+            .collect()
+    }
+
+    /// `key ttl serialized-value [REPLACE] [ABSTTL] [IDLETIME seconds] [FREQ frequency]`.
+    fn restore(&self, args: &[Resp]) -> Result<KeyspaceCommand, AppError> {
+        check_arity(self.name, args, 3, true)?;
+        let ttl = integer::<i64>(&args[1])?;
+        if ttl < 0 {
+            return Err(AppError::Generic(
+                "Invalid TTL value, must be >= 0".to_owned(),
+            ));
+        }
+        let (mut replace, mut absttl, mut idle, mut frequency) = (false, false, None, None);
+        let mut options = args[3..].iter();
+        while let Some(option) = options.next() {
+            match keyword(option)?.as_str() {
+                "REPLACE" => replace = true,
+                "ABSTTL" => absttl = true,
+                "IDLETIME" if frequency.is_none() => {
+                    let seconds = integer::<i64>(options.next().ok_or(AppError::Syntax)?)?;
+                    let seconds = u64::try_from(seconds).map_err(|_| {
+                        AppError::Generic("Invalid IDLETIME value, must be >= 0".to_owned())
+                    })?;
+                    idle = Some(Duration::from_secs(seconds));
+                }
+                "FREQ" if idle.is_none() => {
+                    let value = integer::<i64>(options.next().ok_or(AppError::Syntax)?)?;
+                    frequency = Some(u8::try_from(value).map_err(|_| {
+                        AppError::Generic("Invalid FREQ value, must be >= 0 and <= 255".to_owned())
+                    })?);
+                }
+                _ => return Err(AppError::Syntax),
+            }
+        }
+        let ttl = Duration::from_millis(ttl as u64);
+        let expiry = match (ttl.is_zero(), absttl) {
+            (true, _) => None,
+            (false, true) => Some(UNIX_EPOCH + ttl),
+            (false, false) => Some(SystemTime::now() + ttl),
+        };
+        Ok(KeyspaceCommand::Restore(RestoreArgs {
+            key: key(&args[0])?,
+            expiry,
+            payload: bulk_string(&args[2])?,
+            replace,
+            idle,
+            frequency,
+        }))
+    }
+
+    fn parse(&self, args: &[Resp]) -> Result<KeyspaceCommand, AppError> {
+        if self.name == "RANDOMKEY" {
+            check_arity(self.name, args, 0, false)?;
+            return Ok(KeyspaceCommand::RandomKey);
+        }
+        check_arity(self.name, args, 1, true)?;
+        let keys = || args.iter().map(key).collect::<Result<Vec<_>, _>>();
+
+        let command = match self.name {
+            "MGET" => KeyspaceCommand::MGet(keys()?),
+            "MSET" => KeyspaceCommand::MSet(self.key_values(args)?),
+            "MSETNX" => KeyspaceCommand::MSetNx(self.key_values(args)?),
+            "DEL" => KeyspaceCommand::Del(keys()?),
+            "UNLINK" => KeyspaceCommand::Unlink(keys()?),
+            "EXISTS" => KeyspaceCommand::Exists(keys()?),
+            "TOUCH" => KeyspaceCommand::Touch(keys()?),
+            "SCAN" => {
+                let mut type_name = None;
+                let scan = scan_args(&args[0], &args[1..], |option, options| {
+                    if option != "TYPE" {
+                        return Ok(false);
+                    }
+                    let value = bulk_string(options.next().ok_or(AppError::Syntax)?)?;
+                    type_name = Some(String::from_utf8_lossy(&value).into_owned());
+                    Ok(true)
+                })?;
+                KeyspaceCommand::Scan { scan, type_name }
+            }
+            "KEYS" => {
+                check_arity(self.name, args, 1, false)?;
+                KeyspaceCommand::Keys(bulk_string(&args[0])?)
+            }
+            "RENAME" | "RENAMENX" => {
+                check_arity(self.name, args, 2, false)?;
+                KeyspaceCommand::Rename {
+                    source: key(&args[0])?,
+                    destination: key(&args[1])?,
+                    nx: self.name == "RENAMENX",
+                }
+            }
+            "DUMP" => {
+                check_arity(self.name, args, 1, false)?;
+                KeyspaceCommand::Dump(key(&args[0])?)
+            }
+            "RESTORE" => self.restore(args)?,
+            "OBJECT ENCODING" | "OBJECT IDLETIME" | "OBJECT FREQ" | "OBJECT REFCOUNT" => {
+                check_arity(self.name, args, 1, false)?;
+                let field = match self.name {
+                    "OBJECT ENCODING" => ObjectField::Encoding,
+                    "OBJECT IDLETIME" => ObjectField::IdleTime,
+                    "OBJECT FREQ" => ObjectField::Freq,
+                    _ => ObjectField::RefCount,
+                };
+                KeyspaceCommand::Object(field, key(&args[0])?)
+            }
+            _ => return Err(AppError::UnknownCommand(self.name.to_owned())),
+        };
+        Ok(command)
+    }
+}
+
+#[async_trait]
+impl CommandHandler for KeyspaceCommandHandler {
+    async fn handle(&self, session: &mut Session, args: &[Resp]) -> Result<Resp, AppError> {
+        let command = self.parse(args)?;
+        session
+            .send(&self.data_sender, |sender| {
+                DataRequest::Keyspace(KeyspaceMessage::new(command, sender))
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        commands::{
+            command_registry::CommandHandler,
+            session::Session,
+            test_helpers::{args, key},
+        },
+        data_management::{
+            keyspace::KeyspaceCommand,
+            message::{DataRequest, ResponseChannelMessage},
+        },
+        resp::Resp,
+    };
+
+    use super::KeyspaceCommandHandler;
+
+    #[tokio::test]
+    async fn should_send_pairs_in_a_single_message() {
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(1000);
+        let handler = KeyspaceCommandHandler::new("MSET", sender.into());
+        let task = tokio::spawn(async move {
+            match receiver.recv().await.map(|message| message.request) {
+                Some(DataRequest::Keyspace(message)) => {
+                    message
+                        .sender
+                        .send(ResponseChannelMessage(Resp::simple_string_from_str("OK")))
+                        .unwrap();
+                    message.command
+                }
+                _ => panic!(),
+            }
+        });
+        let result = handler
+            .handle(&mut Session::default(), &args(&["a", "1", "b", "2"]))
+            .await;
+        assert_eq!(result.unwrap(), Resp::simple_string_from_str("OK"));
+        let expected = KeyspaceCommand::MSet(vec![(key("a"), key("1")), (key("b"), key("2"))]);
+        assert_eq!(task.await.unwrap(), expected);
+    }
+
+    #[tokio::test]
+    async fn should_throw_error_on_invalid_scan_cursor() {
+        let (sender, _) = tokio::sync::mpsc::channel(1000);
+        let handler = KeyspaceCommandHandler::new("SCAN", sender.into());
+        let result = handler
+            .handle(&mut Session::default(), &args(&["abc", "COUNT", "10"]))
+            .await;
+        assert_eq!(result.unwrap_err().to_string(), "ERR invalid cursor");
+    }
+
+    #[tokio::test]
+    async fn should_throw_error_on_idletime_with_freq() {
+        let (sender, _) = tokio::sync::mpsc::channel(1000);
+        let handler = KeyspaceCommandHandler::new("RESTORE", sender.into());
+        let arguments = args(&["k", "0", "payload", "FREQ", "3", "IDLETIME", "10"]);
+        let result = handler.handle(&mut Session::default(), &arguments).await;
+        assert_eq!(result.unwrap_err().to_string(), "ERR syntax error");
+    }
+
+    #[tokio::test]
+    async fn should_throw_error_on_odd_key_values() {
+        let (sender, _) = tokio::sync::mpsc::channel(1000);
+        let handler = KeyspaceCommandHandler::new("MSETNX", sender.into());
+        let result = handler
+            .handle(&mut Session::default(), &args(&["a", "1", "b"]))
+            .await;
+        assert!(result.is_err());
+    }
+}
@@ -1,6 +1,27 @@
+pub mod acl;
+pub mod arguments;
+pub mod auth;
+pub mod client;
 pub mod command_registry;
+pub mod consumer_group;
+pub mod database;
 pub mod echo;
+pub mod function;
 pub mod get;
 pub mod get_config;
+pub mod hash;
+pub mod hello;
+pub mod keyspace;
 pub mod ping;
+pub mod pubsub;
+pub mod scripting;
+pub mod session;
 pub mod set;
+pub mod shutdown;
+pub mod sorted_set;
+pub mod stream;
+pub mod string;
+#[cfg(test)]
+pub mod test_helpers;
+pub mod transaction;
+pub mod unordered_set;
@@ -2,21 +2,43 @@ use async_trait::async_trait;
 
 use crate::{errors::AppError, resp::Resp};
 
-use super::command_registry::CommandHandler;
+use super::{command_registry::CommandHandler, session::Session};
 
 #[derive(Debug)]
 pub struct PingCommand;
 
 #[async_trait]
 impl CommandHandler for PingCommand {
-    async fn handle(&self, _args: &[crate::resp::Resp]) -> Result<crate::resp::Resp, AppError> {
+    async fn handle(
+        &self,
+        session: &mut Session,
+        args: &[crate::resp::Resp],
+    ) -> Result<crate::resp::Resp, AppError> {
+        // Subscribed connections get a reply shaped like the messages pushed to them.
+        if session.subscribed() {
+            let message = args
+                .first()
+                .cloned()
+                .unwrap_or(Resp::bulk_string_from_str(""));
+            return Ok(Resp::Array(vec![
+                Resp::bulk_string_from_str("pong"),
+                message,
+            ]));
+        }
         Ok(Resp::simple_string_from_str("PONG"))
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{commands::command_registry::CommandRegistry, resp::Resp};
+    use crate::{
+        commands::{
+            command_registry::{CommandHandler, CommandRegistry},
+            session::Session,
+        },
+        data_management::pubsub::SubscriptionKind,
+        resp::Resp,
+    };
 
     use super::PingCommand;
 
@@ -25,7 +47,18 @@ mod test {
         let mut registry = CommandRegistry::new();
         registry.register("PING", Box::new(PingCommand));
         let handler = registry.command_handler("PING").unwrap();
-        let result = handler.handle(&[]).await.unwrap();
+        let result = handler.handle(&mut Session::default(), &[]).await.unwrap();
         assert_eq!(result, Resp::simple_string_from_str("PONG"))
     }
+
+    #[tokio::test]
+    async fn should_reply_with_an_empty_bulk_string_when_subscribed() {
+        let mut session = Session::default();
+        session.set_subscriptions(SubscriptionKind::Channel, 1);
+        let result = PingCommand.handle(&mut session, &[]).await.unwrap();
+        assert_eq!(
+            result.serialize().unwrap(),
+            b"*2\r\n$4\r\npong\r\n$0\r\n\r\n"
+        )
+    }
 }
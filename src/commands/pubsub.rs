@@ -0,0 +1,220 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::sync::mpsc::Sender;
+
+use crate::{
+    data_management::{
+        message::{send_message, DataChannelMessage, DataRequest, PubSubMessage},
+        pubsub::{PubSubCommand, SubscriptionKind},
+    },
+    errors::AppError,
+    resp::Resp,
+};
+
+use super::{
+    arguments::{bulk_string, check_arity},
+    command_registry::CommandHandler,
+    session::Session,
+};
+
+pub const PUBSUB_COMMAND_NAMES: [&str; 13] = [
+    "SUBSCRIBE",
+    "UNSUBSCRIBE",
+    "PSUBSCRIBE",
+    "PUNSUBSCRIBE",
+    "SSUBSCRIBE",
+    "SUNSUBSCRIBE",
+    "PUBLISH",
+    "SPUBLISH",
+    "PUBSUB CHANNELS",
+    "PUBSUB NUMSUB",
+    "PUBSUB NUMPAT",
+    "PUBSUB SHARDCHANNELS",
+    "PUBSUB SHARDNUMSUB",
+];
+
+#[derive(Debug)]
+pub struct PubSubCommandHandler {
+    name: &'static str,
+    data_sender: Arc<Sender<DataChannelMessage>>,
+}
+
+impl PubSubCommandHandler {
+    pub fn new(name: &'static str, data_sender: Arc<Sender<DataChannelMessage>>) -> Self {
+        Self { name, data_sender }
+    }
+
+    fn names(args: &[Resp]) -> Result<Vec<Vec<u8>>, AppError> {
+        args.iter().map(bulk_string).collect()
+    }
+
+    /// Optional pattern of `PUBSUB CHANNELS` and `PUBSUB SHARDCHANNELS`.
+    fn pattern(args: &[Resp]) -> Result<Option<Vec<u8>>, AppError> {
+        match args {
+            [] => Ok(None),
+            [pattern] => Ok(Some(bulk_string(pattern)?)),
+            _ => Err(AppError::Syntax),
+        }
+    }
+
+    /// (Un)subscribes the connection, the hub pushing one confirmation per name so that
+    /// they are written in order with the messages published meanwhile.
+    async fn subscription(&self, session: &mut Session, args: &[Resp]) -> Result<Resp, AppError> {
+        let (kind, subscribe) = match self.name {
+            "SUBSCRIBE" => (SubscriptionKind::Channel, true),
+            "UNSUBSCRIBE" => (SubscriptionKind::Channel, false),
+            "PSUBSCRIBE" => (SubscriptionKind::Pattern, true),
+            "PUNSUBSCRIBE" => (SubscriptionKind::Pattern, false),
+            "SSUBSCRIBE" => (SubscriptionKind::Shard, true),
+            _ => (SubscriptionKind::Shard, false),
+        };
+        if subscribe {
+            check_arity(self.name, args, 1, true)?;
+        }
+        if session.in_transaction() {
+            return Err(AppError::Generic(
+                "Command not allowed inside a transaction".to_owned(),
+            ));
+        }
+        let client = session
+            .push()
+            .cloned()
+            .ok_or(AppError::Generic("pub/sub is not available".to_owned()))?;
+        let names = Self::names(args)?;
+        let command = if subscribe {
+            PubSubCommand::Subscribe {
+                kind,
+                client,
+                names,
+            }
+        } else {
+            PubSubCommand::Unsubscribe {
+                kind,
+                client,
+                names,
+            }
+        };
+        let reply = send_message(&self.data_sender, session.db, |sender| {
+            DataRequest::PubSub(PubSubMessage::new(command, sender))
+        })
+        .await?;
+        if let Resp::Integers(subscriptions) = reply {
+            session.set_subscriptions(kind, subscriptions as usize);
+        }
+        session.reply_pushed();
+        Ok(reply)
+    }
+
+    fn parse(&self, args: &[Resp]) -> Result<PubSubCommand, AppError> {
+        let command = match self.name {
+            "PUBLISH" => {
+                check_arity(self.name, args, 2, false)?;
+                PubSubCommand::Publish {
+                    channel: bulk_string(&args[0])?,
+                    message: bulk_string(&args[1])?,
+                }
+            }
+            "SPUBLISH" => {
+                check_arity(self.name, args, 2, false)?;
+                PubSubCommand::ShardPublish {
+                    channel: bulk_string(&args[0])?,
+                    message: bulk_string(&args[1])?,
+                }
+            }
+            "PUBSUB CHANNELS" => PubSubCommand::Channels(Self::pattern(args)?),
+            "PUBSUB SHARDCHANNELS" => PubSubCommand::ShardChannels(Self::pattern(args)?),
+            "PUBSUB NUMSUB" => PubSubCommand::NumSub(Self::names(args)?),
+            "PUBSUB SHARDNUMSUB" => PubSubCommand::ShardNumSub(Self::names(args)?),
+            "PUBSUB NUMPAT" => {
+                check_arity(self.name, args, 0, false)?;
+                PubSubCommand::NumPat
+            }
+            _ => return Err(AppError::UnknownCommand(self.name.to_owned())),
+        };
+        Ok(command)
+    }
+}
+
+#[async_trait]
+impl CommandHandler for PubSubCommandHandler {
+    async fn handle(&self, session: &mut Session, args: &[Resp]) -> Result<Resp, AppError> {
+        if self.name.ends_with("SUBSCRIBE") {
+            return self.subscription(session, args).await;
+        }
+        let command = self.parse(args)?;
+        session
+            .send(&self.data_sender, |sender| {
+                DataRequest::PubSub(PubSubMessage::new(command, sender))
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        commands::{command_registry::CommandHandler, session::Session, test_helpers::args},
+        data_management::{
+            message::{DataChannelMessage, DataRequest, ResponseChannelMessage},
+            pubsub::{push_channel, OutputBufferLimits, PubSubCommand, SubscriptionKind},
+        },
+        resp::Resp,
+    };
+
+    use super::PubSubCommandHandler;
+
+    #[tokio::test]
+    async fn should_enter_subscribed_mode() {
+        let (sender, mut receiver) = tokio::sync::mpsc::channel::<DataChannelMessage>(1000);
+        let handler = PubSubCommandHandler::new("SUBSCRIBE", sender.into());
+        let task = tokio::spawn(async move {
+            match receiver.recv().await.map(|message| message.request) {
+                Some(DataRequest::PubSub(message)) => {
+                    message
+                        .sender
+                        .send(ResponseChannelMessage(Resp::Integers(2)))
+                        .unwrap();
+                    message.command
+                }
+                _ => panic!(),
+            }
+        });
+        let (push, _pushes) = push_channel(1, OutputBufferLimits::default());
+        let mut session = Session::with_push(push);
+        handler
+            .handle(&mut session, &args(&["a", "b"]))
+            .await
+            .unwrap();
+        assert!(session.subscribed());
+        assert!(session.take_reply_pushed());
+        match task.await.unwrap() {
+            PubSubCommand::Subscribe { kind, names, .. } => {
+                assert_eq!(kind, SubscriptionKind::Channel);
+                assert_eq!(names, vec![b"a".to_vec(), b"b".to_vec()]);
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn should_parse_publish() {
+        let (sender, mut receiver) = tokio::sync::mpsc::channel::<DataChannelMessage>(1000);
+        let handler = PubSubCommandHandler::new("PUBLISH", sender.into());
+        tokio::spawn(async move {
+            if let Some(DataRequest::PubSub(message)) =
+                receiver.recv().await.map(|message| message.request)
+            {
+                assert!(matches!(message.command, PubSubCommand::Publish { .. }));
+                message
+                    .sender
+                    .send(ResponseChannelMessage(Resp::Integers(1)))
+                    .unwrap();
+            }
+        });
+        let result = handler
+            .handle(&mut Session::default(), &args(&["news", "hello"]))
+            .await;
+        assert_eq!(result.unwrap(), Resp::Integers(1));
+    }
+}
@@ -0,0 +1,182 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::sync::mpsc::Sender;
+
+use crate::{
+    data_management::{
+        message::{DataChannelMessage, DataRequest, ScriptMessage},
+        scripting::{Script, ScriptCommand, ScriptKind, ScriptMonitor},
+    },
+    errors::AppError,
+    resp::Resp,
+};
+
+use super::{
+    arguments::{bulk_string, check_arity, integer, keyword},
+    command_registry::CommandHandler,
+    session::Session,
+};
+
+pub const SCRIPTING_COMMAND_NAMES: [&str; 5] = [
+    "EVAL",
+    "EVALSHA",
+    "SCRIPT LOAD",
+    "SCRIPT EXISTS",
+    "SCRIPT FLUSH",
+];
+
+/// Served by the connections themselves, the worker being busy running the script.
+pub const SCRIPT_KILL_COMMAND_NAMES: [&str; 2] = ["SCRIPT KILL", "FUNCTION KILL"];
+
+#[derive(Debug)]
+pub struct ScriptingCommandHandler {
+    name: &'static str,
+    data_sender: Arc<Sender<DataChannelMessage>>,
+}
+
+impl ScriptingCommandHandler {
+    pub fn new(name: &'static str, data_sender: Arc<Sender<DataChannelMessage>>) -> Self {
+        Self { name, data_sender }
+    }
+
+    fn eval(script: Script, args: &[Resp]) -> Result<ScriptCommand, AppError> {
+        let (keys, args) = keys_and_args(args)?;
+        Ok(ScriptCommand::Eval { script, keys, args })
+    }
+
+    fn parse(&self, args: &[Resp]) -> Result<ScriptCommand, AppError> {
+        let command = match self.name {
+            "EVAL" => {
+                check_arity(self.name, args, 2, true)?;
+                Self::eval(Script::Body(bulk_string(&args[0])?), &args[1..])?
+            }
+            "EVALSHA" => {
+                check_arity(self.name, args, 2, true)?;
+                let sha = String::from_utf8_lossy(&bulk_string(&args[0])?).to_lowercase();
+                Self::eval(Script::Sha(sha), &args[1..])?
+            }
+            "SCRIPT LOAD" => {
+                check_arity(self.name, args, 1, false)?;
+                ScriptCommand::Load(bulk_string(&args[0])?)
+            }
+            "SCRIPT EXISTS" => {
+                check_arity(self.name, args, 1, true)?;
+                let shas = args
+                    .iter()
+                    .map(|arg| Ok(String::from_utf8_lossy(&bulk_string(arg)?).to_lowercase()))
+                    .collect::<Result<_, AppError>>()?;
+                ScriptCommand::Exists(shas)
+            }
+            "SCRIPT FLUSH" => {
+                // Flushing is cheap enough for `ASYNC` to run synchronously too.
+                match args {
+                    [] => (),
+                    [mode] if matches!(keyword(mode)?.as_str(), "ASYNC" | "SYNC") => (),
+                    _ => return Err(AppError::Syntax),
+                }
+                ScriptCommand::Flush
+            }
+            _ => return Err(AppError::UnknownCommand(self.name.to_owned())),
+        };
+        Ok(command)
+    }
+}
+
+type KeysAndArgs = (Vec<Vec<u8>>, Vec<Vec<u8>>);
+
+/// `numkeys [key ...] [arg ...]` of `EVAL` and `FCALL`, keys being handed to the script as
+/// sent.
+pub fn keys_and_args(args: &[Resp]) -> Result<KeysAndArgs, AppError> {
+    let numkeys: i64 = integer(&args[0])?;
+    if numkeys < 0 {
+        return Err(AppError::Generic(
+            "Number of keys can't be negative".to_owned(),
+        ));
+    }
+    let mut keys = args[1..]
+        .iter()
+        .map(bulk_string)
+        .collect::<Result<Vec<_>, _>>()?;
+    if numkeys as usize > keys.len() {
+        return Err(AppError::Generic(
+            "Number of keys can't be greater than number of args".to_owned(),
+        ));
+    }
+    let args = keys.split_off(numkeys as usize);
+    Ok((keys, args))
+}
+
+#[derive(Debug)]
+pub struct ScriptKillCommandHandler {
+    kind: ScriptKind,
+    monitor: Arc<ScriptMonitor>,
+}
+
+impl ScriptKillCommandHandler {
+    pub fn new(name: &'static str, monitor: Arc<ScriptMonitor>) -> Self {
+        let kind = match name {
+            "FUNCTION KILL" => ScriptKind::Function,
+            _ => ScriptKind::Script,
+        };
+        Self { kind, monitor }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for ScriptKillCommandHandler {
+    async fn handle(&self, _session: &mut Session, args: &[Resp]) -> Result<Resp, AppError> {
+        check_arity(self.kind.kill_command(), args, 0, false)?;
+        self.monitor.kill(self.kind)?;
+        Ok(Resp::simple_string_from_str("OK"))
+    }
+}
+
+#[async_trait]
+impl CommandHandler for ScriptingCommandHandler {
+    async fn handle(&self, session: &mut Session, args: &[Resp]) -> Result<Resp, AppError> {
+        let command = self.parse(args)?;
+        let user = session.user().to_owned();
+        session
+            .send(&self.data_sender, |sender| {
+                DataRequest::Script(ScriptMessage::new(command, user, sender))
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        commands::test_helpers::args,
+        data_management::scripting::{Script, ScriptCommand},
+    };
+
+    use super::ScriptingCommandHandler;
+
+    #[test]
+    fn should_split_keys_from_args() {
+        let (sender, _receiver) = tokio::sync::mpsc::channel(1);
+        let handler = ScriptingCommandHandler::new("EVAL", sender.into());
+        let command = handler
+            .parse(&args(&["return 1", "1", "key", "arg"]))
+            .unwrap();
+        match command {
+            ScriptCommand::Eval {
+                script: Script::Body(body),
+                keys,
+                args,
+            } => {
+                assert_eq!(body, b"return 1");
+                assert_eq!(keys, vec![b"key".to_vec()]);
+                assert_eq!(args, vec![b"arg".to_vec()]);
+            }
+            _ => panic!(),
+        }
+        let err = handler.parse(&args(&["return 1", "2", "key"])).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ERR Number of keys can't be greater than number of args"
+        );
+    }
+}
@@ -0,0 +1,431 @@
+use std::{net::SocketAddr, path::PathBuf, time::Instant};
+
+use tokio::sync::{mpsc::Sender, oneshot};
+
+use crate::{
+    acl::DEFAULT_USER,
+    clients::ClientInfo,
+    data_management::{
+        message::{
+            send_tracked_message, DataChannelMessage, DataRequest, MessageChannelError,
+            ResponseChannelMessage, WatchedKey,
+        },
+        pubsub::{PushSender, SubscriptionKind},
+        tracking::{TrackingContext, TrackingOptions},
+    },
+    errors::AppError,
+    resp::Resp,
+};
+
+/// State of a single client connection, handed to every command it sends.
+#[derive(Debug, Default)]
+pub struct Session {
+    /// Logical database selected with `SELECT`.
+    pub db: usize,
+    transaction: Option<Transaction>,
+    watched: Vec<WatchedKey>,
+    /// Where pub/sub messages are pushed, absent for sessions without a connection.
+    push: Option<PushSender>,
+    /// Channels and patterns subscribed to, the connection only taking pub/sub commands
+    /// while there are some.
+    subscriptions: usize,
+    /// Shard channels subscribed to, counted apart like Redis does.
+    shard_subscriptions: usize,
+    reply_pushed: bool,
+    /// Set by `HELLO 3`, pushed messages then being sent as RESP3 pushes.
+    resp3: bool,
+    /// Options of `CLIENT TRACKING ON`, absent while tracking is off.
+    tracking: Option<TrackingOptions>,
+    /// `CLIENT CACHING` answer, applying to the next command reading keys.
+    caching: Option<bool>,
+    /// Set for the commands of a script, whose requests the data worker runs itself
+    /// instead of taking them from its channel.
+    script: Option<Sender<DataChannelMessage>>,
+    /// Set until the client authenticates when the server requires a password.
+    unauthenticated: bool,
+    /// ACL user logged in with `AUTH`, the default one when absent.
+    user: Option<String>,
+    /// Where the client is connected from, absent for sessions without a connection.
+    address: Option<ClientAddress>,
+    /// Set with `CLIENT SETNAME`.
+    name: Option<String>,
+    /// Set with `CLIENT NO-EVICT ON`.
+    no_evict: bool,
+    /// Set with `CLIENT REPLY OFF`, until `CLIENT REPLY ON`.
+    replies_off: bool,
+    /// Replies left to drop after `CLIENT REPLY SKIP`, its own included.
+    skipped_replies: usize,
+    /// When the connection was accepted, absent for sessions without a connection.
+    connected: Option<Instant>,
+    /// When the last command was received.
+    last_interaction: Option<Instant>,
+    /// Name of the last command received, like `client|list`.
+    command: Option<String>,
+}
+
+/// `CLIENT REPLY` mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplyMode {
+    On,
+    Off,
+    Skip,
+}
+
+/// Address of a client connection: its peer and local ends over TCP, or the path of the
+/// Unix socket it connected to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientAddress {
+    Tcp { peer: SocketAddr, local: SocketAddr },
+    Unix(PathBuf),
+}
+
+impl ClientAddress {
+    pub fn unix(&self) -> bool {
+        matches!(self, Self::Unix(_))
+    }
+
+    /// `addr` field of `CLIENT LIST`, Unix socket clients having port 0 like in Redis.
+    pub fn addr(&self) -> String {
+        match self {
+            Self::Tcp { peer, .. } => peer.to_string(),
+            Self::Unix(path) => format!("{}:0", path.display()),
+        }
+    }
+
+    /// `laddr` field of `CLIENT LIST`, the end the server accepted the connection on.
+    pub fn laddr(&self) -> String {
+        match self {
+            Self::Tcp { local, .. } => local.to_string(),
+            Self::Unix(path) => format!("{}:0", path.display()),
+        }
+    }
+}
+
+/// Commands queued between `MULTI` and `EXEC`.
+#[derive(Debug)]
+pub struct Transaction {
+    /// Database selected when `MULTI` was received, restored by `DISCARD`.
+    db: usize,
+    /// Commands received since `MULTI`, only run once `EXEC` is.
+    commands: Vec<QueuedCommand>,
+    requests: Vec<DataChannelMessage>,
+    replies: Vec<QueuedReply>,
+    aborted: bool,
+}
+
+/// Command queued by a transaction, under the name its handler is registered with.
+#[derive(Debug)]
+pub struct QueuedCommand {
+    pub command: String,
+    pub args: Vec<Resp>,
+}
+
+/// Reply of a queued command: either still to come from the data worker, or already known
+/// for commands that do not reach it, like `PING`.
+#[derive(Debug)]
+pub enum QueuedReply {
+    Pending(oneshot::Receiver<ResponseChannelMessage>),
+    Ready(Resp),
+}
+
+impl QueuedReply {
+    pub async fn resolve(self) -> Result<Resp, AppError> {
+        match self {
+            Self::Pending(receiver) => {
+                let reply = receiver.await.map_err(MessageChannelError::from)?;
+                Ok(reply.0)
+            }
+            Self::Ready(reply) => Ok(reply),
+        }
+    }
+}
+
+impl Transaction {
+    pub fn db(&self) -> usize {
+        self.db
+    }
+
+    pub fn aborted(&self) -> bool {
+        self.aborted
+    }
+
+    /// Splits the transaction into the requests to run and the replies to collect.
+    pub fn into_parts(self) -> (Vec<DataChannelMessage>, Vec<QueuedReply>) {
+        (self.requests, self.replies)
+    }
+}
+
+impl Session {
+    pub fn with_push(push: PushSender) -> Self {
+        let now = Instant::now();
+        Self {
+            push: Some(push),
+            connected: Some(now),
+            last_interaction: Some(now),
+            ..Default::default()
+        }
+    }
+
+    /// Session of the commands sent by a script through `redis.call`, run with the
+    /// permissions of the `user` calling it.
+    pub fn for_script(db: usize, user: String, script: Sender<DataChannelMessage>) -> Self {
+        Self {
+            db,
+            script: Some(script),
+            user: Some(user),
+            ..Default::default()
+        }
+    }
+
+    pub fn in_script(&self) -> bool {
+        self.script.is_some()
+    }
+
+    pub fn user(&self) -> &str {
+        self.user.as_deref().unwrap_or(DEFAULT_USER)
+    }
+
+    pub fn set_user(&mut self, user: String) {
+        self.user = Some(user);
+    }
+
+    pub fn address(&self) -> Option<&ClientAddress> {
+        self.address.as_ref()
+    }
+
+    pub fn set_address(&mut self, address: ClientAddress) {
+        self.address = Some(address);
+    }
+
+    /// Line describing the connection, as replied by `CLIENT INFO`.
+    pub fn client_info(&self) -> String {
+        ClientInfo::new(self).to_string()
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn set_name(&mut self, name: Option<String>) {
+        self.name = name;
+    }
+
+    pub fn no_evict(&self) -> bool {
+        self.no_evict
+    }
+
+    pub fn set_no_evict(&mut self, no_evict: bool) {
+        self.no_evict = no_evict;
+    }
+
+    pub fn set_reply_mode(&mut self, mode: ReplyMode) {
+        match mode {
+            ReplyMode::On => {
+                self.replies_off = false;
+                self.skipped_replies = 0;
+            }
+            ReplyMode::Off => self.replies_off = true,
+            ReplyMode::Skip => self.skipped_replies = 2,
+        }
+    }
+
+    /// Whether the reply of the command that just ran is to be dropped, per
+    /// `CLIENT REPLY`.
+    pub fn reply_suppressed(&mut self) -> bool {
+        if self.skipped_replies > 0 {
+            self.skipped_replies -= 1;
+            return true;
+        }
+        self.replies_off
+    }
+
+    pub fn connected(&self) -> Option<Instant> {
+        self.connected
+    }
+
+    pub fn last_interaction(&self) -> Option<Instant> {
+        self.last_interaction
+    }
+
+    pub fn command(&self) -> Option<&str> {
+        self.command.as_deref()
+    }
+
+    /// Records that the connection received the command named `name`.
+    pub fn start_command(&mut self, name: String) {
+        self.command = Some(name);
+        self.last_interaction = Some(Instant::now());
+    }
+
+    pub fn authenticated(&self) -> bool {
+        !self.unauthenticated
+    }
+
+    pub fn set_authenticated(&mut self, authenticated: bool) {
+        self.unauthenticated = !authenticated;
+    }
+
+    pub fn push(&self) -> Option<&PushSender> {
+        self.push.as_ref()
+    }
+
+    /// Id of the connection, 0 for sessions without one.
+    pub fn id(&self) -> u64 {
+        self.push.as_ref().map_or(0, PushSender::id)
+    }
+
+    pub fn resp3(&self) -> bool {
+        self.resp3
+    }
+
+    pub fn set_resp3(&mut self, resp3: bool) {
+        self.resp3 = resp3;
+    }
+
+    pub fn tracking(&self) -> Option<&TrackingOptions> {
+        self.tracking.as_ref()
+    }
+
+    pub fn set_tracking(&mut self, tracking: Option<TrackingOptions>) {
+        self.tracking = tracking;
+        self.caching = None;
+    }
+
+    pub fn caching(&self) -> Option<bool> {
+        self.caching
+    }
+
+    pub fn set_caching(&mut self, caching: bool) {
+        self.caching = Some(caching);
+    }
+
+    /// Tracking state of the next request, consuming the `CLIENT CACHING` answer.
+    fn tracking_context(&mut self) -> Option<TrackingContext> {
+        let caching = self.caching.take();
+        let tracking = self.tracking.as_ref()?;
+        Some(TrackingContext {
+            client: self.id(),
+            remember: tracking.remembers(caching),
+        })
+    }
+
+    /// Channel and pattern subscriptions, then shard channel ones.
+    pub fn subscriptions(&self) -> (usize, usize) {
+        (self.subscriptions, self.shard_subscriptions)
+    }
+
+    pub fn subscribed(&self) -> bool {
+        self.subscriptions > 0 || self.shard_subscriptions > 0
+    }
+
+    pub fn set_subscriptions(&mut self, kind: SubscriptionKind, subscriptions: usize) {
+        match kind {
+            SubscriptionKind::Channel | SubscriptionKind::Pattern => {
+                self.subscriptions = subscriptions
+            }
+            SubscriptionKind::Shard => self.shard_subscriptions = subscriptions,
+        }
+    }
+
+    /// Records that the reply of the current command went through the push channel, so
+    /// that the connection does not write one itself.
+    pub fn reply_pushed(&mut self) {
+        self.reply_pushed = true;
+    }
+
+    pub fn take_reply_pushed(&mut self) -> bool {
+        std::mem::take(&mut self.reply_pushed)
+    }
+
+    /// Sends the request built by `request` to the data worker and waits for its reply, or
+    /// queues it when a transaction is open.
+    pub async fn send<F>(
+        &mut self,
+        data_sender: &Sender<DataChannelMessage>,
+        request: F,
+    ) -> Result<Resp, AppError>
+    where
+        F: FnOnce(oneshot::Sender<ResponseChannelMessage>) -> DataRequest,
+    {
+        let tracking = self.tracking_context();
+        let data_sender = self.script.as_ref().unwrap_or(data_sender);
+        let Some(transaction) = &mut self.transaction else {
+            return send_tracked_message(data_sender, self.db, tracking, request).await;
+        };
+        let (sender, receiver) = oneshot::channel();
+        let message = DataChannelMessage::new(self.db, request(sender)).tracked(tracking);
+        transaction.requests.push(message);
+        transaction.replies.push(QueuedReply::Pending(receiver));
+        Ok(queued())
+    }
+
+    pub fn in_transaction(&self) -> bool {
+        self.transaction.is_some()
+    }
+
+    pub fn begin(&mut self) {
+        self.transaction = Some(Transaction {
+            db: self.db,
+            commands: Vec::new(),
+            requests: Vec::new(),
+            replies: Vec::new(),
+            aborted: false,
+        });
+    }
+
+    pub fn take_transaction(&mut self) -> Option<Transaction> {
+        self.transaction.take()
+    }
+
+    /// Number of commands queued so far, 0 outside of a transaction.
+    pub fn queued(&self) -> usize {
+        self.transaction.as_ref().map_or(0, |transaction| {
+            transaction.commands.len() + transaction.replies.len()
+        })
+    }
+
+    /// Queues `command` to run when `EXEC` is received.
+    pub fn queue_command(&mut self, command: &str, args: &[Resp]) {
+        if let Some(transaction) = &mut self.transaction {
+            transaction.commands.push(QueuedCommand {
+                command: command.to_owned(),
+                args: args.to_vec(),
+            });
+        }
+    }
+
+    /// Takes the commands to run on `EXEC`, none when the transaction was aborted.
+    pub fn take_queued_commands(&mut self) -> Vec<QueuedCommand> {
+        match &mut self.transaction {
+            Some(transaction) if !transaction.aborted => std::mem::take(&mut transaction.commands),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Queues the reply of a command that ran without reaching the data worker.
+    pub fn queue_reply(&mut self, reply: Resp) {
+        if let Some(transaction) = &mut self.transaction {
+            transaction.replies.push(QueuedReply::Ready(reply));
+        }
+    }
+
+    /// Makes the next `EXEC` fail, after a command was rejected while queuing.
+    pub fn abort(&mut self) {
+        if let Some(transaction) = &mut self.transaction {
+            transaction.aborted = true;
+        }
+    }
+
+    pub fn watch(&mut self, keys: impl IntoIterator<Item = WatchedKey>) {
+        self.watched.extend(keys);
+    }
+
+    pub fn take_watched(&mut self) -> Vec<WatchedKey> {
+        std::mem::take(&mut self.watched)
+    }
+}
+
+/// Reply to a command queued by a transaction.
+pub fn queued() -> Resp {
+    Resp::simple_string_from_str("QUEUED")
+}
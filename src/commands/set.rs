@@ -1,16 +1,15 @@
 use std::{sync::Arc, time::Duration};
 
 use async_trait::async_trait;
-use futures::TryFutureExt;
 use tokio::sync::mpsc::Sender;
 
 use crate::{
-    data_management::message::{DataChannelMessage, MessageChannelError, SetMessage},
+    data_management::message::{DataChannelMessage, DataRequest, SetMessage},
     errors::AppError,
     resp::Resp,
 };
 
-use super::command_registry::CommandHandler;
+use super::{command_registry::CommandHandler, session::Session};
 
 pub const SET_COMMAND_NAME: &str = "SET";
 
@@ -69,7 +68,7 @@ impl SetCommandHandler {
                 return Err(AppError::InvalidArgType("bulk string".to_owned()));
             }
             if let Resp::BulkString(duration) = &args[3] {
-                let duration = std::str::from_utf8(&duration)?;
+                let duration = std::str::from_utf8(duration)?;
                 let duration = duration.parse::<u64>()?;
                 let duration = Duration::from_millis(duration);
                 return Ok((key, value, Some(duration)));
@@ -85,28 +84,25 @@ impl SetCommandHandler {
 impl CommandHandler for SetCommandHandler {
     async fn handle(
         &self,
+        session: &mut Session,
         args: &[crate::resp::Resp],
     ) -> Result<crate::resp::Resp, crate::errors::AppError> {
         self.check_args_len(args.len())?;
 
         let (key, value, duration) = self.handle_args(args)?;
-        let (sender, receiver) = tokio::sync::oneshot::channel();
-        let message = SetMessage::new(key.serialize()?, value.serialize()?, sender, duration);
-
-        self.data_sender
-            .send(DataChannelMessage::Set(message))
-            .map_err(MessageChannelError::from)
-            .await?;
-
-        let reply = receiver.await.map_err(MessageChannelError::from)?;
-        Ok(reply.0)
+        let (key, value) = (key.serialize()?, value.serialize()?);
+        session
+            .send(&self.data_sender, |sender| {
+                DataRequest::Set(SetMessage::new(key, value, sender, duration))
+            })
+            .await
     }
 }
 #[cfg(test)]
 mod test {
     use crate::{
-        commands::{command_registry::CommandHandler, set::SET_COMMAND_NAME},
-        data_management::message::{DataChannelMessage, ResponseChannelMessage},
+        commands::{command_registry::CommandHandler, session::Session, set::SET_COMMAND_NAME},
+        data_management::message::{DataRequest, ResponseChannelMessage},
         errors::AppError,
         resp::Resp,
     };
@@ -118,9 +114,9 @@ mod test {
         let (sender, mut receiver) = tokio::sync::mpsc::channel(1000);
         let handler = SetCommandHandler::new(sender.into());
         tokio::spawn(async move {
-            if let Some(message) = receiver.recv().await {
+            if let Some(message) = receiver.recv().await.map(|message| message.request) {
                 match message {
-                    DataChannelMessage::Set(message) => message
+                    DataRequest::Set(message) => message
                         .sender
                         .send(ResponseChannelMessage(Resp::simple_string_from_str("OK")))
                         .unwrap(),
@@ -129,10 +125,13 @@ mod test {
             };
         });
         let result = handler
-            .handle(&[
-                Resp::bulk_string_from_str("HELLO"),
-                Resp::bulk_string_from_str("WORLD"),
-            ])
+            .handle(
+                &mut Session::default(),
+                &[
+                    Resp::bulk_string_from_str("HELLO"),
+                    Resp::bulk_string_from_str("WORLD"),
+                ],
+            )
             .await;
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), Resp::simple_string_from_str("OK"));
@@ -143,7 +142,12 @@ mod test {
         let (sender, _) = tokio::sync::mpsc::channel(1000);
         let handler = SetCommandHandler::new(sender.into());
 
-        let result = handler.handle(&[Resp::bulk_string_from_str("HELLO")]).await;
+        let result = handler
+            .handle(
+                &mut Session::default(),
+                &[Resp::bulk_string_from_str("HELLO")],
+            )
+            .await;
         assert!(result.is_err());
         assert_eq!(
             result.unwrap_err().to_string(),
@@ -161,9 +165,9 @@ mod test {
         let (sender, mut receiver) = tokio::sync::mpsc::channel(1000);
         let handler = SetCommandHandler::new(sender.into());
         tokio::spawn(async move {
-            if let Some(message) = receiver.recv().await {
+            if let Some(message) = receiver.recv().await.map(|message| message.request) {
                 match message {
-                    DataChannelMessage::Set(message) => message
+                    DataRequest::Set(message) => message
                         .sender
                         .send(ResponseChannelMessage(Resp::simple_string_from_str("OK")))
                         .unwrap(),
@@ -172,12 +176,15 @@ mod test {
             };
         });
         let result = handler
-            .handle(&[
-                Resp::bulk_string_from_str("HELLO"),
-                Resp::bulk_string_from_str("WORLD"),
-                Resp::bulk_string_from_str("PX"),
-                Resp::bulk_string_from_str("1000"),
-            ])
+            .handle(
+                &mut Session::default(),
+                &[
+                    Resp::bulk_string_from_str("HELLO"),
+                    Resp::bulk_string_from_str("WORLD"),
+                    Resp::bulk_string_from_str("PX"),
+                    Resp::bulk_string_from_str("1000"),
+                ],
+            )
             .await;
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), Resp::simple_string_from_str("OK"));
@@ -189,11 +196,14 @@ mod test {
         let handler = SetCommandHandler::new(sender.into());
 
         let result = handler
-            .handle(&[
-                Resp::bulk_string_from_str("HELLO"),
-                Resp::bulk_string_from_str("WORLD"),
-                Resp::bulk_string_from_str("PX"),
-            ])
+            .handle(
+                &mut Session::default(),
+                &[
+                    Resp::bulk_string_from_str("HELLO"),
+                    Resp::bulk_string_from_str("WORLD"),
+                    Resp::bulk_string_from_str("PX"),
+                ],
+            )
             .await;
         assert!(result.is_err());
         assert_eq!(
@@ -213,12 +223,15 @@ mod test {
         let handler = SetCommandHandler::new(sender.into());
 
         let result = handler
-            .handle(&[
-                Resp::bulk_string_from_str("HELLO"),
-                Resp::bulk_string_from_str("WORLD"),
-                Resp::bulk_string_from_str("XYZ"),
-                Resp::bulk_string_from_str("1000"),
-            ])
+            .handle(
+                &mut Session::default(),
+                &[
+                    Resp::bulk_string_from_str("HELLO"),
+                    Resp::bulk_string_from_str("WORLD"),
+                    Resp::bulk_string_from_str("XYZ"),
+                    Resp::bulk_string_from_str("1000"),
+                ],
+            )
             .await;
         assert!(result.is_err());
         assert_eq!(
@@ -238,12 +251,15 @@ mod test {
         let handler = SetCommandHandler::new(sender.into());
 
         let result = handler
-            .handle(&[
-                Resp::bulk_string_from_str("HELLO"),
-                Resp::bulk_string_from_str("WORLD"),
-                Resp::bulk_string_from_str("PX"),
-                Resp::bulk_string_from_str("dqlskdml"),
-            ])
+            .handle(
+                &mut Session::default(),
+                &[
+                    Resp::bulk_string_from_str("HELLO"),
+                    Resp::bulk_string_from_str("WORLD"),
+                    Resp::bulk_string_from_str("PX"),
+                    Resp::bulk_string_from_str("dqlskdml"),
+                ],
+            )
             .await;
         assert!(result.is_err());
         assert_eq!(
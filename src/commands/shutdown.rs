@@ -0,0 +1,109 @@
+use async_trait::async_trait;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::{
+    errors::AppError,
+    resp::Resp,
+    shutdown::{SaveMode, ShutdownRequest},
+};
+
+use super::{arguments::bulk_string, command_registry::CommandHandler, session::Session};
+
+pub const SHUTDOWN_COMMAND_NAME: &str = "SHUTDOWN";
+
+/// `SHUTDOWN [NOSAVE|SAVE] [NOW] [FORCE] [ABORT]`, handing the shutdown to the event loop.
+/// The connection closes without a reply once the server goes down, like in Redis, and
+/// gets an error when it stays up because the dataset could not be saved.
+#[derive(Debug)]
+pub struct ShutdownCommand {
+    requests: mpsc::Sender<ShutdownRequest>,
+}
+
+impl ShutdownCommand {
+    pub fn new(requests: mpsc::Sender<ShutdownRequest>) -> Self {
+        Self { requests }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for ShutdownCommand {
+    async fn handle(&self, session: &mut Session, args: &[Resp]) -> Result<Resp, AppError> {
+        if session.in_transaction() {
+            return Err(AppError::Generic(
+                "Command not allowed inside a transaction".to_owned(),
+            ));
+        }
+        let mut request = ShutdownRequest::default();
+        for arg in args {
+            let save = match bulk_string(arg)?.to_ascii_uppercase().as_slice() {
+                b"NOSAVE" => SaveMode::NoSave,
+                b"SAVE" => SaveMode::Save,
+                // No replica is ever waited for, so shutting down is always immediate.
+                b"NOW" => continue,
+                b"FORCE" => {
+                    request.force = true;
+                    continue;
+                }
+                b"ABORT" => return Err(AppError::Generic("No shutdown in progress.".to_owned())),
+                _ => return Err(AppError::Syntax),
+            };
+            if request.save != SaveMode::Configured && request.save != save {
+                return Err(AppError::Syntax);
+            }
+            request.save = save;
+        }
+        let failed = || AppError::Generic("Errors trying to SHUTDOWN. Check logs.".to_owned());
+        let (reply, stopped) = oneshot::channel();
+        request.reply = Some(reply);
+        self.requests.send(request).await.map_err(|_| failed())?;
+        let stopped = stopped.await.map_err(|_| failed())?;
+        stopped.map(|_| Resp::simple_string_from_str("OK"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::sync::mpsc;
+
+    use crate::{
+        commands::{command_registry::CommandHandler, session::Session},
+        errors::AppError,
+        resp::Resp,
+        shutdown::{SaveMode, ShutdownRequest},
+    };
+
+    use super::ShutdownCommand;
+
+    #[tokio::test]
+    async fn should_request_shutdown() {
+        let (sender, mut requests) = mpsc::channel(1);
+        let command = ShutdownCommand::new(sender);
+        let mut session = Session::default();
+        let args = [
+            Resp::bulk_string_from_str("nosave"),
+            Resp::bulk_string_from_str("FORCE"),
+        ];
+        let event_loop = tokio::spawn(async move {
+            let request: ShutdownRequest = requests.recv().await.unwrap();
+            assert_eq!((request.save, request.force), (SaveMode::NoSave, true));
+            request.reply.unwrap().send(Ok(())).unwrap();
+            let request = requests.recv().await.unwrap();
+            let failed = AppError::Generic("Errors trying to SHUTDOWN. Check logs.".to_owned());
+            request.reply.unwrap().send(Err(failed)).unwrap();
+        });
+        command.handle(&mut session, &args).await.unwrap();
+        let err = command.handle(&mut session, &[]).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ERR Errors trying to SHUTDOWN. Check logs."
+        );
+        event_loop.await.unwrap();
+
+        let args = [
+            Resp::bulk_string_from_str("SAVE"),
+            Resp::bulk_string_from_str("NOSAVE"),
+        ];
+        let err = command.handle(&mut session, &args).await.unwrap_err();
+        assert_eq!(err.to_string(), "ERR syntax error");
+    }
+}
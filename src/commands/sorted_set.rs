@@ -0,0 +1,499 @@
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use tokio::sync::mpsc::Sender;
+
+use crate::{
+    data_management::{
+        blocking::BlockingCommand,
+        message::{BlockingMessage, DataChannelMessage, DataRequest, SortedSetMessage},
+        sorted_set::{
+            AddFlags, Aggregate, LexBound, RangeBy, RangeQuery, ScoreBound, SortedSetCommand,
+        },
+    },
+    errors::AppError,
+    helpers::number::parse_float,
+    resp::Resp,
+};
+
+use super::{
+    arguments::{bulk_string, check_arity, float, integer, key, keyword, scan_args},
+    command_registry::CommandHandler,
+    session::Session,
+};
+
+pub const SORTED_SET_COMMAND_NAMES: [&str; 24] = [
+    "ZADD",
+    "ZINCRBY",
+    "ZREM",
+    "ZCARD",
+    "ZCOUNT",
+    "ZSCORE",
+    "ZRANK",
+    "ZREVRANK",
+    "ZRANGE",
+    "ZREVRANGE",
+    "ZRANGEBYSCORE",
+    "ZREVRANGEBYSCORE",
+    "ZRANGEBYLEX",
+    "ZREVRANGEBYLEX",
+    "ZREMRANGEBYRANK",
+    "ZREMRANGEBYSCORE",
+    "ZREMRANGEBYLEX",
+    "ZPOPMIN",
+    "ZPOPMAX",
+    "BZPOPMIN",
+    "BZPOPMAX",
+    "ZUNIONSTORE",
+    "ZINTERSTORE",
+    "ZSCAN",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RangeKind {
+    Rank,
+    Score,
+    Lex,
+}
+
+fn score_bound(arg: &Resp) -> Result<ScoreBound, AppError> {
+    let bytes = bulk_string(arg)?;
+    let (bound, exclusive) = match bytes.strip_prefix(b"(") {
+        Some(bound) => (bound, true),
+        None => (bytes.as_slice(), false),
+    };
+    let value = parse_float(bound)
+        .ok_or_else(|| AppError::Generic("min or max is not a float".to_owned()))?;
+    Ok(ScoreBound { value, exclusive })
+}
+
+fn lex_bound(arg: &Resp) -> Result<LexBound, AppError> {
+    let bytes = bulk_string(arg)?;
+    match bytes.split_first() {
+        Some((b'-', [])) => Ok(LexBound::Min),
+        Some((b'+', [])) => Ok(LexBound::Max),
+        Some((b'[', bound)) => Ok(LexBound::Inclusive(bound.to_vec())),
+        Some((b'(', bound)) => Ok(LexBound::Exclusive(bound.to_vec())),
+        _ => Err(AppError::Generic(
+            "min or max not valid string range item".to_owned(),
+        )),
+    }
+}
+
+fn range_by(kind: RangeKind, min: &Resp, max: &Resp) -> Result<RangeBy, AppError> {
+    let range = match kind {
+        RangeKind::Rank => RangeBy::Rank(integer(min)?, integer(max)?),
+        RangeKind::Score => RangeBy::Score(score_bound(min)?, score_bound(max)?),
+        RangeKind::Lex => RangeBy::Lex(lex_bound(min)?, lex_bound(max)?),
+    };
+    Ok(range)
+}
+
+#[derive(Debug)]
+pub struct SortedSetCommandHandler {
+    name: &'static str,
+    data_sender: Arc<Sender<DataChannelMessage>>,
+}
+
+impl SortedSetCommandHandler {
+    pub fn new(name: &'static str, data_sender: Arc<Sender<DataChannelMessage>>) -> Self {
+        Self { name, data_sender }
+    }
+
+    fn add(&self, args: &[Resp]) -> Result<SortedSetCommand, AppError> {
+        check_arity(self.name, args, 3, true)?;
+        let mut flags = AddFlags::default();
+        let mut position = 1;
+        while let Some(arg) = args.get(position) {
+            match keyword(arg)?.as_str() {
+                "NX" => flags.nx = true,
+                "XX" => flags.xx = true,
+                "GT" => flags.gt = true,
+                "LT" => flags.lt = true,
+                "CH" => flags.ch = true,
+                "INCR" => flags.incr = true,
+                _ => break,
+            }
+            position += 1;
+        }
+        let pairs = &args[position..];
+        if pairs.is_empty() || !pairs.len().is_multiple_of(2) {
+            return Err(AppError::Syntax);
+        }
+        if flags.nx && flags.xx {
+            return Err(AppError::Generic(
+                "XX and NX options at the same time are not compatible".to_owned(),
+            ));
+        }
+        if [flags.nx, flags.gt, flags.lt]
+            .iter()
+            .filter(|flag| **flag)
+            .count()
+            > 1
+        {
+            return Err(AppError::Generic(
+                "GT, LT, and/or NX options at the same time are not compatible".to_owned(),
+            ));
+        }
+        if flags.incr && pairs.len() > 2 {
+            return Err(AppError::Generic(
+                "INCR option supports a single increment-element pair".to_owned(),
+            ));
+        }
+        let elements = pairs
+            .chunks(2)
+            .map(|pair| Ok((float(&pair[0])?, bulk_string(&pair[1])?)))
+            .collect::<Result<_, AppError>>()?;
+        Ok(SortedSetCommand::Add {
+            key: key(&args[0])?,
+            elements,
+            flags,
+        })
+    }
+
+    fn range(&self, args: &[Resp]) -> Result<SortedSetCommand, AppError> {
+        check_arity(self.name, args, 3, true)?;
+        let (mut kind, mut reverse) = match self.name {
+            "ZREVRANGE" => (RangeKind::Rank, true),
+            "ZRANGEBYSCORE" => (RangeKind::Score, false),
+            "ZREVRANGEBYSCORE" => (RangeKind::Score, true),
+            "ZRANGEBYLEX" => (RangeKind::Lex, false),
+            "ZREVRANGEBYLEX" => (RangeKind::Lex, true),
+            _ => (RangeKind::Rank, false),
+        };
+        let (mut withscores, mut limit) = (false, None);
+        let mut options = args[3..].iter();
+        while let Some(option) = options.next() {
+            match keyword(option)?.as_str() {
+                "WITHSCORES" => withscores = true,
+                "LIMIT" => {
+                    let offset = integer::<i64>(options.next().ok_or(AppError::Syntax)?)?;
+                    let count = integer::<i64>(options.next().ok_or(AppError::Syntax)?)?;
+                    limit = Some((usize::try_from(offset).unwrap_or(usize::MAX), count));
+                }
+                "BYSCORE" if self.name == "ZRANGE" => kind = RangeKind::Score,
+                "BYLEX" if self.name == "ZRANGE" => kind = RangeKind::Lex,
+                "REV" if self.name == "ZRANGE" => reverse = true,
+                _ => return Err(AppError::Syntax),
+            }
+        }
+        if limit.is_some() && kind == RangeKind::Rank {
+            return Err(AppError::Generic(
+                "syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX"
+                    .to_owned(),
+            ));
+        }
+        if withscores && kind == RangeKind::Lex {
+            return Err(AppError::Generic(
+                "syntax error, WITHSCORES not supported in combination with BYLEX".to_owned(),
+            ));
+        }
+        let (min, max) = if reverse && kind != RangeKind::Rank {
+            (&args[2], &args[1])
+        } else {
+            (&args[1], &args[2])
+        };
+        Ok(SortedSetCommand::Range {
+            key: key(&args[0])?,
+            query: RangeQuery {
+                by: range_by(kind, min, max)?,
+                reverse,
+                limit,
+            },
+            withscores,
+        })
+    }
+
+    fn store(&self, args: &[Resp]) -> Result<SortedSetCommand, AppError> {
+        check_arity(self.name, args, 3, true)?;
+        let numkeys = integer::<usize>(&args[1])?;
+        if numkeys == 0 {
+            return Err(AppError::Generic(format!(
+                "at least 1 input key is needed for '{}' command",
+                self.name.to_lowercase()
+            )));
+        }
+        if numkeys > args.len() - 2 {
+            return Err(AppError::Syntax);
+        }
+        let keys: Vec<_> = args[2..2 + numkeys]
+            .iter()
+            .map(key)
+            .collect::<Result<_, _>>()?;
+        let (mut weights, mut aggregate) = (vec![], Aggregate::Sum);
+        let mut options = args[2 + numkeys..].iter();
+        while let Some(option) = options.next() {
+            match keyword(option)?.as_str() {
+                "WEIGHTS" => {
+                    weights = options
+                        .by_ref()
+                        .take(numkeys)
+                        .map(|weight| {
+                            float(weight).map_err(|_| {
+                                AppError::Generic("weight value is not a float".to_owned())
+                            })
+                        })
+                        .collect::<Result<_, _>>()?;
+                    if weights.len() != numkeys {
+                        return Err(AppError::Syntax);
+                    }
+                }
+                "AGGREGATE" => {
+                    let method = options.next().ok_or(AppError::Syntax)?;
+                    aggregate = match keyword(method)?.as_str() {
+                        "SUM" => Aggregate::Sum,
+                        "MIN" => Aggregate::Min,
+                        "MAX" => Aggregate::Max,
+                        _ => return Err(AppError::Syntax),
+                    };
+                }
+                _ => return Err(AppError::Syntax),
+            }
+        }
+        Ok(SortedSetCommand::Store {
+            destination: key(&args[0])?,
+            keys,
+            weights,
+            aggregate,
+            union: self.name == "ZUNIONSTORE",
+        })
+    }
+
+    fn parse(&self, args: &[Resp]) -> Result<SortedSetCommand, AppError> {
+        check_arity(self.name, args, 1, true)?;
+        let command = match self.name {
+            "ZADD" => self.add(args)?,
+            "ZINCRBY" => {
+                check_arity(self.name, args, 3, false)?;
+                SortedSetCommand::IncrBy(key(&args[0])?, float(&args[1])?, bulk_string(&args[2])?)
+            }
+            "ZREM" => {
+                check_arity(self.name, args, 2, true)?;
+                let members = args[1..]
+                    .iter()
+                    .map(bulk_string)
+                    .collect::<Result<_, _>>()?;
+                SortedSetCommand::Rem(key(&args[0])?, members)
+            }
+            "ZCARD" => {
+                check_arity(self.name, args, 1, false)?;
+                SortedSetCommand::Card(key(&args[0])?)
+            }
+            "ZCOUNT" => {
+                check_arity(self.name, args, 3, false)?;
+                SortedSetCommand::Count(
+                    key(&args[0])?,
+                    score_bound(&args[1])?,
+                    score_bound(&args[2])?,
+                )
+            }
+            "ZSCORE" => {
+                check_arity(self.name, args, 2, false)?;
+                SortedSetCommand::Score(key(&args[0])?, bulk_string(&args[1])?)
+            }
+            "ZRANK" | "ZREVRANK" => {
+                check_arity(self.name, args, 2, true)?;
+                let withscore = match &args[2..] {
+                    [] => false,
+                    [option] if keyword(option)? == "WITHSCORE" => true,
+                    _ => return Err(AppError::Syntax),
+                };
+                SortedSetCommand::Rank {
+                    key: key(&args[0])?,
+                    member: bulk_string(&args[1])?,
+                    reverse: self.name == "ZREVRANK",
+                    withscore,
+                }
+            }
+            "ZRANGE" | "ZREVRANGE" | "ZRANGEBYSCORE" | "ZREVRANGEBYSCORE" | "ZRANGEBYLEX"
+            | "ZREVRANGEBYLEX" => self.range(args)?,
+            "ZREMRANGEBYRANK" | "ZREMRANGEBYSCORE" | "ZREMRANGEBYLEX" => {
+                check_arity(self.name, args, 3, false)?;
+                let kind = match self.name {
+                    "ZREMRANGEBYRANK" => RangeKind::Rank,
+                    "ZREMRANGEBYSCORE" => RangeKind::Score,
+                    _ => RangeKind::Lex,
+                };
+                SortedSetCommand::RemRange(key(&args[0])?, range_by(kind, &args[1], &args[2])?)
+            }
+            "ZPOPMIN" | "ZPOPMAX" => {
+                if args.len() > 2 {
+                    return Err(AppError::Syntax);
+                }
+                let count = args
+                    .get(1)
+                    .map(|count| {
+                        integer::<usize>(count).map_err(|_| {
+                            AppError::Generic("value is out of range, must be positive".to_owned())
+                        })
+                    })
+                    .transpose()?;
+                SortedSetCommand::Pop {
+                    key: key(&args[0])?,
+                    count,
+                    max: self.name == "ZPOPMAX",
+                }
+            }
+            "ZUNIONSTORE" | "ZINTERSTORE" => self.store(args)?,
+            "ZSCAN" => {
+                check_arity(self.name, args, 2, true)?;
+                SortedSetCommand::Scan {
+                    key: key(&args[0])?,
+                    scan: scan_args(&args[1], &args[2..], |_, _| Ok(false))?,
+                }
+            }
+            _ => return Err(AppError::UnknownCommand(self.name.to_owned())),
+        };
+        Ok(command)
+    }
+
+    /// `BZPOPMIN key [key ...] timeout`, a timeout of 0 blocking forever.
+    fn blocking_pop(&self, args: &[Resp]) -> Result<(BlockingCommand, Option<Duration>), AppError> {
+        check_arity(self.name, args, 2, true)?;
+        let (timeout, keys) = args.split_last().expect("arity checked above");
+        let timeout = float(timeout)
+            .map_err(|_| AppError::Generic("timeout is not a float or out of range".to_owned()))?;
+        if timeout < 0.0 {
+            return Err(AppError::Generic("timeout is negative".to_owned()));
+        }
+        let timeout = Duration::try_from_secs_f64(timeout)
+            .map_err(|_| AppError::Generic("timeout is not a float or out of range".to_owned()))?;
+        let command = BlockingCommand::ZPop {
+            keys: keys.iter().map(key).collect::<Result<_, _>>()?,
+            max: self.name == "BZPOPMAX",
+        };
+        Ok((command, (!timeout.is_zero()).then_some(timeout)))
+    }
+}
+
+#[async_trait]
+impl CommandHandler for SortedSetCommandHandler {
+    async fn handle(&self, session: &mut Session, args: &[Resp]) -> Result<Resp, AppError> {
+        if let "BZPOPMIN" | "BZPOPMAX" = self.name {
+            let (command, timeout) = self.blocking_pop(args)?;
+            return session
+                .send(&self.data_sender, |sender| {
+                    DataRequest::Blocking(BlockingMessage::new(command, timeout, sender))
+                })
+                .await;
+        }
+        let command = self.parse(args)?;
+        session
+            .send(&self.data_sender, |sender| {
+                DataRequest::SortedSet(SortedSetMessage::new(command, sender))
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        commands::{
+            command_registry::CommandHandler,
+            session::Session,
+            test_helpers::{args, key},
+        },
+        data_management::{
+            message::{DataRequest, ResponseChannelMessage},
+            sorted_set::{LexBound, RangeBy, RangeQuery, ScoreBound, SortedSetCommand},
+        },
+        resp::Resp,
+    };
+
+    use super::SortedSetCommandHandler;
+
+    async fn parsed_command(name: &'static str, arguments: &[&str]) -> SortedSetCommand {
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(1000);
+        let handler = SortedSetCommandHandler::new(name, sender.into());
+        let task = tokio::spawn(async move {
+            match receiver.recv().await.map(|message| message.request) {
+                Some(DataRequest::SortedSet(message)) => {
+                    message
+                        .sender
+                        .send(ResponseChannelMessage(Resp::Integers(0)))
+                        .unwrap();
+                    message.command
+                }
+                _ => panic!(),
+            }
+        });
+        handler
+            .handle(&mut Session::default(), &args(arguments))
+            .await
+            .unwrap();
+        task.await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_swap_bounds_of_reversed_score_range() {
+        let command = parsed_command(
+            "ZRANGE",
+            &["board", "+inf", "(1", "BYSCORE", "REV", "LIMIT", "0", "2"],
+        )
+        .await;
+        let expected = SortedSetCommand::Range {
+            key: key("board"),
+            query: RangeQuery {
+                by: RangeBy::Score(
+                    ScoreBound {
+                        value: 1.0,
+                        exclusive: true,
+                    },
+                    ScoreBound {
+                        value: f64::INFINITY,
+                        exclusive: false,
+                    },
+                ),
+                reverse: true,
+                limit: Some((0, 2)),
+            },
+            withscores: false,
+        };
+        assert_eq!(command, expected);
+    }
+
+    #[tokio::test]
+    async fn should_parse_lex_range() {
+        let command = parsed_command("ZRANGEBYLEX", &["board", "[a", "+"]).await;
+        let expected = SortedSetCommand::Range {
+            key: key("board"),
+            query: RangeQuery {
+                by: RangeBy::Lex(LexBound::Inclusive(b"a".to_vec()), LexBound::Max),
+                reverse: false,
+                limit: None,
+            },
+            withscores: false,
+        };
+        assert_eq!(command, expected);
+    }
+
+    #[tokio::test]
+    async fn should_throw_error_on_incompatible_add_flags() {
+        let (sender, _) = tokio::sync::mpsc::channel(1000);
+        let handler = SortedSetCommandHandler::new("ZADD", sender.into());
+        let result = handler
+            .handle(
+                &mut Session::default(),
+                &args(&["board", "NX", "GT", "1", "a"]),
+            )
+            .await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "ERR GT, LT, and/or NX options at the same time are not compatible"
+        );
+    }
+
+    #[tokio::test]
+    async fn should_throw_error_on_limit_without_by_score_or_lex() {
+        let (sender, _) = tokio::sync::mpsc::channel(1000);
+        let handler = SortedSetCommandHandler::new("ZRANGE", sender.into());
+        let result = handler
+            .handle(
+                &mut Session::default(),
+                &args(&["board", "0", "-1", "LIMIT", "0", "1"]),
+            )
+            .await;
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,394 @@
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use tokio::sync::mpsc::Sender;
+
+use crate::{
+    data_management::{
+        blocking::BlockingCommand,
+        message::{BlockingMessage, DataChannelMessage, DataRequest, StreamMessage},
+        stream::{IdSpec, ReadFrom, ReadStreams, StreamCommand, StreamId, Trim, TrimStrategy},
+    },
+    errors::AppError,
+    resp::Resp,
+};
+
+use super::{
+    arguments::{bulk_string, check_arity, integer, key, keyword},
+    command_registry::CommandHandler,
+    session::Session,
+};
+
+pub const STREAM_COMMAND_NAMES: [&str; 7] = [
+    "XADD",
+    "XRANGE",
+    "XREVRANGE",
+    "XLEN",
+    "XTRIM",
+    "XDEL",
+    "XREAD",
+];
+
+/// Parses `ms-seq`, a bare `ms` getting `default_seq` as its sequence.
+pub fn stream_id(arg: &Resp, default_seq: u64) -> Result<StreamId, AppError> {
+    StreamId::parse(&bulk_string(arg)?, default_seq).ok_or(AppError::InvalidStreamId)
+}
+
+/// `$` for the last ID of the stream, or an explicit ID.
+pub fn read_from(arg: &Resp) -> Result<ReadFrom, AppError> {
+    match bulk_string(arg)?.as_slice() {
+        b"$" => Ok(ReadFrom::Last),
+        _ => Ok(ReadFrom::Id(stream_id(arg, 0)?)),
+    }
+}
+
+/// `BLOCK milliseconds`, 0 blocking forever.
+pub fn block_timeout(arg: &Resp) -> Result<Option<Duration>, AppError> {
+    let timeout = integer::<i64>(arg)?;
+    let timeout =
+        u64::try_from(timeout).map_err(|_| AppError::Generic("timeout is negative".to_owned()))?;
+    Ok((timeout > 0).then(|| Duration::from_millis(timeout)))
+}
+
+/// Splits the `key [key ...] id [id ...]` tail following `STREAMS`.
+pub fn split_streams<'a>(
+    command: &str,
+    streams: &'a [Resp],
+) -> Result<(&'a [Resp], &'a [Resp]), AppError> {
+    if streams.is_empty() || !streams.len().is_multiple_of(2) {
+        return Err(AppError::Generic(format!(
+            "Unbalanced '{}' list of streams: for each stream key an ID or '$' must be specified.",
+            command.to_lowercase()
+        )));
+    }
+    Ok(streams.split_at(streams.len() / 2))
+}
+
+/// `-`, `+` or an ID, optionally prefixed by `(` to exclude it from the interval.
+pub fn range_bound(arg: &Resp, start: bool) -> Result<StreamId, AppError> {
+    let bytes = bulk_string(arg)?;
+    match bytes.as_slice() {
+        b"-" => return Ok(StreamId::MIN),
+        b"+" => return Ok(StreamId::MAX),
+        _ => {}
+    }
+    let default_seq = if start { 0 } else { u64::MAX };
+    let Some(exclusive) = bytes.strip_prefix(b"(") else {
+        return StreamId::parse(&bytes, default_seq).ok_or(AppError::InvalidStreamId);
+    };
+    let id = StreamId::parse(exclusive, default_seq).ok_or(AppError::InvalidStreamId)?;
+    let bound = if start { id.next() } else { id.previous() };
+    bound.ok_or_else(|| {
+        let side = if start { "start" } else { "end" };
+        AppError::Generic(format!("invalid {side} ID for the interval"))
+    })
+}
+
+/// Parses `MAXLEN | MINID [= | ~] threshold [LIMIT count]` starting at `position`,
+/// leaving `position` on the first argument past the block.
+pub fn trim(args: &[Resp], position: &mut usize) -> Result<Trim, AppError> {
+    let arg = |position: usize| args.get(position).ok_or(AppError::Syntax);
+    let strategy = keyword(arg(*position)?)?;
+    *position += 1;
+    let approximate = match bulk_string(arg(*position)?)?.as_slice() {
+        b"~" => Some(true),
+        b"=" => Some(false),
+        _ => None,
+    };
+    if approximate.is_some() {
+        *position += 1;
+    }
+    let threshold = arg(*position)?;
+    *position += 1;
+    let strategy = match strategy.as_str() {
+        "MAXLEN" => TrimStrategy::MaxLen(
+            integer::<usize>(threshold)
+                .map_err(|_| AppError::Generic("The MAXLEN argument must be >= 0.".to_owned()))?,
+        ),
+        _ => TrimStrategy::MinId(stream_id(threshold, 0)?),
+    };
+    let mut limit = None;
+    if let Some(option) = args.get(*position) {
+        if keyword(option)? == "LIMIT" {
+            if approximate != Some(true) {
+                return Err(AppError::Generic(
+                    "syntax error, LIMIT cannot be used without the special ~ option".to_owned(),
+                ));
+            }
+            let count = args.get(*position + 1).ok_or(AppError::Syntax)?;
+            limit = Some(integer::<usize>(count)?).filter(|limit| *limit > 0);
+            *position += 2;
+        }
+    }
+    Ok(Trim { strategy, limit })
+}
+
+/// `XREAD` either answers right away or, with `BLOCK`, parks the client in the worker.
+enum Read {
+    Now(StreamCommand),
+    Block(BlockingCommand, Option<Duration>),
+}
+
+#[derive(Debug)]
+pub struct StreamCommandHandler {
+    name: &'static str,
+    data_sender: Arc<Sender<DataChannelMessage>>,
+}
+
+impl StreamCommandHandler {
+    pub fn new(name: &'static str, data_sender: Arc<Sender<DataChannelMessage>>) -> Self {
+        Self { name, data_sender }
+    }
+
+    fn add(&self, args: &[Resp]) -> Result<StreamCommand, AppError> {
+        check_arity(self.name, args, 4, true)?;
+        let (mut nomkstream, mut trim_option) = (false, None);
+        let mut position = 1;
+        while let Some(arg) = args.get(position) {
+            match keyword(arg)?.as_str() {
+                "NOMKSTREAM" => {
+                    nomkstream = true;
+                    position += 1;
+                }
+                "MAXLEN" | "MINID" => trim_option = Some(trim(args, &mut position)?),
+                _ => break,
+            }
+        }
+        let id = bulk_string(args.get(position).ok_or(AppError::Syntax)?)?;
+        let id = match id.as_slice() {
+            b"*" => IdSpec::Auto,
+            id => match id.strip_suffix(b"-*") {
+                Some(ms) => IdSpec::Partial(
+                    std::str::from_utf8(ms)
+                        .ok()
+                        .and_then(|ms| ms.parse().ok())
+                        .ok_or(AppError::InvalidStreamId)?,
+                ),
+                None => IdSpec::Explicit(StreamId::parse(id, 0).ok_or(AppError::InvalidStreamId)?),
+            },
+        };
+        let pairs = &args[position + 1..];
+        if pairs.is_empty() || !pairs.len().is_multiple_of(2) {
+            return Err(AppError::InvalidArgLength(
+                self.name.to_owned(),
+                "field value pairs".to_owned(),
+                pairs.len().to_string(),
+            ));
+        }
+        let fields = pairs
+            .chunks(2)
+            .map(|pair| Ok((bulk_string(&pair[0])?, bulk_string(&pair[1])?)))
+            .collect::<Result<_, AppError>>()?;
+        Ok(StreamCommand::Add {
+            key: key(&args[0])?,
+            id,
+            fields,
+            trim: trim_option,
+            nomkstream,
+        })
+    }
+
+    /// `BLOCK 0` blocks forever.
+    fn read(&self, args: &[Resp]) -> Result<Read, AppError> {
+        check_arity(self.name, args, 3, true)?;
+        let (mut count, mut block) = (None, None);
+        let mut position = 0;
+        loop {
+            let option = args.get(position).ok_or(AppError::Syntax)?;
+            let value = args.get(position + 1).ok_or(AppError::Syntax);
+            match keyword(option)?.as_str() {
+                "COUNT" => count = Some(integer::<usize>(value?)?),
+                "BLOCK" => block = Some(block_timeout(value?)?),
+                "STREAMS" => break,
+                _ => return Err(AppError::Syntax),
+            }
+            position += 2;
+        }
+        let (keys, ids) = split_streams(self.name, &args[position + 1..])?;
+        let streams: ReadStreams = keys
+            .iter()
+            .zip(ids)
+            .map(|(stream_key, id)| Ok((key(stream_key)?, read_from(id)?)))
+            .collect::<Result<_, AppError>>()?;
+        Ok(match block {
+            Some(timeout) => Read::Block(BlockingCommand::XRead { streams, count }, timeout),
+            None => Read::Now(StreamCommand::Read { streams, count }),
+        })
+    }
+
+    fn parse(&self, args: &[Resp]) -> Result<StreamCommand, AppError> {
+        check_arity(self.name, args, 1, true)?;
+        let command = match self.name {
+            "XADD" => self.add(args)?,
+            "XRANGE" | "XREVRANGE" => {
+                check_arity(self.name, args, 3, true)?;
+                let reverse = self.name == "XREVRANGE";
+                let (start, end) = if reverse {
+                    (&args[2], &args[1])
+                } else {
+                    (&args[1], &args[2])
+                };
+                let count = match &args[3..] {
+                    [] => None,
+                    [option, count] if keyword(option)? == "COUNT" => Some(integer(count)?),
+                    _ => return Err(AppError::Syntax),
+                };
+                StreamCommand::Range {
+                    key: key(&args[0])?,
+                    start: range_bound(start, true)?,
+                    end: range_bound(end, false)?,
+                    count,
+                    reverse,
+                }
+            }
+            "XLEN" => {
+                check_arity(self.name, args, 1, false)?;
+                StreamCommand::Len(key(&args[0])?)
+            }
+            "XTRIM" => {
+                check_arity(self.name, args, 3, true)?;
+                let mut position = 1;
+                let trim = trim(args, &mut position)?;
+                if position != args.len() {
+                    return Err(AppError::Syntax);
+                }
+                StreamCommand::Trim(key(&args[0])?, trim)
+            }
+            "XDEL" => {
+                check_arity(self.name, args, 2, true)?;
+                let ids = args[1..]
+                    .iter()
+                    .map(|id| stream_id(id, 0))
+                    .collect::<Result<_, _>>()?;
+                StreamCommand::Del(key(&args[0])?, ids)
+            }
+            _ => return Err(AppError::UnknownCommand(self.name.to_owned())),
+        };
+        Ok(command)
+    }
+}
+
+#[async_trait]
+impl CommandHandler for StreamCommandHandler {
+    async fn handle(&self, session: &mut Session, args: &[Resp]) -> Result<Resp, AppError> {
+        let command = match self.name {
+            "XREAD" => match self.read(args)? {
+                Read::Now(command) => command,
+                Read::Block(command, timeout) => {
+                    return session
+                        .send(&self.data_sender, |sender| {
+                            DataRequest::Blocking(BlockingMessage::new(command, timeout, sender))
+                        })
+                        .await;
+                }
+            },
+            _ => self.parse(args)?,
+        };
+        session
+            .send(&self.data_sender, |sender| {
+                DataRequest::Stream(StreamMessage::new(command, sender))
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        commands::{
+            command_registry::CommandHandler,
+            session::Session,
+            test_helpers::{args, key},
+        },
+        data_management::{
+            message::{DataRequest, ResponseChannelMessage},
+            stream::{IdSpec, StreamCommand, StreamId, Trim, TrimStrategy},
+        },
+        resp::Resp,
+    };
+
+    use super::StreamCommandHandler;
+
+    async fn parsed_command(name: &'static str, arguments: &[&str]) -> StreamCommand {
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(1000);
+        let handler = StreamCommandHandler::new(name, sender.into());
+        let task = tokio::spawn(async move {
+            match receiver.recv().await.map(|message| message.request) {
+                Some(DataRequest::Stream(message)) => {
+                    message
+                        .sender
+                        .send(ResponseChannelMessage(Resp::Integers(0)))
+                        .unwrap();
+                    message.command
+                }
+                _ => panic!(),
+            }
+        });
+        handler
+            .handle(&mut Session::default(), &args(arguments))
+            .await
+            .unwrap();
+        task.await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_parse_add_with_trimming() {
+        let command = parsed_command(
+            "XADD",
+            &[
+                "events", "MAXLEN", "~", "10", "LIMIT", "5", "7-*", "type", "click",
+            ],
+        )
+        .await;
+        let expected = StreamCommand::Add {
+            key: key("events"),
+            id: IdSpec::Partial(7),
+            fields: vec![(b"type".to_vec(), b"click".to_vec())],
+            trim: Some(Trim {
+                strategy: TrimStrategy::MaxLen(10),
+                limit: Some(5),
+            }),
+            nomkstream: false,
+        };
+        assert_eq!(command, expected);
+    }
+
+    #[tokio::test]
+    async fn should_parse_exclusive_range_bounds() {
+        let command = parsed_command("XREVRANGE", &["events", "(5", "-", "COUNT", "2"]).await;
+        let expected = StreamCommand::Range {
+            key: key("events"),
+            start: StreamId::MIN,
+            end: StreamId::new(5, u64::MAX - 1),
+            count: Some(2),
+            reverse: true,
+        };
+        assert_eq!(command, expected);
+    }
+
+    #[tokio::test]
+    async fn should_throw_error_on_limit_without_approximation() {
+        let (sender, _) = tokio::sync::mpsc::channel(1000);
+        let handler = StreamCommandHandler::new("XTRIM", sender.into());
+        let result = handler
+            .handle(
+                &mut Session::default(),
+                &args(&["events", "MAXLEN", "10", "LIMIT", "5"]),
+            )
+            .await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "ERR syntax error, LIMIT cannot be used without the special ~ option"
+        );
+    }
+
+    #[tokio::test]
+    async fn should_throw_error_on_unbalanced_read_streams() {
+        let (sender, _) = tokio::sync::mpsc::channel(1000);
+        let handler = StreamCommandHandler::new("XREAD", sender.into());
+        let result = handler
+            .handle(&mut Session::default(), &args(&["STREAMS", "a", "b", "0"]))
+            .await;
+        assert!(result.is_err());
+    }
+}
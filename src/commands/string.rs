@@ -0,0 +1,232 @@
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use tokio::sync::mpsc::Sender;
+
+use crate::{
+    data_management::{
+        message::{DataChannelMessage, DataRequest, StringMessage},
+        string::{ExpiryUpdate, StringCommand},
+    },
+    errors::AppError,
+    resp::Resp,
+};
+
+use super::{
+    arguments::{bulk_string, check_arity, float, integer, key, keyword},
+    command_registry::CommandHandler,
+    session::Session,
+};
+
+pub const STRING_COMMAND_NAMES: [&str; 12] = [
+    "INCR",
+    "DECR",
+    "INCRBY",
+    "DECRBY",
+    "INCRBYFLOAT",
+    "APPEND",
+    "STRLEN",
+    "GETRANGE",
+    "SUBSTR",
+    "SETRANGE",
+    "GETDEL",
+    "GETEX",
+];
+
+#[derive(Debug)]
+pub struct StringCommandHandler {
+    name: &'static str,
+    data_sender: Arc<Sender<DataChannelMessage>>,
+}
+
+impl StringCommandHandler {
+    pub fn new(name: &'static str, data_sender: Arc<Sender<DataChannelMessage>>) -> Self {
+        Self { name, data_sender }
+    }
+
+    /// `EX seconds | PX milliseconds | EXAT timestamp | PXAT timestamp | PERSIST`.
+    fn expiry_update(&self, args: &[Resp]) -> Result<Option<ExpiryUpdate>, AppError> {
+        let (option, time) = match args {
+            [] => return Ok(None),
+            [option] if keyword(option)? == "PERSIST" => return Ok(Some(ExpiryUpdate::Persist)),
+            [option, time] => (keyword(option)?, integer::<i64>(time)?),
+            _ => return Err(AppError::Syntax),
+        };
+        if time <= 0 {
+            return Err(AppError::InvalidExpireTime(self.name.to_lowercase()));
+        }
+        let time = time as u64;
+        let at = match option.as_str() {
+            "EX" => SystemTime::now() + Duration::from_secs(time),
+            "PX" => SystemTime::now() + Duration::from_millis(time),
+            "EXAT" => UNIX_EPOCH + Duration::from_secs(time),
+            "PXAT" => UNIX_EPOCH + Duration::from_millis(time),
+            _ => return Err(AppError::Syntax),
+        };
+        Ok(Some(ExpiryUpdate::At(at)))
+    }
+
+    fn parse(&self, args: &[Resp]) -> Result<StringCommand, AppError> {
+        check_arity(self.name, args, 1, true)?;
+        let key = key(&args[0])?;
+        let args = &args[1..];
+
+        let command = match self.name {
+            "INCR" | "DECR" => {
+                check_arity(self.name, args, 0, false)?;
+                StringCommand::IncrBy(key, if self.name == "INCR" { 1 } else { -1 })
+            }
+            "INCRBY" => {
+                check_arity(self.name, args, 1, false)?;
+                StringCommand::IncrBy(key, integer(&args[0])?)
+            }
+            "DECRBY" => {
+                check_arity(self.name, args, 1, false)?;
+                let decrement = integer::<i64>(&args[0])?;
+                StringCommand::IncrBy(key, decrement.checked_neg().ok_or(AppError::Overflow)?)
+            }
+            "INCRBYFLOAT" => {
+                check_arity(self.name, args, 1, false)?;
+                StringCommand::IncrByFloat(key, float(&args[0])?)
+            }
+            "APPEND" => {
+                check_arity(self.name, args, 1, false)?;
+                StringCommand::Append(key, bulk_string(&args[0])?)
+            }
+            "STRLEN" => {
+                check_arity(self.name, args, 0, false)?;
+                StringCommand::StrLen(key)
+            }
+            "GETRANGE" | "SUBSTR" => {
+                check_arity(self.name, args, 2, false)?;
+                StringCommand::GetRange {
+                    key,
+                    start: integer(&args[0])?,
+                    end: integer(&args[1])?,
+                }
+            }
+            "SETRANGE" => {
+                check_arity(self.name, args, 2, false)?;
+                let offset = integer::<i64>(&args[0])?;
+                if offset < 0 {
+                    return Err(AppError::Generic("offset is out of range".to_owned()));
+                }
+                StringCommand::SetRange {
+                    key,
+                    offset: offset as usize,
+                    value: bulk_string(&args[1])?,
+                }
+            }
+            "GETDEL" => {
+                check_arity(self.name, args, 0, false)?;
+                StringCommand::GetDel(key)
+            }
+            "GETEX" => StringCommand::GetEx {
+                key,
+                update: self.expiry_update(args)?,
+            },
+            _ => return Err(AppError::UnknownCommand(self.name.to_owned())),
+        };
+        Ok(command)
+    }
+}
+
+#[async_trait]
+impl CommandHandler for StringCommandHandler {
+    async fn handle(&self, session: &mut Session, args: &[Resp]) -> Result<Resp, AppError> {
+        let command = self.parse(args)?;
+        session
+            .send(&self.data_sender, |sender| {
+                DataRequest::String(StringMessage::new(command, sender))
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        commands::{
+            command_registry::CommandHandler,
+            session::Session,
+            test_helpers::{args, key},
+        },
+        data_management::{
+            message::{DataRequest, ResponseChannelMessage},
+            string::{ExpiryUpdate, StringCommand},
+        },
+        errors::AppError,
+        resp::Resp,
+    };
+
+    use super::StringCommandHandler;
+
+    async fn parsed_command(name: &'static str, arguments: &[&str]) -> StringCommand {
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(1000);
+        let handler = StringCommandHandler::new(name, sender.into());
+        let task = tokio::spawn(async move {
+            match receiver.recv().await.map(|message| message.request) {
+                Some(DataRequest::String(message)) => {
+                    message
+                        .sender
+                        .send(ResponseChannelMessage(Resp::Integers(0)))
+                        .unwrap();
+                    message.command
+                }
+                _ => panic!(),
+            }
+        });
+        handler
+            .handle(&mut Session::default(), &args(arguments))
+            .await
+            .unwrap();
+        task.await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_parse_decrement_as_negative_increment() {
+        let command = parsed_command("DECRBY", &["counter", "5"]).await;
+        assert_eq!(command, StringCommand::IncrBy(key("counter"), -5));
+        let command = parsed_command("DECR", &["counter"]).await;
+        assert_eq!(command, StringCommand::IncrBy(key("counter"), -1));
+    }
+
+    #[tokio::test]
+    async fn should_parse_getex_persist() {
+        let command = parsed_command("GETEX", &["session", "persist"]).await;
+        let expected = StringCommand::GetEx {
+            key: key("session"),
+            update: Some(ExpiryUpdate::Persist),
+        };
+        assert_eq!(command, expected);
+    }
+
+    #[tokio::test]
+    async fn should_throw_error_on_non_integer_increment() {
+        let (sender, _) = tokio::sync::mpsc::channel(1000);
+        let handler = StringCommandHandler::new("INCRBY", sender.into());
+        let result = handler
+            .handle(&mut Session::default(), &args(&["counter", "1.5"]))
+            .await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            AppError::NotAnInteger.to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn should_throw_error_on_invalid_getex_expiry() {
+        let (sender, _) = tokio::sync::mpsc::channel(1000);
+        let handler = StringCommandHandler::new("GETEX", sender.into());
+        let result = handler
+            .handle(&mut Session::default(), &args(&["session", "EX", "0"]))
+            .await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            AppError::InvalidExpireTime("getex".to_owned()).to_string()
+        );
+    }
+}
@@ -0,0 +1,12 @@
+use crate::resp::Resp;
+
+pub fn args(args: &[&str]) -> Vec<Resp> {
+    args.iter()
+        .map(|arg| Resp::bulk_string_from_str(arg))
+        .collect()
+}
+
+/// Serialized form of a key, as the data store holds it.
+pub fn key(key: &str) -> Vec<u8> {
+    Resp::bulk_string_from_str(key).serialize().unwrap()
+}
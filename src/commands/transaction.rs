@@ -0,0 +1,204 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::sync::mpsc::Sender;
+
+use crate::{
+    data_management::message::{
+        send_message, DataChannelMessage, DataRequest, ExecMessage, WatchMessage, WatchedKey,
+    },
+    errors::AppError,
+    resp::Resp,
+};
+
+use super::{
+    arguments::{check_arity, key},
+    command_registry::CommandHandler,
+    session::Session,
+};
+
+pub const TRANSACTION_COMMAND_NAMES: [&str; 5] = ["MULTI", "EXEC", "DISCARD", "WATCH", "UNWATCH"];
+
+#[derive(Debug)]
+pub struct TransactionCommandHandler {
+    name: &'static str,
+    data_sender: Arc<Sender<DataChannelMessage>>,
+}
+
+impl TransactionCommandHandler {
+    pub fn new(name: &'static str, data_sender: Arc<Sender<DataChannelMessage>>) -> Self {
+        Self { name, data_sender }
+    }
+
+    /// Ships the queued requests as a single message so that no other client runs in
+    /// between, then collects their replies.
+    async fn exec(&self, session: &mut Session) -> Result<Resp, AppError> {
+        let transaction = session
+            .take_transaction()
+            .ok_or(AppError::Generic("EXEC without MULTI".to_owned()))?;
+        let watched = session.take_watched();
+        if transaction.aborted() {
+            return Err(AppError::ExecAbort);
+        }
+        let (requests, replies) = transaction.into_parts();
+        let reply = send_message(&self.data_sender, session.db, |sender| {
+            DataRequest::Exec(ExecMessage::new(watched, requests, sender))
+        })
+        .await?;
+        if reply == Resp::NullArray {
+            return Ok(reply);
+        }
+        let mut results = Vec::with_capacity(replies.len());
+        for reply in replies {
+            results.push(reply.resolve().await?);
+        }
+        Ok(Resp::Array(results))
+    }
+
+    async fn watch(&self, session: &mut Session, args: &[Resp]) -> Result<Resp, AppError> {
+        if session.in_transaction() {
+            return Err(AppError::Generic(
+                "WATCH inside MULTI is not allowed".to_owned(),
+            ));
+        }
+        check_arity(self.name, args, 1, true)?;
+        let keys = args.iter().map(key).collect::<Result<Vec<_>, _>>()?;
+        let versions = send_message(&self.data_sender, session.db, |sender| {
+            DataRequest::Watch(WatchMessage::new(keys.clone(), sender))
+        })
+        .await?;
+        let Resp::Array(versions) = versions else {
+            return Err(AppError::Generic("could not watch keys".to_owned()));
+        };
+        let db = session.db;
+        session.watch(keys.into_iter().zip(versions).map(|(key, version)| {
+            let version = match version {
+                Resp::Integers(version) => version as u64,
+                _ => 0,
+            };
+            WatchedKey::new(db, key, version)
+        }));
+        Ok(Resp::simple_string_from_str("OK"))
+    }
+}
+
+#[async_trait]
+impl CommandHandler for TransactionCommandHandler {
+    async fn handle(&self, session: &mut Session, args: &[Resp]) -> Result<Resp, AppError> {
+        match self.name {
+            "MULTI" => {
+                check_arity(self.name, args, 0, false)?;
+                if session.in_transaction() {
+                    return Err(AppError::Generic(
+                        "MULTI calls can not be nested".to_owned(),
+                    ));
+                }
+                session.begin();
+            }
+            "EXEC" => {
+                check_arity(self.name, args, 0, false)?;
+                return self.exec(session).await;
+            }
+            "DISCARD" => {
+                check_arity(self.name, args, 0, false)?;
+                let transaction = session
+                    .take_transaction()
+                    .ok_or(AppError::Generic("DISCARD without MULTI".to_owned()))?;
+                session.db = transaction.db();
+                session.take_watched();
+            }
+            "WATCH" => return self.watch(session, args).await,
+            "UNWATCH" => {
+                check_arity(self.name, args, 0, false)?;
+                // Queued inside a transaction, where `EXEC` unwatches anyway.
+                if !session.in_transaction() {
+                    session.take_watched();
+                }
+            }
+            _ => return Err(AppError::UnknownCommand(self.name.to_owned())),
+        }
+        Ok(Resp::simple_string_from_str("OK"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        commands::{command_registry::CommandHandler, get::GetCommandHandler, session::Session},
+        data_management::message::{DataChannelMessage, DataRequest, ResponseChannelMessage},
+        resp::Resp,
+    };
+
+    use super::TransactionCommandHandler;
+
+    fn handler(
+        name: &'static str,
+        sender: &tokio::sync::mpsc::Sender<DataChannelMessage>,
+    ) -> TransactionCommandHandler {
+        TransactionCommandHandler::new(name, sender.clone().into())
+    }
+
+    #[tokio::test]
+    async fn should_queue_requests_and_ship_them_on_exec() {
+        let (sender, mut receiver) = tokio::sync::mpsc::channel::<DataChannelMessage>(1000);
+        let task = tokio::spawn(async move {
+            let Some(DataRequest::Exec(message)) =
+                receiver.recv().await.map(|message| message.request)
+            else {
+                panic!()
+            };
+            let requests = message.requests.len();
+            for request in message.requests {
+                let DataRequest::Get(get) = request.request else {
+                    panic!()
+                };
+                get.sender
+                    .send(ResponseChannelMessage(Resp::null_bulk_string()))
+                    .unwrap();
+            }
+            message
+                .sender
+                .send(ResponseChannelMessage(Resp::simple_string_from_str("OK")))
+                .unwrap();
+            requests
+        });
+        let mut session = Session::default();
+        handler("MULTI", &sender)
+            .handle(&mut session, &[])
+            .await
+            .unwrap();
+        let get = GetCommandHandler::new(sender.clone().into());
+        for _ in 0..2 {
+            let result = get
+                .handle(&mut session, &[Resp::bulk_string_from_str("key")])
+                .await;
+            assert_eq!(result.unwrap(), Resp::simple_string_from_str("QUEUED"));
+        }
+        let result = handler("EXEC", &sender).handle(&mut session, &[]).await;
+        assert_eq!(
+            result.unwrap(),
+            Resp::Array(vec![Resp::null_bulk_string(), Resp::null_bulk_string()])
+        );
+        assert_eq!(task.await.unwrap(), 2);
+        assert!(!session.in_transaction());
+    }
+
+    #[tokio::test]
+    async fn should_reject_exec_of_aborted_transaction() {
+        let (sender, _receiver) = tokio::sync::mpsc::channel(1000);
+        let mut session = Session::default();
+        let result = handler("EXEC", &sender).handle(&mut session, &[]).await;
+        assert_eq!(result.unwrap_err().to_string(), "ERR EXEC without MULTI");
+        handler("MULTI", &sender)
+            .handle(&mut session, &[])
+            .await
+            .unwrap();
+        session.abort();
+        let result = handler("EXEC", &sender).handle(&mut session, &[]).await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "EXECABORT Transaction discarded because of previous errors."
+        );
+        assert!(!session.in_transaction());
+    }
+}
@@ -0,0 +1,246 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::sync::mpsc::Sender;
+
+use crate::{
+    data_management::{
+        message::{DataChannelMessage, DataRequest, UnorderedSetMessage},
+        unordered_set::{SetOperation, UnorderedSetCommand},
+    },
+    errors::AppError,
+    resp::Resp,
+};
+
+use super::{
+    arguments::{bulk_string, check_arity, integer, key, keyword, scan_args},
+    command_registry::CommandHandler,
+    session::Session,
+};
+
+pub const UNORDERED_SET_COMMAND_NAMES: [&str; 17] = [
+    "SADD",
+    "SREM",
+    "SMEMBERS",
+    "SISMEMBER",
+    "SMISMEMBER",
+    "SCARD",
+    "SINTER",
+    "SUNION",
+    "SDIFF",
+    "SINTERSTORE",
+    "SUNIONSTORE",
+    "SDIFFSTORE",
+    "SINTERCARD",
+    "SRANDMEMBER",
+    "SPOP",
+    "SMOVE",
+    "SSCAN",
+];
+
+#[derive(Debug)]
+pub struct UnorderedSetCommandHandler {
+    name: &'static str,
+    data_sender: Arc<Sender<DataChannelMessage>>,
+}
+
+impl UnorderedSetCommandHandler {
+    pub fn new(name: &'static str, data_sender: Arc<Sender<DataChannelMessage>>) -> Self {
+        Self { name, data_sender }
+    }
+
+    fn operation(&self) -> SetOperation {
+        match self.name {
+            "SINTER" | "SINTERSTORE" => SetOperation::Inter,
+            "SUNION" | "SUNIONSTORE" => SetOperation::Union,
+            _ => SetOperation::Diff,
+        }
+    }
+
+    fn parse(&self, args: &[Resp]) -> Result<UnorderedSetCommand, AppError> {
+        check_arity(self.name, args, 1, true)?;
+        let keys = || args.iter().map(key).collect::<Result<Vec<_>, _>>();
+        let members = |args: &[Resp]| args.iter().map(bulk_string).collect::<Result<_, _>>();
+
+        let command = match self.name {
+            "SADD" | "SREM" | "SMISMEMBER" => {
+                check_arity(self.name, args, 2, true)?;
+                let (key, members) = (key(&args[0])?, members(&args[1..])?);
+                match self.name {
+                    "SADD" => UnorderedSetCommand::Add(key, members),
+                    "SREM" => UnorderedSetCommand::Rem(key, members),
+                    _ => UnorderedSetCommand::MIsMember(key, members),
+                }
+            }
+            "SMEMBERS" | "SCARD" => {
+                check_arity(self.name, args, 1, false)?;
+                match self.name {
+                    "SMEMBERS" => UnorderedSetCommand::Members(key(&args[0])?),
+                    _ => UnorderedSetCommand::Card(key(&args[0])?),
+                }
+            }
+            "SISMEMBER" => {
+                check_arity(self.name, args, 2, false)?;
+                UnorderedSetCommand::IsMember(key(&args[0])?, bulk_string(&args[1])?)
+            }
+            "SINTER" | "SUNION" | "SDIFF" => {
+                UnorderedSetCommand::Combine(self.operation(), keys()?)
+            }
+            "SINTERSTORE" | "SUNIONSTORE" | "SDIFFSTORE" => {
+                check_arity(self.name, args, 2, true)?;
+                let keys = keys()?;
+                UnorderedSetCommand::Store {
+                    operation: self.operation(),
+                    destination: keys[0].to_owned(),
+                    keys: keys[1..].to_vec(),
+                }
+            }
+            "SINTERCARD" => {
+                check_arity(self.name, args, 2, true)?;
+                let numkeys = integer::<usize>(&args[0])?;
+                if numkeys == 0 {
+                    return Err(AppError::Generic(
+                        "numkeys should be greater than 0".to_owned(),
+                    ));
+                }
+                if numkeys > args.len() - 1 {
+                    return Err(AppError::Generic(
+                        "Number of keys can't be greater than number of args".to_owned(),
+                    ));
+                }
+                let keys = args[1..=numkeys]
+                    .iter()
+                    .map(key)
+                    .collect::<Result<_, _>>()?;
+                let limit = match &args[numkeys + 1..] {
+                    [] => 0,
+                    [option, limit] if keyword(option)? == "LIMIT" => integer::<usize>(limit)
+                        .map_err(|_| AppError::Generic("LIMIT can't be negative".to_owned()))?,
+                    _ => return Err(AppError::Syntax),
+                };
+                UnorderedSetCommand::InterCard { keys, limit }
+            }
+            "SRANDMEMBER" | "SPOP" => {
+                if args.len() > 2 {
+                    return Err(AppError::Syntax);
+                }
+                let key = key(&args[0])?;
+                let count = args.get(1).map(integer::<i64>).transpose()?;
+                if self.name == "SRANDMEMBER" {
+                    return Ok(UnorderedSetCommand::RandMember(key, count));
+                }
+                let count = count
+                    .map(|count| {
+                        usize::try_from(count).map_err(|_| {
+                            AppError::Generic("value is out of range, must be positive".to_owned())
+                        })
+                    })
+                    .transpose()?;
+                UnorderedSetCommand::Pop(key, count)
+            }
+            "SMOVE" => {
+                check_arity(self.name, args, 3, false)?;
+                UnorderedSetCommand::Move {
+                    source: key(&args[0])?,
+                    destination: key(&args[1])?,
+                    member: bulk_string(&args[2])?,
+                }
+            }
+            "SSCAN" => {
+                check_arity(self.name, args, 2, true)?;
+                UnorderedSetCommand::Scan {
+                    key: key(&args[0])?,
+                    scan: scan_args(&args[1], &args[2..], |_, _| Ok(false))?,
+                }
+            }
+            _ => return Err(AppError::UnknownCommand(self.name.to_owned())),
+        };
+        Ok(command)
+    }
+}
+
+#[async_trait]
+impl CommandHandler for UnorderedSetCommandHandler {
+    async fn handle(&self, session: &mut Session, args: &[Resp]) -> Result<Resp, AppError> {
+        let command = self.parse(args)?;
+        session
+            .send(&self.data_sender, |sender| {
+                DataRequest::UnorderedSet(UnorderedSetMessage::new(command, sender))
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        commands::{
+            command_registry::CommandHandler,
+            session::Session,
+            test_helpers::{args, key},
+        },
+        data_management::{
+            message::{DataRequest, ResponseChannelMessage},
+            unordered_set::{SetOperation, UnorderedSetCommand},
+        },
+        resp::Resp,
+    };
+
+    use super::UnorderedSetCommandHandler;
+
+    #[tokio::test]
+    async fn should_send_store_command() {
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(1000);
+        let handler = UnorderedSetCommandHandler::new("SINTERSTORE", sender.into());
+        tokio::spawn(async move {
+            if let Some(DataRequest::UnorderedSet(message)) =
+                receiver.recv().await.map(|message| message.request)
+            {
+                assert_eq!(
+                    message.command,
+                    UnorderedSetCommand::Store {
+                        operation: SetOperation::Inter,
+                        destination: key("dest"),
+                        keys: vec![key("a"), key("b")],
+                    }
+                );
+                message
+                    .sender
+                    .send(ResponseChannelMessage(Resp::Integers(0)))
+                    .unwrap();
+            } else {
+                panic!()
+            }
+        });
+        let result = handler
+            .handle(&mut Session::default(), &args(&["dest", "a", "b"]))
+            .await;
+        assert_eq!(result.unwrap(), Resp::Integers(0));
+    }
+
+    #[tokio::test]
+    async fn should_throw_error_on_negative_pop_count() {
+        let (sender, _) = tokio::sync::mpsc::channel(1000);
+        let handler = UnorderedSetCommandHandler::new("SPOP", sender.into());
+        let result = handler
+            .handle(&mut Session::default(), &args(&["key", "-1"]))
+            .await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "ERR value is out of range, must be positive"
+        );
+    }
+
+    #[tokio::test]
+    async fn should_throw_error_when_numkeys_exceeds_args() {
+        let (sender, _) = tokio::sync::mpsc::channel(1000);
+        let handler = UnorderedSetCommandHandler::new("SINTERCARD", sender.into());
+        let result = handler
+            .handle(&mut Session::default(), &args(&["3", "a", "b"]))
+            .await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "ERR Number of keys can't be greater than number of args"
+        );
+    }
+}
@@ -1,13 +1,48 @@
-use std::{collections::HashMap, path::PathBuf};
+use std::{collections::HashMap, fmt, path::PathBuf, time::Duration};
 
-use clap::{command, Parser};
+use clap::{Parser, ValueEnum};
 
-use crate::{errors::AppError, resp::Resp};
+use crate::{
+    data_management::{notification::NotifyFlags, pubsub::OutputBufferLimits},
+    errors::AppError,
+    helpers::number::parse_memory,
+    resp::Resp,
+};
+
+/// TCP port connections are accepted on when `--port` is not given, like Redis.
+const DEFAULT_PORT: u16 = 6379;
+/// Address listened on when `--bind` is not given.
+const DEFAULT_BIND: &str = "127.0.0.1";
+/// Number of logical databases when `--databases` is not given, like Redis.
+const DEFAULT_DATABASES: usize = 16;
+/// Milliseconds a script runs before other clients are replied `-BUSY`, like Redis.
+const DEFAULT_BUSY_REPLY_THRESHOLD: u64 = 5000;
+/// Connections accepted at once when `--maxclients` is not given, like Redis.
+const DEFAULT_MAXCLIENTS: usize = 10000;
+/// Bytes of a command a client may send before it is fully read, like Redis' 1gb.
+const DEFAULT_CLIENT_QUERY_BUFFER_LIMIT: usize = 1024 * 1024 * 1024;
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub enum ConfigField {
+    Port,
+    Bind,
     Dbfilename,
     Dir,
+    Databases,
+    NotifyKeyspaceEvents,
+    BusyReplyThreshold,
+    Requirepass,
+    Aclfile,
+    TlsPort,
+    TlsCertFile,
+    TlsKeyFile,
+    TlsCaCertFile,
+    TlsAuthClients,
+    Unixsocket,
+    Unixsocketperm,
+    Maxclients,
+    ClientOutputBufferLimit,
+    ClientQueryBufferLimit,
 }
 
 impl TryFrom<&Resp> for ConfigField {
@@ -15,10 +50,27 @@ impl TryFrom<&Resp> for ConfigField {
     fn try_from(value: &Resp) -> Result<Self, Self::Error> {
         if let Resp::BulkString(field) = value {
             match field.as_slice() {
+                b"port" => Ok(Self::Port),
+                b"bind" => Ok(Self::Bind),
                 b"dbfilename" => Ok(Self::Dbfilename),
                 b"dir" => Ok(Self::Dir),
+                b"databases" => Ok(Self::Databases),
+                b"notify-keyspace-events" => Ok(Self::NotifyKeyspaceEvents),
+                b"busy-reply-threshold" | b"lua-time-limit" => Ok(Self::BusyReplyThreshold),
+                b"requirepass" => Ok(Self::Requirepass),
+                b"aclfile" => Ok(Self::Aclfile),
+                b"tls-port" => Ok(Self::TlsPort),
+                b"tls-cert-file" => Ok(Self::TlsCertFile),
+                b"tls-key-file" => Ok(Self::TlsKeyFile),
+                b"tls-ca-cert-file" => Ok(Self::TlsCaCertFile),
+                b"tls-auth-clients" => Ok(Self::TlsAuthClients),
+                b"unixsocket" => Ok(Self::Unixsocket),
+                b"unixsocketperm" => Ok(Self::Unixsocketperm),
+                b"maxclients" => Ok(Self::Maxclients),
+                b"client-output-buffer-limit" => Ok(Self::ClientOutputBufferLimit),
+                b"client-query-buffer-limit" => Ok(Self::ClientQueryBufferLimit),
                 _ => Err(AppError::InvalidConfigField(
-                    String::from_utf8_lossy(&field).to_string(),
+                    String::from_utf8_lossy(field).to_string(),
                 )),
             }
         } else {
@@ -30,10 +82,157 @@ impl TryFrom<&Resp> for ConfigField {
 #[derive(Parser, Debug, Default)]
 #[command(version, about, long_about = None)]
 pub struct AppConfig {
+    /// TCP port accepting plain connections, none when 0.
+    #[arg(long)]
+    pub port: Option<u16>,
+    /// Address the TCP and TLS ports listen on.
+    #[arg(long)]
+    pub bind: Option<String>,
     #[arg(long)]
     pub dir: Option<PathBuf>,
     #[arg(long)]
     pub dbfilename: Option<String>,
+    #[arg(long, value_parser = parse_databases)]
+    pub databases: Option<usize>,
+    /// Keyspace event classes to publish, e.g. `KEA` or `Ex`; none when unset.
+    #[arg(long, value_parser = NotifyFlags::parse)]
+    pub notify_keyspace_events: Option<NotifyFlags>,
+    /// Milliseconds after which a running script makes other clients get `-BUSY` replies.
+    #[arg(long, alias = "lua-time-limit")]
+    pub busy_reply_threshold: Option<u64>,
+    /// Password clients must `AUTH` with before sending other commands.
+    #[arg(long)]
+    pub requirepass: Option<String>,
+    /// File ACL users are loaded from on startup and by `ACL LOAD`, and saved to by
+    /// `ACL SAVE`.
+    #[arg(long)]
+    pub aclfile: Option<PathBuf>,
+    /// Port accepting TLS connections, alongside the plain TCP one unless that is 0.
+    #[arg(long)]
+    pub tls_port: Option<u16>,
+    /// PEM certificate chain the server presents to TLS clients.
+    #[arg(long)]
+    pub tls_cert_file: Option<PathBuf>,
+    /// PEM private key of `tls-cert-file`.
+    #[arg(long)]
+    pub tls_key_file: Option<PathBuf>,
+    /// PEM certificates of the authorities client certificates are verified against.
+    #[arg(long)]
+    pub tls_ca_cert_file: Option<PathBuf>,
+    /// Whether TLS clients must present a certificate signed by `tls-ca-cert-file`.
+    #[arg(long, value_enum)]
+    pub tls_auth_clients: Option<TlsAuthClients>,
+    /// Path of a Unix socket accepting connections, alongside the TCP port.
+    #[arg(long)]
+    pub unixsocket: Option<PathBuf>,
+    /// Octal permissions of `unixsocket`, e.g. `700`; left to the umask when unset.
+    #[arg(long, value_parser = parse_unixsocketperm)]
+    pub unixsocketperm: Option<u32>,
+    /// Clients connected at once, others being replied an error and disconnected.
+    #[arg(long, value_parser = parse_maxclients)]
+    pub maxclients: Option<usize>,
+    /// Limits of replies waiting to be written per client class, e.g.
+    /// `pubsub 32mb 8mb 60`.
+    #[arg(long, value_parser = OutputBufferLimits::parse)]
+    pub client_output_buffer_limit: Option<OutputBufferLimits>,
+    /// Bytes of a command a client may send before it is fully read, e.g. `1gb`.
+    #[arg(long, value_parser = parse_client_query_buffer_limit)]
+    pub client_query_buffer_limit: Option<usize>,
+}
+
+/// `tls-auth-clients`: mutual TLS is required, off, or checked only when a client sends a
+/// certificate.
+#[derive(ValueEnum, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TlsAuthClients {
+    #[default]
+    Yes,
+    No,
+    Optional,
+}
+
+impl fmt::Display for TlsAuthClients {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Yes => "yes",
+            Self::No => "no",
+            Self::Optional => "optional",
+        })
+    }
+}
+
+fn parse_databases(value: &str) -> Result<usize, String> {
+    match value.parse() {
+        Ok(databases) if databases > 0 => Ok(databases),
+        _ => Err("databases must be a positive integer".to_owned()),
+    }
+}
+
+fn parse_maxclients(value: &str) -> Result<usize, String> {
+    match value.parse() {
+        Ok(maxclients) if maxclients > 0 => Ok(maxclients),
+        _ => Err("maxclients must be a positive integer".to_owned()),
+    }
+}
+
+fn parse_client_query_buffer_limit(value: &str) -> Result<usize, String> {
+    match parse_memory(value) {
+        Some(limit) if limit >= 1024 * 1024 => Ok(limit),
+        _ => Err("client-query-buffer-limit must be a memory amount of at least 1mb".to_owned()),
+    }
+}
+
+fn parse_unixsocketperm(value: &str) -> Result<u32, String> {
+    match u32::from_str_radix(value, 8) {
+        Ok(perm) if perm <= 0o777 => Ok(perm),
+        _ => Err("unixsocketperm must be octal permissions like 700".to_owned()),
+    }
+}
+
+impl AppConfig {
+    pub fn port(&self) -> u16 {
+        self.port.unwrap_or(DEFAULT_PORT)
+    }
+
+    pub fn bind(&self) -> &str {
+        self.bind.as_deref().unwrap_or(DEFAULT_BIND)
+    }
+
+    pub fn databases(&self) -> usize {
+        self.databases.unwrap_or(DEFAULT_DATABASES)
+    }
+
+    pub fn notify_keyspace_events(&self) -> NotifyFlags {
+        self.notify_keyspace_events.unwrap_or_default()
+    }
+
+    pub fn busy_reply_threshold(&self) -> Duration {
+        Duration::from_millis(
+            self.busy_reply_threshold
+                .unwrap_or(DEFAULT_BUSY_REPLY_THRESHOLD),
+        )
+    }
+
+    pub fn maxclients(&self) -> usize {
+        self.maxclients.unwrap_or(DEFAULT_MAXCLIENTS)
+    }
+
+    pub fn client_output_buffer_limit(&self) -> OutputBufferLimits {
+        self.client_output_buffer_limit.unwrap_or_default()
+    }
+
+    pub fn client_query_buffer_limit(&self) -> usize {
+        self.client_query_buffer_limit
+            .unwrap_or(DEFAULT_CLIENT_QUERY_BUFFER_LIMIT)
+    }
+
+    pub fn tls_auth_clients(&self) -> TlsAuthClients {
+        self.tls_auth_clients.unwrap_or_default()
+    }
+
+    /// Path of the RDB file loaded on startup, when both `dir` and `dbfilename` are set.
+    pub fn rdb_path(&self) -> Option<PathBuf> {
+        Some(self.dir.as_ref()?.join(self.dbfilename.as_ref()?))
+    }
 }
 
 impl From<&AppConfig> for HashMap<ConfigField, Resp> {
@@ -50,9 +249,53 @@ impl From<&AppConfig> for HashMap<ConfigField, Resp> {
             .map(|dbfilename| Resp::bulk_string_from_str(&dbfilename))
             .unwrap_or(Resp::null_bulk_string());
 
+        let databases = Resp::bulk_string_from_str(&value.databases().to_string());
+        let notify_keyspace_events =
+            Resp::bulk_string_from_str(&value.notify_keyspace_events().to_string());
+        let busy_reply_threshold =
+            Resp::bulk_string_from_str(&value.busy_reply_threshold().as_millis().to_string());
+
+        let requirepass = Resp::bulk_string_from_str(value.requirepass.as_deref().unwrap_or(""));
+        let string = |value: &dyn ToString| Resp::bulk_string_from_str(&value.to_string());
+        let path = |path: &Option<PathBuf>| {
+            Resp::bulk_string_from_str(
+                &path
+                    .as_ref()
+                    .map(|path| path.display().to_string())
+                    .unwrap_or_default(),
+            )
+        };
+        let tls_port = Resp::bulk_string_from_str(&value.tls_port.unwrap_or_default().to_string());
+        let tls_auth_clients = Resp::bulk_string_from_str(&value.tls_auth_clients().to_string());
+        let unixsocketperm =
+            Resp::bulk_string_from_str(&format!("{:o}", value.unixsocketperm.unwrap_or_default()));
+
         [
+            (ConfigField::Port, string(&value.port())),
+            (ConfigField::Bind, string(&value.bind())),
             (ConfigField::Dir, dir),
             (ConfigField::Dbfilename, dbfilename),
+            (ConfigField::Databases, databases),
+            (ConfigField::NotifyKeyspaceEvents, notify_keyspace_events),
+            (ConfigField::BusyReplyThreshold, busy_reply_threshold),
+            (ConfigField::Requirepass, requirepass),
+            (ConfigField::Aclfile, path(&value.aclfile)),
+            (ConfigField::TlsPort, tls_port),
+            (ConfigField::TlsCertFile, path(&value.tls_cert_file)),
+            (ConfigField::TlsKeyFile, path(&value.tls_key_file)),
+            (ConfigField::TlsCaCertFile, path(&value.tls_ca_cert_file)),
+            (ConfigField::TlsAuthClients, tls_auth_clients),
+            (ConfigField::Unixsocket, path(&value.unixsocket)),
+            (ConfigField::Unixsocketperm, unixsocketperm),
+            (ConfigField::Maxclients, string(&value.maxclients())),
+            (
+                ConfigField::ClientOutputBufferLimit,
+                string(&value.client_output_buffer_limit()),
+            ),
+            (
+                ConfigField::ClientQueryBufferLimit,
+                string(&value.client_query_buffer_limit()),
+            ),
         ]
         .into()
     }
@@ -69,9 +312,119 @@ mod tests {
         assert_eq!(args.dir.unwrap(), PathBuf::from_str("/tmp/redis").unwrap());
     }
 
+    #[test]
+    fn should_parse_port_and_bind_args() {
+        let args =
+            AppConfig::try_parse_from(["config", "--port", "0", "--bind", "0.0.0.0"]).unwrap();
+        assert_eq!(args.port(), 0);
+        assert_eq!(args.bind(), "0.0.0.0");
+        assert_eq!(AppConfig::default().port(), 6379);
+        assert_eq!(AppConfig::default().bind(), "127.0.0.1");
+        assert!(AppConfig::try_parse_from(["config", "--port", "65536"]).is_err());
+    }
+
     #[test]
     fn should_parse_dbfilename_arg() {
         let args = AppConfig::try_parse_from(["config", "--dbfilename", "redis.rdb"]).unwrap();
         assert_eq!(args.dbfilename.unwrap(), "redis.rdb");
     }
+
+    #[test]
+    fn should_parse_databases_arg() {
+        let args = AppConfig::try_parse_from(["config", "--databases", "4"]).unwrap();
+        assert_eq!(args.databases(), 4);
+        assert_eq!(AppConfig::default().databases(), 16);
+        assert!(AppConfig::try_parse_from(["config", "--databases", "0"]).is_err());
+    }
+
+    #[test]
+    fn should_parse_notify_keyspace_events_arg() {
+        let args = AppConfig::try_parse_from(["config", "--notify-keyspace-events", "xE"]).unwrap();
+        assert_eq!(args.notify_keyspace_events().to_string(), "xE");
+        assert_eq!(
+            AppConfig::default().notify_keyspace_events().to_string(),
+            ""
+        );
+        assert!(AppConfig::try_parse_from(["config", "--notify-keyspace-events", "?"]).is_err());
+    }
+
+    #[test]
+    fn should_parse_busy_reply_threshold_arg() {
+        let args = AppConfig::try_parse_from(["config", "--busy-reply-threshold", "100"]).unwrap();
+        assert_eq!(args.busy_reply_threshold(), Duration::from_millis(100));
+        let args = AppConfig::try_parse_from(["config", "--lua-time-limit", "200"]).unwrap();
+        assert_eq!(args.busy_reply_threshold(), Duration::from_millis(200));
+        assert_eq!(
+            AppConfig::default().busy_reply_threshold(),
+            Duration::from_secs(5)
+        );
+    }
+
+    #[test]
+    fn should_parse_tls_args() {
+        let args = AppConfig::try_parse_from([
+            "config",
+            "--tls-port",
+            "6380",
+            "--tls-cert-file",
+            "redis.crt",
+            "--tls-auth-clients",
+            "optional",
+        ])
+        .unwrap();
+        assert_eq!(args.tls_port, Some(6380));
+        assert_eq!(args.tls_cert_file, Some(PathBuf::from("redis.crt")));
+        assert_eq!(args.tls_auth_clients(), TlsAuthClients::Optional);
+        assert_eq!(AppConfig::default().tls_auth_clients(), TlsAuthClients::Yes);
+        assert!(AppConfig::try_parse_from(["config", "--tls-auth-clients", "maybe"]).is_err());
+    }
+
+    #[test]
+    fn should_parse_unixsocket_args() {
+        let args = AppConfig::try_parse_from([
+            "config",
+            "--unixsocket",
+            "/tmp/redis.sock",
+            "--unixsocketperm",
+            "770",
+        ])
+        .unwrap();
+        assert_eq!(args.unixsocket, Some(PathBuf::from("/tmp/redis.sock")));
+        assert_eq!(args.unixsocketperm, Some(0o770));
+        assert!(AppConfig::try_parse_from(["config", "--unixsocketperm", "800"]).is_err());
+    }
+
+    #[test]
+    fn should_parse_client_limit_args() {
+        let args = AppConfig::try_parse_from([
+            "config",
+            "--maxclients",
+            "2",
+            "--client-output-buffer-limit",
+            "normal 1mb 512kb 10 pubsub 64mb 16mb 30",
+            "--client-query-buffer-limit",
+            "2mb",
+        ])
+        .unwrap();
+        assert_eq!(args.maxclients(), 2);
+        let limits = args.client_output_buffer_limit();
+        assert_eq!(limits.normal.hard, 1024 * 1024);
+        assert_eq!(limits.normal.soft, 512 * 1024);
+        assert_eq!(limits.pubsub.soft_seconds, 30);
+        assert_eq!(limits.replica.hard, 256 * 1024 * 1024);
+        assert_eq!(args.client_query_buffer_limit(), 2 * 1024 * 1024);
+        assert_eq!(
+            AppConfig::default()
+                .client_output_buffer_limit()
+                .to_string(),
+            "normal 0 0 0 replica 268435456 67108864 60 pubsub 33554432 8388608 60"
+        );
+        assert!(
+            AppConfig::try_parse_from(["config", "--client-output-buffer-limit", "x 1 1 1"])
+                .is_err()
+        );
+        assert!(
+            AppConfig::try_parse_from(["config", "--client-query-buffer-limit", "1k"]).is_err()
+        );
+    }
 }
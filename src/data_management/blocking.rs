@@ -0,0 +1,87 @@
+use std::time::Duration;
+
+use tokio::{sync::oneshot, time::Instant};
+
+use crate::{errors::AppError, resp::Resp};
+
+use super::{
+    consumer_group::{read_group, GroupStreams},
+    datastore::DataStore,
+    message::ResponseChannelMessage,
+    sorted_set::pop_first_available,
+    stream::{read_streams, resolve_last_ids, ReadStreams},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockingCommand {
+    /// `BZPOPMIN` / `BZPOPMAX`.
+    ZPop { keys: Vec<Vec<u8>>, max: bool },
+    /// `XREAD ... BLOCK`.
+    XRead {
+        streams: ReadStreams,
+        count: Option<usize>,
+    },
+    /// `XREADGROUP ... BLOCK` where every stream is read with `>`.
+    XReadGroup {
+        group: Vec<u8>,
+        consumer: Vec<u8>,
+        streams: GroupStreams,
+        count: Option<usize>,
+        noack: bool,
+    },
+}
+
+impl BlockingCommand {
+    /// Pins state that has to be captured when the command is received, like `XREAD`'s `$`.
+    pub fn prepare<T: DataStore>(&mut self, store: &mut T) -> Result<(), AppError> {
+        match self {
+            Self::ZPop { .. } | Self::XReadGroup { .. } => Ok(()),
+            Self::XRead { streams, .. } => resolve_last_ids(store, streams),
+        }
+    }
+
+    /// Runs the command, returning `None` when the client has to keep waiting.
+    pub fn try_serve<T: DataStore>(&self, store: &mut T) -> Result<Option<Resp>, AppError> {
+        match self {
+            Self::ZPop { keys, max } => pop_first_available(store, keys, *max),
+            Self::XRead { streams, count } => read_streams(store, streams, *count),
+            Self::XReadGroup {
+                group,
+                consumer,
+                streams,
+                count,
+                noack,
+            } => read_group(store, group, consumer, streams, *count, *noack),
+        }
+    }
+}
+
+/// A client parked by the data worker until its command can be served or `deadline` passes.
+#[derive(Debug)]
+pub struct BlockedClient {
+    pub db: usize,
+    pub command: BlockingCommand,
+    pub deadline: Option<Instant>,
+    pub sender: oneshot::Sender<ResponseChannelMessage>,
+}
+
+impl BlockedClient {
+    /// A `None` timeout blocks forever.
+    pub fn new(
+        db: usize,
+        command: BlockingCommand,
+        timeout: Option<Duration>,
+        sender: oneshot::Sender<ResponseChannelMessage>,
+    ) -> Self {
+        Self {
+            db,
+            command,
+            deadline: timeout.map(|timeout| Instant::now() + timeout),
+            sender,
+        }
+    }
+
+    pub fn timed_out(&self, now: Instant) -> bool {
+        self.deadline.is_some_and(|deadline| deadline <= now)
+    }
+}
@@ -0,0 +1,820 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ops::Bound,
+};
+
+use crate::{errors::AppError, resp::Resp};
+
+use super::{
+    datastore::{DataStore, DataStoreEntry, DataValue},
+    notification::NotifyFlags,
+    stream::{entry_reply, now_ms, stream_mut, ReadFrom, StreamFields, StreamId, StreamValue},
+};
+
+/// Entry of a group's pending entries list (PEL): delivered but not acknowledged yet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingEntry {
+    pub consumer: Vec<u8>,
+    pub delivery_time: u64,
+    pub delivery_count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Consumer {
+    pub seen_time: u64,
+    pub active_time: Option<u64>,
+    pub pending: BTreeSet<StreamId>,
+}
+
+impl Consumer {
+    fn new(now: u64) -> Self {
+        Self {
+            seen_time: now,
+            active_time: None,
+            pending: BTreeSet::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsumerGroup {
+    pub last_delivered_id: StreamId,
+    pub entries_read: Option<u64>,
+    pub pending: BTreeMap<StreamId, PendingEntry>,
+    pub consumers: BTreeMap<Vec<u8>, Consumer>,
+}
+
+impl ConsumerGroup {
+    pub fn new(last_delivered_id: StreamId, entries_read: Option<u64>) -> Self {
+        Self {
+            last_delivered_id,
+            entries_read,
+            pending: BTreeMap::new(),
+            consumers: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the consumer, creating it if needed, and marks it as seen.
+    fn consumer(&mut self, name: &[u8], now: u64) -> &mut Consumer {
+        let consumer = self
+            .consumers
+            .entry(name.to_owned())
+            .or_insert_with(|| Consumer::new(now));
+        consumer.seen_time = now;
+        consumer
+    }
+
+    /// Gives the pending entry `id` to `consumer`, taking it from its previous owner.
+    fn assign(&mut self, id: StreamId, consumer: &[u8], delivery_time: u64, delivery_count: u64) {
+        if let Some(previous) = self.pending.get(&id) {
+            if let Some(owner) = self.consumers.get_mut(&previous.consumer) {
+                owner.pending.remove(&id);
+            }
+        }
+        self.consumer(consumer, delivery_time).pending.insert(id);
+        let entry = PendingEntry {
+            consumer: consumer.to_owned(),
+            delivery_time,
+            delivery_count,
+        };
+        self.pending.insert(id, entry);
+    }
+
+    pub fn ack(&mut self, id: &StreamId) -> bool {
+        let Some(entry) = self.pending.remove(id) else {
+            return false;
+        };
+        if let Some(consumer) = self.consumers.get_mut(&entry.consumer) {
+            consumer.pending.remove(id);
+        }
+        true
+    }
+}
+
+/// ID argument of `XREADGROUP`: `>` for never delivered entries, or an ID to replay
+/// the consumer's own pending history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadGroupFrom {
+    New,
+    History(StreamId),
+}
+
+pub type GroupStreams = Vec<(Vec<u8>, ReadGroupFrom)>;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClaimOptions {
+    pub idle: Option<u64>,
+    pub time: Option<u64>,
+    pub retry_count: Option<u64>,
+    pub force: bool,
+    pub justid: bool,
+    pub last_id: Option<StreamId>,
+}
+
+/// Extended form of `XPENDING`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingFilter {
+    pub min_idle: Option<u64>,
+    pub start: StreamId,
+    pub end: StreamId,
+    pub count: usize,
+    pub consumer: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConsumerGroupCommand {
+    Create {
+        key: Vec<u8>,
+        group: Vec<u8>,
+        start: ReadFrom,
+        mkstream: bool,
+        entries_read: Option<u64>,
+    },
+    SetId {
+        key: Vec<u8>,
+        group: Vec<u8>,
+        start: ReadFrom,
+        entries_read: Option<u64>,
+    },
+    Destroy {
+        key: Vec<u8>,
+        group: Vec<u8>,
+    },
+    CreateConsumer {
+        key: Vec<u8>,
+        group: Vec<u8>,
+        consumer: Vec<u8>,
+    },
+    DelConsumer {
+        key: Vec<u8>,
+        group: Vec<u8>,
+        consumer: Vec<u8>,
+    },
+    ReadGroup {
+        group: Vec<u8>,
+        consumer: Vec<u8>,
+        streams: GroupStreams,
+        count: Option<usize>,
+        noack: bool,
+    },
+    Ack {
+        key: Vec<u8>,
+        group: Vec<u8>,
+        ids: Vec<StreamId>,
+    },
+    Pending {
+        key: Vec<u8>,
+        group: Vec<u8>,
+        filter: Option<PendingFilter>,
+    },
+    Claim {
+        key: Vec<u8>,
+        group: Vec<u8>,
+        consumer: Vec<u8>,
+        min_idle: u64,
+        ids: Vec<StreamId>,
+        options: ClaimOptions,
+    },
+    AutoClaim {
+        key: Vec<u8>,
+        group: Vec<u8>,
+        consumer: Vec<u8>,
+        min_idle: u64,
+        start: StreamId,
+        count: usize,
+        justid: bool,
+    },
+    InfoStream(Vec<u8>),
+    InfoGroups(Vec<u8>),
+    InfoConsumers {
+        key: Vec<u8>,
+        group: Vec<u8>,
+    },
+}
+
+/// Human readable name of a key stored under its serialized RESP form.
+fn key_name(key: &[u8]) -> String {
+    match Resp::deserialize(key) {
+        Ok(Resp::BulkString(name)) => String::from_utf8_lossy(&name).into_owned(),
+        _ => String::from_utf8_lossy(key).into_owned(),
+    }
+}
+
+fn no_group(key: &[u8], group: &[u8]) -> AppError {
+    AppError::NoGroup(key_name(key), String::from_utf8_lossy(group).into_owned())
+}
+
+fn existing_stream<'a, T: DataStore>(
+    store: &'a mut T,
+    key: &[u8],
+) -> Result<&'a mut StreamValue, AppError> {
+    stream_mut(store, key)?.ok_or_else(|| {
+        AppError::Generic(
+            "The XGROUP subcommand requires the key to exist. Note that for CREATE you may want \
+             to use the MKSTREAM option to create an empty stream automatically."
+                .to_owned(),
+        )
+    })
+}
+
+fn group_mut<'a, T: DataStore>(
+    store: &'a mut T,
+    key: &[u8],
+    group: &[u8],
+) -> Result<&'a mut ConsumerGroup, AppError> {
+    stream_mut(store, key)?
+        .and_then(|stream| stream.groups.get_mut(group))
+        .ok_or_else(|| no_group(key, group))
+}
+
+fn start_id(stream: &StreamValue, start: ReadFrom) -> StreamId {
+    match start {
+        ReadFrom::Id(id) => id,
+        ReadFrom::Last => stream.last_id(),
+    }
+}
+
+fn optional_integer(value: Option<u64>) -> Resp {
+    value.map_or(Resp::null_bulk_string(), |value| {
+        Resp::Integers(value as i64)
+    })
+}
+
+fn entry_or_nil(entries: &BTreeMap<StreamId, StreamFields>, id: StreamId) -> Resp {
+    match entries.get(&id) {
+        Some(fields) => entry_reply(id, fields),
+        None => Resp::Array(vec![id.reply(), Resp::NullArray]),
+    }
+}
+
+/// `XREADGROUP` reply, `None` when every stream is read with `>` and has nothing new,
+/// so that a `BLOCK` read keeps waiting.
+pub fn read_group<T: DataStore>(
+    store: &mut T,
+    group: &[u8],
+    consumer: &[u8],
+    streams: &[(Vec<u8>, ReadGroupFrom)],
+    count: Option<usize>,
+    noack: bool,
+) -> Result<Option<Resp>, AppError> {
+    let now = now_ms();
+    let count = count.filter(|count| *count > 0).unwrap_or(usize::MAX);
+    let mut reply = vec![];
+    let mut history = false;
+    for (key, from) in streams {
+        let stream = stream_mut(store, key)?.ok_or_else(|| no_group(key, group))?;
+        let entries_added = stream.entries_added();
+        let last_id = stream.last_id();
+        let entries = &stream.entries;
+        let consumer_group = stream
+            .groups
+            .get_mut(group)
+            .ok_or_else(|| no_group(key, group))?;
+        consumer_group.consumer(consumer, now);
+        let delivered: Vec<Resp> = match from {
+            ReadGroupFrom::New => {
+                let range = (
+                    Bound::Excluded(consumer_group.last_delivered_id),
+                    Bound::Unbounded,
+                );
+                let delivered: Vec<_> = entries
+                    .range(range)
+                    .take(count)
+                    .map(|(id, fields)| (*id, entry_reply(*id, fields)))
+                    .collect();
+                for (id, _) in &delivered {
+                    consumer_group.last_delivered_id = *id;
+                    consumer_group.entries_read = match consumer_group.entries_read {
+                        _ if *id == last_id => Some(entries_added),
+                        Some(read) => Some(read + 1),
+                        None => None,
+                    };
+                    if !noack {
+                        consumer_group.assign(*id, consumer, now, 1);
+                    }
+                }
+                if !delivered.is_empty() {
+                    consumer_group.consumer(consumer, now).active_time = Some(now);
+                }
+                delivered.into_iter().map(|(_, entry)| entry).collect()
+            }
+            ReadGroupFrom::History(after) => {
+                let pending = &consumer_group.consumer(consumer, now).pending;
+                pending
+                    .range((Bound::Excluded(*after), Bound::Unbounded))
+                    .take(count)
+                    .map(|id| entry_or_nil(entries, *id))
+                    .collect()
+            }
+        };
+        let is_history = matches!(from, ReadGroupFrom::History(_));
+        history |= is_history;
+        if is_history || !delivered.is_empty() {
+            reply.push(Resp::Array(vec![
+                Resp::deserialize(key)?,
+                Resp::Array(delivered),
+            ]));
+        }
+    }
+    Ok((history || !reply.is_empty()).then_some(Resp::Array(reply)))
+}
+
+fn flat_map(fields: Vec<(&str, Resp)>) -> Resp {
+    Resp::Array(
+        fields
+            .into_iter()
+            .flat_map(|(name, value)| [Resp::bulk_string_from_str(name), value])
+            .collect(),
+    )
+}
+
+fn lag(stream: &StreamValue, group: &ConsumerGroup) -> Option<u64> {
+    if group.last_delivered_id >= stream.last_id() {
+        return Some(0);
+    }
+    let no_tombstone = stream.max_deleted_id() < group.last_delivered_id
+        || stream
+            .first_entry()
+            .is_none_or(|(first, _)| stream.max_deleted_id() < *first);
+    let read = group.entries_read?;
+    no_tombstone.then(|| stream.entries_added().saturating_sub(read))
+}
+
+pub fn apply_consumer_group_command<T: DataStore>(
+    store: &mut T,
+    command: ConsumerGroupCommand,
+) -> Result<Resp, AppError> {
+    let now = now_ms();
+    match command {
+        ConsumerGroupCommand::Create {
+            key,
+            group,
+            start,
+            mkstream,
+            entries_read,
+        } => {
+            if mkstream && stream_mut(store, &key)?.is_none() {
+                let value = DataValue::Stream(StreamValue::default());
+                store.insert_entry(key.clone(), DataStoreEntry::with_value(value, None));
+            }
+            let stream = existing_stream(store, &key)?;
+            if stream.groups.contains_key(&group) {
+                return Err(AppError::BusyGroup);
+            }
+            let id = start_id(stream, start);
+            let entries_read = entries_read.or_else(|| stream.entries_read_at(id));
+            stream
+                .groups
+                .insert(group, ConsumerGroup::new(id, entries_read));
+            store.notify(NotifyFlags::STREAM, "xgroup-create", &key);
+            Ok(Resp::simple_string_from_str("OK"))
+        }
+        ConsumerGroupCommand::SetId {
+            key,
+            group,
+            start,
+            entries_read,
+        } => {
+            let stream = existing_stream(store, &key)?;
+            let id = start_id(stream, start);
+            let estimated = entries_read.or_else(|| stream.entries_read_at(id));
+            let consumer_group = stream
+                .groups
+                .get_mut(&group)
+                .ok_or_else(|| no_group(&key, &group))?;
+            consumer_group.last_delivered_id = id;
+            consumer_group.entries_read = estimated;
+            store.notify(NotifyFlags::STREAM, "xgroup-setid", &key);
+            Ok(Resp::simple_string_from_str("OK"))
+        }
+        ConsumerGroupCommand::Destroy { key, group } => {
+            let removed = existing_stream(store, &key)?
+                .groups
+                .remove(&group)
+                .is_some();
+            if removed {
+                store.notify(NotifyFlags::STREAM, "xgroup-destroy", &key);
+            }
+            Ok(Resp::Integers(removed as i64))
+        }
+        ConsumerGroupCommand::CreateConsumer {
+            key,
+            group,
+            consumer,
+        } => {
+            existing_stream(store, &key)?;
+            let consumer_group = group_mut(store, &key, &group)?;
+            if consumer_group.consumers.contains_key(&consumer) {
+                return Ok(Resp::Integers(0));
+            }
+            consumer_group.consumer(&consumer, now);
+            store.notify(NotifyFlags::STREAM, "xgroup-createconsumer", &key);
+            Ok(Resp::Integers(1))
+        }
+        ConsumerGroupCommand::DelConsumer {
+            key,
+            group,
+            consumer,
+        } => {
+            existing_stream(store, &key)?;
+            let consumer_group = group_mut(store, &key, &group)?;
+            let Some(removed) = consumer_group.consumers.remove(&consumer) else {
+                return Ok(Resp::Integers(0));
+            };
+            for id in &removed.pending {
+                consumer_group.pending.remove(id);
+            }
+            store.notify(NotifyFlags::STREAM, "xgroup-delconsumer", &key);
+            Ok(Resp::Integers(removed.pending.len() as i64))
+        }
+        ConsumerGroupCommand::ReadGroup {
+            group,
+            consumer,
+            streams,
+            count,
+            noack,
+        } => Ok(
+            read_group(store, &group, &consumer, &streams, count, noack)?
+                .unwrap_or(Resp::NullArray),
+        ),
+        ConsumerGroupCommand::Ack { key, group, ids } => {
+            let Ok(consumer_group) = group_mut(store, &key, &group) else {
+                return Ok(Resp::Integers(0));
+            };
+            let acked = ids.iter().filter(|id| consumer_group.ack(id)).count();
+            Ok(Resp::Integers(acked as i64))
+        }
+        ConsumerGroupCommand::Pending { key, group, filter } => {
+            let consumer_group = group_mut(store, &key, &group)?;
+            let Some(filter) = filter else {
+                let (Some((first, _)), Some((last, _))) = (
+                    consumer_group.pending.first_key_value(),
+                    consumer_group.pending.last_key_value(),
+                ) else {
+                    return Ok(Resp::Array(vec![
+                        Resp::Integers(0),
+                        Resp::null_bulk_string(),
+                        Resp::null_bulk_string(),
+                        Resp::NullArray,
+                    ]));
+                };
+                let consumers = consumer_group
+                    .consumers
+                    .iter()
+                    .filter(|(_, consumer)| !consumer.pending.is_empty())
+                    .map(|(name, consumer)| {
+                        Resp::Array(vec![
+                            Resp::BulkString(name.to_owned()),
+                            Resp::BulkString(consumer.pending.len().to_string().into_bytes()),
+                        ])
+                    })
+                    .collect();
+                return Ok(Resp::Array(vec![
+                    Resp::Integers(consumer_group.pending.len() as i64),
+                    first.reply(),
+                    last.reply(),
+                    Resp::Array(consumers),
+                ]));
+            };
+            if filter.start > filter.end {
+                return Ok(Resp::Array(vec![]));
+            }
+            let entries = consumer_group
+                .pending
+                .range(filter.start..=filter.end)
+                .filter(|(_, entry)| {
+                    filter
+                        .consumer
+                        .as_ref()
+                        .is_none_or(|consumer| *consumer == entry.consumer)
+                })
+                .filter(|(_, entry)| {
+                    filter
+                        .min_idle
+                        .is_none_or(|min_idle| now.saturating_sub(entry.delivery_time) >= min_idle)
+                })
+                .take(filter.count)
+                .map(|(id, entry)| {
+                    Resp::Array(vec![
+                        id.reply(),
+                        Resp::BulkString(entry.consumer.to_owned()),
+                        Resp::Integers(now.saturating_sub(entry.delivery_time) as i64),
+                        Resp::Integers(entry.delivery_count as i64),
+                    ])
+                })
+                .collect();
+            Ok(Resp::Array(entries))
+        }
+        ConsumerGroupCommand::Claim {
+            key,
+            group,
+            consumer,
+            min_idle,
+            ids,
+            options,
+        } => {
+            let stream = stream_mut(store, &key)?.ok_or_else(|| no_group(&key, &group))?;
+            let entries = &stream.entries;
+            let consumer_group = stream
+                .groups
+                .get_mut(&group)
+                .ok_or_else(|| no_group(&key, &group))?;
+            consumer_group.consumer(&consumer, now);
+            let delivery_time = options
+                .time
+                .or(options.idle.map(|idle| now.saturating_sub(idle)))
+                .unwrap_or(now);
+            let mut claimed = vec![];
+            for id in ids {
+                let delivery_count = match consumer_group.pending.get(&id) {
+                    Some(entry) if now.saturating_sub(entry.delivery_time) < min_idle => continue,
+                    Some(_) if !entries.contains_key(&id) => {
+                        consumer_group.ack(&id);
+                        continue;
+                    }
+                    Some(entry) => entry.delivery_count,
+                    None if options.force && entries.contains_key(&id) => 0,
+                    None => continue,
+                };
+                let delivery_count = options
+                    .retry_count
+                    .unwrap_or(delivery_count + u64::from(!options.justid));
+                consumer_group.assign(id, &consumer, delivery_time, delivery_count);
+                claimed.push(if options.justid {
+                    id.reply()
+                } else {
+                    entry_or_nil(entries, id)
+                });
+            }
+            if let Some(last_id) = options.last_id {
+                consumer_group.last_delivered_id = consumer_group.last_delivered_id.max(last_id);
+            }
+            if !claimed.is_empty() {
+                consumer_group.consumer(&consumer, now).active_time = Some(now);
+            }
+            Ok(Resp::Array(claimed))
+        }
+        ConsumerGroupCommand::AutoClaim {
+            key,
+            group,
+            consumer,
+            min_idle,
+            start,
+            count,
+            justid,
+        } => {
+            let stream = stream_mut(store, &key)?.ok_or_else(|| no_group(&key, &group))?;
+            let entries = &stream.entries;
+            let consumer_group = stream
+                .groups
+                .get_mut(&group)
+                .ok_or_else(|| no_group(&key, &group))?;
+            consumer_group.consumer(&consumer, now);
+            // Like Redis, scan at most ten times `count` entries of the PEL per call.
+            let attempts = count.saturating_mul(10);
+            let candidates: Vec<_> = consumer_group
+                .pending
+                .range(start..)
+                .map(|(id, entry)| (*id, entry.delivery_time, entry.delivery_count))
+                .take(attempts.saturating_add(1))
+                .collect();
+            let (mut claimed, mut deleted, mut next) = (vec![], vec![], StreamId::MIN);
+            for (scanned, (id, delivery_time, delivery_count)) in candidates.into_iter().enumerate()
+            {
+                if claimed.len() >= count || scanned >= attempts {
+                    next = id;
+                    break;
+                }
+                if now.saturating_sub(delivery_time) < min_idle {
+                    continue;
+                }
+                if !entries.contains_key(&id) {
+                    consumer_group.ack(&id);
+                    deleted.push(id.reply());
+                    continue;
+                }
+                let delivery_count = delivery_count + u64::from(!justid);
+                consumer_group.assign(id, &consumer, now, delivery_count);
+                claimed.push(if justid {
+                    id.reply()
+                } else {
+                    entry_or_nil(entries, id)
+                });
+            }
+            if !claimed.is_empty() {
+                consumer_group.consumer(&consumer, now).active_time = Some(now);
+            }
+            Ok(Resp::Array(vec![
+                next.reply(),
+                Resp::Array(claimed),
+                Resp::Array(deleted),
+            ]))
+        }
+        ConsumerGroupCommand::InfoStream(key) => {
+            let stream = stream_mut(store, &key)?
+                .ok_or_else(|| AppError::Generic("no such key".to_owned()))?;
+            let entry = |entry: Option<(&StreamId, &StreamFields)>| {
+                entry.map_or(Resp::null_bulk_string(), |(id, fields)| {
+                    entry_reply(*id, fields)
+                })
+            };
+            let recorded_first_id = stream.first_entry().map_or(StreamId::MIN, |(id, _)| *id);
+            Ok(flat_map(vec![
+                ("length", Resp::Integers(stream.len() as i64)),
+                ("last-generated-id", stream.last_id().reply()),
+                ("max-deleted-entry-id", stream.max_deleted_id().reply()),
+                (
+                    "entries-added",
+                    Resp::Integers(stream.entries_added() as i64),
+                ),
+                ("recorded-first-entry-id", recorded_first_id.reply()),
+                ("groups", Resp::Integers(stream.groups.len() as i64)),
+                ("first-entry", entry(stream.first_entry())),
+                ("last-entry", entry(stream.last_entry())),
+            ]))
+        }
+        ConsumerGroupCommand::InfoGroups(key) => {
+            let stream = stream_mut(store, &key)?
+                .ok_or_else(|| AppError::Generic("no such key".to_owned()))?;
+            let groups = stream
+                .groups
+                .iter()
+                .map(|(name, group)| {
+                    flat_map(vec![
+                        ("name", Resp::BulkString(name.to_owned())),
+                        ("consumers", Resp::Integers(group.consumers.len() as i64)),
+                        ("pending", Resp::Integers(group.pending.len() as i64)),
+                        ("last-delivered-id", group.last_delivered_id.reply()),
+                        ("entries-read", optional_integer(group.entries_read)),
+                        ("lag", optional_integer(lag(stream, group))),
+                    ])
+                })
+                .collect();
+            Ok(Resp::Array(groups))
+        }
+        ConsumerGroupCommand::InfoConsumers { key, group } => {
+            let stream = stream_mut(store, &key)?
+                .ok_or_else(|| AppError::Generic("no such key".to_owned()))?;
+            let consumer_group = stream
+                .groups
+                .get(&group)
+                .ok_or_else(|| no_group(&key, &group))?;
+            let consumers = consumer_group
+                .consumers
+                .iter()
+                .map(|(name, consumer)| {
+                    let inactive = consumer
+                        .active_time
+                        .map_or(-1, |active| now.saturating_sub(active) as i64);
+                    flat_map(vec![
+                        ("name", Resp::BulkString(name.to_owned())),
+                        ("pending", Resp::Integers(consumer.pending.len() as i64)),
+                        (
+                            "idle",
+                            Resp::Integers(now.saturating_sub(consumer.seen_time) as i64),
+                        ),
+                        ("inactive", Resp::Integers(inactive)),
+                    ])
+                })
+                .collect();
+            Ok(Resp::Array(consumers))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data_management::{
+        hash_table_store::HashTableDataStore,
+        stream::{apply_stream_command, IdSpec, StreamCommand},
+    };
+
+    fn key() -> Vec<u8> {
+        Resp::bulk_string_from_str("events").serialize().unwrap()
+    }
+
+    fn store_with_group(entries: u64) -> HashTableDataStore {
+        let mut store = HashTableDataStore::default();
+        for ms in 1..=entries {
+            let add = StreamCommand::Add {
+                key: key(),
+                id: IdSpec::Explicit(StreamId::new(ms, 0)),
+                fields: vec![(b"n".to_vec(), ms.to_string().into_bytes())],
+                trim: None,
+                nomkstream: false,
+            };
+            apply_stream_command(&mut store, add).unwrap();
+        }
+        let create = ConsumerGroupCommand::Create {
+            key: key(),
+            group: b"workers".to_vec(),
+            start: ReadFrom::Id(StreamId::MIN),
+            mkstream: true,
+            entries_read: None,
+        };
+        apply_consumer_group_command(&mut store, create).unwrap();
+        store
+    }
+
+    fn read(store: &mut HashTableDataStore, consumer: &[u8], from: ReadGroupFrom) -> Option<Resp> {
+        let streams = vec![(key(), from)];
+        read_group(store, b"workers", consumer, &streams, Some(2), false).unwrap()
+    }
+
+    fn group(store: &mut HashTableDataStore) -> &mut ConsumerGroup {
+        group_mut(store, &key(), b"workers").unwrap()
+    }
+
+    #[test]
+    fn should_deliver_new_entries_once_and_track_them_as_pending() {
+        let mut store = store_with_group(3);
+        assert!(read(&mut store, b"alice", ReadGroupFrom::New).is_some());
+        assert!(read(&mut store, b"bob", ReadGroupFrom::New).is_some());
+        assert!(read(&mut store, b"bob", ReadGroupFrom::New).is_none());
+        let workers = group(&mut store);
+        assert_eq!(workers.last_delivered_id, StreamId::new(3, 0));
+        assert_eq!(workers.entries_read, Some(3));
+        assert_eq!(workers.consumers[b"alice".as_slice()].pending.len(), 2);
+        assert_eq!(workers.pending[&StreamId::new(3, 0)].consumer, b"bob");
+    }
+
+    #[test]
+    fn should_replay_history_until_acknowledged() {
+        let mut store = store_with_group(2);
+        read(&mut store, b"alice", ReadGroupFrom::New);
+        let ack = ConsumerGroupCommand::Ack {
+            key: key(),
+            group: b"workers".to_vec(),
+            ids: vec![StreamId::new(1, 0), StreamId::new(9, 0)],
+        };
+        let acked = apply_consumer_group_command(&mut store, ack).unwrap();
+        assert_eq!(acked, Resp::Integers(1));
+        let Some(Resp::Array(streams)) =
+            read(&mut store, b"alice", ReadGroupFrom::History(StreamId::MIN))
+        else {
+            panic!()
+        };
+        let Resp::Array(stream) = &streams[0] else {
+            panic!()
+        };
+        assert_eq!(
+            stream[1],
+            Resp::Array(vec![entry_reply(
+                StreamId::new(2, 0),
+                &vec![(b"n".to_vec(), b"2".to_vec())]
+            )])
+        );
+    }
+
+    #[test]
+    fn should_claim_idle_entries_for_another_consumer() {
+        let mut store = store_with_group(1);
+        read(&mut store, b"alice", ReadGroupFrom::New);
+        let claim = |min_idle| ConsumerGroupCommand::Claim {
+            key: key(),
+            group: b"workers".to_vec(),
+            consumer: b"bob".to_vec(),
+            min_idle,
+            ids: vec![StreamId::new(1, 0)],
+            options: ClaimOptions {
+                justid: true,
+                ..Default::default()
+            },
+        };
+        let claimed = apply_consumer_group_command(&mut store, claim(60_000)).unwrap();
+        assert_eq!(claimed, Resp::Array(vec![]));
+        let claimed = apply_consumer_group_command(&mut store, claim(0)).unwrap();
+        assert_eq!(claimed, Resp::Array(vec![StreamId::new(1, 0).reply()]));
+        let workers = group(&mut store);
+        assert!(workers.consumers[b"alice".as_slice()].pending.is_empty());
+        assert_eq!(workers.pending[&StreamId::new(1, 0)].consumer, b"bob");
+        assert_eq!(workers.pending[&StreamId::new(1, 0)].delivery_count, 1);
+    }
+
+    #[test]
+    fn should_reject_duplicate_and_missing_groups() {
+        let mut store = store_with_group(0);
+        let create = ConsumerGroupCommand::Create {
+            key: key(),
+            group: b"workers".to_vec(),
+            start: ReadFrom::Last,
+            mkstream: false,
+            entries_read: None,
+        };
+        let result = apply_consumer_group_command(&mut store, create);
+        assert!(matches!(result, Err(AppError::BusyGroup)));
+        let result = read_group(
+            &mut store,
+            b"missing",
+            b"alice",
+            &[(key(), ReadGroupFrom::New)],
+            None,
+            false,
+        );
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "NOGROUP No such key 'events' or consumer group 'missing'"
+        );
+    }
+}
@@ -0,0 +1,189 @@
+use crate::{errors::AppError, resp::Resp};
+
+use super::{datastore::DataStore, keyspace::lazy_drop, notification::NotifyFlags};
+
+/// Commands addressing whole logical databases, or keys across two of them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DatabaseCommand {
+    DbSize,
+    FlushDb {
+        lazy: bool,
+    },
+    FlushAll {
+        lazy: bool,
+    },
+    SwapDb(usize, usize),
+    Copy {
+        source: Vec<u8>,
+        destination: Vec<u8>,
+        db: Option<usize>,
+        replace: bool,
+    },
+    Move {
+        key: Vec<u8>,
+        db: usize,
+    },
+}
+
+fn same_object() -> AppError {
+    AppError::Generic("source and destination objects are the same".to_owned())
+}
+
+fn check_index<T>(databases: &[T], db: usize) -> Result<(), AppError> {
+    if db >= databases.len() {
+        return Err(AppError::Generic("DB index is out of range".to_owned()));
+    }
+    Ok(())
+}
+
+/// Mutable borrows of two distinct databases.
+fn pair_mut<T>(databases: &mut [T], first: usize, second: usize) -> (&mut T, &mut T) {
+    if first < second {
+        let (head, tail) = databases.split_at_mut(second);
+        (&mut head[first], &mut tail[0])
+    } else {
+        let (head, tail) = databases.split_at_mut(first);
+        (&mut tail[0], &mut head[second])
+    }
+}
+
+/// Empties `store`, dropping its content off the worker when `lazy`.
+fn flush<T: DataStore>(store: &mut T, lazy: bool) {
+    let content = std::mem::take(store);
+    if lazy {
+        lazy_drop(content);
+    }
+}
+
+pub fn apply_database_command<T: DataStore>(
+    databases: &mut [T],
+    db: usize,
+    command: DatabaseCommand,
+) -> Result<Resp, AppError> {
+    match command {
+        DatabaseCommand::DbSize => Ok(Resp::Integers(databases[db].len() as i64)),
+        DatabaseCommand::FlushDb { lazy } => {
+            flush(&mut databases[db], lazy);
+            Ok(Resp::simple_string_from_str("OK"))
+        }
+        DatabaseCommand::FlushAll { lazy } => {
+            databases.iter_mut().for_each(|store| flush(store, lazy));
+            Ok(Resp::simple_string_from_str("OK"))
+        }
+        DatabaseCommand::SwapDb(first, second) => {
+            check_index(databases, first)?;
+            check_index(databases, second)?;
+            databases.swap(first, second);
+            Ok(Resp::simple_string_from_str("OK"))
+        }
+        DatabaseCommand::Copy {
+            source,
+            destination,
+            db: target,
+            replace,
+        } => {
+            let target = target.unwrap_or(db);
+            check_index(databases, target)?;
+            if target == db && source == destination {
+                return Err(same_object());
+            }
+            let Some(entry) = databases[db].entry(&source).cloned() else {
+                return Ok(Resp::Integers(0));
+            };
+            let target = &mut databases[target];
+            if !replace && target.entry(&destination).is_some() {
+                return Ok(Resp::Integers(0));
+            }
+            target.insert_entry(destination.clone(), entry);
+            target.notify(NotifyFlags::GENERIC, "copy_to", &destination);
+            Ok(Resp::Integers(1))
+        }
+        DatabaseCommand::Move { key, db: target } => {
+            check_index(databases, target)?;
+            if target == db {
+                return Err(same_object());
+            }
+            let (store, target) = pair_mut(databases, db, target);
+            if store.entry(&key).is_none() || target.entry(&key).is_some() {
+                return Ok(Resp::Integers(0));
+            }
+            let entry = store.remove(&key).expect("entry checked above");
+            store.notify(NotifyFlags::GENERIC, "move_from", &key);
+            target.insert_entry(key.clone(), entry);
+            target.notify(NotifyFlags::GENERIC, "move_to", &key);
+            Ok(Resp::Integers(1))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        commands::test_helpers::key, data_management::hash_table_store::HashTableDataStore,
+    };
+
+    fn databases(count: usize) -> Vec<HashTableDataStore> {
+        (0..count).map(|_| HashTableDataStore::default()).collect()
+    }
+
+    #[test]
+    fn should_move_only_when_target_misses_the_key() {
+        let mut databases = databases(3);
+        databases[0].insert(key("a"), key("1"), None);
+        databases[2].insert(key("b"), key("2"), None);
+        let command = DatabaseCommand::Move {
+            key: key("a"),
+            db: 2,
+        };
+        let result = apply_database_command(&mut databases, 0, command).unwrap();
+        assert_eq!(result, Resp::Integers(1));
+        assert!(databases[0].entry(&key("a")).is_none());
+        databases[0].insert(key("b"), key("3"), None);
+        let command = DatabaseCommand::Move {
+            key: key("b"),
+            db: 2,
+        };
+        let result = apply_database_command(&mut databases, 0, command).unwrap();
+        assert_eq!(result, Resp::Integers(0));
+        let command = DatabaseCommand::Move {
+            key: key("b"),
+            db: 3,
+        };
+        let result = apply_database_command(&mut databases, 0, command);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "ERR DB index is out of range"
+        );
+    }
+
+    #[test]
+    fn should_copy_across_databases_without_replacing() {
+        let mut databases = databases(2);
+        databases[0].insert(key("a"), key("1"), None);
+        databases[1].insert(key("a"), key("2"), None);
+        let copy = |replace| DatabaseCommand::Copy {
+            source: key("a"),
+            destination: key("a"),
+            db: Some(1),
+            replace,
+        };
+        let result = apply_database_command(&mut databases, 0, copy(false)).unwrap();
+        assert_eq!(result, Resp::Integers(0));
+        let result = apply_database_command(&mut databases, 0, copy(true)).unwrap();
+        assert_eq!(result, Resp::Integers(1));
+        assert_eq!(databases[1].get(key("a")), Some(key("1")));
+    }
+
+    #[test]
+    fn should_swap_and_flush_databases() {
+        let mut databases = databases(2);
+        databases[0].insert(key("a"), key("1"), None);
+        apply_database_command(&mut databases, 0, DatabaseCommand::SwapDb(0, 1)).unwrap();
+        let size = apply_database_command(&mut databases, 1, DatabaseCommand::DbSize).unwrap();
+        assert_eq!(size, Resp::Integers(1));
+        let command = DatabaseCommand::FlushAll { lazy: false };
+        apply_database_command(&mut databases, 0, command).unwrap();
+        assert!(databases.iter().all(|store| store.is_empty()));
+    }
+}
@@ -1,32 +1,242 @@
-use std::time::{Duration, SystemTime};
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant, SystemTime},
+};
+
+use rand::Rng;
+
+use crate::resp::Resp;
+
+use super::{
+    hash::HashValue,
+    notification::{KeyspaceEvent, NotifyFlags},
+    sorted_set::SortedSetValue,
+    stream::StreamValue,
+    unordered_set::SetValue,
+};
+
+/// Source of entry versions, shared by every database so that versions stay unique when
+/// `SWAPDB` exchanges them. Version 0 stands for a missing key.
+static VERSION_CLOCK: AtomicU64 = AtomicU64::new(1);
+
+fn next_version() -> u64 {
+    VERSION_CLOCK.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Name of a key as clients know it, keys being stored serialized as bulk strings.
+pub fn key_name(key: &[u8]) -> Vec<u8> {
+    match Resp::deserialize(key) {
+        Ok(Resp::BulkString(name)) => name,
+        _ => key.to_owned(),
+    }
+}
+
+pub fn is_expired(expiry: Option<SystemTime>) -> bool {
+    match expiry {
+        Some(expiry) => SystemTime::now() >= expiry,
+        None => false,
+    }
+}
+
+/// `NX | XX | GT | LT` flags shared by the expire family of commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpireCondition {
+    Nx,
+    Xx,
+    Gt,
+    Lt,
+}
+
+impl ExpireCondition {
+    /// A missing expiry is treated as an infinite TTL, like Redis does.
+    pub fn allows(&self, current: Option<SystemTime>, new: SystemTime) -> bool {
+        match (self, current) {
+            (Self::Nx, current) => current.is_none(),
+            (Self::Xx, current) => current.is_some(),
+            (Self::Gt, Some(current)) => new > current,
+            (Self::Gt, None) => false,
+            (Self::Lt, Some(current)) => new < current,
+            (Self::Lt, None) => true,
+        }
+    }
+}
+
+/// Counter given to new keys so that they are not evicted right away, Redis' `LFU_INIT_VAL`.
+const LFU_INIT_VAL: u8 = 5;
+/// Redis' default `lfu-log-factor`.
+const LFU_LOG_FACTOR: f64 = 10.0;
+/// Redis' default `lfu-decay-time`, in minutes.
+const LFU_DECAY_MINUTES: u64 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DataValue {
+    String(Vec<u8>),
+    Hash(HashValue),
+    Set(SetValue),
+    SortedSet(SortedSetValue),
+    Stream(StreamValue),
+}
+
+impl DataValue {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::String(_) => "string",
+            Self::Hash(_) => "hash",
+            Self::Set(_) => "set",
+            Self::SortedSet(_) => "zset",
+            Self::Stream(_) => "stream",
+        }
+    }
+
+    /// Internal representation reported by `OBJECT ENCODING`.
+    pub fn encoding(&self) -> &'static str {
+        match self {
+            Self::String(data) => match Resp::deserialize(data) {
+                Ok(Resp::BulkString(bytes)) => {
+                    let int = std::str::from_utf8(&bytes)
+                        .ok()
+                        .and_then(|int| int.parse::<i64>().ok());
+                    if int.is_some_and(|int| int.to_string().as_bytes() == bytes) {
+                        "int"
+                    } else if bytes.len() <= 44 {
+                        "embstr"
+                    } else {
+                        "raw"
+                    }
+                }
+                _ => "raw",
+            },
+            Self::Hash(_) => "hashtable",
+            Self::Set(set) => set.encoding(),
+            Self::SortedSet(_) => "skiplist",
+            Self::Stream(_) => "stream",
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct DataStoreEntry {
-    pub data: Vec<u8>,
+    pub value: DataValue,
     expiry: Option<SystemTime>,
+    last_access: Instant,
+    frequency: u8,
+    version: u64,
 }
 
 impl DataStoreEntry {
     pub fn new(data: Vec<u8>, expiry: Option<Duration>) -> Self {
+        Self::with_value(DataValue::String(data), expiry)
+    }
+
+    pub fn with_value(value: DataValue, expiry: Option<Duration>) -> Self {
         Self {
-            data,
+            value,
             expiry: expiry.map(|duration| SystemTime::now() + duration),
+            last_access: Instant::now(),
+            frequency: LFU_INIT_VAL,
+            version: next_version(),
         }
     }
 
+    pub fn expiry(&self) -> Option<SystemTime> {
+        self.expiry
+    }
+
+    pub fn set_expiry(&mut self, expiry: Option<SystemTime>) {
+        self.expiry = expiry;
+    }
+
     pub fn expired(&self) -> bool {
-        if let Some(expiry) = self.expiry {
-            let now = SystemTime::now();
-            return now >= expiry;
+        is_expired(self.expiry)
+    }
+
+    /// Whether the entry or some of its hash fields have a TTL.
+    pub fn volatile(&self) -> bool {
+        self.expiry.is_some() || matches!(&self.value, DataValue::Hash(hash) if hash.volatile())
+    }
+
+    pub fn idle_time(&self) -> Duration {
+        self.last_access.elapsed()
+    }
+
+    pub fn set_idle_time(&mut self, idle: Duration) {
+        self.last_access = Instant::now().checked_sub(idle).unwrap_or(self.last_access);
+    }
+
+    /// Logarithmic access counter decayed by the time elapsed since the last access, like
+    /// Redis' LFU counter.
+    pub fn frequency(&self) -> u8 {
+        let periods = self.idle_time().as_secs() / 60 / LFU_DECAY_MINUTES;
+        self.frequency
+            .saturating_sub(periods.min(u8::MAX as u64) as u8)
+    }
+
+    pub fn set_frequency(&mut self, frequency: u8) {
+        self.frequency = frequency;
+    }
+
+    /// Changes whenever the entry is written, letting `WATCH` detect modifications.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn bump_version(&mut self) {
+        self.version = next_version();
+    }
+
+    /// Records an access, bumping the counter with a probability shrinking as it grows.
+    pub fn touch(&mut self) {
+        let mut frequency = self.frequency();
+        if frequency < u8::MAX {
+            let base = frequency.saturating_sub(LFU_INIT_VAL) as f64;
+            if rand::thread_rng().gen::<f64>() < 1.0 / (base * LFU_LOG_FACTOR + 1.0) {
+                frequency += 1;
+            }
         }
-        return false;
+        self.frequency = frequency;
+        self.last_access = Instant::now();
     }
 }
 
 pub trait DataStore: Send + Sync + Default + 'static {
     fn insert(&mut self, key: Vec<u8>, data: Vec<u8>, expiry: Option<Duration>);
     fn get(&mut self, key: Vec<u8>) -> Option<Vec<u8>>;
-    fn clean(&mut self) -> ();
+    /// Returns the live entry stored under `key`, lazily dropping it when expired and
+    /// recording the access.
+    fn entry_mut(&mut self, key: &[u8]) -> Option<&mut DataStoreEntry>;
+    /// Looks up the live entry stored under `key` without recording an access.
+    fn entry(&self, key: &[u8]) -> Option<&DataStoreEntry>;
+    /// Stores `entry` under `key` as a new version of it.
+    fn insert_entry(&mut self, key: Vec<u8>, entry: DataStoreEntry);
+    fn remove(&mut self, key: &[u8]) -> Option<DataStoreEntry>;
+    /// Version of the live entry stored under `key`, 0 when there is none.
+    fn version(&self, key: &[u8]) -> u64 {
+        self.entry(key).map_or(0, DataStoreEntry::version)
+    }
+    /// Records that the entry stored under `key` was modified in place.
+    fn signal_modified(&mut self, key: &[u8]) {
+        if let Some(entry) = self.entry_mut(key) {
+            entry.bump_version();
+        }
+    }
+    /// Records that the entry stored under `key`, or some of its fields, got a TTL in place,
+    /// for active expiry to find it.
+    fn signal_volatile(&mut self, key: &[u8]);
+    /// Records a keyspace event, published by the data worker once the command ran.
+    fn notify(&mut self, class: NotifyFlags, event: &'static str, key: &[u8]);
+    /// Events recorded since the last call.
+    fn take_events(&mut self) -> Vec<KeyspaceEvent>;
+    /// Number of keys, including expired ones not yet evicted.
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Live keys of the buckets walked from `cursor`, with the cursor to resume from.
+    fn scan(&mut self, cursor: u64, count: usize) -> (u64, Vec<Vec<u8>>);
+    fn keys(&self) -> Vec<Vec<u8>>;
+    fn random_key(&mut self) -> Option<Vec<u8>>;
+    /// Active expiry cycle, evicting expired keys and hash fields.
+    fn clean(&mut self);
 }
 
 #[cfg(test)]
@@ -0,0 +1,312 @@
+use std::{
+    collections::hash_map::RandomState,
+    fmt::Debug,
+    hash::{BuildHasher, Hash},
+};
+
+use rand::Rng;
+
+use crate::resp::Resp;
+
+const INITIAL_SIZE: usize = 4;
+
+/// `cursor [MATCH pattern] [COUNT count]` shared by the `SCAN` family.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanArgs {
+    pub cursor: u64,
+    pub pattern: Option<Vec<u8>>,
+    pub count: usize,
+}
+
+impl Default for ScanArgs {
+    fn default() -> Self {
+        Self {
+            cursor: 0,
+            pattern: None,
+            count: 10,
+        }
+    }
+}
+
+/// `[next cursor, items]` reply of the `SCAN` family.
+pub fn scan_reply(cursor: u64, items: Vec<Resp>) -> Resp {
+    Resp::Array(vec![
+        Resp::BulkString(cursor.to_string().into_bytes()),
+        Resp::Array(items),
+    ])
+}
+
+/// Chained hash table with a power of two number of buckets, modeled on Redis' dict so
+/// that it can be walked with a reverse binary cursor: every element present for the
+/// whole iteration is returned even when the table grows or shrinks between calls.
+#[derive(Clone)]
+pub struct Dict<K, V> {
+    buckets: Vec<Vec<(K, V)>>,
+    len: usize,
+    hasher: RandomState,
+}
+
+impl<K, V> Default for Dict<K, V> {
+    fn default() -> Self {
+        Self {
+            buckets: (0..INITIAL_SIZE).map(|_| vec![]).collect(),
+            len: 0,
+            hasher: RandomState::new(),
+        }
+    }
+}
+
+impl<K: Debug, V: Debug> Debug for Dict<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Hash + Eq, V: PartialEq> PartialEq for Dict<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len
+            && self
+                .iter()
+                .all(|(key, value)| other.get(key).is_some_and(|other| other == value))
+    }
+}
+
+impl<K, V> Dict<K, V> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.buckets
+            .iter()
+            .flatten()
+            .map(|(key, value)| (key, value))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.iter().map(|(key, _)| key)
+    }
+
+    /// Visits buckets from `cursor` until `count` elements are collected, returning the
+    /// cursor to resume from, `0` once the table has been fully walked.
+    pub fn scan(&self, mut cursor: u64, count: usize) -> (u64, Vec<(&K, &V)>) {
+        let mask = self.buckets.len() as u64 - 1;
+        let mut elements = vec![];
+        // Bounds the work spent on a sparse table, like Redis does.
+        let mut empty_visits = count.saturating_mul(10);
+        loop {
+            let bucket = &self.buckets[(cursor & mask) as usize];
+            if bucket.is_empty() {
+                empty_visits = empty_visits.saturating_sub(1);
+            }
+            elements.extend(bucket.iter().map(|(key, value)| (key, value)));
+            // Increments the reversed cursor so that the buckets an element may be moved
+            // to by a resize are always visited after the bucket it came from.
+            cursor |= !mask;
+            cursor = cursor.reverse_bits().wrapping_add(1).reverse_bits();
+            if cursor == 0 || elements.len() >= count || empty_visits == 0 {
+                return (cursor, elements);
+            }
+        }
+    }
+
+    pub fn random(&self) -> Option<(&K, &V)> {
+        if self.is_empty() {
+            return None;
+        }
+        let mut rng = rand::thread_rng();
+        loop {
+            let bucket = &self.buckets[rng.gen_range(0..self.buckets.len())];
+            if !bucket.is_empty() {
+                let (key, value) = &bucket[rng.gen_range(0..bucket.len())];
+                return Some((key, value));
+            }
+        }
+    }
+}
+
+impl<K: Hash + Eq, V> Dict<K, V> {
+    fn bucket<Q: Hash + ?Sized>(&self, key: &Q) -> usize {
+        (self.hasher.hash_one(key) as usize) & (self.buckets.len() - 1)
+    }
+
+    fn position<Q>(&self, key: &Q) -> Option<(usize, usize)>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let bucket = self.bucket(key);
+        let position = self.buckets[bucket]
+            .iter()
+            .position(|(candidate, _)| candidate.borrow() == key)?;
+        Some((bucket, position))
+    }
+
+    /// Grows the table once it is full and shrinks it below one eighth of use.
+    fn resize(&mut self) {
+        let size = self.buckets.len();
+        let target = if self.len >= size {
+            (self.len * 2).next_power_of_two()
+        } else if size > INITIAL_SIZE && self.len * 8 < size {
+            self.len.next_power_of_two().max(INITIAL_SIZE)
+        } else {
+            return;
+        };
+        let elements = std::mem::take(&mut self.buckets);
+        self.buckets = (0..target).map(|_| vec![]).collect();
+        for (key, value) in elements.into_iter().flatten() {
+            let bucket = self.bucket(&key);
+            self.buckets[bucket].push((key, value));
+        }
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let (bucket, position) = self.position(key)?;
+        Some(&self.buckets[bucket][position].1)
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let (bucket, position) = self.position(key)?;
+        Some(&mut self.buckets[bucket][position].1)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.position(key).is_some()
+    }
+
+    /// Returns the value previously stored under `key`.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some((bucket, position)) = self.position(&key) {
+            return Some(std::mem::replace(
+                &mut self.buckets[bucket][position].1,
+                value,
+            ));
+        }
+        let bucket = self.bucket(&key);
+        self.buckets[bucket].push((key, value));
+        self.len += 1;
+        self.resize();
+        None
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let (bucket, position) = self.position(key)?;
+        let (_, value) = self.buckets[bucket].swap_remove(position);
+        self.len -= 1;
+        self.resize();
+        Some(value)
+    }
+
+    pub fn retain(&mut self, mut keep: impl FnMut(&K, &mut V) -> bool) {
+        for bucket in &mut self.buckets {
+            bucket.retain_mut(|(key, value)| keep(key, value));
+        }
+        self.len = self.buckets.iter().map(Vec::len).sum();
+        self.resize();
+    }
+}
+
+impl<K: Hash + Eq, V> FromIterator<(K, V)> for Dict<K, V> {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        let mut dict = Self::default();
+        for (key, value) in iter {
+            dict.insert(key, value);
+        }
+        dict
+    }
+}
+
+impl<K, V> IntoIterator for Dict<K, V> {
+    type Item = (K, V);
+    type IntoIter = std::iter::Flatten<std::vec::IntoIter<Vec<(K, V)>>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.buckets.into_iter().flatten()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn scan_all(dict: &Dict<u32, ()>, count: usize) -> Vec<u32> {
+        let (mut cursor, mut keys) = (0, vec![]);
+        loop {
+            let (next, elements) = dict.scan(cursor, count);
+            keys.extend(elements.into_iter().map(|(key, _)| *key));
+            if next == 0 {
+                return keys;
+            }
+            cursor = next;
+        }
+    }
+
+    #[test]
+    fn should_grow_and_shrink_with_its_elements() {
+        let mut dict: Dict<u32, u32> = (0..100).map(|n| (n, n * 2)).collect();
+        assert_eq!(dict.len(), 100);
+        assert_eq!(dict.get(&42), Some(&84));
+        assert_eq!(dict.insert(42, 0), Some(84));
+        assert!(dict.buckets.len() >= 100);
+        dict.retain(|key, _| *key < 3);
+        assert_eq!(dict.len(), 3);
+        assert_eq!(dict.buckets.len(), INITIAL_SIZE);
+        assert_eq!(dict.remove(&1), Some(2));
+        assert!(!dict.contains_key(&1));
+    }
+
+    #[test]
+    fn should_scan_every_element_once_without_resize() {
+        let dict: Dict<u32, ()> = (0..500).map(|n| (n, ())).collect();
+        let mut keys = scan_all(&dict, 10);
+        keys.sort();
+        assert_eq!(keys, (0..500).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn should_return_elements_present_for_the_whole_scan_across_resizes() {
+        let mut dict: Dict<u32, ()> = (0..64).map(|n| (n, ())).collect();
+        let (mut cursor, mut seen) = (0, HashSet::new());
+        let mut step = 0;
+        loop {
+            let (next, elements) = dict.scan(cursor, 5);
+            seen.extend(elements.into_iter().map(|(key, _)| *key));
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+            step += 1;
+            // Alternates growing the table and shrinking it back between calls.
+            if step % 2 == 0 {
+                (1000..1200).for_each(|n| {
+                    dict.insert(n, ());
+                });
+            } else {
+                dict.retain(|key, _| *key < 1000);
+            }
+        }
+        assert!((0..64).all(|n| seen.contains(&n)));
+    }
+}
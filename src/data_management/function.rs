@@ -0,0 +1,470 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use mlua::{Function, Lua, MultiValue, RegistryKey, Table, Value};
+
+use crate::{errors::AppError, helpers::glob::glob_match, resp::Resp};
+
+use super::{
+    rdb,
+    scripting::{first_line, new_lua, run, ScriptKind, ScriptMonitor, FUNCTION_CHUNK},
+};
+
+/// Flags `redis.register_function` accepts, only `no-writes` changing anything here.
+const FUNCTION_FLAGS: [&str; 5] = [
+    "no-writes",
+    "allow-oom",
+    "allow-stale",
+    "no-cluster",
+    "allow-cross-slot-keys",
+];
+
+/// What `FUNCTION RESTORE` does with the libraries already loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestorePolicy {
+    Append,
+    Replace,
+    Flush,
+}
+
+#[derive(Debug)]
+pub enum FunctionCommand {
+    Load {
+        code: Vec<u8>,
+        replace: bool,
+    },
+    List {
+        pattern: Option<Vec<u8>>,
+        with_code: bool,
+    },
+    Delete(String),
+    Flush,
+    Dump,
+    Restore {
+        payload: Vec<u8>,
+        policy: RestorePolicy,
+    },
+    Call {
+        name: String,
+        keys: Vec<Vec<u8>>,
+        args: Vec<Vec<u8>>,
+        read_only: bool,
+    },
+}
+
+#[derive(Debug)]
+struct RegisteredFunction {
+    library: String,
+    key: RegistryKey,
+    description: Option<Vec<u8>>,
+    flags: Vec<String>,
+}
+
+#[derive(Debug)]
+struct Library {
+    code: Vec<u8>,
+    functions: Vec<String>,
+}
+
+/// Function libraries, loaded in an interpreter of their own so that `SCRIPT FLUSH`
+/// leaves them alone, like Redis does.
+#[derive(Debug)]
+pub struct FunctionLibraries {
+    lua: Lua,
+    libraries: BTreeMap<String, Library>,
+    functions: HashMap<String, RegisteredFunction>,
+}
+
+impl Default for FunctionLibraries {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FunctionLibraries {
+    pub fn new() -> Self {
+        Self {
+            lua: new_lua(),
+            libraries: BTreeMap::new(),
+            functions: HashMap::new(),
+        }
+    }
+
+    /// Loads the library `code` declares with its `#!lua name=<library>` first line,
+    /// returning its name. Loading a library already there needs `replace`.
+    pub fn load(&mut self, code: Vec<u8>, replace: bool) -> Result<String, AppError> {
+        let (name, body) = metadata(&code)?;
+        if self.libraries.contains_key(&name) && !replace {
+            return Err(AppError::Generic(format!(
+                "Library '{name}' already exists"
+            )));
+        }
+        let registered = self.register(&body)?;
+        if registered.is_empty() {
+            return Err(AppError::Generic("No functions registered".to_owned()));
+        }
+        for (function, _) in &registered {
+            if let Some(existing) = self.functions.get(function) {
+                if existing.library != name {
+                    return Err(AppError::Generic(format!(
+                        "Function {function} already exists"
+                    )));
+                }
+            }
+        }
+        self.delete(&name).ok();
+        let mut functions = Vec::with_capacity(registered.len());
+        for (function, mut registration) in registered {
+            registration.library = name.clone();
+            functions.push(function.clone());
+            self.functions.insert(function, registration);
+        }
+        self.libraries
+            .insert(name.clone(), Library { code, functions });
+        Ok(name)
+    }
+
+    /// Runs `body` with `redis.register_function` collecting what it registers.
+    fn register(&self, body: &[u8]) -> Result<Vec<(String, RegisteredFunction)>, AppError> {
+        let chunk = self
+            .lua
+            .load(body)
+            .set_name(format!("@{FUNCTION_CHUNK}"))
+            .into_function()
+            .map_err(|err| {
+                AppError::Generic(format!("Error compiling function: {}", first_line(&err)))
+            })?;
+        let registered = RefCell::new(Vec::<(String, RegisteredFunction)>::new());
+        let result = self.lua.scope(|scope| {
+            let register = scope.create_function(|lua, args: MultiValue| {
+                let (name, callback, flags, description) = registration(args)?;
+                if registered.borrow().iter().any(|(other, _)| *other == name) {
+                    return Err(mlua::Error::RuntimeError(format!(
+                        "Function {name} already exists"
+                    )));
+                }
+                let function = RegisteredFunction {
+                    library: String::new(),
+                    key: lua.create_registry_value(callback)?,
+                    description,
+                    flags,
+                };
+                registered.borrow_mut().push((name, function));
+                Ok(())
+            })?;
+            let redis: Table = self.lua.globals().get("redis")?;
+            redis.set("register_function", register)?;
+            let result = chunk.call::<_, ()>(());
+            redis.set("register_function", Value::Nil)?;
+            result
+        });
+        result.map_err(|err| {
+            AppError::Generic(format!("Error registering functions: {}", first_line(&err)))
+        })?;
+        Ok(registered.into_inner())
+    }
+
+    pub fn delete(&mut self, name: &str) -> Result<(), AppError> {
+        let library = self
+            .libraries
+            .remove(name)
+            .ok_or(AppError::Generic("Library not found".to_owned()))?;
+        for function in library.functions {
+            if let Some(function) = self.functions.remove(&function) {
+                self.lua.remove_registry_value(function.key).ok();
+            }
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) {
+        *self = Self::new();
+    }
+
+    /// `FUNCTION LIST` reply, libraries whose name matches `pattern` in name order.
+    pub fn list(&self, pattern: Option<&[u8]>, with_code: bool) -> Resp {
+        let libraries = self
+            .libraries
+            .iter()
+            .filter(|(name, _)| {
+                pattern.is_none_or(|pattern| glob_match(pattern, name.as_bytes(), false))
+            })
+            .map(|(name, library)| {
+                let functions = library
+                    .functions
+                    .iter()
+                    .map(|function| {
+                        let registered = &self.functions[function];
+                        let flags = registered
+                            .flags
+                            .iter()
+                            .map(|flag| Resp::bulk_string_from_str(flag))
+                            .collect();
+                        let description = registered.description.clone().unwrap_or_default();
+                        Resp::Array(vec![
+                            Resp::bulk_string_from_str("name"),
+                            Resp::bulk_string_from_str(function),
+                            Resp::bulk_string_from_str("description"),
+                            Resp::BulkString(description),
+                            Resp::bulk_string_from_str("flags"),
+                            Resp::Array(flags),
+                        ])
+                    })
+                    .collect();
+                let mut fields = vec![
+                    Resp::bulk_string_from_str("library_name"),
+                    Resp::bulk_string_from_str(name),
+                    Resp::bulk_string_from_str("engine"),
+                    Resp::bulk_string_from_str("LUA"),
+                    Resp::bulk_string_from_str("functions"),
+                    Resp::Array(functions),
+                ];
+                if with_code {
+                    fields.push(Resp::bulk_string_from_str("library_code"));
+                    fields.push(Resp::BulkString(library.code.clone()));
+                }
+                Resp::Array(fields)
+            })
+            .collect();
+        Resp::Array(libraries)
+    }
+
+    /// Code of every library, in name order.
+    pub fn codes(&self) -> impl Iterator<Item = &[u8]> {
+        self.libraries
+            .values()
+            .map(|library| library.code.as_slice())
+    }
+
+    pub fn dump(&self) -> Vec<u8> {
+        rdb::dump_functions(self.codes())
+    }
+
+    /// Loads the libraries of a `FUNCTION DUMP` payload. Conflicts are checked before
+    /// anything is loaded, and a flush only happens once every library loaded fine.
+    pub fn restore(&mut self, payload: &[u8], policy: RestorePolicy) -> Result<(), AppError> {
+        let codes = rdb::restore_functions(payload)?;
+        if policy == RestorePolicy::Flush {
+            let mut restored = Self::new();
+            for code in codes {
+                restored.load(code, false)?;
+            }
+            *self = restored;
+            return Ok(());
+        }
+        if policy == RestorePolicy::Append {
+            for code in &codes {
+                let (name, _) = metadata(code)?;
+                if self.libraries.contains_key(&name) {
+                    return Err(AppError::Generic(format!(
+                        "Library '{name}' already exists"
+                    )));
+                }
+            }
+        }
+        for code in codes {
+            self.load(code, policy == RestorePolicy::Replace)?;
+        }
+        Ok(())
+    }
+
+    /// Runs function `name`, see `scripting::run`. `read_only` calls, from `FCALL_RO`,
+    /// need functions flagged `no-writes`.
+    pub fn call(
+        &self,
+        name: &str,
+        keys: Vec<Vec<u8>>,
+        args: Vec<Vec<u8>>,
+        read_only: bool,
+        monitor: &Arc<ScriptMonitor>,
+        call: impl FnMut(Vec<Vec<u8>>) -> Resp,
+    ) -> Result<Resp, AppError> {
+        let function = self
+            .functions
+            .get(name)
+            .ok_or(AppError::Generic("Function not found".to_owned()))?;
+        if read_only && !function.flags.iter().any(|flag| flag == "no-writes") {
+            return Err(AppError::Generic(
+                "Can not execute a script with write flag using *_ro command.".to_owned(),
+            ));
+        }
+        let running = (monitor, ScriptKind::Function);
+        run(&self.lua, &function.key, keys, args, running, call)
+    }
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'_')
+}
+
+/// Library name declared by the `#!<engine> name=<library>` first line of `code`, along
+/// with the code following it. The first line is blanked so that line numbers hold.
+fn metadata(code: &[u8]) -> Result<(String, Vec<u8>), AppError> {
+    let end = code
+        .iter()
+        .position(|byte| *byte == b'\n')
+        .unwrap_or(code.len());
+    let Some(shebang) = code[..end].strip_prefix(b"#!") else {
+        return Err(AppError::Generic("Missing library metadata".to_owned()));
+    };
+    let shebang = String::from_utf8_lossy(shebang);
+    let mut parts = shebang.split_whitespace();
+    let engine = parts.next().unwrap_or_default();
+    if !engine.eq_ignore_ascii_case("lua") {
+        return Err(AppError::Generic(format!("Engine '{engine}' not found")));
+    }
+    let mut name = None;
+    for part in parts {
+        match part.split_once('=') {
+            Some(("name", value)) => name = Some(value.to_owned()),
+            _ => {
+                return Err(AppError::Generic(format!(
+                    "Invalid metadata value given: {part}"
+                )))
+            }
+        }
+    }
+    let name = name.ok_or(AppError::Generic("Library name was not given".to_owned()))?;
+    if !valid_name(&name) {
+        return Err(AppError::Generic(
+            "Library names can only contain letters, numbers, or underscores(_) and must be at least one character long".to_owned(),
+        ));
+    }
+    Ok((name, code[end..].to_vec()))
+}
+
+type Registration<'lua> = (String, Function<'lua>, Vec<String>, Option<Vec<u8>>);
+
+/// Arguments of `redis.register_function`: either a name and a callback, or a table with
+/// `function_name`, `callback` and optional `flags` and `description` fields.
+fn registration(args: MultiValue) -> mlua::Result<Registration> {
+    let error = |message: &str| mlua::Error::RuntimeError(message.to_owned());
+    let mut args = args.into_iter();
+    let (name, callback, flags, description) = match (args.next(), args.next()) {
+        (Some(Value::String(name)), Some(Value::Function(callback))) => {
+            (name.to_str()?.to_owned(), callback, vec![], None)
+        }
+        (Some(Value::Table(table)), None) => {
+            let name: mlua::String = table.get("function_name").map_err(|_| {
+                error("function_name argument given to redis.register_function must be a string")
+            })?;
+            let callback: Function = table.get("callback").map_err(|_| {
+                error("callback argument given to redis.register_function must be a function")
+            })?;
+            let flags: Option<Vec<String>> = table
+                .get("flags")
+                .map_err(|_| error("flags argument to redis.register_function must be a table representing function flags"))?;
+            let description: Option<mlua::String> = table.get("description").map_err(|_| {
+                error("description argument given to redis.register_function must be a string")
+            })?;
+            (
+                name.to_str()?.to_owned(),
+                callback,
+                flags.unwrap_or_default(),
+                description.map(|description| description.as_bytes().to_vec()),
+            )
+        }
+        _ => {
+            return Err(error(
+                "wrong number of arguments to redis.register_function",
+            ))
+        }
+    };
+    if !valid_name(&name) {
+        return Err(error("Function names can only contain letters, numbers, or underscores(_) and must be at least one character long"));
+    }
+    if flags
+        .iter()
+        .any(|flag| !FUNCTION_FLAGS.contains(&flag.as_str()))
+    {
+        return Err(error("unknown flag given"));
+    }
+    Ok((name, callback, flags, description))
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::resp::Resp;
+
+    use super::{FunctionLibraries, RestorePolicy};
+
+    const LIBRARY: &[u8] = b"#!lua name=mylib\n\
+        redis.register_function('echo', function(keys, args) return args[1] end)\n\
+        redis.register_function{function_name='peek', callback=function(keys) \
+            return redis.call('GET', keys[1]) end, flags={'no-writes'}}";
+
+    #[test]
+    fn should_load_and_call_registered_functions() {
+        let mut functions = FunctionLibraries::new();
+        let monitor = Arc::default();
+        assert_eq!(functions.load(LIBRARY.to_vec(), false).unwrap(), "mylib");
+        let reply = functions
+            .call(
+                "echo",
+                vec![],
+                vec![b"hi".to_vec()],
+                false,
+                &monitor,
+                |_| unreachable!(),
+            )
+            .unwrap();
+        assert_eq!(reply, Resp::bulk_string_from_str("hi"));
+        let reply = functions
+            .call(
+                "peek",
+                vec![b"k".to_vec()],
+                vec![],
+                true,
+                &monitor,
+                |args| {
+                    assert_eq!(args, vec![b"GET".to_vec(), b"k".to_vec()]);
+                    Resp::bulk_string_from_str("v")
+                },
+            )
+            .unwrap();
+        assert_eq!(reply, Resp::bulk_string_from_str("v"));
+        let err = functions
+            .call("echo", vec![], vec![], true, &monitor, |_| unreachable!())
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ERR Can not execute a script with write flag using *_ro command."
+        );
+    }
+
+    #[test]
+    fn should_reject_conflicting_libraries() {
+        let mut functions = FunctionLibraries::new();
+        functions.load(LIBRARY.to_vec(), false).unwrap();
+        let err = functions.load(LIBRARY.to_vec(), false).unwrap_err();
+        assert_eq!(err.to_string(), "ERR Library 'mylib' already exists");
+        let other = b"#!lua name=other\nredis.register_function('echo', function() end)";
+        let err = functions.load(other.to_vec(), false).unwrap_err();
+        assert_eq!(err.to_string(), "ERR Function echo already exists");
+        let err = functions.load(b"return 1".to_vec(), false).unwrap_err();
+        assert_eq!(err.to_string(), "ERR Missing library metadata");
+        functions.load(LIBRARY.to_vec(), true).unwrap();
+    }
+
+    #[test]
+    fn should_restore_dumped_libraries() {
+        let mut functions = FunctionLibraries::new();
+        functions.load(LIBRARY.to_vec(), false).unwrap();
+        let payload = functions.dump();
+        assert!(functions.restore(&payload, RestorePolicy::Append).is_err());
+        functions.restore(&payload, RestorePolicy::Replace).unwrap();
+
+        let mut restored = FunctionLibraries::new();
+        restored.restore(&payload, RestorePolicy::Flush).unwrap();
+        assert_eq!(restored.list(None, true), functions.list(None, true));
+        assert_eq!(restored.list(Some(b"other*"), false), Resp::Array(vec![]));
+    }
+}
@@ -0,0 +1,636 @@
+use std::time::{Duration, SystemTime};
+
+use crate::{
+    errors::AppError,
+    helpers::{glob::glob_match, number::parse_float},
+    resp::Resp,
+};
+
+use super::{
+    datastore::{is_expired, DataStore, DataStoreEntry, DataValue, ExpireCondition},
+    dict::{scan_reply, Dict, ScanArgs},
+    notification::NotifyFlags,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HashField {
+    pub value: Vec<u8>,
+    expiry: Option<SystemTime>,
+}
+
+impl HashField {
+    pub fn new(value: Vec<u8>) -> Self {
+        Self {
+            value,
+            expiry: None,
+        }
+    }
+
+    /// Field expiring at `expiry`, as read back from an RDB encoding.
+    pub fn with_expiry(value: Vec<u8>, expiry: Option<SystemTime>) -> Self {
+        Self { value, expiry }
+    }
+
+    pub fn expiry(&self) -> Option<SystemTime> {
+        self.expiry
+    }
+
+    pub fn expired(&self) -> bool {
+        is_expired(self.expiry)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HashValue(Dict<Vec<u8>, HashField>);
+
+impl HashValue {
+    pub fn get(&self, field: &[u8]) -> Option<&Vec<u8>> {
+        self.0
+            .get(field)
+            .filter(|field| !field.expired())
+            .map(|field| &field.value)
+    }
+
+    /// Inserts or overwrites `field`, dropping any TTL it had. Returns true for new fields.
+    pub fn insert(&mut self, field: Vec<u8>, value: Vec<u8>) -> bool {
+        self.0.insert(field, HashField::new(value)).is_none()
+    }
+
+    pub fn remove(&mut self, field: &[u8]) -> bool {
+        self.0.remove(field).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Vec<u8>, &HashField)> {
+        self.0.iter()
+    }
+
+    pub fn scan(&self, cursor: u64, count: usize) -> (u64, Vec<(&Vec<u8>, &HashField)>) {
+        let (cursor, mut fields) = self.0.scan(cursor, count);
+        fields.retain(|(_, field)| !field.expired());
+        (cursor, fields)
+    }
+
+    /// Whether some field has a TTL.
+    pub fn volatile(&self) -> bool {
+        self.0.iter().any(|(_, field)| field.expiry.is_some())
+    }
+
+    /// Drops the expired fields, returning how many there were.
+    pub fn purge_expired(&mut self) -> usize {
+        let len = self.0.len();
+        self.0.retain(|_, field| !field.expired());
+        len - self.0.len()
+    }
+}
+
+impl<I> From<I> for HashValue
+where
+    I: IntoIterator<Item = (Vec<u8>, Vec<u8>)>,
+{
+    fn from(value: I) -> Self {
+        Self(
+            value
+                .into_iter()
+                .map(|(field, value)| (field, HashField::new(value)))
+                .collect(),
+        )
+    }
+}
+
+impl FromIterator<(Vec<u8>, HashField)> for HashValue {
+    fn from_iter<I: IntoIterator<Item = (Vec<u8>, HashField)>>(fields: I) -> Self {
+        Self(fields.into_iter().collect())
+    }
+}
+
+pub type FieldValues = Vec<(Vec<u8>, Vec<u8>)>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HashCommand {
+    Set(FieldValues),
+    MSet(FieldValues),
+    SetNx(Vec<u8>, Vec<u8>),
+    Get(Vec<u8>),
+    MGet(Vec<Vec<u8>>),
+    Del(Vec<Vec<u8>>),
+    GetAll,
+    Keys,
+    Vals,
+    Len,
+    Exists(Vec<u8>),
+    StrLen(Vec<u8>),
+    IncrBy(Vec<u8>, i64),
+    IncrByFloat(Vec<u8>, f64),
+    Scan {
+        scan: ScanArgs,
+        novalues: bool,
+    },
+    Expire {
+        at: SystemTime,
+        condition: Option<ExpireCondition>,
+        fields: Vec<Vec<u8>>,
+    },
+    Ttl {
+        millis: bool,
+        fields: Vec<Vec<u8>>,
+    },
+    Persist(Vec<Vec<u8>>),
+}
+
+/// Returns the hash stored at `key` with its expired fields purged, removing the key
+/// altogether once no field is left.
+fn hash_mut<'a, T: DataStore>(
+    store: &'a mut T,
+    key: &[u8],
+) -> Result<Option<&'a mut HashValue>, AppError> {
+    let (expired, empty) = match store.entry_mut(key) {
+        None => return Ok(None),
+        Some(DataStoreEntry {
+            value: DataValue::Hash(hash),
+            ..
+        }) => (hash.purge_expired(), hash.is_empty()),
+        Some(_) => return Err(AppError::WrongType),
+    };
+    if expired > 0 {
+        store.notify(NotifyFlags::HASH, "hexpired", key);
+    }
+    if empty {
+        store.remove(key);
+        store.notify(NotifyFlags::GENERIC, "del", key);
+        return Ok(None);
+    }
+    match store.entry_mut(key) {
+        Some(DataStoreEntry {
+            value: DataValue::Hash(hash),
+            ..
+        }) => Ok(Some(hash)),
+        _ => Ok(None),
+    }
+}
+
+fn hash_or_create<'a, T: DataStore>(
+    store: &'a mut T,
+    key: &[u8],
+) -> Result<&'a mut HashValue, AppError> {
+    if hash_mut(store, key)?.is_none() {
+        let entry = DataStoreEntry::with_value(DataValue::Hash(HashValue::default()), None);
+        store.insert_entry(key.to_owned(), entry);
+    }
+    match store.entry_mut(key) {
+        Some(DataStoreEntry {
+            value: DataValue::Hash(hash),
+            ..
+        }) => Ok(hash),
+        _ => Err(AppError::WrongType),
+    }
+}
+
+fn is_empty<T: DataStore>(store: &mut T, key: &[u8]) -> bool {
+    matches!(
+        store.entry_mut(key),
+        Some(DataStoreEntry {
+            value: DataValue::Hash(hash),
+            ..
+        }) if hash.is_empty()
+    )
+}
+
+fn remove_if_empty<T: DataStore>(store: &mut T, key: &[u8]) {
+    if is_empty(store, key) {
+        store.remove(key);
+        store.notify(NotifyFlags::GENERIC, "del", key);
+    }
+}
+
+fn bulk(value: Option<&Vec<u8>>) -> Resp {
+    value
+        .map(|value| Resp::BulkString(value.to_owned()))
+        .unwrap_or(Resp::null_bulk_string())
+}
+
+fn remaining(expiry: SystemTime, millis: bool) -> i64 {
+    let remaining = expiry
+        .duration_since(SystemTime::now())
+        .unwrap_or(Duration::ZERO)
+        .as_millis() as i64;
+    if millis {
+        remaining
+    } else {
+        (remaining + 500) / 1000
+    }
+}
+
+pub fn apply_hash_command<T: DataStore>(
+    store: &mut T,
+    key: &[u8],
+    command: HashCommand,
+) -> Result<Resp, AppError> {
+    match command {
+        HashCommand::Set(fields) => {
+            let hash = hash_or_create(store, key)?;
+            let created = fields
+                .into_iter()
+                .filter(|(field, value)| hash.insert(field.to_owned(), value.to_owned()))
+                .count();
+            store.signal_modified(key);
+            store.notify(NotifyFlags::HASH, "hset", key);
+            Ok(Resp::Integers(created as i64))
+        }
+        HashCommand::MSet(fields) => {
+            let hash = hash_or_create(store, key)?;
+            for (field, value) in fields {
+                hash.insert(field, value);
+            }
+            store.signal_modified(key);
+            store.notify(NotifyFlags::HASH, "hset", key);
+            Ok(Resp::simple_string_from_str("OK"))
+        }
+        HashCommand::SetNx(field, value) => {
+            let hash = hash_or_create(store, key)?;
+            if hash.get(&field).is_some() {
+                return Ok(Resp::Integers(0));
+            }
+            hash.insert(field, value);
+            store.signal_modified(key);
+            store.notify(NotifyFlags::HASH, "hset", key);
+            Ok(Resp::Integers(1))
+        }
+        HashCommand::Get(field) => {
+            let hash = hash_mut(store, key)?;
+            Ok(bulk(hash.and_then(|hash| hash.get(&field))))
+        }
+        HashCommand::MGet(fields) => {
+            let hash = hash_mut(store, key)?;
+            let values = fields
+                .iter()
+                .map(|field| bulk(hash.as_ref().and_then(|hash| hash.get(field))))
+                .collect();
+            Ok(Resp::Array(values))
+        }
+        HashCommand::Del(fields) => {
+            let Some(hash) = hash_mut(store, key)? else {
+                return Ok(Resp::Integers(0));
+            };
+            let deleted = fields.iter().filter(|field| hash.remove(field)).count();
+            if deleted > 0 {
+                store.signal_modified(key);
+                store.notify(NotifyFlags::HASH, "hdel", key);
+            }
+            remove_if_empty(store, key);
+            Ok(Resp::Integers(deleted as i64))
+        }
+        HashCommand::GetAll | HashCommand::Keys | HashCommand::Vals => {
+            let Some(hash) = hash_mut(store, key)? else {
+                return Ok(Resp::Array(vec![]));
+            };
+            let reply = hash
+                .iter()
+                .flat_map(|(field, value)| match command {
+                    HashCommand::Keys => vec![Resp::BulkString(field.to_owned())],
+                    HashCommand::Vals => vec![Resp::BulkString(value.value.to_owned())],
+                    _ => vec![
+                        Resp::BulkString(field.to_owned()),
+                        Resp::BulkString(value.value.to_owned()),
+                    ],
+                })
+                .collect();
+            Ok(Resp::Array(reply))
+        }
+        HashCommand::Len => {
+            let len = hash_mut(store, key)?.map(|hash| hash.len()).unwrap_or(0);
+            Ok(Resp::Integers(len as i64))
+        }
+        HashCommand::Exists(field) => {
+            let exists = hash_mut(store, key)?.is_some_and(|hash| hash.get(&field).is_some());
+            Ok(Resp::Integers(exists as i64))
+        }
+        HashCommand::StrLen(field) => {
+            let len = hash_mut(store, key)?
+                .and_then(|hash| hash.get(&field).map(Vec::len))
+                .unwrap_or(0);
+            Ok(Resp::Integers(len as i64))
+        }
+        HashCommand::IncrBy(field, increment) => {
+            let hash = hash_or_create(store, key)?;
+            let current = match hash.get(&field) {
+                Some(value) => std::str::from_utf8(value)
+                    .ok()
+                    .and_then(|value| value.parse::<i64>().ok())
+                    .ok_or(AppError::HashValueNotInteger)?,
+                None => 0,
+            };
+            let new = current.checked_add(increment).ok_or(AppError::Overflow)?;
+            set_keeping_ttl(hash, field, new.to_string().into_bytes());
+            store.signal_modified(key);
+            store.notify(NotifyFlags::HASH, "hincrby", key);
+            Ok(Resp::Integers(new))
+        }
+        HashCommand::IncrByFloat(field, increment) => {
+            let hash = hash_or_create(store, key)?;
+            let current = match hash.get(&field) {
+                Some(value) => parse_float(value).ok_or(AppError::HashValueNotFloat)?,
+                None => 0.0,
+            };
+            let new = current + increment;
+            if !new.is_finite() {
+                return Err(AppError::NanOrInfinity);
+            }
+            let new = new.to_string().into_bytes();
+            set_keeping_ttl(hash, field, new.clone());
+            store.signal_modified(key);
+            store.notify(NotifyFlags::HASH, "hincrbyfloat", key);
+            Ok(Resp::BulkString(new))
+        }
+        HashCommand::Scan { scan, novalues } => {
+            let Some(hash) = hash_mut(store, key)? else {
+                return Ok(scan_reply(0, vec![]));
+            };
+            let (cursor, fields) = hash.scan(scan.cursor, scan.count);
+            let mut items = vec![];
+            for (field, value) in fields {
+                if scan
+                    .pattern
+                    .as_ref()
+                    .is_some_and(|pattern| !glob_match(pattern, field, false))
+                {
+                    continue;
+                }
+                items.push(Resp::BulkString(field.to_owned()));
+                if !novalues {
+                    items.push(Resp::BulkString(value.value.to_owned()));
+                }
+            }
+            Ok(scan_reply(cursor, items))
+        }
+        HashCommand::Expire {
+            at,
+            condition,
+            fields,
+        } => {
+            let Some(hash) = hash_mut(store, key)? else {
+                return Ok(Resp::Array(vec![Resp::Integers(-2); fields.len()]));
+            };
+            let reply = fields
+                .iter()
+                .map(|field| {
+                    let Some(entry) = hash.0.get_mut(field) else {
+                        return Resp::Integers(-2);
+                    };
+                    if condition.is_some_and(|condition| !condition.allows(entry.expiry, at)) {
+                        return Resp::Integers(0);
+                    }
+                    if is_expired(Some(at)) {
+                        hash.0.remove(field);
+                        return Resp::Integers(2);
+                    }
+                    entry.expiry = Some(at);
+                    Resp::Integers(1)
+                })
+                .collect::<Vec<_>>();
+            store.signal_modified(key);
+            if reply
+                .iter()
+                .any(|reply| matches!(reply, Resp::Integers(1 | 2)))
+            {
+                store.signal_volatile(key);
+                store.notify(NotifyFlags::HASH, "hexpire", key);
+            }
+            remove_if_empty(store, key);
+            Ok(Resp::Array(reply))
+        }
+        HashCommand::Ttl { millis, fields } => {
+            let hash = hash_mut(store, key)?;
+            let reply = fields
+                .iter()
+                .map(
+                    |field| match hash.as_ref().and_then(|hash| hash.0.get(field)) {
+                        None => Resp::Integers(-2),
+                        Some(HashField { expiry: None, .. }) => Resp::Integers(-1),
+                        Some(HashField {
+                            expiry: Some(expiry),
+                            ..
+                        }) => Resp::Integers(remaining(*expiry, millis)),
+                    },
+                )
+                .collect();
+            Ok(Resp::Array(reply))
+        }
+        HashCommand::Persist(fields) => {
+            let mut hash = hash_mut(store, key)?;
+            let reply = fields
+                .iter()
+                .map(
+                    |field| match hash.as_mut().and_then(|hash| hash.0.get_mut(field)) {
+                        None => Resp::Integers(-2),
+                        Some(HashField { expiry: None, .. }) => Resp::Integers(-1),
+                        Some(field) => {
+                            field.expiry = None;
+                            Resp::Integers(1)
+                        }
+                    },
+                )
+                .collect::<Vec<_>>();
+            store.signal_modified(key);
+            if reply.contains(&Resp::Integers(1)) {
+                store.notify(NotifyFlags::HASH, "hpersist", key);
+            }
+            Ok(Resp::Array(reply))
+        }
+    }
+}
+
+fn set_keeping_ttl(hash: &mut HashValue, field: Vec<u8>, value: Vec<u8>) {
+    match hash.0.get_mut(&field) {
+        Some(entry) => entry.value = value,
+        None => {
+            hash.insert(field, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, SystemTime};
+
+    use super::*;
+    use crate::data_management::hash_table_store::HashTableDataStore;
+
+    fn field(name: &str) -> Vec<u8> {
+        name.as_bytes().to_vec()
+    }
+
+    fn store_with_hash() -> HashTableDataStore {
+        let hash = HashValue::from([
+            (field("name"), field("redis")),
+            (field("count"), field("1")),
+        ]);
+        let entry = DataStoreEntry::with_value(DataValue::Hash(hash), None);
+        HashTableDataStore::from([(b"session".to_vec(), entry)])
+    }
+
+    #[test]
+    fn should_set_and_get_fields() {
+        let mut store = HashTableDataStore::default();
+        let created = apply_hash_command(
+            &mut store,
+            b"session",
+            HashCommand::Set(vec![(field("a"), field("1")), (field("b"), field("2"))]),
+        );
+        assert_eq!(created.unwrap(), Resp::Integers(2));
+        let value = apply_hash_command(&mut store, b"session", HashCommand::Get(field("b")));
+        assert_eq!(value.unwrap(), Resp::bulk_string_from_str("2"));
+    }
+
+    #[test]
+    fn should_reply_wrong_type_on_string_key() {
+        let mut store = HashTableDataStore::default();
+        store.insert(b"session".to_vec(), field("value"), None);
+        let result = apply_hash_command(&mut store, b"session", HashCommand::Len);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            AppError::WrongType.to_string()
+        );
+    }
+
+    #[test]
+    fn should_delete_key_with_last_field() {
+        let mut store = store_with_hash();
+        let deleted = apply_hash_command(
+            &mut store,
+            b"session",
+            HashCommand::Del(vec![field("name"), field("count"), field("missing")]),
+        );
+        assert_eq!(deleted.unwrap(), Resp::Integers(2));
+        assert!(store.entry_mut(b"session").is_none());
+    }
+
+    #[test]
+    fn should_increment_fields() {
+        let mut store = store_with_hash();
+        let result = apply_hash_command(
+            &mut store,
+            b"session",
+            HashCommand::IncrBy(field("count"), 41),
+        );
+        assert_eq!(result.unwrap(), Resp::Integers(42));
+        let result = apply_hash_command(
+            &mut store,
+            b"session",
+            HashCommand::IncrByFloat(field("count"), 0.5),
+        );
+        assert_eq!(result.unwrap(), Resp::bulk_string_from_str("42.5"));
+        let result = apply_hash_command(
+            &mut store,
+            b"session",
+            HashCommand::IncrBy(field("name"), 1),
+        );
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            AppError::HashValueNotInteger.to_string()
+        );
+    }
+
+    #[test]
+    fn should_expire_fields() {
+        let mut store = store_with_hash();
+        let result = apply_hash_command(
+            &mut store,
+            b"session",
+            HashCommand::Expire {
+                at: SystemTime::now() + Duration::from_millis(1),
+                condition: None,
+                fields: vec![field("name"), field("missing")],
+            },
+        );
+        assert_eq!(
+            result.unwrap(),
+            Resp::Array(vec![Resp::Integers(1), Resp::Integers(-2)])
+        );
+        std::thread::sleep(Duration::from_millis(2));
+        let result = apply_hash_command(&mut store, b"session", HashCommand::Get(field("name")));
+        assert_eq!(result.unwrap(), Resp::null_bulk_string());
+        let result = apply_hash_command(&mut store, b"session", HashCommand::Len);
+        assert_eq!(result.unwrap(), Resp::Integers(1));
+    }
+
+    #[test]
+    fn should_respect_expire_conditions() {
+        let mut store = store_with_hash();
+        let at = SystemTime::now() + Duration::from_secs(100);
+        let expire = |condition| HashCommand::Expire {
+            at,
+            condition: Some(condition),
+            fields: vec![field("name")],
+        };
+        let result = apply_hash_command(&mut store, b"session", expire(ExpireCondition::Xx));
+        assert_eq!(result.unwrap(), Resp::Array(vec![Resp::Integers(0)]));
+        let result = apply_hash_command(&mut store, b"session", expire(ExpireCondition::Nx));
+        assert_eq!(result.unwrap(), Resp::Array(vec![Resp::Integers(1)]));
+        let result = apply_hash_command(
+            &mut store,
+            b"session",
+            HashCommand::Ttl {
+                millis: false,
+                fields: vec![field("name"), field("count")],
+            },
+        );
+        assert_eq!(
+            result.unwrap(),
+            Resp::Array(vec![Resp::Integers(100), Resp::Integers(-1)])
+        );
+    }
+
+    #[test]
+    fn should_persist_fields() {
+        let mut store = store_with_hash();
+        let expire = HashCommand::Expire {
+            at: SystemTime::now() + Duration::from_secs(100),
+            condition: None,
+            fields: vec![field("name")],
+        };
+        apply_hash_command(&mut store, b"session", expire).unwrap();
+        let result = apply_hash_command(
+            &mut store,
+            b"session",
+            HashCommand::Persist(vec![field("name"), field("count")]),
+        );
+        assert_eq!(
+            result.unwrap(),
+            Resp::Array(vec![Resp::Integers(1), Resp::Integers(-1)])
+        );
+    }
+
+    #[test]
+    fn should_scan_matching_fields() {
+        let mut store = store_with_hash();
+        let result = apply_hash_command(
+            &mut store,
+            b"session",
+            HashCommand::Scan {
+                scan: ScanArgs {
+                    pattern: Some(field("na*")),
+                    ..Default::default()
+                },
+                novalues: false,
+            },
+        );
+        assert_eq!(
+            result.unwrap(),
+            Resp::Array(vec![
+                Resp::bulk_string_from_str("0"),
+                Resp::Array(vec![
+                    Resp::bulk_string_from_str("name"),
+                    Resp::bulk_string_from_str("redis")
+                ])
+            ])
+        );
+    }
+}
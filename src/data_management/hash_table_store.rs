@@ -1,56 +1,225 @@
-use std::{collections::HashMap, time::Duration};
+use std::time::{Duration, Instant};
 
-use super::datastore::{DataStore, DataStoreEntry};
+use super::{
+    datastore::{DataStore, DataStoreEntry, DataValue},
+    dict::Dict,
+    notification::{KeyspaceEvent, NotifyFlags},
+};
 #[derive(Debug, Default)]
-pub struct HashTableDataStore(HashMap<Vec<u8>, DataStoreEntry>);
+pub struct HashTableDataStore {
+    dict: Dict<Vec<u8>, DataStoreEntry>,
+    /// Keys that had a TTL, on themselves or their fields, which active expiry samples.
+    /// Keys losing it are only dropped once sampled.
+    volatile: Dict<Vec<u8>, ()>,
+    events: Vec<KeyspaceEvent>,
+}
 
 impl<I> From<I> for HashTableDataStore
 where
-    I: Into<HashMap<Vec<u8>, DataStoreEntry>>, //I: IntoIterator<Item = (K, V)> + FromIterator<(K, V)>,
-                                               //K: Hash,
+    I: IntoIterator<Item = (Vec<u8>, DataStoreEntry)>,
 {
     fn from(value: I) -> Self {
-        Self(value.into())
+        let dict: Dict<_, _> = value.into_iter().collect();
+        let volatile = dict
+            .iter()
+            .filter(|(_, entry)| entry.volatile())
+            .map(|(key, _)| (key.to_owned(), ()))
+            .collect();
+        Self {
+            dict,
+            volatile,
+            events: Vec::new(),
+        }
+    }
+}
+
+/// Attempts made by `random_key` before giving up on a keyspace full of expired keys.
+const RANDOM_KEY_ATTEMPTS: usize = 100;
+/// Keys with a TTL looked at by each round of an active expiry cycle, Redis'
+/// `ACTIVE_EXPIRE_CYCLE_KEYS_PER_LOOP`.
+const ACTIVE_EXPIRE_KEYS_PER_LOOP: usize = 20;
+/// Share of expired keys in a round above which the cycle goes on with another round.
+const ACTIVE_EXPIRE_ACCEPTABLE_STALE: f64 = 0.25;
+/// Time an active expiry cycle may take, a quarter of the 100ms between two cycles.
+const ACTIVE_EXPIRE_CYCLE_TIME: Duration = Duration::from_millis(25);
+
+impl HashTableDataStore {
+    /// Expires `key` or its fields when due, dropping it from the volatile keys once it
+    /// has no TTL left. Returns whether anything expired.
+    fn expire_sampled(&mut self, key: Vec<u8>) -> bool {
+        let Some(entry) = self.dict.get_mut(&key) else {
+            self.volatile.remove(&key);
+            return false;
+        };
+        if entry.expired() {
+            self.dict.remove(&key);
+            self.volatile.remove(&key);
+            self.notify(NotifyFlags::EXPIRED, "expired", &key);
+            return true;
+        }
+        let (mut purged, mut emptied) = (false, false);
+        if let DataValue::Hash(hash) = &mut entry.value {
+            purged = hash.purge_expired() > 0;
+            emptied = hash.is_empty();
+        }
+        if !entry.volatile() || emptied {
+            self.volatile.remove(&key);
+        }
+        if emptied {
+            self.dict.remove(&key);
+        }
+        if purged {
+            self.notify(NotifyFlags::HASH, "hexpired", &key);
+        }
+        if emptied {
+            self.notify(NotifyFlags::GENERIC, "del", &key);
+        }
+        purged
     }
 }
 
 impl DataStore for HashTableDataStore {
     fn insert(&mut self, key: Vec<u8>, data: Vec<u8>, expiry: Option<Duration>) {
-        let new_entry = DataStoreEntry::new(data, expiry);
-        self.0.insert(key, new_entry);
+        self.insert_entry(key, DataStoreEntry::new(data, expiry));
     }
 
     fn get(&mut self, key: Vec<u8>) -> Option<Vec<u8>> {
-        match self.0.get(&key) {
-            Some(entry) => {
-                if entry.expired() {
-                    self.0.remove(&key);
-                    None
-                } else {
-                    Some(entry.data.to_owned())
-                }
+        match self.entry_mut(&key) {
+            Some(DataStoreEntry {
+                value: DataValue::String(data),
+                ..
+            }) => Some(data.to_owned()),
+            _ => None,
+        }
+    }
+
+    fn entry_mut(&mut self, key: &[u8]) -> Option<&mut DataStoreEntry> {
+        if self.dict.get(key)?.expired() {
+            self.dict.remove(key);
+            self.volatile.remove(key);
+            self.notify(NotifyFlags::EXPIRED, "expired", key);
+            return None;
+        }
+        let entry = self.dict.get_mut(key)?;
+        entry.touch();
+        Some(entry)
+    }
+
+    fn entry(&self, key: &[u8]) -> Option<&DataStoreEntry> {
+        self.dict.get(key).filter(|entry| !entry.expired())
+    }
+
+    fn insert_entry(&mut self, key: Vec<u8>, mut entry: DataStoreEntry) {
+        entry.bump_version();
+        if self.entry(&key).is_none() {
+            self.notify(NotifyFlags::NEW, "new", &key);
+        }
+        if entry.volatile() {
+            self.volatile.insert(key.clone(), ());
+        }
+        self.dict.insert(key, entry);
+    }
+
+    fn remove(&mut self, key: &[u8]) -> Option<DataStoreEntry> {
+        let entry = self.dict.remove(key)?;
+        self.volatile.remove(key);
+        if entry.expired() {
+            self.notify(NotifyFlags::EXPIRED, "expired", key);
+            return None;
+        }
+        Some(entry)
+    }
+
+    fn signal_volatile(&mut self, key: &[u8]) {
+        if self.dict.contains_key(key) {
+            self.volatile.insert(key.to_owned(), ());
+        }
+    }
+
+    fn notify(&mut self, class: NotifyFlags, event: &'static str, key: &[u8]) {
+        self.events.push(KeyspaceEvent {
+            class,
+            event,
+            key: key.to_owned(),
+        });
+    }
+
+    fn take_events(&mut self) -> Vec<KeyspaceEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    fn len(&self) -> usize {
+        self.dict.len()
+    }
+
+    fn scan(&mut self, cursor: u64, count: usize) -> (u64, Vec<Vec<u8>>) {
+        let (cursor, entries) = self.dict.scan(cursor, count);
+        let keys = entries
+            .into_iter()
+            .filter(|(_, entry)| !entry.expired())
+            .map(|(key, _)| key.to_owned())
+            .collect();
+        (cursor, keys)
+    }
+
+    fn keys(&self) -> Vec<Vec<u8>> {
+        self.dict
+            .iter()
+            .filter(|(_, entry)| !entry.expired())
+            .map(|(key, _)| key.to_owned())
+            .collect()
+    }
+
+    fn random_key(&mut self) -> Option<Vec<u8>> {
+        for _ in 0..RANDOM_KEY_ATTEMPTS {
+            let (key, entry) = self.dict.random()?;
+            if !entry.expired() {
+                return Some(key.to_owned());
             }
-            None => None,
+            let key = key.to_owned();
+            self.dict.remove(&key);
+            self.volatile.remove(&key);
+            self.notify(NotifyFlags::EXPIRED, "expired", &key);
         }
+        None
     }
-    fn clean(&mut self) -> () {
-        self.0.retain(|_, v| !v.expired());
+
+    /// Active expiry cycle sampling keys with a TTL like Redis does: rounds of a few
+    /// random ones go on while more than a quarter of them expired, within a time budget.
+    /// Records the same events as lazy expiry does.
+    fn clean(&mut self) {
+        let started = Instant::now();
+        while !self.volatile.is_empty() && started.elapsed() < ACTIVE_EXPIRE_CYCLE_TIME {
+            let sampled = ACTIVE_EXPIRE_KEYS_PER_LOOP.min(self.volatile.len());
+            let mut expired = 0;
+            for _ in 0..sampled {
+                let Some((key, _)) = self.volatile.random() else {
+                    break;
+                };
+                if self.expire_sampled(key.to_owned()) {
+                    expired += 1;
+                }
+            }
+            if (expired as f64) <= sampled as f64 * ACTIVE_EXPIRE_ACCEPTABLE_STALE {
+                break;
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod test {
-    use std::{collections::HashMap, time::Duration};
+    use std::time::Duration;
 
     use crate::data_management::{
-        datastore::{DataStore, DataStoreEntry},
+        datastore::{DataStore, DataStoreEntry, DataValue},
         hash_table_store::HashTableDataStore,
     };
 
     #[test]
     fn should_delete_expired_data() {
         let entry_with_expiry = DataStoreEntry::new(vec![], Some(Duration::from_millis(1)));
-        let mut store = HashTableDataStore(HashMap::from([(b"hello".to_vec(), entry_with_expiry)]));
+        let mut store = HashTableDataStore::from([(b"hello".to_vec(), entry_with_expiry)]);
         std::thread::sleep(Duration::from_millis(2));
         assert!(store.get(b"hello".to_vec()).is_none())
     }
@@ -58,7 +227,7 @@ mod test {
     #[test]
     fn should_retrieve_data() {
         let entry_with_expiry = DataStoreEntry::new(vec![], Some(Duration::from_millis(10000)));
-        let mut store = HashTableDataStore(HashMap::from([(b"hello".to_vec(), entry_with_expiry)]));
+        let mut store = HashTableDataStore::from([(b"hello".to_vec(), entry_with_expiry)]);
         assert!(store.get(b"hello".to_vec()).is_some())
     }
 
@@ -73,8 +242,24 @@ mod test {
         ]);
         store.clean();
         assert_eq!(
-            store.get(b"hello".to_vec()).unwrap(),
-            entry_not_expired.data
+            DataValue::String(store.get(b"hello".to_vec()).unwrap()),
+            entry_not_expired.value
         )
     }
+
+    #[test]
+    fn should_only_sample_keys_with_ttl() {
+        let persistent = (0..1000).map(|i| (format!("p{i}").into_bytes(), None));
+        let expiring = (0..100).map(|i| (format!("e{i}").into_bytes(), Some(Duration::ZERO)));
+        let mut store = HashTableDataStore::from(
+            persistent
+                .chain(expiring)
+                .map(|(key, ttl)| (key, DataStoreEntry::new(vec![], ttl))),
+        );
+        assert_eq!(store.volatile.len(), 100);
+        store.clean();
+        assert_eq!(store.len(), 1000);
+        assert!(store.volatile.is_empty());
+        assert_eq!(store.take_events().len(), 100);
+    }
 }
@@ -0,0 +1,462 @@
+use std::time::{Duration, SystemTime};
+
+use crate::{errors::AppError, helpers::glob::glob_match, resp::Resp};
+
+use super::{
+    datastore::{DataStore, DataStoreEntry, DataValue},
+    dict::{scan_reply, ScanArgs},
+    notification::NotifyFlags,
+    rdb,
+};
+
+/// Values with more elements than this are dropped on a blocking task by `UNLINK`, like
+/// Redis' `LAZYFREE_THRESHOLD`.
+const LAZYFREE_THRESHOLD: usize = 64;
+
+pub type KeyValues = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// Commands spanning several keys of any type, applied in a single worker turn so that
+/// the batch is atomic.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyspaceCommand {
+    MGet(Vec<Vec<u8>>),
+    MSet(KeyValues),
+    MSetNx(KeyValues),
+    Del(Vec<Vec<u8>>),
+    Unlink(Vec<Vec<u8>>),
+    Exists(Vec<Vec<u8>>),
+    Touch(Vec<Vec<u8>>),
+    Scan {
+        scan: ScanArgs,
+        type_name: Option<String>,
+    },
+    Keys(Vec<u8>),
+    RandomKey,
+    Rename {
+        source: Vec<u8>,
+        destination: Vec<u8>,
+        nx: bool,
+    },
+    Dump(Vec<u8>),
+    Restore(RestoreArgs),
+    Object(ObjectField, Vec<u8>),
+}
+
+/// `RESTORE key ttl payload [REPLACE] [ABSTTL] [IDLETIME seconds] [FREQ frequency]`, the
+/// ttl being resolved to a point in time by the handler.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RestoreArgs {
+    pub key: Vec<u8>,
+    pub expiry: Option<SystemTime>,
+    pub payload: Vec<u8>,
+    pub replace: bool,
+    pub idle: Option<Duration>,
+    pub frequency: Option<u8>,
+}
+
+/// Metadata reported by the `OBJECT` subcommands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectField {
+    Encoding,
+    IdleTime,
+    Freq,
+    RefCount,
+}
+
+/// Number of allocations freeing `value` costs, mirroring Redis' lazy free effort.
+fn free_effort(value: &DataValue) -> usize {
+    match value {
+        DataValue::String(_) => 1,
+        DataValue::Hash(hash) => hash.len(),
+        DataValue::Set(set) => set.len(),
+        DataValue::SortedSet(sorted_set) => sorted_set.len(),
+        DataValue::Stream(stream) => stream.len(),
+    }
+}
+
+/// Drops `value` on a blocking task when running inside the runtime.
+pub fn lazy_drop<V: Send + 'static>(value: V) {
+    match tokio::runtime::Handle::try_current() {
+        Ok(runtime) => {
+            runtime.spawn_blocking(move || drop(value));
+        }
+        Err(_) => drop(value),
+    }
+}
+
+/// Drops large entries off the worker so that unlinking them does not stall it.
+fn lazy_free(entries: Vec<DataStoreEntry>) {
+    let (large, small): (Vec<_>, Vec<_>) = entries
+        .into_iter()
+        .partition(|entry| free_effort(&entry.value) > LAZYFREE_THRESHOLD);
+    drop(small);
+    if !large.is_empty() {
+        lazy_drop(large);
+    }
+}
+
+/// Removes `key` for `DEL` and `UNLINK`, notifying when it existed.
+fn remove<T: DataStore>(store: &mut T, key: &[u8]) -> Option<DataStoreEntry> {
+    let entry = store.remove(key)?;
+    store.notify(NotifyFlags::GENERIC, "del", key);
+    Some(entry)
+}
+
+fn count_existing<T: DataStore>(store: &T, keys: &[Vec<u8>]) -> i64 {
+    keys.iter().filter(|key| store.entry(key).is_some()).count() as i64
+}
+
+fn count_touched<T: DataStore>(store: &mut T, keys: &[Vec<u8>]) -> i64 {
+    keys.iter()
+        .filter(|key| store.entry_mut(key).is_some())
+        .count() as i64
+}
+
+fn rename<T: DataStore>(
+    store: &mut T,
+    source: Vec<u8>,
+    destination: Vec<u8>,
+    nx: bool,
+) -> Result<Resp, AppError> {
+    if store.entry(&source).is_none() {
+        return Err(AppError::NoSuchKey);
+    }
+    let reply = |renamed: bool| match nx {
+        true => Resp::Integers(renamed as i64),
+        false => Resp::simple_string_from_str("OK"),
+    };
+    if source == destination {
+        return Ok(reply(false));
+    }
+    if nx && store.entry(&destination).is_some() {
+        return Ok(reply(false));
+    }
+    let entry = store.remove(&source).ok_or(AppError::NoSuchKey)?;
+    store.notify(NotifyFlags::GENERIC, "rename_from", &source);
+    store.insert_entry(destination.clone(), entry);
+    store.notify(NotifyFlags::GENERIC, "rename_to", &destination);
+    Ok(reply(true))
+}
+
+fn restore<T: DataStore>(store: &mut T, args: RestoreArgs) -> Result<Resp, AppError> {
+    if !args.replace && store.entry(&args.key).is_some() {
+        return Err(AppError::BusyKey);
+    }
+    let value = rdb::restore(&args.payload)?;
+    if args.expiry.is_some_and(|at| at <= SystemTime::now()) {
+        // Restoring an already expired key only deletes what it would replace.
+        if store.remove(&args.key).is_some() {
+            store.notify(NotifyFlags::GENERIC, "del", &args.key);
+        }
+        return Ok(Resp::simple_string_from_str("OK"));
+    }
+    let mut entry = DataStoreEntry::with_value(value, None);
+    entry.set_expiry(args.expiry);
+    if let Some(idle) = args.idle {
+        entry.set_idle_time(idle);
+    }
+    if let Some(frequency) = args.frequency {
+        entry.set_frequency(frequency);
+    }
+    store.insert_entry(args.key.clone(), entry);
+    store.notify(NotifyFlags::GENERIC, "restore", &args.key);
+    Ok(Resp::simple_string_from_str("OK"))
+}
+
+fn object_field(entry: &DataStoreEntry, field: ObjectField) -> Resp {
+    match field {
+        ObjectField::Encoding => Resp::bulk_string_from_str(entry.value.encoding()),
+        ObjectField::IdleTime => Resp::Integers(entry.idle_time().as_secs() as i64),
+        ObjectField::Freq => Resp::Integers(entry.frequency() as i64),
+        ObjectField::RefCount => Resp::Integers(1),
+    }
+}
+
+/// Replies with the names of `keys` matching `pattern`, keys being stored serialized.
+fn matching_keys(keys: Vec<Vec<u8>>, pattern: Option<&[u8]>) -> Result<Vec<Resp>, AppError> {
+    let mut names = vec![];
+    for key in keys {
+        let name = Resp::deserialize(&key)?;
+        let matches = match (&name, pattern) {
+            (Resp::BulkString(name), Some(pattern)) => glob_match(pattern, name, false),
+            _ => true,
+        };
+        if matches {
+            names.push(name);
+        }
+    }
+    Ok(names)
+}
+
+pub fn apply_keyspace_command<T: DataStore>(
+    store: &mut T,
+    command: KeyspaceCommand,
+) -> Result<Resp, AppError> {
+    match command {
+        KeyspaceCommand::MGet(keys) => {
+            let values = keys
+                .iter()
+                .map(|key| match store.entry_mut(key) {
+                    Some(DataStoreEntry {
+                        value: DataValue::String(data),
+                        ..
+                    }) => Resp::deserialize(data).map_err(AppError::from),
+                    _ => Ok(Resp::null_bulk_string()),
+                })
+                .collect::<Result<_, _>>()?;
+            Ok(Resp::Array(values))
+        }
+        KeyspaceCommand::MSet(pairs) => {
+            for (key, value) in pairs {
+                store.insert(key.clone(), value, None);
+                store.notify(NotifyFlags::STRING, "set", &key);
+            }
+            Ok(Resp::simple_string_from_str("OK"))
+        }
+        KeyspaceCommand::MSetNx(pairs) => {
+            if pairs.iter().any(|(key, _)| store.entry_mut(key).is_some()) {
+                return Ok(Resp::Integers(0));
+            }
+            for (key, value) in pairs {
+                store.insert(key.clone(), value, None);
+                store.notify(NotifyFlags::STRING, "set", &key);
+            }
+            Ok(Resp::Integers(1))
+        }
+        KeyspaceCommand::Del(keys) => {
+            let deleted = keys
+                .iter()
+                .filter(|key| remove(store, key).is_some())
+                .count();
+            Ok(Resp::Integers(deleted as i64))
+        }
+        KeyspaceCommand::Unlink(keys) => {
+            let removed: Vec<_> = keys.iter().filter_map(|key| remove(store, key)).collect();
+            let unlinked = removed.len();
+            lazy_free(removed);
+            Ok(Resp::Integers(unlinked as i64))
+        }
+        KeyspaceCommand::Exists(keys) => Ok(Resp::Integers(count_existing(store, &keys))),
+        KeyspaceCommand::Touch(keys) => Ok(Resp::Integers(count_touched(store, &keys))),
+        KeyspaceCommand::Scan { scan, type_name } => {
+            let (cursor, mut keys) = store.scan(scan.cursor, scan.count);
+            if let Some(type_name) = type_name {
+                keys.retain(|key| {
+                    store.entry(key).is_some_and(|entry| {
+                        entry.value.type_name().eq_ignore_ascii_case(&type_name)
+                    })
+                });
+            }
+            let names = matching_keys(keys, scan.pattern.as_deref())?;
+            Ok(scan_reply(cursor, names))
+        }
+        KeyspaceCommand::Keys(pattern) => {
+            let keys = store.keys();
+            Ok(Resp::Array(matching_keys(keys, Some(&pattern))?))
+        }
+        KeyspaceCommand::RandomKey => match store.random_key() {
+            Some(key) => Ok(Resp::deserialize(&key)?),
+            None => Ok(Resp::null_bulk_string()),
+        },
+        KeyspaceCommand::Rename {
+            source,
+            destination,
+            nx,
+        } => rename(store, source, destination, nx),
+        KeyspaceCommand::Dump(key) => match store.entry_mut(&key) {
+            Some(entry) => Ok(Resp::BulkString(rdb::dump(&entry.value)?)),
+            None => Ok(Resp::null_bulk_string()),
+        },
+        KeyspaceCommand::Restore(args) => restore(store, args),
+        KeyspaceCommand::Object(field, key) => match store.entry(&key) {
+            Some(entry) => Ok(object_field(entry, field)),
+            None => Ok(Resp::null_bulk_string()),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        commands::test_helpers::key,
+        data_management::{hash_table_store::HashTableDataStore, unordered_set::SetValue},
+    };
+
+    fn pairs(pairs: &[(&str, &str)]) -> KeyValues {
+        pairs.iter().map(|(k, v)| (key(k), key(v))).collect()
+    }
+
+    #[test]
+    fn should_set_and_get_many_keys() {
+        let mut store = HashTableDataStore::default();
+        let command = KeyspaceCommand::MSet(pairs(&[("a", "1"), ("b", "2")]));
+        apply_keyspace_command(&mut store, command).unwrap();
+        let command = KeyspaceCommand::MGet(vec![key("a"), key("missing"), key("b")]);
+        let values = apply_keyspace_command(&mut store, command).unwrap();
+        assert_eq!(
+            values,
+            Resp::Array(vec![
+                Resp::bulk_string_from_str("1"),
+                Resp::null_bulk_string(),
+                Resp::bulk_string_from_str("2"),
+            ])
+        );
+    }
+
+    #[test]
+    fn should_not_set_any_key_when_one_exists() {
+        let mut store = HashTableDataStore::default();
+        store.insert(key("b"), key("old"), None);
+        let command = KeyspaceCommand::MSetNx(pairs(&[("a", "1"), ("b", "2")]));
+        let result = apply_keyspace_command(&mut store, command).unwrap();
+        assert_eq!(result, Resp::Integers(0));
+        assert!(store.entry_mut(&key("a")).is_none());
+    }
+
+    #[test]
+    fn should_count_repeated_keys_in_exists() {
+        let mut store = HashTableDataStore::default();
+        store.insert(key("a"), key("1"), None);
+        let command = KeyspaceCommand::Exists(vec![key("a"), key("a"), key("b")]);
+        let result = apply_keyspace_command(&mut store, command).unwrap();
+        assert_eq!(result, Resp::Integers(2));
+    }
+
+    #[test]
+    fn should_scan_keys_of_a_type_across_calls() {
+        let mut store = HashTableDataStore::default();
+        for n in 0..50 {
+            store.insert(key(&format!("string:{n}")), key("v"), None);
+        }
+        let set = DataValue::Set(SetValue::from([b"m".to_vec()]));
+        store.insert_entry(key("set:0"), DataStoreEntry::with_value(set, None));
+        let (mut cursor, mut names) = (0, vec![]);
+        loop {
+            let command = KeyspaceCommand::Scan {
+                scan: ScanArgs {
+                    cursor,
+                    pattern: Some(b"*:0".to_vec()),
+                    count: 7,
+                },
+                type_name: Some("SET".to_owned()),
+            };
+            let Resp::Array(reply) = apply_keyspace_command(&mut store, command).unwrap() else {
+                panic!()
+            };
+            let [Resp::BulkString(next), Resp::Array(keys)] = reply.as_slice() else {
+                panic!()
+            };
+            names.extend(keys.iter().cloned());
+            cursor = std::str::from_utf8(next).unwrap().parse().unwrap();
+            if cursor == 0 {
+                break;
+            }
+        }
+        assert_eq!(names, vec![Resp::bulk_string_from_str("set:0")]);
+    }
+
+    #[test]
+    fn should_list_keys_matching_pattern() {
+        let mut store = HashTableDataStore::default();
+        for name in ["hello", "hallo", "world"] {
+            store.insert(key(name), key("v"), None);
+        }
+        let result = apply_keyspace_command(&mut store, KeyspaceCommand::Keys(b"h?llo".to_vec()));
+        let Resp::Array(mut names) = result.unwrap() else {
+            panic!()
+        };
+        names.sort_by_key(|name| name.clone().serialize().unwrap());
+        assert_eq!(
+            names,
+            vec![
+                Resp::bulk_string_from_str("hallo"),
+                Resp::bulk_string_from_str("hello")
+            ]
+        );
+        let random = apply_keyspace_command(&mut store, KeyspaceCommand::RandomKey).unwrap();
+        assert_ne!(random, Resp::null_bulk_string());
+    }
+
+    #[test]
+    fn should_rename_keeping_the_expiry() {
+        let mut store = HashTableDataStore::default();
+        store.insert(key("a"), key("1"), Some(Duration::from_secs(100)));
+        store.insert(key("b"), key("2"), None);
+        let command = KeyspaceCommand::Rename {
+            source: key("a"),
+            destination: key("b"),
+            nx: true,
+        };
+        let result = apply_keyspace_command(&mut store, command.clone()).unwrap();
+        assert_eq!(result, Resp::Integers(0));
+        let KeyspaceCommand::Rename {
+            source,
+            destination,
+            ..
+        } = command
+        else {
+            panic!()
+        };
+        let command = KeyspaceCommand::Rename {
+            source,
+            destination,
+            nx: false,
+        };
+        apply_keyspace_command(&mut store, command.clone()).unwrap();
+        assert!(store.entry(&key("a")).is_none());
+        assert!(store.entry(&key("b")).unwrap().expiry().is_some());
+        let result = apply_keyspace_command(&mut store, command);
+        assert_eq!(result.unwrap_err().to_string(), "ERR no such key");
+    }
+
+    #[test]
+    fn should_restore_a_dumped_value_with_its_metadata() {
+        let mut store = HashTableDataStore::default();
+        let set = DataValue::Set(SetValue::from([b"a".to_vec(), b"b".to_vec()]));
+        store.insert_entry(key("set"), DataStoreEntry::with_value(set.clone(), None));
+        let Resp::BulkString(payload) =
+            apply_keyspace_command(&mut store, KeyspaceCommand::Dump(key("set"))).unwrap()
+        else {
+            panic!()
+        };
+        let args = RestoreArgs {
+            key: key("set"),
+            expiry: None,
+            payload,
+            replace: false,
+            idle: Some(Duration::from_secs(1000)),
+            frequency: None,
+        };
+        let result = apply_keyspace_command(&mut store, KeyspaceCommand::Restore(args.clone()));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            AppError::BusyKey.to_string()
+        );
+        let args = RestoreArgs {
+            key: key("copy"),
+            ..args
+        };
+        apply_keyspace_command(&mut store, KeyspaceCommand::Restore(args)).unwrap();
+        assert_eq!(store.entry(&key("copy")).unwrap().value, set);
+        let command = KeyspaceCommand::Object(ObjectField::IdleTime, key("copy"));
+        let idle = apply_keyspace_command(&mut store, command).unwrap();
+        assert_eq!(idle, Resp::Integers(1000));
+        let command = KeyspaceCommand::Object(ObjectField::Encoding, key("copy"));
+        let encoding = apply_keyspace_command(&mut store, command).unwrap();
+        assert_eq!(encoding, Resp::bulk_string_from_str("hashtable"));
+    }
+
+    #[tokio::test]
+    async fn should_unlink_large_values() {
+        let mut store = HashTableDataStore::default();
+        let members: Vec<Vec<u8>> = (0..1000).map(|n| format!("m{n}").into_bytes()).collect();
+        let set = DataValue::Set(SetValue::from(members));
+        store.insert_entry(key("big"), DataStoreEntry::with_value(set, None));
+        store.insert(key("small"), key("1"), None);
+        let command = KeyspaceCommand::Unlink(vec![key("big"), key("small"), key("missing")]);
+        let result = apply_keyspace_command(&mut store, command).unwrap();
+        assert_eq!(result, Resp::Integers(2));
+        assert!(store.entry_mut(&key("big")).is_none());
+    }
+}
@@ -1,15 +1,40 @@
-use std::time::Duration;
+use std::{path::PathBuf, time::Duration};
 
-use crate::resp::Resp;
+use futures::TryFutureExt;
+use tokio::sync::mpsc::Sender;
+
+use crate::{errors::AppError, resp::Resp};
+
+use super::{
+    blocking::BlockingCommand,
+    database::DatabaseCommand,
+    function::FunctionCommand,
+    hash::HashCommand,
+    keyspace::KeyspaceCommand,
+    pubsub::PubSubCommand,
+    scripting::ScriptCommand,
+    sorted_set::SortedSetCommand,
+    stream::StreamCommand,
+    string::StringCommand,
+    tracking::{TrackingCommand, TrackingContext},
+    unordered_set::UnorderedSetCommand,
+};
 
 #[derive(Debug, thiserror::Error)]
 pub enum MessageChannelError {
     #[error(transparent)]
-    DataSending(#[from] tokio::sync::mpsc::error::SendError<DataChannelMessage>),
+    DataSending(Box<tokio::sync::mpsc::error::SendError<DataChannelMessage>>),
     #[error(transparent)]
     DataReplying(#[from] tokio::sync::oneshot::error::RecvError),
 }
 
+// Boxed so `AppError` stays small as `DataChannelMessage` grows.
+impl From<tokio::sync::mpsc::error::SendError<DataChannelMessage>> for MessageChannelError {
+    fn from(value: tokio::sync::mpsc::error::SendError<DataChannelMessage>) -> Self {
+        Self::DataSending(Box::new(value))
+    }
+}
+
 #[derive(Debug)]
 pub struct SetMessage {
     pub key: Vec<u8>,
@@ -47,9 +72,358 @@ impl GetMessage {
 }
 
 #[derive(Debug)]
-pub enum DataChannelMessage {
+pub struct KeyspaceMessage {
+    pub command: KeyspaceCommand,
+    pub sender: tokio::sync::oneshot::Sender<ResponseChannelMessage>,
+}
+
+impl KeyspaceMessage {
+    pub fn new(
+        command: KeyspaceCommand,
+        sender: tokio::sync::oneshot::Sender<ResponseChannelMessage>,
+    ) -> Self {
+        Self { command, sender }
+    }
+}
+
+#[derive(Debug)]
+pub struct StringMessage {
+    pub command: StringCommand,
+    pub sender: tokio::sync::oneshot::Sender<ResponseChannelMessage>,
+}
+
+impl StringMessage {
+    pub fn new(
+        command: StringCommand,
+        sender: tokio::sync::oneshot::Sender<ResponseChannelMessage>,
+    ) -> Self {
+        Self { command, sender }
+    }
+}
+
+#[derive(Debug)]
+pub struct HashMessage {
+    pub key: Vec<u8>,
+    pub command: HashCommand,
+    pub sender: tokio::sync::oneshot::Sender<ResponseChannelMessage>,
+}
+
+impl HashMessage {
+    pub fn new(
+        key: Vec<u8>,
+        command: HashCommand,
+        sender: tokio::sync::oneshot::Sender<ResponseChannelMessage>,
+    ) -> Self {
+        Self {
+            key,
+            command,
+            sender,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct UnorderedSetMessage {
+    pub command: UnorderedSetCommand,
+    pub sender: tokio::sync::oneshot::Sender<ResponseChannelMessage>,
+}
+
+impl UnorderedSetMessage {
+    pub fn new(
+        command: UnorderedSetCommand,
+        sender: tokio::sync::oneshot::Sender<ResponseChannelMessage>,
+    ) -> Self {
+        Self { command, sender }
+    }
+}
+
+#[derive(Debug)]
+pub struct SortedSetMessage {
+    pub command: SortedSetCommand,
+    pub sender: tokio::sync::oneshot::Sender<ResponseChannelMessage>,
+}
+
+impl SortedSetMessage {
+    pub fn new(
+        command: SortedSetCommand,
+        sender: tokio::sync::oneshot::Sender<ResponseChannelMessage>,
+    ) -> Self {
+        Self { command, sender }
+    }
+}
+
+#[derive(Debug)]
+pub struct StreamMessage {
+    pub command: StreamCommand,
+    pub sender: tokio::sync::oneshot::Sender<ResponseChannelMessage>,
+}
+
+impl StreamMessage {
+    pub fn new(
+        command: StreamCommand,
+        sender: tokio::sync::oneshot::Sender<ResponseChannelMessage>,
+    ) -> Self {
+        Self { command, sender }
+    }
+}
+
+#[derive(Debug)]
+pub struct DatabaseMessage {
+    pub command: DatabaseCommand,
+    pub sender: tokio::sync::oneshot::Sender<ResponseChannelMessage>,
+}
+
+impl DatabaseMessage {
+    pub fn new(
+        command: DatabaseCommand,
+        sender: tokio::sync::oneshot::Sender<ResponseChannelMessage>,
+    ) -> Self {
+        Self { command, sender }
+    }
+}
+
+#[derive(Debug)]
+pub struct BlockingMessage {
+    pub command: BlockingCommand,
+    pub timeout: Option<Duration>,
+    pub sender: tokio::sync::oneshot::Sender<ResponseChannelMessage>,
+}
+
+impl BlockingMessage {
+    pub fn new(
+        command: BlockingCommand,
+        timeout: Option<Duration>,
+        sender: tokio::sync::oneshot::Sender<ResponseChannelMessage>,
+    ) -> Self {
+        Self {
+            command,
+            timeout,
+            sender,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct PubSubMessage {
+    pub command: PubSubCommand,
+    pub sender: tokio::sync::oneshot::Sender<ResponseChannelMessage>,
+}
+
+impl PubSubMessage {
+    pub fn new(
+        command: PubSubCommand,
+        sender: tokio::sync::oneshot::Sender<ResponseChannelMessage>,
+    ) -> Self {
+        Self { command, sender }
+    }
+}
+
+/// A key recorded by `WATCH`, along with the version it had at that time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchedKey {
+    pub db: usize,
+    pub key: Vec<u8>,
+    pub version: u64,
+}
+
+impl WatchedKey {
+    pub fn new(db: usize, key: Vec<u8>, version: u64) -> Self {
+        Self { db, key, version }
+    }
+}
+
+/// Looks up the current versions of `keys`, replied as an array of integers.
+#[derive(Debug)]
+pub struct WatchMessage {
+    pub keys: Vec<Vec<u8>>,
+    pub sender: tokio::sync::oneshot::Sender<ResponseChannelMessage>,
+}
+
+impl WatchMessage {
+    pub fn new(
+        keys: Vec<Vec<u8>>,
+        sender: tokio::sync::oneshot::Sender<ResponseChannelMessage>,
+    ) -> Self {
+        Self { keys, sender }
+    }
+}
+
+/// The requests queued by a `MULTI` block, run back to back unless one of the `watched`
+/// keys changed. `sender` receives `OK` once they ran, or a null array when aborted, in
+/// which case the queued requests are dropped without a reply.
+#[derive(Debug)]
+pub struct ExecMessage {
+    pub watched: Vec<WatchedKey>,
+    pub requests: Vec<DataChannelMessage>,
+    pub sender: tokio::sync::oneshot::Sender<ResponseChannelMessage>,
+}
+
+impl ExecMessage {
+    pub fn new(
+        watched: Vec<WatchedKey>,
+        requests: Vec<DataChannelMessage>,
+        sender: tokio::sync::oneshot::Sender<ResponseChannelMessage>,
+    ) -> Self {
+        Self {
+            watched,
+            requests,
+            sender,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct TrackingMessage {
+    pub command: TrackingCommand,
+    pub sender: tokio::sync::oneshot::Sender<ResponseChannelMessage>,
+}
+
+impl TrackingMessage {
+    pub fn new(
+        command: TrackingCommand,
+        sender: tokio::sync::oneshot::Sender<ResponseChannelMessage>,
+    ) -> Self {
+        Self { command, sender }
+    }
+}
+
+#[derive(Debug)]
+pub struct ScriptMessage {
+    pub command: ScriptCommand,
+    /// ACL user whose permissions the commands of the script run with.
+    pub user: String,
+    pub sender: tokio::sync::oneshot::Sender<ResponseChannelMessage>,
+}
+
+impl ScriptMessage {
+    pub fn new(
+        command: ScriptCommand,
+        user: String,
+        sender: tokio::sync::oneshot::Sender<ResponseChannelMessage>,
+    ) -> Self {
+        Self {
+            command,
+            user,
+            sender,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct FunctionMessage {
+    pub command: FunctionCommand,
+    /// ACL user whose permissions the commands of the script run with.
+    pub user: String,
+    pub sender: tokio::sync::oneshot::Sender<ResponseChannelMessage>,
+}
+
+impl FunctionMessage {
+    pub fn new(
+        command: FunctionCommand,
+        user: String,
+        sender: tokio::sync::oneshot::Sender<ResponseChannelMessage>,
+    ) -> Self {
+        Self {
+            command,
+            user,
+            sender,
+        }
+    }
+}
+
+/// Stops the data worker, after saving the dataset to `save` when set. The worker keeps
+/// running when the save fails, replying the error.
+#[derive(Debug)]
+pub struct ShutdownMessage {
+    pub save: Option<PathBuf>,
+    pub sender: tokio::sync::oneshot::Sender<ResponseChannelMessage>,
+}
+
+impl ShutdownMessage {
+    pub fn new(
+        save: Option<PathBuf>,
+        sender: tokio::sync::oneshot::Sender<ResponseChannelMessage>,
+    ) -> Self {
+        Self { save, sender }
+    }
+}
+
+#[derive(Debug)]
+pub enum DataRequest {
     Set(SetMessage),
     Get(GetMessage),
+    Keyspace(KeyspaceMessage),
+    String(StringMessage),
+    Hash(HashMessage),
+    UnorderedSet(UnorderedSetMessage),
+    SortedSet(SortedSetMessage),
+    Stream(StreamMessage),
+    Blocking(BlockingMessage),
+    Database(DatabaseMessage),
+    PubSub(PubSubMessage),
+    Watch(WatchMessage),
+    Exec(ExecMessage),
+    Tracking(TrackingMessage),
+    Script(ScriptMessage),
+    Function(FunctionMessage),
+    Shutdown(ShutdownMessage),
+}
+
+/// A request for the data worker along with the logical database it applies to.
+#[derive(Debug)]
+pub struct DataChannelMessage {
+    pub db: usize,
+    pub request: DataRequest,
+    /// Set when the sending client has `CLIENT TRACKING` on.
+    pub tracking: Option<TrackingContext>,
+}
+
+impl DataChannelMessage {
+    pub fn new(db: usize, request: DataRequest) -> Self {
+        Self {
+            db,
+            request,
+            tracking: None,
+        }
+    }
+
+    pub fn tracked(mut self, tracking: Option<TrackingContext>) -> Self {
+        self.tracking = tracking;
+        self
+    }
+}
+
+/// Sends the request built by `request` against database `db` to the data worker and
+/// waits for its reply.
+pub async fn send_message<F>(
+    data_sender: &Sender<DataChannelMessage>,
+    db: usize,
+    request: F,
+) -> Result<Resp, AppError>
+where
+    F: FnOnce(tokio::sync::oneshot::Sender<ResponseChannelMessage>) -> DataRequest,
+{
+    send_tracked_message(data_sender, db, None, request).await
+}
+
+/// Like `send_message`, for a request of a client tracking the keys it reads.
+pub async fn send_tracked_message<F>(
+    data_sender: &Sender<DataChannelMessage>,
+    db: usize,
+    tracking: Option<TrackingContext>,
+    request: F,
+) -> Result<Resp, AppError>
+where
+    F: FnOnce(tokio::sync::oneshot::Sender<ResponseChannelMessage>) -> DataRequest,
+{
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    data_sender
+        .send(DataChannelMessage::new(db, request(sender)).tracked(tracking))
+        .map_err(MessageChannelError::from)
+        .await?;
+
+    let reply = receiver.await.map_err(MessageChannelError::from)?;
+    Ok(reply.0)
 }
 
 #[derive(Debug)]
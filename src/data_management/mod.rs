@@ -1,4 +1,21 @@
+pub mod blocking;
+pub mod consumer_group;
+pub mod database;
 pub mod datastore;
+pub mod dict;
+pub mod function;
+pub mod hash;
 pub mod hash_table_store;
+pub mod keyspace;
 pub mod message;
+pub mod notification;
+pub mod pubsub;
+pub mod rdb;
+pub mod scripting;
+pub mod skiplist;
+pub mod sorted_set;
+pub mod stream;
+pub mod string;
+pub mod tracking;
+pub mod unordered_set;
 pub mod worker;
@@ -0,0 +1,157 @@
+use std::fmt::Display;
+
+use super::{datastore::key_name, pubsub::PubSubHub};
+
+/// Event classes selected by `notify-keyspace-events`, one bit per flag character.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct NotifyFlags(u16);
+
+impl NotifyFlags {
+    pub const KEYSPACE: Self = Self(1 << 0);
+    pub const KEYEVENT: Self = Self(1 << 1);
+    pub const GENERIC: Self = Self(1 << 2);
+    pub const STRING: Self = Self(1 << 3);
+    pub const LIST: Self = Self(1 << 4);
+    pub const SET: Self = Self(1 << 5);
+    pub const HASH: Self = Self(1 << 6);
+    pub const ZSET: Self = Self(1 << 7);
+    pub const EXPIRED: Self = Self(1 << 8);
+    pub const EVICTED: Self = Self(1 << 9);
+    pub const STREAM: Self = Self(1 << 10);
+    pub const KEY_MISS: Self = Self(1 << 11);
+    pub const MODULE: Self = Self(1 << 12);
+    pub const NEW: Self = Self(1 << 13);
+    /// Classes enabled by the `A` alias, which leaves out key misses and new keys.
+    pub const ALL: Self = Self(
+        Self::GENERIC.0
+            | Self::STRING.0
+            | Self::LIST.0
+            | Self::SET.0
+            | Self::HASH.0
+            | Self::ZSET.0
+            | Self::EXPIRED.0
+            | Self::EVICTED.0
+            | Self::STREAM.0
+            | Self::MODULE.0,
+    );
+
+    const CLASSES: [(char, Self); 10] = [
+        ('g', Self::GENERIC),
+        ('$', Self::STRING),
+        ('l', Self::LIST),
+        ('s', Self::SET),
+        ('h', Self::HASH),
+        ('z', Self::ZSET),
+        ('x', Self::EXPIRED),
+        ('e', Self::EVICTED),
+        ('t', Self::STREAM),
+        ('d', Self::MODULE),
+    ];
+    const EXTRA: [(char, Self); 4] = [
+        ('K', Self::KEYSPACE),
+        ('E', Self::KEYEVENT),
+        ('m', Self::KEY_MISS),
+        ('n', Self::NEW),
+    ];
+
+    pub fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    fn intersects(&self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    /// Parses flag characters like Redis' `keyspaceEventsStringToFlags`.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        value.chars().try_fold(Self::default(), |flags, flag| {
+            let class = match flag {
+                'A' => Self::ALL,
+                _ => Self::CLASSES
+                    .iter()
+                    .chain(Self::EXTRA.iter())
+                    .find(|(name, _)| *name == flag)
+                    .map(|(_, class)| *class)
+                    .ok_or(format!("invalid notify-keyspace-events flag '{flag}'"))?,
+            };
+            Ok(Self(flags.0 | class.0))
+        })
+    }
+
+    /// Whether an event of `class` has to be published at all.
+    pub fn enabled(&self, class: Self) -> bool {
+        self.intersects(Self(Self::KEYSPACE.0 | Self::KEYEVENT.0)) && self.intersects(class)
+    }
+}
+
+impl Display for NotifyFlags {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.contains(Self::ALL) {
+            write!(f, "A")?;
+        } else {
+            for (name, class) in Self::CLASSES {
+                if self.contains(class) {
+                    write!(f, "{name}")?;
+                }
+            }
+        }
+        for (name, class) in Self::EXTRA {
+            if self.contains(class) {
+                write!(f, "{name}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An event recorded by a store, `key` being kept in its stored, serialized, form.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyspaceEvent {
+    pub class: NotifyFlags,
+    pub event: &'static str,
+    pub key: Vec<u8>,
+}
+
+/// Publishes `events` of database `db` on the `__keyspace@<db>__:<key>` and
+/// `__keyevent@<db>__:<event>` channels selected by `flags`.
+pub fn publish_events(
+    hub: &mut PubSubHub,
+    flags: NotifyFlags,
+    db: usize,
+    events: Vec<KeyspaceEvent>,
+) {
+    for KeyspaceEvent { class, event, key } in events {
+        if !flags.enabled(class) {
+            continue;
+        }
+        let key = key_name(&key);
+        if flags.contains(NotifyFlags::KEYSPACE) {
+            let mut channel = format!("__keyspace@{db}__:").into_bytes();
+            channel.extend_from_slice(&key);
+            hub.publish(&channel, event.as_bytes());
+        }
+        if flags.contains(NotifyFlags::KEYEVENT) {
+            let channel = format!("__keyevent@{db}__:{event}");
+            hub.publish(channel.as_bytes(), &key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::NotifyFlags;
+
+    #[test]
+    fn should_parse_and_display_flags() {
+        let flags = NotifyFlags::parse("Kx$").unwrap();
+        assert!(flags.contains(NotifyFlags::KEYSPACE));
+        assert!(flags.enabled(NotifyFlags::EXPIRED));
+        assert!(!flags.enabled(NotifyFlags::GENERIC));
+        assert_eq!(flags.to_string(), "$xK");
+        assert_eq!(NotifyFlags::parse("AKEn").unwrap().to_string(), "AKEn");
+        assert!(!NotifyFlags::parse("g$")
+            .unwrap()
+            .enabled(NotifyFlags::GENERIC));
+        assert!(NotifyFlags::parse("Kw").is_err());
+    }
+}
@@ -0,0 +1,825 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use tokio::sync::mpsc;
+
+use crate::{
+    errors::AppError,
+    helpers::{crc16::key_hash_slot, glob::glob_match, number::parse_memory},
+    resp::Resp,
+};
+
+/// `client-output-buffer-limit` of a class of clients: they are disconnected once more
+/// than `hard` bytes wait to be written, or more than `soft` bytes for `soft_seconds` in a
+/// row. Limits of 0 are disabled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputBufferLimit {
+    pub hard: usize,
+    pub soft: usize,
+    pub soft_seconds: u64,
+}
+
+impl OutputBufferLimit {
+    /// Whether `queued` bytes exceed the limit, `soft_since` tracking since when the soft
+    /// limit has been.
+    fn exceeded(&self, queued: usize, soft_since: &mut Option<Instant>) -> bool {
+        if self.hard > 0 && queued > self.hard {
+            return true;
+        }
+        if self.soft == 0 || queued <= self.soft {
+            *soft_since = None;
+            return false;
+        }
+        let since = soft_since.get_or_insert_with(Instant::now);
+        since.elapsed() >= Duration::from_secs(self.soft_seconds)
+    }
+}
+
+/// `client-output-buffer-limit` of every class, Redis' defaults being
+/// `normal 0 0 0 replica 256mb 64mb 60 pubsub 32mb 8mb 60`. There are no replication
+/// links, so the replica limits apply to no client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputBufferLimits {
+    pub normal: OutputBufferLimit,
+    pub replica: OutputBufferLimit,
+    pub pubsub: OutputBufferLimit,
+}
+
+impl Default for OutputBufferLimits {
+    fn default() -> Self {
+        const MB: usize = 1024 * 1024;
+        let limit = |hard, soft, soft_seconds| OutputBufferLimit {
+            hard,
+            soft,
+            soft_seconds,
+        };
+        Self {
+            normal: limit(0, 0, 0),
+            replica: limit(256 * MB, 64 * MB, 60),
+            pubsub: limit(32 * MB, 8 * MB, 60),
+        }
+    }
+}
+
+impl OutputBufferLimits {
+    /// Parses `<class> <hard> <soft> <soft seconds>` groups, like `pubsub 32mb 8mb 60`,
+    /// the classes left out keeping their default.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let words: Vec<&str> = value.split_whitespace().collect();
+        if words.is_empty() || !words.len().is_multiple_of(4) {
+            return Err("Wrong number of arguments in buffer limit configuration.".to_owned());
+        }
+        let mut limits = Self::default();
+        for group in words.chunks(4) {
+            let memory = |value: &str| {
+                parse_memory(value).ok_or(
+                    "Error in hard, soft or soft_seconds setting in buffer limit configuration."
+                        .to_owned(),
+                )
+            };
+            let limit = OutputBufferLimit {
+                hard: memory(group[1])?,
+                soft: memory(group[2])?,
+                soft_seconds: group[3].parse().map_err(|_| {
+                    "Error in hard, soft or soft_seconds setting in buffer limit configuration."
+                        .to_owned()
+                })?,
+            };
+            match group[0].to_lowercase().as_str() {
+                "normal" => limits.normal = limit,
+                "replica" | "slave" => limits.replica = limit,
+                "pubsub" => limits.pubsub = limit,
+                _ => {
+                    return Err(
+                        "Invalid client class specified in buffer limit configuration.".to_owned(),
+                    )
+                }
+            }
+        }
+        Ok(limits)
+    }
+}
+
+impl fmt::Display for OutputBufferLimits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let classes = [
+            ("normal", self.normal),
+            ("replica", self.replica),
+            ("pubsub", self.pubsub),
+        ];
+        let groups: Vec<String> = classes
+            .iter()
+            .map(|(class, limit)| {
+                format!(
+                    "{class} {} {} {}",
+                    limit.hard, limit.soft, limit.soft_seconds
+                )
+            })
+            .collect();
+        f.write_str(&groups.join(" "))
+    }
+}
+
+#[derive(Debug)]
+struct PushState {
+    queued: AtomicUsize,
+    overflowed: AtomicBool,
+    limits: OutputBufferLimits,
+    /// Whether the client has subscriptions, the pubsub limits then applying.
+    pubsub: AtomicBool,
+    soft_since: Mutex<Option<Instant>>,
+}
+
+impl PushState {
+    /// Marks the client as overflowing once `queued` bytes exceed the limits of its class.
+    fn check(&self, queued: usize) {
+        let limit = match self.pubsub.load(Ordering::Acquire) {
+            true => self.limits.pubsub,
+            false => self.limits.normal,
+        };
+        if limit.exceeded(queued, &mut self.soft_since.lock().unwrap()) {
+            self.overflowed.store(true, Ordering::Release);
+        }
+    }
+}
+
+/// Pushes messages to a connection without waiting for it to write them, so that a slow
+/// subscriber never holds up publishers.
+#[derive(Debug, Clone)]
+pub struct PushSender {
+    id: u64,
+    sender: mpsc::UnboundedSender<Resp>,
+    state: Arc<PushState>,
+}
+
+/// The connection side of a `PushSender`.
+#[derive(Debug)]
+pub struct PushReceiver {
+    receiver: mpsc::UnboundedReceiver<Resp>,
+    state: Arc<PushState>,
+}
+
+/// Creates the push channel of client `id`, which overflows once the bytes waiting to be
+/// written exceed the `limits` of its class.
+pub fn push_channel(id: u64, limits: OutputBufferLimits) -> (PushSender, PushReceiver) {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let state = Arc::new(PushState {
+        queued: AtomicUsize::new(0),
+        overflowed: AtomicBool::new(false),
+        limits,
+        pubsub: AtomicBool::new(false),
+        soft_since: Mutex::new(None),
+    });
+    let push_sender = PushSender {
+        id,
+        sender,
+        state: state.clone(),
+    };
+    (push_sender, PushReceiver { receiver, state })
+}
+
+impl PushSender {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Queues `message`, returning false once the client has to be dropped.
+    pub fn push(&self, message: Resp) -> bool {
+        if self.is_closed() {
+            return false;
+        }
+        let size = message.size();
+        let queued = self.state.queued.fetch_add(size, Ordering::AcqRel) + size;
+        self.state.check(queued);
+        // Sent even when overflowing so that the connection wakes up and disconnects.
+        self.sender.send(message).is_ok() && !self.is_closed()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.sender.is_closed() || self.state.overflowed.load(Ordering::Acquire)
+    }
+}
+
+impl PushReceiver {
+    /// Waits for the next message to write, `None` meaning the client has to be
+    /// disconnected.
+    pub async fn recv(&mut self) -> Option<Resp> {
+        let message = self.receiver.recv().await?;
+        self.accept(message)
+    }
+
+    /// Takes a message already waiting to be written.
+    pub fn try_recv(&mut self) -> Option<Resp> {
+        let message = self.receiver.try_recv().ok()?;
+        self.accept(message)
+    }
+
+    pub fn overflowed(&self) -> bool {
+        self.state.overflowed.load(Ordering::Acquire)
+    }
+
+    /// Bytes of the messages waiting to be written.
+    pub fn queued(&self) -> usize {
+        self.state.queued.load(Ordering::Acquire)
+    }
+
+    /// Counts the `size` bytes of a reply as waiting to be written, returning false once
+    /// they make the client exceed its limits.
+    pub fn reply_queued(&self, size: usize) -> bool {
+        let queued = self.state.queued.fetch_add(size, Ordering::AcqRel) + size;
+        self.state.check(queued);
+        !self.overflowed()
+    }
+
+    /// Stops counting the `size` bytes of a reply once written.
+    pub fn reply_written(&self, size: usize) {
+        let queued = self.state.queued.fetch_sub(size, Ordering::AcqRel) - size;
+        self.state.check(queued);
+    }
+
+    /// Sets whether the client has subscriptions, making the pubsub limits apply.
+    pub fn set_pubsub(&self, pubsub: bool) {
+        self.state.pubsub.store(pubsub, Ordering::Release);
+    }
+
+    fn accept(&mut self, message: Resp) -> Option<Resp> {
+        if self.overflowed() {
+            return None;
+        }
+        let size = message.size();
+        let queued = self.state.queued.fetch_sub(size, Ordering::AcqRel) - size;
+        self.state.check(queued);
+        Some(message)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscriptionKind {
+    Channel,
+    Pattern,
+    Shard,
+}
+
+impl SubscriptionKind {
+    fn subscribe_reply(&self) -> &'static str {
+        match self {
+            Self::Channel => "subscribe",
+            Self::Pattern => "psubscribe",
+            Self::Shard => "ssubscribe",
+        }
+    }
+
+    fn unsubscribe_reply(&self) -> &'static str {
+        match self {
+            Self::Channel => "unsubscribe",
+            Self::Pattern => "punsubscribe",
+            Self::Shard => "sunsubscribe",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum PubSubCommand {
+    Subscribe {
+        kind: SubscriptionKind,
+        client: PushSender,
+        names: Vec<Vec<u8>>,
+    },
+    /// Unsubscribes from every channel, or pattern, of that kind when `names` is empty.
+    Unsubscribe {
+        kind: SubscriptionKind,
+        client: PushSender,
+        names: Vec<Vec<u8>>,
+    },
+    Publish {
+        channel: Vec<u8>,
+        message: Vec<u8>,
+    },
+    ShardPublish {
+        channel: Vec<u8>,
+        message: Vec<u8>,
+    },
+    Channels(Option<Vec<u8>>),
+    ShardChannels(Option<Vec<u8>>),
+    NumSub(Vec<Vec<u8>>),
+    ShardNumSub(Vec<Vec<u8>>),
+    NumPat,
+}
+
+/// Two-way index of the subscriptions of one kind.
+#[derive(Debug, Default)]
+struct Subscriptions {
+    subscribers: HashMap<Vec<u8>, BTreeMap<u64, PushSender>>,
+    by_client: HashMap<u64, BTreeSet<Vec<u8>>>,
+}
+
+impl Subscriptions {
+    fn subscribe(&mut self, client: &PushSender, name: Vec<u8>) {
+        self.by_client
+            .entry(client.id())
+            .or_default()
+            .insert(name.clone());
+        self.subscribers
+            .entry(name)
+            .or_default()
+            .insert(client.id(), client.clone());
+    }
+
+    fn unsubscribe(&mut self, client: u64, name: &[u8]) {
+        if let Some(names) = self.by_client.get_mut(&client) {
+            names.remove(name);
+            if names.is_empty() {
+                self.by_client.remove(&client);
+            }
+        }
+        if let Some(subscribers) = self.subscribers.get_mut(name) {
+            subscribers.remove(&client);
+            if subscribers.is_empty() {
+                self.subscribers.remove(name);
+            }
+        }
+    }
+
+    fn names_of(&self, client: u64) -> Vec<Vec<u8>> {
+        self.by_client
+            .get(&client)
+            .map(|names| names.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    fn count(&self, client: u64) -> usize {
+        self.by_client.get(&client).map_or(0, BTreeSet::len)
+    }
+
+    fn remove_client(&mut self, client: u64) {
+        for name in self.names_of(client) {
+            self.unsubscribe(client, &name);
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.subscribers.is_empty()
+    }
+}
+
+/// Channel, pattern and shard channel subscriptions of every client, owned by the data
+/// worker.
+#[derive(Debug, Default)]
+pub struct PubSubHub {
+    channels: Subscriptions,
+    patterns: Subscriptions,
+    /// Shard channels grouped by hash slot, like keys once cluster mode exists.
+    shard_channels: HashMap<u16, Subscriptions>,
+}
+
+impl PubSubHub {
+    fn subscriptions(&mut self, kind: SubscriptionKind, name: &[u8]) -> &mut Subscriptions {
+        match kind {
+            SubscriptionKind::Channel => &mut self.channels,
+            SubscriptionKind::Pattern => &mut self.patterns,
+            SubscriptionKind::Shard => self.shard_channels.entry(key_hash_slot(name)).or_default(),
+        }
+    }
+
+    fn names_of(&self, kind: SubscriptionKind, client: u64) -> Vec<Vec<u8>> {
+        match kind {
+            SubscriptionKind::Channel => self.channels.names_of(client),
+            SubscriptionKind::Pattern => self.patterns.names_of(client),
+            SubscriptionKind::Shard => self
+                .shard_channels
+                .values()
+                .flat_map(|slot| slot.names_of(client))
+                .collect(),
+        }
+    }
+
+    /// Number of subscriptions reported to `client` in (un)subscribe confirmations, shard
+    /// channels being counted apart from the others.
+    fn count(&self, kind: SubscriptionKind, client: u64) -> usize {
+        match kind {
+            SubscriptionKind::Channel | SubscriptionKind::Pattern => {
+                self.channels.count(client) + self.patterns.count(client)
+            }
+            SubscriptionKind::Shard => self
+                .shard_channels
+                .values()
+                .map(|slot| slot.count(client))
+                .sum(),
+        }
+    }
+
+    /// Push channel of `client` while it is subscribed to a channel or a pattern, the only
+    /// state in which a RESP2 connection takes messages it did not ask for.
+    pub fn subscriber(&self, client: u64) -> Option<&PushSender> {
+        [&self.channels, &self.patterns]
+            .into_iter()
+            .find_map(|subscriptions| {
+                let name = subscriptions.by_client.get(&client)?.first()?;
+                subscriptions.subscribers.get(name)?.get(&client)
+            })
+    }
+
+    fn remove_client(&mut self, client: u64) {
+        self.channels.remove_client(client);
+        self.patterns.remove_client(client);
+        for slot in self.shard_channels.values_mut() {
+            slot.remove_client(client);
+        }
+        self.shard_channels.retain(|_, slot| !slot.is_empty());
+    }
+
+    fn deliver(&mut self, deliveries: Vec<(PushSender, Resp)>) -> usize {
+        let mut delivered = 0;
+        for (client, push) in deliveries {
+            if client.push(push) {
+                delivered += 1;
+            } else {
+                self.remove_client(client.id());
+            }
+        }
+        delivered
+    }
+
+    /// Delivers `message` to the subscribers of `channel` and of the patterns matching
+    /// it, returning how many clients received it.
+    pub fn publish(&mut self, channel: &[u8], message: &[u8]) -> usize {
+        let mut deliveries = Vec::new();
+        if let Some(subscribers) = self.channels.subscribers.get(channel) {
+            let push = Resp::Array(vec![
+                Resp::bulk_string_from_str("message"),
+                Resp::BulkString(channel.to_vec()),
+                Resp::BulkString(message.to_vec()),
+            ]);
+            deliveries.extend(
+                subscribers
+                    .values()
+                    .map(|client| (client.clone(), push.clone())),
+            );
+        }
+        for (pattern, subscribers) in &self.patterns.subscribers {
+            if !glob_match(pattern, channel, false) {
+                continue;
+            }
+            let push = Resp::Array(vec![
+                Resp::bulk_string_from_str("pmessage"),
+                Resp::BulkString(pattern.clone()),
+                Resp::BulkString(channel.to_vec()),
+                Resp::BulkString(message.to_vec()),
+            ]);
+            deliveries.extend(
+                subscribers
+                    .values()
+                    .map(|client| (client.clone(), push.clone())),
+            );
+        }
+        self.deliver(deliveries)
+    }
+
+    /// Delivers `message` to the subscribers of shard channel `channel`, patterns not
+    /// applying to shard channels.
+    pub fn shard_publish(&mut self, channel: &[u8], message: &[u8]) -> usize {
+        let subscribers = self
+            .shard_channels
+            .get(&key_hash_slot(channel))
+            .and_then(|slot| slot.subscribers.get(channel));
+        let Some(subscribers) = subscribers else {
+            return 0;
+        };
+        let push = Resp::Array(vec![
+            Resp::bulk_string_from_str("smessage"),
+            Resp::BulkString(channel.to_vec()),
+            Resp::BulkString(message.to_vec()),
+        ]);
+        let deliveries = subscribers
+            .values()
+            .map(|client| (client.clone(), push.clone()))
+            .collect();
+        self.deliver(deliveries)
+    }
+
+    fn subscribe(
+        &mut self,
+        kind: SubscriptionKind,
+        client: PushSender,
+        names: Vec<Vec<u8>>,
+    ) -> usize {
+        for name in names {
+            self.subscriptions(kind, &name)
+                .subscribe(&client, name.clone());
+            client.push(Resp::Array(vec![
+                Resp::bulk_string_from_str(kind.subscribe_reply()),
+                Resp::BulkString(name),
+                Resp::Integers(self.count(kind, client.id()) as i64),
+            ]));
+        }
+        self.count(kind, client.id())
+    }
+
+    fn unsubscribe(
+        &mut self,
+        kind: SubscriptionKind,
+        client: PushSender,
+        mut names: Vec<Vec<u8>>,
+    ) -> usize {
+        if names.is_empty() {
+            names = self.names_of(kind, client.id());
+        }
+        if names.is_empty() {
+            client.push(Resp::Array(vec![
+                Resp::bulk_string_from_str(kind.unsubscribe_reply()),
+                Resp::null_bulk_string(),
+                Resp::Integers(self.count(kind, client.id()) as i64),
+            ]));
+        }
+        for name in names {
+            self.subscriptions(kind, &name)
+                .unsubscribe(client.id(), &name);
+            client.push(Resp::Array(vec![
+                Resp::bulk_string_from_str(kind.unsubscribe_reply()),
+                Resp::BulkString(name),
+                Resp::Integers(self.count(kind, client.id()) as i64),
+            ]));
+        }
+        self.shard_channels.retain(|_, slot| !slot.is_empty());
+        self.count(kind, client.id())
+    }
+
+    /// Drops clients whose connection went away, so that introspection does not count
+    /// them.
+    fn purge_closed(&mut self) {
+        let closed: BTreeSet<u64> = self
+            .channels
+            .subscribers
+            .values()
+            .chain(self.patterns.subscribers.values())
+            .chain(
+                self.shard_channels
+                    .values()
+                    .flat_map(|slot| slot.subscribers.values()),
+            )
+            .flat_map(BTreeMap::values)
+            .filter(|client| client.is_closed())
+            .map(PushSender::id)
+            .collect();
+        for client in closed {
+            self.remove_client(client);
+        }
+    }
+}
+
+/// Names of the channels of `subscriptions` with subscribers, sorted and filtered by
+/// `pattern`.
+fn channels_reply<'a>(
+    subscriptions: impl Iterator<Item = &'a Subscriptions>,
+    pattern: Option<&[u8]>,
+) -> Resp {
+    let mut channels: Vec<&Vec<u8>> = subscriptions
+        .flat_map(|subscriptions| subscriptions.subscribers.keys())
+        .filter(|channel| pattern.is_none_or(|pattern| glob_match(pattern, channel, false)))
+        .collect();
+    channels.sort();
+    Resp::Array(
+        channels
+            .into_iter()
+            .map(|channel| Resp::BulkString(channel.clone()))
+            .collect(),
+    )
+}
+
+fn numsub_reply(channels: Vec<Vec<u8>>, count: impl Fn(&[u8]) -> usize) -> Resp {
+    let mut reply = Vec::with_capacity(channels.len() * 2);
+    for channel in channels {
+        let subscribers = count(&channel);
+        reply.push(Resp::BulkString(channel));
+        reply.push(Resp::Integers(subscribers as i64));
+    }
+    Resp::Array(reply)
+}
+
+pub fn apply_pubsub_command(hub: &mut PubSubHub, command: PubSubCommand) -> Result<Resp, AppError> {
+    let reply = match command {
+        PubSubCommand::Subscribe {
+            kind,
+            client,
+            names,
+        } => Resp::Integers(hub.subscribe(kind, client, names) as i64),
+        PubSubCommand::Unsubscribe {
+            kind,
+            client,
+            names,
+        } => Resp::Integers(hub.unsubscribe(kind, client, names) as i64),
+        PubSubCommand::Publish { channel, message } => {
+            Resp::Integers(hub.publish(&channel, &message) as i64)
+        }
+        PubSubCommand::ShardPublish { channel, message } => {
+            Resp::Integers(hub.shard_publish(&channel, &message) as i64)
+        }
+        PubSubCommand::Channels(pattern) => {
+            hub.purge_closed();
+            channels_reply(std::iter::once(&hub.channels), pattern.as_deref())
+        }
+        PubSubCommand::ShardChannels(pattern) => {
+            hub.purge_closed();
+            channels_reply(hub.shard_channels.values(), pattern.as_deref())
+        }
+        PubSubCommand::NumSub(channels) => {
+            hub.purge_closed();
+            numsub_reply(channels, |channel| {
+                hub.channels
+                    .subscribers
+                    .get(channel)
+                    .map_or(0, BTreeMap::len)
+            })
+        }
+        PubSubCommand::ShardNumSub(channels) => {
+            hub.purge_closed();
+            numsub_reply(channels, |channel| {
+                hub.shard_channels
+                    .get(&key_hash_slot(channel))
+                    .and_then(|slot| slot.subscribers.get(channel))
+                    .map_or(0, BTreeMap::len)
+            })
+        }
+        PubSubCommand::NumPat => {
+            hub.purge_closed();
+            Resp::Integers(hub.patterns.subscribers.len() as i64)
+        }
+    };
+    Ok(reply)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::resp::Resp;
+
+    use super::{
+        apply_pubsub_command, push_channel, OutputBufferLimit, OutputBufferLimits, PubSubCommand,
+        PubSubHub, SubscriptionKind,
+    };
+
+    fn names(names: &[&str]) -> Vec<Vec<u8>> {
+        names.iter().map(|name| name.as_bytes().to_vec()).collect()
+    }
+
+    fn publish(hub: &mut PubSubHub, channel: &str, message: &str) -> Resp {
+        let command = PubSubCommand::Publish {
+            channel: channel.into(),
+            message: message.into(),
+        };
+        apply_pubsub_command(hub, command).unwrap()
+    }
+
+    #[test]
+    fn should_deliver_to_channel_and_pattern_subscribers() {
+        let mut hub = PubSubHub::default();
+        let (client, mut pushes) = push_channel(1, OutputBufferLimits::default());
+        let subscribe = |kind, subscribed: &[&str]| PubSubCommand::Subscribe {
+            kind,
+            client: client.clone(),
+            names: names(subscribed),
+        };
+        apply_pubsub_command(&mut hub, subscribe(SubscriptionKind::Channel, &["news"])).unwrap();
+        let count =
+            apply_pubsub_command(&mut hub, subscribe(SubscriptionKind::Pattern, &["n*"])).unwrap();
+        assert_eq!(count, Resp::Integers(2));
+        assert_eq!(publish(&mut hub, "news", "hello"), Resp::Integers(2));
+        assert_eq!(publish(&mut hub, "other", "hello"), Resp::Integers(0));
+
+        let pushed: Vec<Resp> = std::iter::from_fn(|| pushes.try_recv()).collect();
+        assert_eq!(pushed.len(), 4);
+        assert_eq!(
+            pushed[2],
+            Resp::Array(vec![
+                Resp::bulk_string_from_str("message"),
+                Resp::bulk_string_from_str("news"),
+                Resp::bulk_string_from_str("hello"),
+            ])
+        );
+        assert_eq!(
+            pushed[3],
+            Resp::Array(vec![
+                Resp::bulk_string_from_str("pmessage"),
+                Resp::bulk_string_from_str("n*"),
+                Resp::bulk_string_from_str("news"),
+                Resp::bulk_string_from_str("hello"),
+            ])
+        );
+    }
+
+    #[test]
+    fn should_drop_subscriber_over_output_buffer_limit() {
+        let mut hub = PubSubHub::default();
+        let limits = OutputBufferLimits {
+            pubsub: OutputBufferLimit {
+                hard: 128,
+                soft: 0,
+                soft_seconds: 0,
+            },
+            ..Default::default()
+        };
+        let (client, mut pushes) = push_channel(1, limits);
+        pushes.set_pubsub(true);
+        let command = PubSubCommand::Subscribe {
+            kind: SubscriptionKind::Channel,
+            client,
+            names: names(&["news"]),
+        };
+        apply_pubsub_command(&mut hub, command).unwrap();
+        assert_eq!(publish(&mut hub, "news", "hello"), Resp::Integers(1));
+        assert_eq!(
+            publish(&mut hub, "news", &"x".repeat(64)),
+            Resp::Integers(0)
+        );
+        assert!(pushes.overflowed());
+        assert_eq!(pushes.try_recv(), None);
+        let numsub = apply_pubsub_command(&mut hub, PubSubCommand::NumSub(names(&["news"])));
+        assert_eq!(
+            numsub.unwrap(),
+            Resp::Array(vec![Resp::bulk_string_from_str("news"), Resp::Integers(0)])
+        );
+    }
+
+    #[test]
+    fn should_count_replies_against_output_buffer_limit() {
+        let limits = OutputBufferLimits::parse("normal 100 0 0").unwrap();
+        let (_client, pushes) = push_channel(1, limits);
+        assert!(pushes.reply_queued(60));
+        assert_eq!(pushes.queued(), 60);
+        pushes.reply_written(60);
+        assert_eq!(pushes.queued(), 0);
+        assert!(!pushes.reply_queued(101));
+        assert!(pushes.overflowed());
+    }
+
+    #[test]
+    fn should_parse_limits_and_wait_out_soft_limit() {
+        let limits = OutputBufferLimits::parse("normal 0 1kb 0 pubsub 1mb 0 0").unwrap();
+        assert_eq!(limits.replica, OutputBufferLimits::default().replica);
+        assert_eq!(limits.pubsub.hard, 1024 * 1024);
+        assert!(OutputBufferLimits::parse("normal 0 0").is_err());
+
+        let mut soft_since = None;
+        assert!(!limits.normal.exceeded(1024, &mut soft_since));
+        assert!(limits.normal.exceeded(1025, &mut soft_since));
+        let slow = OutputBufferLimit {
+            soft_seconds: 60,
+            ..limits.normal
+        };
+        assert!(!slow.exceeded(1025, &mut None));
+    }
+
+    #[test]
+    fn should_keep_shard_subscriptions_apart() {
+        let mut hub = PubSubHub::default();
+        let (client, mut pushes) = push_channel(1, OutputBufferLimits::default());
+        let subscribe = |kind| PubSubCommand::Subscribe {
+            kind,
+            client: client.clone(),
+            names: names(&["news"]),
+        };
+        apply_pubsub_command(&mut hub, subscribe(SubscriptionKind::Channel)).unwrap();
+        let count = apply_pubsub_command(&mut hub, subscribe(SubscriptionKind::Shard));
+        assert_eq!(count.unwrap(), Resp::Integers(1));
+
+        let command = PubSubCommand::ShardPublish {
+            channel: b"news".to_vec(),
+            message: b"hello".to_vec(),
+        };
+        assert_eq!(
+            apply_pubsub_command(&mut hub, command).unwrap(),
+            Resp::Integers(1)
+        );
+        let pushed: Vec<Resp> = std::iter::from_fn(|| pushes.try_recv()).collect();
+        assert_eq!(
+            pushed.last().unwrap(),
+            &Resp::Array(vec![
+                Resp::bulk_string_from_str("smessage"),
+                Resp::bulk_string_from_str("news"),
+                Resp::bulk_string_from_str("hello"),
+            ])
+        );
+
+        let command = PubSubCommand::Unsubscribe {
+            kind: SubscriptionKind::Shard,
+            client: client.clone(),
+            names: vec![],
+        };
+        assert_eq!(
+            apply_pubsub_command(&mut hub, command).unwrap(),
+            Resp::Integers(0)
+        );
+        let shard_numsub = PubSubCommand::ShardNumSub(names(&["news"]));
+        assert_eq!(
+            apply_pubsub_command(&mut hub, shard_numsub).unwrap(),
+            Resp::Array(vec![Resp::bulk_string_from_str("news"), Resp::Integers(0)])
+        );
+        assert_eq!(publish(&mut hub, "news", "hello"), Resp::Integers(1));
+    }
+}
@@ -0,0 +1,1304 @@
+use std::{
+    collections::BTreeMap,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    errors::AppError,
+    helpers::{crc64::crc64, lzf::lzf_decompress},
+    resp::Resp,
+};
+
+use super::{
+    consumer_group::{Consumer, ConsumerGroup, PendingEntry},
+    datastore::{key_name, DataStore, DataStoreEntry, DataValue},
+    hash::{HashField, HashValue},
+    keyspace::KeyValues,
+    sorted_set::SortedSetValue,
+    stream::{StreamFields, StreamId, StreamValue},
+    unordered_set::SetValue,
+};
+
+/// RDB version written in `DUMP` payloads and RDB files, the one of Redis 7.2 so that
+/// upstream servers from that release on accept them.
+const RDB_VERSION: u16 = 11;
+/// Newest RDB version accepted by `RESTORE`, the one of Redis 7.4 and written when an
+/// encoding it introduced is needed, like the one of hash field TTLs.
+const RDB_MAX_VERSION: u16 = 12;
+
+const TYPE_STRING: u8 = 0;
+const TYPE_SET: u8 = 2;
+const TYPE_ZSET: u8 = 3;
+const TYPE_HASH: u8 = 4;
+const TYPE_ZSET_2: u8 = 5;
+const TYPE_SET_INTSET: u8 = 11;
+const TYPE_STREAM_LISTPACKS: u8 = 15;
+const TYPE_HASH_LISTPACK: u8 = 16;
+const TYPE_ZSET_LISTPACK: u8 = 17;
+/// Streams with their first and max deleted IDs, entries added and groups' entries read.
+const TYPE_STREAM_LISTPACKS_2: u8 = 19;
+const TYPE_SET_LISTPACK: u8 = 20;
+/// Streams whose consumers also have an active time, the type Redis 7.2 writes.
+const TYPE_STREAM_LISTPACKS_3: u8 = 21;
+/// Hash with field TTLs as absolute times, from Redis 7.4 release candidates.
+const TYPE_HASH_METADATA_PRE_GA: u8 = 22;
+/// Hash with field TTLs relative to the smallest one, which goes first.
+const TYPE_HASH_METADATA: u8 = 24;
+
+/// Followed by the code of a function library, Redis 7's `RDB_OPCODE_FUNCTION2`.
+const OPCODE_FUNCTION: u8 = 0xf5;
+const OPCODE_IDLE: u8 = 0xf8;
+const OPCODE_FREQ: u8 = 0xf9;
+const OPCODE_AUX: u8 = 0xfa;
+const OPCODE_RESIZEDB: u8 = 0xfb;
+const OPCODE_EXPIRETIME_MS: u8 = 0xfc;
+const OPCODE_EXPIRETIME: u8 = 0xfd;
+const OPCODE_SELECTDB: u8 = 0xfe;
+const OPCODE_EOF: u8 = 0xff;
+/// First RDB version ending with a checksum.
+const RDB_CHECKSUM_VERSION: u16 = 5;
+
+const ENCODING_INT8: u8 = 0;
+const ENCODING_INT16: u8 = 1;
+const ENCODING_INT32: u8 = 2;
+const ENCODING_LZF: u8 = 3;
+
+/// Entries of a stream listpack node, past which Redis starts a new one.
+const STREAM_NODE_MAX_ENTRIES: usize = 100;
+const STREAM_ITEM_FLAG_DELETED: i64 = 1;
+const STREAM_ITEM_FLAG_SAMEFIELDS: i64 = 2;
+
+fn bad_format() -> AppError {
+    AppError::Generic("Bad data format".to_owned())
+}
+
+fn write_length(output: &mut Vec<u8>, length: usize) {
+    match length {
+        0..0x40 => output.push(length as u8),
+        0x40..0x4000 => output.extend_from_slice(&(0x4000 | length as u16).to_be_bytes()),
+        _ if length <= u32::MAX as usize => {
+            output.push(0x80);
+            output.extend_from_slice(&(length as u32).to_be_bytes());
+        }
+        _ => {
+            output.push(0x81);
+            output.extend_from_slice(&(length as u64).to_be_bytes());
+        }
+    }
+}
+
+fn write_string(output: &mut Vec<u8>, string: &[u8]) {
+    write_length(output, string.len());
+    output.extend_from_slice(string);
+}
+
+fn write_millis(output: &mut Vec<u8>, at: SystemTime) {
+    output.extend_from_slice(&unix_millis(at).to_le_bytes());
+}
+
+fn unix_millis(at: SystemTime) -> u64 {
+    at.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Serializes `value` the way Redis' `DUMP` does: the RDB encoded object followed by the
+/// RDB version and a CRC64 of everything before it, both little endian.
+///
+/// Values are written with the plain encodings every RDB reader understands, streams with
+/// the listpacks Redis always keeps them in, and hashes with field TTLs with the encoding
+/// of Redis 7.4, whose RDB version the payload then carries.
+pub fn dump(value: &DataValue) -> Result<Vec<u8>, AppError> {
+    let mut payload = vec![];
+    let version = write_value(&mut payload, value)?;
+    Ok(with_footer(payload, version))
+}
+
+/// Writes the type of `value` followed by its encoding, returning the RDB version that
+/// encoding needs.
+fn write_value(payload: &mut Vec<u8>, value: &DataValue) -> Result<u16, AppError> {
+    match value {
+        DataValue::String(data) => {
+            let Resp::BulkString(bytes) = Resp::deserialize(data)? else {
+                return Err(AppError::WrongType);
+            };
+            payload.push(TYPE_STRING);
+            write_string(payload, &bytes);
+        }
+        DataValue::Set(set) => {
+            payload.push(TYPE_SET);
+            write_length(payload, set.len());
+            for member in set.members() {
+                write_string(payload, &member);
+            }
+        }
+        DataValue::Hash(hash) => {
+            let fields: Vec<_> = hash.iter().filter(|(_, field)| !field.expired()).collect();
+            let min_expiry = fields.iter().filter_map(|(_, field)| field.expiry()).min();
+            let Some(min_expiry) = min_expiry else {
+                payload.push(TYPE_HASH);
+                write_length(payload, fields.len());
+                for (field, value) in fields {
+                    write_string(payload, field);
+                    write_string(payload, &value.value);
+                }
+                return Ok(RDB_VERSION);
+            };
+            payload.push(TYPE_HASH_METADATA);
+            write_millis(payload, min_expiry);
+            write_length(payload, fields.len());
+            let min_expiry = unix_millis(min_expiry);
+            for (field, value) in fields {
+                // 0 stands for no TTL, hence the offset of the others.
+                let ttl = value
+                    .expiry()
+                    .map_or(0, |expiry| unix_millis(expiry) - min_expiry + 1);
+                write_length(payload, ttl as usize);
+                write_string(payload, field);
+                write_string(payload, &value.value);
+            }
+            return Ok(RDB_MAX_VERSION);
+        }
+        DataValue::SortedSet(sorted_set) => {
+            payload.push(TYPE_ZSET_2);
+            write_length(payload, sorted_set.len());
+            // Redis loads the elements in reverse so that its skiplist inserts are O(1).
+            let elements: Vec<_> = sorted_set.iter().collect();
+            for (member, score) in elements.into_iter().rev() {
+                write_string(payload, member);
+                payload.extend_from_slice(&score.to_le_bytes());
+            }
+        }
+        DataValue::Stream(stream) => write_stream(payload, stream),
+    }
+    Ok(RDB_VERSION)
+}
+
+fn write_stream_id(output: &mut Vec<u8>, id: StreamId) {
+    write_length(output, id.ms as usize);
+    write_length(output, id.seq as usize);
+}
+
+/// Stream IDs in listpack node keys and PELs: ms then seq, big endian.
+fn raw_stream_id(id: StreamId) -> [u8; 16] {
+    let mut raw = [0; 16];
+    raw[..8].copy_from_slice(&id.ms.to_be_bytes());
+    raw[8..].copy_from_slice(&id.seq.to_be_bytes());
+    raw
+}
+
+/// Writes `stream` as Redis 7.2 does: its entries in listpack nodes keyed by the ID of
+/// their first one, its metadata, then its consumer groups with their PEL and consumers.
+fn write_stream(payload: &mut Vec<u8>, stream: &StreamValue) {
+    payload.push(TYPE_STREAM_LISTPACKS_3);
+    let entries: Vec<_> = stream.entries.iter().collect();
+    let nodes = entries.chunks(STREAM_NODE_MAX_ENTRIES);
+    write_length(payload, nodes.len());
+    for node in nodes {
+        let (master, master_fields) = node[0];
+        write_string(payload, &raw_stream_id(*master));
+        write_string(payload, &write_stream_node(*master, master_fields, node));
+    }
+    write_length(payload, stream.len());
+    write_stream_id(payload, stream.last_id);
+    let first_id = stream.first_entry().map_or(StreamId::MIN, |(id, _)| *id);
+    write_stream_id(payload, first_id);
+    write_stream_id(payload, stream.max_deleted_id);
+    write_length(payload, stream.entries_added as usize);
+    write_length(payload, stream.groups.len());
+    for (name, group) in &stream.groups {
+        write_string(payload, name);
+        write_stream_id(payload, group.last_delivered_id);
+        // Unknown entries read are -1 to Redis.
+        write_length(
+            payload,
+            group.entries_read.map_or(u64::MAX, |read| read) as usize,
+        );
+        write_length(payload, group.pending.len());
+        for (id, pending) in &group.pending {
+            payload.extend_from_slice(&raw_stream_id(*id));
+            payload.extend_from_slice(&pending.delivery_time.to_le_bytes());
+            write_length(payload, pending.delivery_count as usize);
+        }
+        write_length(payload, group.consumers.len());
+        for (name, consumer) in &group.consumers {
+            write_string(payload, name);
+            payload.extend_from_slice(&consumer.seen_time.to_le_bytes());
+            let active_time = consumer.active_time.unwrap_or(u64::MAX);
+            payload.extend_from_slice(&active_time.to_le_bytes());
+            write_length(payload, consumer.pending.len());
+            for id in &consumer.pending {
+                payload.extend_from_slice(&raw_stream_id(*id));
+            }
+        }
+    }
+}
+
+/// Listpack of a stream node: a master entry with the fields of the first entry, then
+/// every entry with its ID relative to `master` and its own fields.
+fn write_stream_node(
+    master: StreamId,
+    master_fields: &StreamFields,
+    node: &[(&StreamId, &StreamFields)],
+) -> Vec<u8> {
+    let mut items = vec![
+        ListpackItem::Int(node.len() as i64),
+        ListpackItem::Int(0),
+        ListpackItem::Int(master_fields.len() as i64),
+    ];
+    items.extend(
+        master_fields
+            .iter()
+            .map(|(field, _)| ListpackItem::Bytes(field)),
+    );
+    items.push(ListpackItem::Int(0));
+    for (id, fields) in node {
+        items.push(ListpackItem::Int(0));
+        items.push(ListpackItem::Int((id.ms - master.ms) as i64));
+        items.push(ListpackItem::Int(id.seq.wrapping_sub(master.seq) as i64));
+        items.push(ListpackItem::Int(fields.len() as i64));
+        for (field, value) in fields.iter() {
+            items.push(ListpackItem::Bytes(field));
+            items.push(ListpackItem::Bytes(value));
+        }
+        // Listpack items making up the entry, flags and ID included, but not this one.
+        items.push(ListpackItem::Int(fields.len() as i64 * 2 + 4));
+    }
+    write_listpack(&items)
+}
+
+/// Serializes function libraries the way `FUNCTION DUMP` does, each code behind the
+/// opcode RDB files use for them, with the same footer as `DUMP` payloads.
+pub fn dump_functions<'a>(codes: impl IntoIterator<Item = &'a [u8]>) -> Vec<u8> {
+    let mut payload = vec![];
+    for code in codes {
+        payload.push(OPCODE_FUNCTION);
+        write_string(&mut payload, code);
+    }
+    with_footer(payload, RDB_VERSION)
+}
+
+fn with_footer(mut payload: Vec<u8>, version: u16) -> Vec<u8> {
+    payload.extend_from_slice(&version.to_le_bytes());
+    let checksum = crc64(0, &payload);
+    payload.extend_from_slice(&checksum.to_le_bytes());
+    payload
+}
+
+/// Checks the footer of a `DUMP` or `FUNCTION DUMP` payload, returning what it follows.
+fn payload_body(payload: &[u8]) -> Result<&[u8], AppError> {
+    let wrong_payload =
+        || AppError::Generic("DUMP payload version or checksum are wrong".to_owned());
+    let Some(body_len) = payload.len().checked_sub(10) else {
+        return Err(wrong_payload());
+    };
+    let (body, footer) = payload.split_at(body_len);
+    let version = u16::from_le_bytes([footer[0], footer[1]]);
+    let checksum = u64::from_le_bytes(footer[2..].try_into().expect("8 bytes checksum"));
+    if version > RDB_MAX_VERSION || checksum != crc64(0, &payload[..body_len + 2]) {
+        return Err(wrong_payload());
+    }
+    Ok(body)
+}
+
+/// Decodes a `DUMP` payload produced by this server or by upstream Redis.
+pub fn restore(payload: &[u8]) -> Result<DataValue, AppError> {
+    let mut reader = Reader {
+        bytes: payload_body(payload)?,
+    };
+    let value = reader.value()?;
+    if !reader.bytes.is_empty() {
+        return Err(bad_format());
+    }
+    Ok(value)
+}
+
+/// Decodes a `FUNCTION DUMP` payload into the codes of the libraries it holds.
+pub fn restore_functions(payload: &[u8]) -> Result<Vec<Vec<u8>>, AppError> {
+    let mut reader = Reader {
+        bytes: payload_body(payload)?,
+    };
+    let mut codes = vec![];
+    while !reader.bytes.is_empty() {
+        if reader.byte()? != OPCODE_FUNCTION {
+            return Err(AppError::Generic("given type is not a function".to_owned()));
+        }
+        codes.push(reader.string()?);
+    }
+    Ok(codes)
+}
+
+/// What an RDB file holds: its logical databases and the code of its function libraries.
+#[derive(Debug)]
+pub struct Snapshot<T> {
+    pub databases: Vec<T>,
+    pub libraries: Vec<Vec<u8>>,
+}
+
+/// Loads an RDB file into `databases` logical databases, keys following a `SELECTDB`
+/// opcode going to the database it names. Keys already expired are skipped.
+pub fn load<T: DataStore>(bytes: &[u8], databases: usize) -> Result<Snapshot<T>, AppError> {
+    let mut stores: Vec<T> = (0..databases).map(|_| T::default()).collect();
+    let mut libraries = vec![];
+    let mut reader = Reader { bytes };
+    let header = reader.take(9)?;
+    let version = std::str::from_utf8(&header[5..])
+        .ok()
+        .and_then(|version| version.parse::<u16>().ok())
+        .filter(|version| &header[..5] == b"REDIS" && *version <= RDB_MAX_VERSION)
+        .ok_or_else(|| AppError::Generic("Unsupported RDB file".to_owned()))?;
+    let (mut db, mut expiry, mut idle, mut frequency) = (0, None, None, None);
+    loop {
+        match reader.byte()? {
+            OPCODE_EOF => break,
+            OPCODE_SELECTDB => {
+                db = reader.length()?;
+                if db >= databases {
+                    return Err(AppError::Generic(format!(
+                        "RDB file uses database {db} out of the {databases} configured"
+                    )));
+                }
+            }
+            OPCODE_RESIZEDB => {
+                reader.length()?;
+                reader.length()?;
+            }
+            OPCODE_AUX => {
+                reader.string()?;
+                reader.string()?;
+            }
+            OPCODE_FUNCTION => libraries.push(reader.string()?),
+            OPCODE_EXPIRETIME => {
+                let seconds = u32::from_le_bytes(reader.take(4)?.try_into().expect("4 bytes"));
+                expiry = Some(UNIX_EPOCH + Duration::from_secs(seconds as u64));
+            }
+            OPCODE_EXPIRETIME_MS => {
+                expiry = Some(UNIX_EPOCH + Duration::from_millis(reader.millis()?));
+            }
+            OPCODE_IDLE => idle = Some(Duration::from_secs(reader.length()? as u64)),
+            OPCODE_FREQ => frequency = Some(reader.byte()?),
+            kind => {
+                let key = Resp::BulkString(reader.string()?).serialize()?;
+                let value = reader.typed_value(kind)?;
+                let expiry = expiry.take();
+                let (idle, frequency) = (idle.take(), frequency.take());
+                let emptied = matches!(&value, DataValue::Hash(hash) if hash.is_empty());
+                if emptied || expiry.is_some_and(|at| at <= SystemTime::now()) {
+                    continue;
+                }
+                let mut entry = DataStoreEntry::with_value(value, None);
+                entry.set_expiry(expiry);
+                if let Some(idle) = idle {
+                    entry.set_idle_time(idle);
+                }
+                if let Some(frequency) = frequency {
+                    entry.set_frequency(frequency);
+                }
+                stores[db].insert_entry(key, entry);
+            }
+        }
+    }
+    if version >= RDB_CHECKSUM_VERSION {
+        let body_len = bytes.len() - reader.bytes.len();
+        let checksum = u64::from_le_bytes(reader.take(8)?.try_into().expect("8 bytes"));
+        if checksum != 0 && checksum != crc64(0, &bytes[..body_len]) {
+            return Err(AppError::Generic("Wrong RDB checksum".to_owned()));
+        }
+    }
+    Ok(Snapshot {
+        databases: stores,
+        libraries,
+    })
+}
+
+pub fn load_file<T: DataStore>(path: &Path, databases: usize) -> Result<Snapshot<T>, AppError> {
+    load(&std::fs::read(path)?, databases)
+}
+
+/// Serializes `databases` and the code of function libraries into an RDB file that
+/// `load` reads back. Expired keys are left out.
+pub fn save<'a, T: DataStore>(
+    databases: &[T],
+    libraries: impl IntoIterator<Item = &'a [u8]>,
+) -> Result<Vec<u8>, AppError> {
+    let mut version = RDB_VERSION;
+    let mut output = vec![];
+    for code in libraries {
+        output.push(OPCODE_FUNCTION);
+        write_string(&mut output, code);
+    }
+    for (db, store) in databases.iter().enumerate() {
+        let entries: Vec<_> = store
+            .keys()
+            .into_iter()
+            .filter_map(|key| Some((key_name(&key), store.entry(&key)?)))
+            .filter(|(_, entry)| !entry.expired())
+            .collect();
+        if entries.is_empty() {
+            continue;
+        }
+        output.push(OPCODE_SELECTDB);
+        write_length(&mut output, db);
+        output.push(OPCODE_RESIZEDB);
+        write_length(&mut output, entries.len());
+        let expires = entries.iter().filter(|(_, entry)| entry.expiry().is_some());
+        write_length(&mut output, expires.count());
+        for (name, entry) in entries {
+            if let Some(at) = entry.expiry() {
+                output.push(OPCODE_EXPIRETIME_MS);
+                write_millis(&mut output, at);
+            }
+            let mut value = vec![];
+            version = version.max(write_value(&mut value, &entry.value)?);
+            // The type byte goes before the key, its encoding after it.
+            output.push(value[0]);
+            write_string(&mut output, &name);
+            output.extend_from_slice(&value[1..]);
+        }
+    }
+    output.push(OPCODE_EOF);
+    // The header names the newest encoding used, which is only known once written.
+    let mut output = [format!("REDIS{version:04}").into_bytes(), output].concat();
+    let checksum = crc64(0, &output);
+    output.extend_from_slice(&checksum.to_le_bytes());
+    Ok(output)
+}
+
+/// Saves like `save` to `path`, through a temporary file renamed over it so that a failed
+/// save leaves the previous file whole.
+pub fn save_file<'a, T: DataStore>(
+    path: &Path,
+    databases: &[T],
+    libraries: impl IntoIterator<Item = &'a [u8]>,
+) -> Result<(), AppError> {
+    let bytes = save(databases, libraries)?;
+    let temp = path.with_file_name(format!("temp-{}.rdb", std::process::id()));
+    std::fs::write(&temp, bytes)?;
+    std::fs::rename(&temp, path)?;
+    Ok(())
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+/// Length or special string encoding, as found in front of RDB strings.
+enum Length {
+    Plain(usize),
+    Encoded(u8),
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], AppError> {
+        if self.bytes.len() < count {
+            return Err(bad_format());
+        }
+        let (taken, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> Result<u8, AppError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn raw_length(&mut self) -> Result<Length, AppError> {
+        let first = self.byte()?;
+        let length = match first >> 6 {
+            0 => (first & 0x3f) as usize,
+            1 => (((first & 0x3f) as usize) << 8) | self.byte()? as usize,
+            3 => return Ok(Length::Encoded(first & 0x3f)),
+            _ => match first {
+                0x80 => u32::from_be_bytes(self.take(4)?.try_into().expect("4 bytes")) as usize,
+                0x81 => u64::from_be_bytes(self.take(8)?.try_into().expect("8 bytes")) as usize,
+                _ => return Err(bad_format()),
+            },
+        };
+        Ok(Length::Plain(length))
+    }
+
+    fn length(&mut self) -> Result<usize, AppError> {
+        match self.raw_length()? {
+            Length::Plain(length) => Ok(length),
+            Length::Encoded(_) => Err(bad_format()),
+        }
+    }
+
+    fn string(&mut self) -> Result<Vec<u8>, AppError> {
+        let int = match self.raw_length()? {
+            Length::Plain(length) => return Ok(self.take(length)?.to_vec()),
+            Length::Encoded(ENCODING_INT8) => self.byte()? as i8 as i64,
+            Length::Encoded(ENCODING_INT16) => {
+                i16::from_le_bytes(self.take(2)?.try_into().expect("2 bytes")) as i64
+            }
+            Length::Encoded(ENCODING_INT32) => {
+                i32::from_le_bytes(self.take(4)?.try_into().expect("4 bytes")) as i64
+            }
+            Length::Encoded(ENCODING_LZF) => {
+                let (compressed, length) = (self.length()?, self.length()?);
+                let compressed = self.take(compressed)?;
+                return lzf_decompress(compressed, length).ok_or_else(bad_format);
+            }
+            Length::Encoded(_) => return Err(bad_format()),
+        };
+        Ok(int.to_string().into_bytes())
+    }
+
+    /// Score of the legacy `ZSET` type, written as a length prefixed string.
+    fn string_score(&mut self) -> Result<f64, AppError> {
+        match self.byte()? {
+            253 => Err(bad_format()),
+            254 => Ok(f64::INFINITY),
+            255 => Ok(f64::NEG_INFINITY),
+            length => std::str::from_utf8(self.take(length as usize)?)
+                .ok()
+                .and_then(|score| score.parse().ok())
+                .ok_or_else(bad_format),
+        }
+    }
+
+    fn millis(&mut self) -> Result<u64, AppError> {
+        Ok(u64::from_le_bytes(
+            self.take(8)?.try_into().expect("8 bytes"),
+        ))
+    }
+
+    fn stream_id(&mut self) -> Result<StreamId, AppError> {
+        Ok(StreamId::new(self.length()? as u64, self.length()? as u64))
+    }
+
+    fn raw_stream_id(&mut self) -> Result<StreamId, AppError> {
+        parse_raw_stream_id(self.take(16)?)
+    }
+
+    /// Stream of any of the listpack types, those before Redis 7.2 lacking some metadata.
+    fn stream(&mut self, kind: u8) -> Result<StreamValue, AppError> {
+        let mut entries = BTreeMap::new();
+        for _ in 0..self.length()? {
+            let master = parse_raw_stream_id(&self.string()?)?;
+            read_stream_node(master, listpack(&self.string()?)?, &mut entries)?;
+        }
+        let length = self.length()?;
+        let last_id = self.stream_id()?;
+        let (max_deleted_id, entries_added) = match kind {
+            TYPE_STREAM_LISTPACKS => (StreamId::MIN, length as u64),
+            _ => {
+                self.stream_id()?;
+                (self.stream_id()?, self.length()? as u64)
+            }
+        };
+        if entries.len() != length {
+            return Err(bad_format());
+        }
+        let mut groups = BTreeMap::new();
+        for _ in 0..self.length()? {
+            let name = self.string()?;
+            let last_delivered_id = self.stream_id()?;
+            let entries_read = match kind {
+                TYPE_STREAM_LISTPACKS => None,
+                _ => Some(self.length()? as u64).filter(|read| *read != u64::MAX),
+            };
+            let mut group = ConsumerGroup::new(last_delivered_id, entries_read);
+            let mut deliveries = BTreeMap::new();
+            for _ in 0..self.length()? {
+                let id = self.raw_stream_id()?;
+                deliveries.insert(id, (self.millis()?, self.length()? as u64));
+            }
+            for _ in 0..self.length()? {
+                let consumer_name = self.string()?;
+                let seen_time = self.millis()?;
+                let active_time = match kind {
+                    TYPE_STREAM_LISTPACKS_3 => Some(self.millis()?).filter(|at| *at != u64::MAX),
+                    _ => None,
+                };
+                let mut consumer = Consumer {
+                    seen_time,
+                    active_time,
+                    pending: Default::default(),
+                };
+                for _ in 0..self.length()? {
+                    let id = self.raw_stream_id()?;
+                    // Consumers own entries of the group's PEL, each of them only once.
+                    let (delivery_time, delivery_count) =
+                        deliveries.remove(&id).ok_or_else(bad_format)?;
+                    let pending = PendingEntry {
+                        consumer: consumer_name.clone(),
+                        delivery_time,
+                        delivery_count,
+                    };
+                    group.pending.insert(id, pending);
+                    consumer.pending.insert(id);
+                }
+                group.consumers.insert(consumer_name, consumer);
+            }
+            if !deliveries.is_empty() {
+                return Err(bad_format());
+            }
+            groups.insert(name, group);
+        }
+        Ok(StreamValue {
+            entries,
+            groups,
+            last_id,
+            max_deleted_id,
+            entries_added,
+        })
+    }
+
+    fn binary_score(&mut self) -> Result<f64, AppError> {
+        let score = f64::from_le_bytes(self.take(8)?.try_into().expect("8 bytes"));
+        if score.is_nan() {
+            return Err(bad_format());
+        }
+        Ok(score)
+    }
+
+    fn value(&mut self) -> Result<DataValue, AppError> {
+        let kind = self.byte()?;
+        self.typed_value(kind)
+    }
+
+    fn typed_value(&mut self, kind: u8) -> Result<DataValue, AppError> {
+        let value = match kind {
+            TYPE_STRING => {
+                let bytes = self.string()?;
+                DataValue::String(Resp::BulkString(bytes).serialize()?)
+            }
+            TYPE_SET => {
+                let length = self.length()?;
+                let members = (0..length)
+                    .map(|_| self.string())
+                    .collect::<Result<Vec<_>, _>>()?;
+                DataValue::Set(SetValue::from(members))
+            }
+            TYPE_HASH => {
+                let length = self.length()?;
+                let fields = (0..length)
+                    .map(|_| Ok((self.string()?, self.string()?)))
+                    .collect::<Result<Vec<_>, AppError>>()?;
+                DataValue::Hash(HashValue::from(fields))
+            }
+            TYPE_HASH_METADATA_PRE_GA | TYPE_HASH_METADATA => {
+                let min_expiry = match kind {
+                    TYPE_HASH_METADATA => self.millis()?,
+                    _ => 1,
+                };
+                let length = self.length()?;
+                let mut fields = vec![];
+                for _ in 0..length {
+                    let expiry = match self.length()? as u64 {
+                        0 => None,
+                        ttl => Some(UNIX_EPOCH + Duration::from_millis(ttl + min_expiry - 1)),
+                    };
+                    let (field, value) = (self.string()?, self.string()?);
+                    fields.push((field, HashField::with_expiry(value, expiry)));
+                }
+                fields.retain(|(_, field)| !field.expired());
+                DataValue::Hash(fields.into_iter().collect())
+            }
+            TYPE_ZSET | TYPE_ZSET_2 => {
+                let length = self.length()?;
+                let mut sorted_set = SortedSetValue::default();
+                for _ in 0..length {
+                    let member = self.string()?;
+                    let score = if kind == TYPE_ZSET {
+                        self.string_score()?
+                    } else {
+                        self.binary_score()?
+                    };
+                    sorted_set.insert(member, score);
+                }
+                DataValue::SortedSet(sorted_set)
+            }
+            TYPE_SET_INTSET => DataValue::Set(SetValue::from(intset(&self.string()?)?)),
+            TYPE_SET_LISTPACK => DataValue::Set(SetValue::from(listpack(&self.string()?)?)),
+            TYPE_HASH_LISTPACK => {
+                let entries = listpack(&self.string()?)?;
+                let fields = pairs(entries)?;
+                DataValue::Hash(HashValue::from(fields))
+            }
+            TYPE_ZSET_LISTPACK => {
+                let entries = listpack(&self.string()?)?;
+                let mut sorted_set = SortedSetValue::default();
+                for (member, score) in pairs(entries)? {
+                    let score = std::str::from_utf8(&score)
+                        .ok()
+                        .and_then(|score| score.parse().ok())
+                        .ok_or_else(bad_format)?;
+                    sorted_set.insert(member, score);
+                }
+                DataValue::SortedSet(sorted_set)
+            }
+            TYPE_STREAM_LISTPACKS | TYPE_STREAM_LISTPACKS_2 | TYPE_STREAM_LISTPACKS_3 => {
+                DataValue::Stream(self.stream(kind)?)
+            }
+            _ => return Err(bad_format()),
+        };
+        Ok(value)
+    }
+}
+
+fn parse_raw_stream_id(raw: &[u8]) -> Result<StreamId, AppError> {
+    let raw: &[u8; 16] = raw.try_into().map_err(|_| bad_format())?;
+    let (ms, seq) = raw.split_at(8);
+    Ok(StreamId::new(
+        u64::from_be_bytes(ms.try_into().expect("8 bytes")),
+        u64::from_be_bytes(seq.try_into().expect("8 bytes")),
+    ))
+}
+
+/// Adds the live entries of a stream node to `entries`, IDs being relative to `master`
+/// and entries flagged with `SAMEFIELDS` having the fields of the master entry.
+fn read_stream_node(
+    master: StreamId,
+    items: Vec<Vec<u8>>,
+    entries: &mut BTreeMap<StreamId, StreamFields>,
+) -> Result<(), AppError> {
+    let mut items = items.into_iter();
+    let mut next = || items.next().ok_or_else(bad_format);
+    let int = |item: Vec<u8>| -> Result<i64, AppError> {
+        std::str::from_utf8(&item)
+            .ok()
+            .and_then(|int| int.parse().ok())
+            .ok_or_else(bad_format)
+    };
+    let count = int(next()?)? + int(next()?)?;
+    let master_fields = (0..int(next()?)?)
+        .map(|_| next())
+        .collect::<Result<Vec<_>, _>>()?;
+    int(next()?)?;
+    for _ in 0..count {
+        let flags = int(next()?)?;
+        let id = StreamId::new(
+            master.ms.wrapping_add(int(next()?)? as u64),
+            master.seq.wrapping_add(int(next()?)? as u64),
+        );
+        let fields = if flags & STREAM_ITEM_FLAG_SAMEFIELDS != 0 {
+            master_fields
+                .iter()
+                .map(|field| Ok((field.clone(), next()?)))
+                .collect::<Result<StreamFields, AppError>>()?
+        } else {
+            (0..int(next()?)?)
+                .map(|_| Ok((next()?, next()?)))
+                .collect::<Result<StreamFields, AppError>>()?
+        };
+        int(next()?)?;
+        if flags & STREAM_ITEM_FLAG_DELETED == 0 {
+            entries.insert(id, fields);
+        }
+    }
+    Ok(())
+}
+
+fn pairs(entries: Vec<Vec<u8>>) -> Result<KeyValues, AppError> {
+    if !entries.len().is_multiple_of(2) {
+        return Err(bad_format());
+    }
+    let mut entries = entries.into_iter();
+    let mut pairs = vec![];
+    while let (Some(first), Some(second)) = (entries.next(), entries.next()) {
+        pairs.push((first, second));
+    }
+    Ok(pairs)
+}
+
+/// Members of an intset blob: encoding width, length, then little endian integers.
+fn intset(blob: &[u8]) -> Result<Vec<Vec<u8>>, AppError> {
+    let header = |range: std::ops::Range<usize>| -> Result<usize, AppError> {
+        let bytes = blob.get(range).ok_or_else(bad_format)?;
+        Ok(u32::from_le_bytes(bytes.try_into().expect("4 bytes")) as usize)
+    };
+    let (width, length) = (header(0..4)?, header(4..8)?);
+    if !matches!(width, 2 | 4 | 8) || blob.len() != 8 + width * length {
+        return Err(bad_format());
+    }
+    let members = blob[8..]
+        .chunks(width)
+        .map(|int| {
+            let int = match width {
+                2 => i16::from_le_bytes(int.try_into().expect("2 bytes")) as i64,
+                4 => i32::from_le_bytes(int.try_into().expect("4 bytes")) as i64,
+                _ => i64::from_le_bytes(int.try_into().expect("8 bytes")),
+            };
+            int.to_string().into_bytes()
+        })
+        .collect();
+    Ok(members)
+}
+
+enum ListpackItem<'a> {
+    Int(i64),
+    Bytes(&'a [u8]),
+}
+
+/// Bytes taken by the back length of a listpack entry of `size` bytes.
+fn backlen_size(size: usize) -> usize {
+    match size {
+        0..128 => 1,
+        128..16383 => 2,
+        16383..2097151 => 3,
+        2097151..268435455 => 4,
+        _ => 5,
+    }
+}
+
+/// Listpack blob of `items`, each with the smallest encoding Redis would pick for it.
+fn write_listpack(items: &[ListpackItem]) -> Vec<u8> {
+    let mut blob = vec![0; 6];
+    for item in items {
+        let start = blob.len();
+        match *item {
+            ListpackItem::Int(int @ 0..=127) => blob.push(int as u8),
+            ListpackItem::Int(int @ -4096..=4095) => {
+                let int = int as u16 & 0x1fff;
+                blob.extend_from_slice(&[0xc0 | (int >> 8) as u8, int as u8]);
+            }
+            ListpackItem::Int(int) => {
+                let (encoding, width) = match int {
+                    _ if i16::try_from(int).is_ok() => (0xf1, 2),
+                    -8388608..=8388607 => (0xf2, 3),
+                    _ if i32::try_from(int).is_ok() => (0xf3, 4),
+                    _ => (0xf4, 8),
+                };
+                blob.push(encoding);
+                blob.extend_from_slice(&int.to_le_bytes()[..width]);
+            }
+            ListpackItem::Bytes(bytes) => {
+                match bytes.len() {
+                    length @ 0..64 => blob.push(0x80 | length as u8),
+                    length @ 64..4096 => {
+                        blob.extend_from_slice(&[0xe0 | (length >> 8) as u8, length as u8])
+                    }
+                    length => {
+                        blob.push(0xf0);
+                        blob.extend_from_slice(&(length as u32).to_le_bytes());
+                    }
+                }
+                blob.extend_from_slice(bytes);
+            }
+        }
+        // The size of the entry, 7 bits a byte, most significant ones first.
+        let size = blob.len() - start;
+        let backlen = backlen_size(size);
+        for byte in (0..backlen).rev() {
+            let bits = ((size >> (7 * byte)) & 0x7f) as u8;
+            blob.push(if byte == backlen - 1 {
+                bits
+            } else {
+                bits | 0x80
+            });
+        }
+    }
+    blob.push(0xff);
+    let total = blob.len() as u32;
+    blob[..4].copy_from_slice(&total.to_le_bytes());
+    let count = items.len().min(u16::MAX as usize) as u16;
+    blob[4..6].copy_from_slice(&count.to_le_bytes());
+    blob
+}
+
+/// Entries of a listpack blob, integers being rendered back to their decimal form.
+fn listpack(blob: &[u8]) -> Result<Vec<Vec<u8>>, AppError> {
+    let mut position = 6;
+    let mut entries = vec![];
+    let bytes = |start: usize, count: usize| blob.get(start..start + count).ok_or_else(bad_format);
+    let signed = |raw: &[u8], bits: u32| -> i64 {
+        let mut buffer = [0; 8];
+        buffer[..raw.len()].copy_from_slice(raw);
+        let value = u64::from_le_bytes(buffer);
+        ((value << (64 - bits)) as i64) >> (64 - bits)
+    };
+    loop {
+        let encoding = *blob.get(position).ok_or_else(bad_format)?;
+        if encoding == 0xff {
+            break;
+        }
+        let (entry, header, data) = match encoding {
+            0x00..=0x7f => ((encoding as i64).to_string().into_bytes(), 1, 0),
+            0x80..=0xbf => {
+                let length = (encoding & 0x3f) as usize;
+                (bytes(position + 1, length)?.to_vec(), 1, length)
+            }
+            0xc0..=0xdf => {
+                let raw = [bytes(position + 1, 1)?[0], encoding & 0x1f];
+                (signed(&raw, 13).to_string().into_bytes(), 2, 0)
+            }
+            0xe0..=0xef => {
+                let length =
+                    (((encoding & 0x0f) as usize) << 8) | bytes(position + 1, 1)?[0] as usize;
+                (bytes(position + 2, length)?.to_vec(), 2, length)
+            }
+            0xf0 => {
+                let raw = bytes(position + 1, 4)?;
+                let length = u32::from_le_bytes(raw.try_into().expect("4 bytes")) as usize;
+                (bytes(position + 5, length)?.to_vec(), 5, length)
+            }
+            0xf1..=0xf4 => {
+                let width = match encoding {
+                    0xf1 => 2,
+                    0xf2 => 3,
+                    0xf3 => 4,
+                    _ => 8,
+                };
+                let raw = bytes(position + 1, width)?;
+                let int = signed(raw, width as u32 * 8);
+                (int.to_string().into_bytes(), 1 + width, 0)
+            }
+            _ => return Err(bad_format()),
+        };
+        let size = header + data;
+        entries.push(entry);
+        position += size + backlen_size(size);
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_dump_strings_like_redis() {
+        let value = DataValue::String(Resp::bulk_string_from_str("bar").serialize().unwrap());
+        let payload = dump(&value).unwrap();
+        assert_eq!(payload[..payload.len() - 8], *b"\x00\x03bar\x0b\x00");
+        assert_eq!(
+            payload[payload.len() - 8..],
+            crc64(0, &payload[..payload.len() - 8]).to_le_bytes()
+        );
+        assert_eq!(restore(&payload).unwrap(), value);
+    }
+
+    #[test]
+    fn should_round_trip_collections() {
+        let set = DataValue::Set(SetValue::from([b"a".to_vec(), b"1".to_vec()]));
+        let hash = DataValue::Hash(HashValue::from([(b"f".to_vec(), b"v".to_vec())]));
+        let sorted_set = DataValue::SortedSet(SortedSetValue::from([
+            (b"a".to_vec(), 1.5),
+            (b"b".to_vec(), f64::NEG_INFINITY),
+        ]));
+        for value in [set, hash, sorted_set] {
+            assert_eq!(restore(&dump(&value).unwrap()).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn should_round_trip_streams() {
+        use crate::data_management::stream::IdSpec;
+
+        let mut stream = StreamValue::default();
+        for ms in 1..=3 {
+            let fields = vec![(b"n".to_vec(), ms.to_string().into_bytes())];
+            stream
+                .add(IdSpec::Explicit(StreamId::new(ms, 0)), fields)
+                .unwrap();
+        }
+        stream.remove(&StreamId::new(2, 0));
+        let mut group = ConsumerGroup::new(StreamId::new(1, 0), Some(1));
+        let pending = PendingEntry {
+            consumer: b"alice".to_vec(),
+            delivery_time: 1_700_000_000_000,
+            delivery_count: 2,
+        };
+        group.pending.insert(StreamId::new(1, 0), pending);
+        let consumer = Consumer {
+            seen_time: 1_700_000_000_000,
+            active_time: Some(1_700_000_000_000),
+            pending: [StreamId::new(1, 0)].into(),
+        };
+        group.consumers.insert(b"alice".to_vec(), consumer);
+        stream.groups.insert(b"workers".to_vec(), group);
+        let value = DataValue::Stream(stream);
+
+        let payload = dump(&value).unwrap();
+        assert_eq!(payload[0], TYPE_STREAM_LISTPACKS_3);
+        assert_eq!(
+            payload[payload.len() - 10..payload.len() - 8],
+            RDB_VERSION.to_le_bytes()
+        );
+        assert_eq!(restore(&payload).unwrap(), value);
+    }
+
+    #[test]
+    fn should_round_trip_hash_field_ttls() {
+        let in_a_minute =
+            UNIX_EPOCH + Duration::from_millis(unix_millis(SystemTime::now()) + 60_000);
+        let hash: HashValue = [
+            (b"plain".to_vec(), HashField::new(b"a".to_vec())),
+            (
+                b"ttl".to_vec(),
+                HashField::with_expiry(b"b".to_vec(), Some(in_a_minute)),
+            ),
+        ]
+        .into_iter()
+        .collect();
+        let value = DataValue::Hash(hash);
+
+        let payload = dump(&value).unwrap();
+        assert_eq!(payload[0], TYPE_HASH_METADATA);
+        assert_eq!(
+            payload[payload.len() - 10..payload.len() - 8],
+            RDB_MAX_VERSION.to_le_bytes()
+        );
+        assert_eq!(restore(&payload).unwrap(), value);
+    }
+
+    #[test]
+    fn should_reject_corrupted_payloads() {
+        let value = DataValue::String(Resp::bulk_string_from_str("bar").serialize().unwrap());
+        let mut payload = dump(&value).unwrap();
+        payload[2] = b'c';
+        assert_eq!(
+            restore(&payload).unwrap_err().to_string(),
+            "ERR DUMP payload version or checksum are wrong"
+        );
+
+        let mut payload = dump(&value).unwrap();
+        let footer = payload.len() - 8;
+        payload[footer..].fill(0);
+        assert!(restore(&payload).is_err());
+    }
+
+    #[test]
+    fn should_round_trip_function_libraries() {
+        let codes: [&[u8]; 2] = [b"#!lua name=a", b"#!lua name=b"];
+        let payload = dump_functions(codes);
+        assert_eq!(payload[..14], *b"\xf5\x0c#!lua name=a");
+        assert_eq!(restore_functions(&payload).unwrap(), codes);
+        let value = DataValue::String(Resp::bulk_string_from_str("bar").serialize().unwrap());
+        assert!(restore_functions(&dump(&value).unwrap()).is_err());
+    }
+
+    #[test]
+    fn should_restore_compact_upstream_encodings() {
+        // Intset of 16 bit integers 1 and 2.
+        let intset_blob = [2, 0, 0, 0, 2, 0, 0, 0, 1, 0, 2, 0];
+        let mut payload = vec![TYPE_SET_INTSET, intset_blob.len() as u8];
+        payload.extend_from_slice(&intset_blob);
+        let payload = with_footer(payload, RDB_VERSION);
+        let expected = SetValue::from([b"1".to_vec(), b"2".to_vec()]);
+        assert_eq!(restore(&payload).unwrap(), DataValue::Set(expected));
+
+        // Listpack holding the hash field "f" with the 7 bit integer value 7.
+        let listpack_blob = [12, 0, 0, 0, 2, 0, 0x81, b'f', 2, 7, 1, 0xff];
+        let mut payload = vec![TYPE_HASH_LISTPACK, listpack_blob.len() as u8];
+        payload.extend_from_slice(&listpack_blob);
+        let payload = with_footer(payload, RDB_VERSION);
+        let expected = HashValue::from([(b"f".to_vec(), b"7".to_vec())]);
+        assert_eq!(restore(&payload).unwrap(), DataValue::Hash(expected));
+    }
+
+    #[test]
+    fn should_load_keys_into_selected_databases() {
+        use crate::data_management::hash_table_store::HashTableDataStore;
+
+        let mut file = b"REDIS0011".to_vec();
+        file.push(OPCODE_AUX);
+        write_string(&mut file, b"redis-ver");
+        write_string(&mut file, b"7.2.0");
+        file.extend_from_slice(&[OPCODE_SELECTDB, 0, OPCODE_RESIZEDB, 2, 1]);
+        file.push(TYPE_STRING);
+        write_string(&mut file, b"plain");
+        write_string(&mut file, b"a");
+        file.push(OPCODE_EXPIRETIME_MS);
+        file.extend_from_slice(&1u64.to_le_bytes());
+        file.push(TYPE_STRING);
+        write_string(&mut file, b"expired");
+        write_string(&mut file, b"b");
+        file.extend_from_slice(&[OPCODE_SELECTDB, 2, OPCODE_FREQ, 42, TYPE_STRING]);
+        write_string(&mut file, b"other");
+        write_string(&mut file, b"c");
+        file.push(OPCODE_FUNCTION);
+        write_string(&mut file, b"#!lua name=lib");
+        file.push(OPCODE_EOF);
+        file.extend_from_slice(&crc64(0, &file).to_le_bytes());
+
+        let snapshot = load::<HashTableDataStore>(&file, 3).unwrap();
+        assert_eq!(snapshot.libraries, vec![b"#!lua name=lib".to_vec()]);
+        let stores = snapshot.databases;
+        let key = |name: &str| Resp::bulk_string_from_str(name).serialize().unwrap();
+        assert_eq!(
+            stores.iter().map(|store| store.len()).collect::<Vec<_>>(),
+            [1, 0, 1]
+        );
+        assert!(stores[0].entry(&key("expired")).is_none());
+        assert_eq!(stores[2].entry(&key("other")).unwrap().frequency(), 42);
+        assert!(load::<HashTableDataStore>(&file, 2).is_err());
+    }
+
+    #[test]
+    fn should_load_saved_databases() {
+        use crate::data_management::hash_table_store::HashTableDataStore;
+
+        let key = |name: &str| Resp::bulk_string_from_str(name).serialize().unwrap();
+        let mut stores = [HashTableDataStore::default(), HashTableDataStore::default()];
+        let set = DataValue::Set(SetValue::from([b"a".to_vec()]));
+        stores[1].insert_entry(key("set"), DataStoreEntry::with_value(set.clone(), None));
+        let expiring = DataStoreEntry::with_value(set, Some(Duration::from_secs(60)));
+        stores[1].insert_entry(key("expiring"), expiring);
+        let codes: [&[u8]; 1] = [b"#!lua name=lib"];
+
+        let snapshot = load::<HashTableDataStore>(&save(&stores, codes).unwrap(), 2).unwrap();
+        assert_eq!(snapshot.libraries, vec![codes[0].to_vec()]);
+        assert_eq!(snapshot.databases[0].len(), 0);
+        let saved = &snapshot.databases[1];
+        assert_eq!(
+            saved.entry(&key("set")).unwrap().value,
+            stores[1].entry(&key("set")).unwrap().value
+        );
+        assert!(saved.entry(&key("expiring")).unwrap().expiry().is_some());
+    }
+
+    #[test]
+    fn should_save_hash_field_ttls() {
+        use crate::data_management::hash_table_store::HashTableDataStore;
+
+        let key = Resp::bulk_string_from_str("hash").serialize().unwrap();
+        let in_a_minute =
+            UNIX_EPOCH + Duration::from_millis(unix_millis(SystemTime::now()) + 60_000);
+        let hash: HashValue = [
+            (b"plain".to_vec(), HashField::new(b"a".to_vec())),
+            (
+                b"ttl".to_vec(),
+                HashField::with_expiry(b"b".to_vec(), Some(in_a_minute)),
+            ),
+            (
+                b"later".to_vec(),
+                HashField::with_expiry(b"c".to_vec(), Some(in_a_minute + Duration::from_secs(60))),
+            ),
+            (
+                b"expired".to_vec(),
+                HashField::with_expiry(b"d".to_vec(), Some(UNIX_EPOCH)),
+            ),
+        ]
+        .into_iter()
+        .collect();
+        let mut stores = [HashTableDataStore::default()];
+        let entry = DataStoreEntry::with_value(DataValue::Hash(hash.clone()), None);
+        stores[0].insert_entry(key.clone(), entry);
+
+        let saved = save(&stores, []).unwrap();
+        assert_eq!(saved[..9], *b"REDIS0012");
+        let snapshot = load::<HashTableDataStore>(&saved, 1).unwrap();
+        let DataValue::Hash(loaded) = &snapshot.databases[0].entry(&key).unwrap().value else {
+            panic!()
+        };
+        let mut expected = hash;
+        expected.purge_expired();
+        assert_eq!(*loaded, expected);
+    }
+
+    #[test]
+    fn should_write_listpacks_that_read_back() {
+        let long = vec![b'x'; 5000];
+        let items = [
+            ListpackItem::Int(7),
+            ListpackItem::Int(-100),
+            ListpackItem::Int(30_000),
+            ListpackItem::Int(-8_000_000),
+            ListpackItem::Int(2_000_000_000),
+            ListpackItem::Int(i64::MIN),
+            ListpackItem::Bytes(b"field"),
+            ListpackItem::Bytes(&long[..100]),
+            ListpackItem::Bytes(&long),
+        ];
+        let blob = write_listpack(&items);
+        assert_eq!(blob[..4], (blob.len() as u32).to_le_bytes());
+        assert_eq!(blob[4..6], 9u16.to_le_bytes());
+        let entries = listpack(&blob).unwrap();
+        let ints = ["7", "-100", "30000", "-8000000", "2000000000"];
+        for (entry, int) in entries.iter().zip(ints) {
+            assert_eq!(entry, int.as_bytes());
+        }
+        assert_eq!(entries[5], i64::MIN.to_string().into_bytes());
+        assert_eq!(
+            entries[6..],
+            [b"field".to_vec(), long[..100].to_vec(), long]
+        );
+    }
+
+    #[test]
+    fn should_save_streams() {
+        use crate::data_management::{hash_table_store::HashTableDataStore, stream::IdSpec};
+
+        let key = Resp::bulk_string_from_str("stream").serialize().unwrap();
+        let mut stream = StreamValue::default();
+        for ms in 1..=250 {
+            let mut fields = vec![(b"n".to_vec(), ms.to_string().into_bytes())];
+            if ms % 3 == 0 {
+                fields.push((format!("extra{ms}").into_bytes(), vec![b'v'; ms as usize]));
+            }
+            stream
+                .add(IdSpec::Explicit(StreamId::new(ms, ms % 7)), fields)
+                .unwrap();
+        }
+        stream.remove(&StreamId::new(1, 1));
+        stream.remove(&StreamId::new(250, 5));
+        let mut stores = [HashTableDataStore::default()];
+        let entry = DataStoreEntry::with_value(DataValue::Stream(stream.clone()), None);
+        stores[0].insert_entry(key.clone(), entry);
+
+        let saved = save(&stores, []).unwrap();
+        let snapshot = load::<HashTableDataStore>(&saved, 1).unwrap();
+        let DataValue::Stream(loaded) = &snapshot.databases[0].entry(&key).unwrap().value else {
+            panic!()
+        };
+        assert_eq!(*loaded, stream);
+        assert_eq!(loaded.last_id(), StreamId::new(250, 5));
+        assert_eq!(loaded.max_deleted_id(), StreamId::new(250, 5));
+        assert_eq!(loaded.entries_added(), 250);
+    }
+
+    #[test]
+    fn should_save_consumer_groups() {
+        use crate::data_management::{hash_table_store::HashTableDataStore, stream::IdSpec};
+
+        let key = Resp::bulk_string_from_str("stream").serialize().unwrap();
+        let mut stream = StreamValue::default();
+        for ms in 1..=3 {
+            let fields = vec![(b"n".to_vec(), ms.to_string().into_bytes())];
+            stream
+                .add(IdSpec::Explicit(StreamId::new(ms, 0)), fields)
+                .unwrap();
+        }
+        let mut group = ConsumerGroup::new(StreamId::new(2, 0), Some(2));
+        for (id, consumer, delivery_count) in [(1, b"alice", 3), (2, b"bobby", 1)] {
+            let id = StreamId::new(id, 0);
+            let name = consumer.to_vec();
+            let pending = PendingEntry {
+                consumer: name.clone(),
+                delivery_time: 1_700_000_000_000 + delivery_count,
+                delivery_count,
+            };
+            group.pending.insert(id, pending);
+            let state = Consumer {
+                seen_time: 1_700_000_000_500,
+                active_time: (delivery_count > 1).then_some(1_700_000_000_400),
+                pending: [id].into(),
+            };
+            group.consumers.insert(name, state);
+        }
+        let idle = Consumer {
+            seen_time: 1_700_000_000_900,
+            active_time: None,
+            pending: Default::default(),
+        };
+        group.consumers.insert(b"idle".to_vec(), idle);
+        stream.groups.insert(b"workers".to_vec(), group);
+        stream
+            .groups
+            .insert(b"new".to_vec(), ConsumerGroup::new(StreamId::MAX, None));
+        let mut stores = [HashTableDataStore::default()];
+        let entry = DataStoreEntry::with_value(DataValue::Stream(stream.clone()), None);
+        stores[0].insert_entry(key.clone(), entry);
+
+        let saved = save(&stores, []).unwrap();
+        let snapshot = load::<HashTableDataStore>(&saved, 1).unwrap();
+        let DataValue::Stream(loaded) = &snapshot.databases[0].entry(&key).unwrap().value else {
+            panic!()
+        };
+        assert_eq!(*loaded, stream);
+    }
+}
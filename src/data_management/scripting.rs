@@ -0,0 +1,599 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use mlua::{
+    Function, HookTriggers, Lua, LuaOptions, MultiValue, RegistryKey, StdLib, Table, Value,
+};
+
+use crate::{errors::AppError, resp::Resp};
+
+/// Names Lua gives to script and function library chunks in error messages, like Redis.
+const SCRIPT_CHUNK: &str = "user_script";
+pub(super) const FUNCTION_CHUNK: &str = "user_function";
+
+/// Parts of the `redis` library written in Lua, on top of `redis.pcall` which dispatches
+/// commands: `redis.call` raises the errors `redis.pcall` returns.
+const REDIS_LIBRARY: &str = r#"
+function redis.call(...)
+    local reply = redis.pcall(...)
+    if type(reply) == "table" and reply.err then
+        error(reply.err, 0)
+    end
+    return reply
+end
+function redis.error_reply(err)
+    return { err = err }
+end
+function redis.status_reply(status)
+    return { ok = status }
+end
+"#;
+
+/// Metatable making globals read-only like Redis does, so that scripts cannot leave state
+/// behind for the next ones: creating or reading a missing global raises an error.
+const GLOBALS_PROTECTION: &str = r#"
+setmetatable(_G, {
+    __newindex = function(_, name)
+        error("Script attempted to create global variable '" .. tostring(name) .. "'", 2)
+    end,
+    __index = function(_, name)
+        error("Script attempted to access nonexistent global variable '" .. tostring(name) .. "'", 2)
+    end,
+})
+"#;
+
+/// Instructions a script runs between two looks at whether it was killed.
+const KILL_CHECK_INSTRUCTIONS: u32 = 100_000;
+
+#[derive(Debug)]
+pub enum Script {
+    Body(Vec<u8>),
+    Sha(String),
+}
+
+#[derive(Debug)]
+pub enum ScriptCommand {
+    Eval {
+        script: Script,
+        keys: Vec<Vec<u8>>,
+        args: Vec<Vec<u8>>,
+    },
+    Load(Vec<u8>),
+    Exists(Vec<String>),
+    Flush,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptKind {
+    Script,
+    Function,
+}
+
+impl ScriptKind {
+    /// Command interrupting a script of this kind.
+    pub fn kill_command(self) -> &'static str {
+        match self {
+            Self::Script => "SCRIPT KILL",
+            Self::Function => "FUNCTION KILL",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct RunningScript {
+    kind: ScriptKind,
+    started: Instant,
+    wrote: bool,
+    killed: bool,
+}
+
+/// Script the worker is running, shared with the connections so that they can tell
+/// clients the server is busy and interrupt it while the worker cannot take messages.
+#[derive(Debug, Default)]
+pub struct ScriptMonitor {
+    running: Mutex<Option<RunningScript>>,
+}
+
+impl ScriptMonitor {
+    fn begin(&self, kind: ScriptKind) {
+        *self.running.lock().unwrap() = Some(RunningScript {
+            kind,
+            started: Instant::now(),
+            wrote: false,
+            killed: false,
+        });
+    }
+
+    fn end(&self) {
+        *self.running.lock().unwrap() = None;
+    }
+
+    /// Marks the running script as having changed the dataset, so it may not be killed.
+    pub fn wrote(&self) {
+        if let Some(running) = self.running.lock().unwrap().as_mut() {
+            running.wrote = true;
+        }
+    }
+
+    /// Kind of the script running for longer than `threshold`, if any.
+    pub fn busy(&self, threshold: Duration) -> Option<ScriptKind> {
+        self.running
+            .lock()
+            .unwrap()
+            .as_ref()
+            .filter(|running| running.started.elapsed() >= threshold)
+            .map(|running| running.kind)
+    }
+
+    /// Asks the running script of `kind` to stop, which only read-only ones may.
+    pub fn kill(&self, kind: ScriptKind) -> Result<(), AppError> {
+        match self.running.lock().unwrap().as_mut() {
+            Some(running) if running.kind == kind => match running.wrote {
+                true => Err(AppError::Unkillable),
+                false => {
+                    running.killed = true;
+                    Ok(())
+                }
+            },
+            _ => Err(AppError::NotBusy),
+        }
+    }
+
+    /// Stops the running script even after it wrote, for the server to shut down.
+    pub fn abort(&self) {
+        if let Some(running) = self.running.lock().unwrap().as_mut() {
+            running.killed = true;
+        }
+    }
+
+    fn killed(&self) -> Option<ScriptKind> {
+        self.running
+            .lock()
+            .unwrap()
+            .as_ref()
+            .filter(|running| running.killed)
+            .map(|running| running.kind)
+    }
+}
+
+pub fn sha1_hex(body: &[u8]) -> String {
+    sha1_smol::Sha1::from(body).digest().to_string()
+}
+
+/// The Lua interpreter along with the scripts compiled in it, keyed by the SHA1 of their
+/// body. Owned by the data worker, so that a script runs as a single turn of it.
+#[derive(Debug)]
+pub struct ScriptEngine {
+    lua: Lua,
+    scripts: HashMap<String, RegistryKey>,
+}
+
+impl Default for ScriptEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScriptEngine {
+    pub fn new() -> Self {
+        Self {
+            lua: new_lua(),
+            scripts: HashMap::new(),
+        }
+    }
+
+    /// Compiles `body` unless it already was, returning the SHA1 it is cached under.
+    pub fn load(&mut self, body: &[u8]) -> Result<String, AppError> {
+        let sha = sha1_hex(body);
+        if self.scripts.contains_key(&sha) {
+            return Ok(sha);
+        }
+        let function = self
+            .lua
+            .load(body)
+            .set_name(format!("@{SCRIPT_CHUNK}"))
+            .into_function()
+            .map_err(|err| {
+                AppError::Generic(format!(
+                    "Error compiling script (new function): {}",
+                    first_line(&err)
+                ))
+            })?;
+        let key = self
+            .lua
+            .create_registry_value(function)
+            .map_err(|err| AppError::Generic(first_line(&err)))?;
+        self.scripts.insert(sha.clone(), key);
+        Ok(sha)
+    }
+
+    pub fn exists(&self, sha: &str) -> bool {
+        self.scripts.contains_key(&sha.to_lowercase())
+    }
+
+    /// Drops every script along with the interpreter state they left behind.
+    pub fn flush(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Runs the script cached under `sha`, see `run`.
+    pub fn run(
+        &self,
+        sha: &str,
+        keys: Vec<Vec<u8>>,
+        args: Vec<Vec<u8>>,
+        monitor: &Arc<ScriptMonitor>,
+        call: impl FnMut(Vec<Vec<u8>>) -> Resp,
+    ) -> Result<Resp, AppError> {
+        let key = self
+            .scripts
+            .get(&sha.to_lowercase())
+            .ok_or(AppError::NoScript)?;
+        let running = (monitor, ScriptKind::Script);
+        run(&self.lua, key, keys, args, running, call)
+    }
+}
+
+/// Interpreter with the libraries scripts may use and the `redis` one.
+pub(super) fn new_lua() -> Lua {
+    let libs = StdLib::TABLE | StdLib::STRING | StdLib::MATH;
+    let lua = Lua::new_with(libs, LuaOptions::default()).expect("Lua libraries to load");
+    load_redis_library(&lua).expect("redis library to load");
+    lua.load(GLOBALS_PROTECTION)
+        .exec()
+        .expect("globals protection to load");
+    lua
+}
+
+fn load_redis_library(lua: &Lua) -> mlua::Result<()> {
+    let redis = lua.create_table()?;
+    let sha1hex = lua.create_function(|_, body: mlua::String| Ok(sha1_hex(body.as_bytes())))?;
+    redis.set("sha1hex", sha1hex)?;
+    lua.globals().set("redis", redis)?;
+    lua.load(REDIS_LIBRARY).exec()
+}
+
+/// Calls the function stored under `key` with the `KEYS` and `ARGV` tables, both as
+/// globals like scripts expect and as arguments like functions do. `call` runs the
+/// commands it sends through `redis.call` and `redis.pcall`, and errors raised by the
+/// function are replied as error replies. The function is registered on `monitor` as
+/// running for as long as it does, and stops with an error once killed.
+pub(super) fn run(
+    lua: &Lua,
+    key: &RegistryKey,
+    keys: Vec<Vec<u8>>,
+    args: Vec<Vec<u8>>,
+    (monitor, kind): (&Arc<ScriptMonitor>, ScriptKind),
+    call: impl FnMut(Vec<Vec<u8>>) -> Resp,
+) -> Result<Resp, AppError> {
+    let call = RefCell::new(call);
+    monitor.begin(kind);
+    let killer = monitor.clone();
+    let triggers = HookTriggers::new().every_nth_instruction(KILL_CHECK_INSTRUCTIONS);
+    // Raised again every few instructions, so `pcall` cannot keep a killed script alive.
+    lua.set_hook(triggers, move |_, _| match killer.killed() {
+        Some(kind) => Err(mlua::Error::RuntimeError(
+            AppError::ScriptKilled(kind.kill_command()).to_string(),
+        )),
+        None => Ok(()),
+    });
+    let result = lua.scope(|scope| {
+        let pcall = scope.create_function(|lua, args: MultiValue| {
+            let args = args
+                .into_iter()
+                .map(argument)
+                .collect::<mlua::Result<Vec<_>>>()?;
+            if args.is_empty() {
+                return Err(mlua::Error::RuntimeError(
+                    "Please specify at least one argument for this redis lib call".to_owned(),
+                ));
+            }
+            let reply = (call.borrow_mut())(args);
+            to_lua(lua, reply)
+        })?;
+        let globals = lua.globals();
+        globals.get::<_, Table>("redis")?.set("pcall", pcall)?;
+        let (keys, args) = (strings(lua, keys)?, strings(lua, args)?);
+        globals.raw_set("KEYS", keys.clone())?;
+        globals.raw_set("ARGV", args.clone())?;
+        let function: Function = lua.registry_value(key)?;
+        let value = function.call::<_, Value>((keys, args))?;
+        Ok(to_resp(value))
+    });
+    lua.remove_hook();
+    monitor.end();
+    match result {
+        Ok(reply) => Ok(reply),
+        Err(err) => {
+            let message = first_line(&err);
+            // Errors raised by `redis.call` carry the reply of the failed command.
+            match [SCRIPT_CHUNK, FUNCTION_CHUNK]
+                .iter()
+                .any(|chunk| message.starts_with(chunk))
+            {
+                true => Err(AppError::Generic(message)),
+                false => Ok(Resp::SimpleError(message.into_bytes())),
+            }
+        }
+    }
+}
+
+fn strings(lua: &Lua, strings: Vec<Vec<u8>>) -> mlua::Result<Table<'_>> {
+    let strings = strings
+        .into_iter()
+        .map(|string| lua.create_string(&string))
+        .collect::<mlua::Result<Vec<_>>>()?;
+    lua.create_sequence_from(strings)
+}
+
+/// First line of the message of `err`, leaving out the Lua traceback.
+pub(super) fn first_line(err: &mlua::Error) -> String {
+    let message = match err {
+        mlua::Error::RuntimeError(message) => message.to_owned(),
+        mlua::Error::SyntaxError { message, .. } => message.to_owned(),
+        mlua::Error::CallbackError { cause, .. } => return first_line(cause),
+        err => err.to_string(),
+    };
+    message.lines().next().unwrap_or_default().to_owned()
+}
+
+/// Command arguments may be strings or numbers, the latter sent in their decimal form.
+fn argument(value: Value) -> mlua::Result<Vec<u8>> {
+    match value {
+        Value::String(string) => Ok(string.as_bytes().to_vec()),
+        Value::Integer(int) => Ok(int.to_string().into_bytes()),
+        Value::Number(number) if number.fract() == 0.0 => {
+            Ok((number as i64).to_string().into_bytes())
+        }
+        Value::Number(number) => Ok(number.to_string().into_bytes()),
+        _ => Err(mlua::Error::RuntimeError(
+            "Lua redis lib command arguments must be strings or integers".to_owned(),
+        )),
+    }
+}
+
+/// Converts a reply to Lua the way Redis does: nulls become `false`, status and error
+/// replies tables with an `ok` or `err` field.
+fn to_lua(lua: &Lua, reply: Resp) -> mlua::Result<Value<'_>> {
+    let value = match reply {
+        Resp::Integers(int) => Value::Number(int as f64),
+        Resp::BulkString(bytes) => Value::String(lua.create_string(&bytes)?),
+        Resp::Null | Resp::NullArray => Value::Boolean(false),
+        Resp::SimpleString(status) => {
+            let table = lua.create_table()?;
+            table.set("ok", lua.create_string(&status)?)?;
+            Value::Table(table)
+        }
+        Resp::SimpleError(err) => {
+            let table = lua.create_table()?;
+            table.set("err", lua.create_string(&err)?)?;
+            Value::Table(table)
+        }
+        Resp::Array(items) | Resp::Push(items) => {
+            let items = items
+                .into_iter()
+                .map(|item| to_lua(lua, item))
+                .collect::<mlua::Result<Vec<_>>>()?;
+            Value::Table(lua.create_sequence_from(items)?)
+        }
+        Resp::Map(entries) => {
+            let items = entries
+                .into_iter()
+                .flat_map(|(key, value)| [key, value])
+                .map(|item| to_lua(lua, item))
+                .collect::<mlua::Result<Vec<_>>>()?;
+            Value::Table(lua.create_sequence_from(items)?)
+        }
+    };
+    Ok(value)
+}
+
+/// Converts what a script returns to a reply: numbers are truncated to integers, tables
+/// become arrays up to their first nil unless they hold an `ok` or `err` field, `true`
+/// becomes 1 and everything else a null.
+fn to_resp(value: Value) -> Resp {
+    match value {
+        Value::Integer(int) => Resp::Integers(int),
+        Value::Number(number) => Resp::Integers(number as i64),
+        Value::String(string) => Resp::BulkString(string.as_bytes().to_vec()),
+        Value::Boolean(true) => Resp::Integers(1),
+        Value::Table(table) => {
+            if let Ok(Value::String(err)) = table.raw_get::<_, Value>("err") {
+                return Resp::SimpleError(err.as_bytes().to_vec());
+            }
+            if let Ok(Value::String(status)) = table.raw_get::<_, Value>("ok") {
+                return Resp::SimpleString(status.as_bytes().to_vec());
+            }
+            let mut items = Vec::new();
+            for index in 1.. {
+                match table.raw_get::<_, Value>(index) {
+                    Ok(Value::Nil) | Err(_) => break,
+                    Ok(value) => items.push(to_resp(value)),
+                }
+            }
+            Resp::Array(items)
+        }
+        _ => Resp::null_bulk_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::{errors::AppError, resp::Resp};
+
+    use super::{ScriptEngine, ScriptKind, ScriptMonitor};
+
+    fn bytes(strings: &[&str]) -> Vec<Vec<u8>> {
+        strings
+            .iter()
+            .map(|string| string.as_bytes().to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn should_convert_replies_both_ways() {
+        let mut engine = ScriptEngine::new();
+        let monitor = Arc::default();
+        let body = b"local r = redis.call('GET', KEYS[1]) \
+            return {r, redis.call('PING'), ARGV[1] + 1, 3.9, false, redis.status_reply('FINE')}";
+        let sha = engine.load(body).unwrap();
+        let mut calls = vec![];
+        let reply = engine
+            .run(&sha, bytes(&["k"]), bytes(&["41"]), &monitor, |args| {
+                calls.push(args.clone());
+                match args[0].as_slice() {
+                    b"GET" => Resp::null_bulk_string(),
+                    _ => Resp::simple_string_from_str("PONG"),
+                }
+            })
+            .unwrap();
+        assert_eq!(calls, vec![bytes(&["GET", "k"]), bytes(&["PING"])]);
+        assert_eq!(
+            reply,
+            Resp::Array(vec![
+                Resp::null_bulk_string(),
+                Resp::simple_string_from_str("PONG"),
+                Resp::Integers(42),
+                Resp::Integers(3),
+                Resp::null_bulk_string(),
+                Resp::simple_string_from_str("FINE"),
+            ])
+        );
+    }
+
+    #[test]
+    fn should_raise_call_errors_and_return_pcall_ones() {
+        let mut engine = ScriptEngine::new();
+        let monitor = Arc::default();
+        let wrong_type = || Resp::SimpleError(b"WRONGTYPE Operation".to_vec());
+        let sha = engine.load(b"return redis.call('INCR', 'k')").unwrap();
+        let reply = engine
+            .run(&sha, vec![], vec![], &monitor, |_| wrong_type())
+            .unwrap();
+        assert_eq!(reply, wrong_type());
+
+        let sha = engine
+            .load(b"local r = redis.pcall('INCR', 'k') return r.err .. '!'")
+            .unwrap();
+        let reply = engine
+            .run(&sha, vec![], vec![], &monitor, |_| wrong_type())
+            .unwrap();
+        assert_eq!(reply, Resp::bulk_string_from_str("WRONGTYPE Operation!"));
+
+        let sha = engine.load(b"return nil + 1").unwrap();
+        let err = engine
+            .run(&sha, vec![], vec![], &monitor, |_| wrong_type())
+            .unwrap_err();
+        assert!(err.to_string().starts_with("ERR user_script:1: attempt"));
+    }
+
+    #[test]
+    fn should_only_kill_scripts_that_did_not_write() {
+        let mut engine = ScriptEngine::new();
+        let monitor: Arc<ScriptMonitor> = Arc::default();
+        assert!(matches!(
+            monitor.kill(ScriptKind::Script),
+            Err(AppError::NotBusy)
+        ));
+        let sha = engine
+            .load(b"redis.call('PING') while true do pcall(function() end) end")
+            .unwrap();
+        let reply = engine
+            .run(&sha, vec![], vec![], &monitor.clone(), |_| {
+                assert!(matches!(
+                    monitor.kill(ScriptKind::Function),
+                    Err(AppError::NotBusy)
+                ));
+                monitor.kill(ScriptKind::Script).unwrap();
+                Resp::simple_string_from_str("PONG")
+            })
+            .unwrap();
+        assert_eq!(reply, AppError::ScriptKilled("SCRIPT KILL").into());
+        assert_eq!(monitor.busy(Default::default()), None);
+
+        let sha = engine.load(b"return redis.call('SET', 'k', 'v')").unwrap();
+        engine
+            .run(&sha, vec![], vec![], &monitor.clone(), |_| {
+                monitor.wrote();
+                assert!(matches!(
+                    monitor.kill(ScriptKind::Script),
+                    Err(AppError::Unkillable)
+                ));
+                Resp::simple_string_from_str("OK")
+            })
+            .unwrap();
+
+        let sha = engine
+            .load(b"redis.call('SET', 'k', 'v') while true do end")
+            .unwrap();
+        let reply = engine
+            .run(&sha, vec![], vec![], &monitor.clone(), |_| {
+                monitor.wrote();
+                monitor.abort();
+                Resp::simple_string_from_str("OK")
+            })
+            .unwrap();
+        assert_eq!(reply, AppError::ScriptKilled("SCRIPT KILL").into());
+    }
+
+    #[test]
+    fn should_keep_empty_strings_apart_from_nulls() {
+        let mut engine = ScriptEngine::new();
+        let monitor = Arc::default();
+        let sha = engine
+            .load(b"return {redis.call('GET', 'empty') == '', redis.call('GET', 'missing')}")
+            .unwrap();
+        let reply = engine
+            .run(&sha, vec![], vec![], &monitor, |args| {
+                match args[1].as_slice() {
+                    b"empty" => Resp::bulk_string_from_str(""),
+                    _ => Resp::null_bulk_string(),
+                }
+            })
+            .unwrap();
+        assert_eq!(
+            reply,
+            Resp::Array(vec![Resp::Integers(1), Resp::null_bulk_string()])
+        );
+    }
+
+    #[test]
+    fn should_protect_globals() {
+        let mut engine = ScriptEngine::new();
+        let monitor = Arc::default();
+        let sha = engine.load(b"leaked = 1 return 1").unwrap();
+        let err = engine
+            .run(&sha, vec![], vec![], &monitor, |_| Resp::null_bulk_string())
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ERR user_script:1: Script attempted to create global variable 'leaked'"
+        );
+
+        let sha = engine.load(b"return leaked").unwrap();
+        let err = engine
+            .run(&sha, vec![], vec![], &monitor, |_| Resp::null_bulk_string())
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ERR user_script:1: Script attempted to access nonexistent global variable 'leaked'"
+        );
+    }
+
+    #[test]
+    fn should_cache_scripts_by_sha1() {
+        let mut engine = ScriptEngine::new();
+        let sha = engine.load(b"return 1").unwrap();
+        assert_eq!(sha, "e0e1f9fabfc9d4800c877a703b823ac0578ff8db");
+        assert!(engine.exists(&sha.to_uppercase()));
+        assert!(engine.load(b"return (").is_err());
+        engine.flush();
+        assert!(!engine.exists(&sha));
+    }
+}
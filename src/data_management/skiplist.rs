@@ -0,0 +1,381 @@
+use std::cmp::Ordering;
+
+use rand::Rng;
+
+const MAX_LEVEL: usize = 32;
+const LEVEL_PROBABILITY: f64 = 0.25;
+const HEAD: usize = 0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Level {
+    forward: Option<usize>,
+    span: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Node {
+    member: Vec<u8>,
+    score: f64,
+    backward: Option<usize>,
+    levels: Vec<Level>,
+}
+
+/// Skiplist ordered by `(score, member)` with per level spans so ranks can be computed
+/// in O(log n), a port of Redis' `zskiplist`. Nodes live in an arena indexed by `usize`,
+/// the node at index 0 being the header.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkipList {
+    nodes: Vec<Node>,
+    free: Vec<usize>,
+    tail: Option<usize>,
+    level: usize,
+    len: usize,
+}
+
+impl Default for SkipList {
+    fn default() -> Self {
+        let head = Node {
+            member: vec![],
+            score: 0.0,
+            backward: None,
+            levels: vec![
+                Level {
+                    forward: None,
+                    span: 0
+                };
+                MAX_LEVEL
+            ],
+        };
+        Self {
+            nodes: vec![head],
+            free: vec![],
+            tail: None,
+            level: 1,
+            len: 0,
+        }
+    }
+}
+
+fn compare(score: f64, member: &[u8], other_score: f64, other_member: &[u8]) -> Ordering {
+    score
+        .partial_cmp(&other_score)
+        .unwrap_or(Ordering::Equal)
+        .then_with(|| member.cmp(other_member))
+}
+
+fn random_level() -> usize {
+    let mut rng = rand::thread_rng();
+    let mut level = 1;
+    while level < MAX_LEVEL && rng.gen::<f64>() < LEVEL_PROBABILITY {
+        level += 1;
+    }
+    level
+}
+
+impl SkipList {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn forward(&self, node: usize, level: usize) -> Option<usize> {
+        self.nodes[node].levels[level].forward
+    }
+
+    fn is_before(&self, node: usize, score: f64, member: &[u8]) -> bool {
+        let node = &self.nodes[node];
+        compare(node.score, &node.member, score, member) == Ordering::Less
+    }
+
+    pub fn insert(&mut self, score: f64, member: Vec<u8>) {
+        let mut update = [HEAD; MAX_LEVEL];
+        let mut rank = [0usize; MAX_LEVEL];
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            rank[i] = if i == self.level - 1 { 0 } else { rank[i + 1] };
+            while let Some(next) = self.forward(x, i) {
+                if !self.is_before(next, score, &member) {
+                    break;
+                }
+                rank[i] += self.nodes[x].levels[i].span;
+                x = next;
+            }
+            update[i] = x;
+        }
+
+        let level = random_level();
+        if level > self.level {
+            for i in self.level..level {
+                rank[i] = 0;
+                update[i] = HEAD;
+                self.nodes[HEAD].levels[i].span = self.len;
+            }
+            self.level = level;
+        }
+
+        let node = Node {
+            member,
+            score,
+            backward: (update[0] != HEAD).then_some(update[0]),
+            levels: vec![
+                Level {
+                    forward: None,
+                    span: 0
+                };
+                level
+            ],
+        };
+        let new = match self.free.pop() {
+            Some(index) => {
+                self.nodes[index] = node;
+                index
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        };
+
+        for i in 0..level {
+            let previous = self.nodes[update[i]].levels[i];
+            self.nodes[new].levels[i] = Level {
+                forward: previous.forward,
+                span: previous.span - (rank[0] - rank[i]),
+            };
+            self.nodes[update[i]].levels[i] = Level {
+                forward: Some(new),
+                span: rank[0] - rank[i] + 1,
+            };
+        }
+        for (i, previous) in update.iter().enumerate().take(self.level).skip(level) {
+            self.nodes[*previous].levels[i].span += 1;
+        }
+
+        match self.nodes[new].levels[0].forward {
+            Some(next) => self.nodes[next].backward = Some(new),
+            None => self.tail = Some(new),
+        }
+        self.len += 1;
+    }
+
+    pub fn remove(&mut self, score: f64, member: &[u8]) -> bool {
+        let mut update = [HEAD; MAX_LEVEL];
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.forward(x, i) {
+                if !self.is_before(next, score, member) {
+                    break;
+                }
+                x = next;
+            }
+            update[i] = x;
+        }
+
+        match self.forward(x, 0) {
+            Some(node)
+                if compare(
+                    self.nodes[node].score,
+                    &self.nodes[node].member,
+                    score,
+                    member,
+                ) == Ordering::Equal =>
+            {
+                self.remove_node(node, &update);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn remove_node(&mut self, node: usize, update: &[usize; MAX_LEVEL]) {
+        for (i, previous) in update.iter().enumerate().take(self.level) {
+            let removed = self.nodes[node].levels.get(i).copied();
+            let level = &mut self.nodes[*previous].levels[i];
+            match removed {
+                Some(removed) if level.forward == Some(node) => {
+                    level.span += removed.span;
+                    level.span -= 1;
+                    level.forward = removed.forward;
+                }
+                _ => level.span -= 1,
+            }
+        }
+        let backward = self.nodes[node].backward;
+        match self.nodes[node].levels[0].forward {
+            Some(next) => self.nodes[next].backward = backward,
+            None => self.tail = backward,
+        }
+        while self.level > 1 && self.nodes[HEAD].levels[self.level - 1].forward.is_none() {
+            self.level -= 1;
+        }
+        self.nodes[node].member = vec![];
+        self.free.push(node);
+        self.len -= 1;
+    }
+
+    /// 0-based rank of the element, if present.
+    pub fn rank(&self, score: f64, member: &[u8]) -> Option<usize> {
+        let mut x = HEAD;
+        let mut rank = 0;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.forward(x, i) {
+                let node = &self.nodes[next];
+                if compare(node.score, &node.member, score, member) == Ordering::Greater {
+                    break;
+                }
+                rank += self.nodes[x].levels[i].span;
+                x = next;
+            }
+            if x != HEAD && self.nodes[x].member == member {
+                return Some(rank - 1);
+            }
+        }
+        None
+    }
+
+    /// Node at the 0-based `rank`.
+    pub fn by_rank(&self, rank: usize) -> Option<usize> {
+        let target = rank + 1;
+        let mut traversed = 0;
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.forward(x, i) {
+                if traversed + self.nodes[x].levels[i].span > target {
+                    break;
+                }
+                traversed += self.nodes[x].levels[i].span;
+                x = next;
+            }
+            if traversed == target {
+                return Some(x);
+            }
+        }
+        None
+    }
+
+    /// First node for which `above_min` holds, `above_min` being monotonic over the list.
+    pub fn first_where(&self, above_min: impl Fn(f64, &[u8]) -> bool) -> Option<usize> {
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.forward(x, i) {
+                let node = &self.nodes[next];
+                if above_min(node.score, &node.member) {
+                    break;
+                }
+                x = next;
+            }
+        }
+        self.forward(x, 0)
+    }
+
+    /// Last node for which `below_max` holds, `below_max` being monotonic over the list.
+    pub fn last_where(&self, below_max: impl Fn(f64, &[u8]) -> bool) -> Option<usize> {
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.forward(x, i) {
+                let node = &self.nodes[next];
+                if !below_max(node.score, &node.member) {
+                    break;
+                }
+                x = next;
+            }
+        }
+        (x != HEAD).then_some(x)
+    }
+
+    pub fn first(&self) -> Option<usize> {
+        self.forward(HEAD, 0)
+    }
+
+    pub fn last(&self) -> Option<usize> {
+        self.tail
+    }
+
+    pub fn element(&self, node: usize) -> (&[u8], f64) {
+        (&self.nodes[node].member, self.nodes[node].score)
+    }
+
+    /// Elements starting at `node` walking towards the tail, or the head when `reverse`.
+    pub fn iter_from(
+        &self,
+        node: Option<usize>,
+        reverse: bool,
+    ) -> impl Iterator<Item = (&[u8], f64)> + '_ {
+        std::iter::successors(node, move |node| {
+            if reverse {
+                self.nodes[*node].backward
+            } else {
+                self.nodes[*node].levels[0].forward
+            }
+        })
+        .map(|node| self.element(node))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SkipList;
+
+    fn list(elements: &[(f64, &str)]) -> SkipList {
+        let mut list = SkipList::default();
+        for (score, member) in elements {
+            list.insert(*score, member.as_bytes().to_vec());
+        }
+        list
+    }
+
+    fn members(list: &SkipList, reverse: bool) -> Vec<String> {
+        let start = if reverse { list.last() } else { list.first() };
+        list.iter_from(start, reverse)
+            .map(|(member, _)| String::from_utf8(member.to_vec()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn should_keep_elements_ordered_by_score_then_member() {
+        let list = list(&[(2.0, "b"), (1.0, "z"), (2.0, "a"), (0.5, "c")]);
+        assert_eq!(members(&list, false), ["c", "z", "a", "b"]);
+        assert_eq!(members(&list, true), ["b", "a", "z", "c"]);
+    }
+
+    #[test]
+    fn should_compute_ranks() {
+        let elements: Vec<(f64, String)> = (0..200).map(|i| (i as f64, format!("m{i}"))).collect();
+        let mut list = SkipList::default();
+        for (score, member) in elements.iter().rev() {
+            list.insert(*score, member.as_bytes().to_vec());
+        }
+        for (rank, (score, member)) in elements.iter().enumerate() {
+            assert_eq!(list.rank(*score, member.as_bytes()), Some(rank));
+            let node = list.by_rank(rank).unwrap();
+            assert_eq!(list.element(node).0, member.as_bytes());
+        }
+        assert_eq!(list.rank(1000.0, b"missing"), None);
+    }
+
+    #[test]
+    fn should_remove_elements_and_keep_ranks() {
+        let mut list = list(&[(1.0, "a"), (2.0, "b"), (3.0, "c"), (4.0, "d")]);
+        assert!(list.remove(2.0, b"b"));
+        assert!(!list.remove(2.0, b"b"));
+        assert_eq!(list.len(), 3);
+        assert_eq!(list.rank(4.0, b"d"), Some(2));
+        assert_eq!(members(&list, true), ["d", "c", "a"]);
+        list.insert(2.5, b"e".to_vec());
+        assert_eq!(members(&list, false), ["a", "e", "c", "d"]);
+    }
+
+    #[test]
+    fn should_find_range_boundaries() {
+        let list = list(&[(1.0, "a"), (2.0, "b"), (3.0, "c"), (4.0, "d")]);
+        let first = list.first_where(|score, _| score >= 2.0).unwrap();
+        assert_eq!(list.element(first), (&b"b"[..], 2.0));
+        let last = list.last_where(|score, _| score < 4.0).unwrap();
+        assert_eq!(list.element(last), (&b"c"[..], 3.0));
+        assert!(list.first_where(|score, _| score > 4.0).is_none());
+        assert!(list.last_where(|score, _| score < 1.0).is_none());
+    }
+}
@@ -0,0 +1,887 @@
+use std::collections::HashMap;
+
+use crate::{errors::AppError, helpers::glob::glob_match, resp::Resp};
+
+use super::{
+    datastore::{DataStore, DataStoreEntry, DataValue},
+    dict::{scan_reply, Dict, ScanArgs},
+    notification::NotifyFlags,
+    skiplist::SkipList,
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct SortedSetValue {
+    scores: Dict<Vec<u8>, f64>,
+    list: SkipList,
+}
+
+/// The skiplist layout depends on random levels, only the scores tell two sets apart.
+impl PartialEq for SortedSetValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.scores == other.scores
+    }
+}
+
+impl SortedSetValue {
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    pub fn score(&self, member: &[u8]) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// Adds `member` or updates its score. Returns true for new members.
+    pub fn insert(&mut self, member: Vec<u8>, score: f64) -> bool {
+        match self.scores.insert(member.clone(), score) {
+            Some(previous) => {
+                if previous != score {
+                    self.list.remove(previous, &member);
+                    self.list.insert(score, member);
+                }
+                false
+            }
+            None => {
+                self.list.insert(score, member);
+                true
+            }
+        }
+    }
+
+    pub fn remove(&mut self, member: &[u8]) -> bool {
+        match self.scores.remove(member) {
+            Some(score) => self.list.remove(score, member),
+            None => false,
+        }
+    }
+
+    pub fn rank(&self, member: &[u8], reverse: bool) -> Option<usize> {
+        let rank = self.list.rank(self.score(member)?, member)?;
+        Some(if reverse { self.len() - 1 - rank } else { rank })
+    }
+
+    pub fn scan(&self, cursor: u64, count: usize) -> (u64, Vec<(&Vec<u8>, f64)>) {
+        let (cursor, elements) = self.scores.scan(cursor, count);
+        let elements = elements.into_iter().map(|(member, score)| (member, *score));
+        (cursor, elements.collect())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&[u8], f64)> + '_ {
+        self.list.iter_from(self.list.first(), false)
+    }
+
+    pub fn range(&self, query: &RangeQuery) -> Vec<(Vec<u8>, f64)> {
+        let reverse = query.reverse;
+        let elements: Box<dyn Iterator<Item = (&[u8], f64)>> = match &query.by {
+            RangeBy::Rank(start, stop) => {
+                let len = self.len() as i64;
+                let start = if *start < 0 { start + len } else { *start }.max(0);
+                let stop = if *stop < 0 { stop + len } else { *stop }.min(len - 1);
+                if start > stop || start >= len {
+                    return vec![];
+                }
+                let first = if reverse { len - 1 - start } else { start };
+                let node = self.list.by_rank(first as usize);
+                Box::new(
+                    self.list
+                        .iter_from(node, reverse)
+                        .take((stop - start + 1) as usize),
+                )
+            }
+            RangeBy::Score(min, max) => {
+                let start = if reverse {
+                    self.list.last_where(|score, _| max.below_max(score))
+                } else {
+                    self.list.first_where(|score, _| min.above_min(score))
+                };
+                Box::new(
+                    self.list
+                        .iter_from(start, reverse)
+                        .take_while(move |(_, score)| {
+                            if reverse {
+                                min.above_min(*score)
+                            } else {
+                                max.below_max(*score)
+                            }
+                        }),
+                )
+            }
+            RangeBy::Lex(min, max) => {
+                let start = if reverse {
+                    self.list.last_where(|_, member| max.below_max(member))
+                } else {
+                    self.list.first_where(|_, member| min.above_min(member))
+                };
+                Box::new(
+                    self.list
+                        .iter_from(start, reverse)
+                        .take_while(move |(member, _)| {
+                            if reverse {
+                                min.above_min(member)
+                            } else {
+                                max.below_max(member)
+                            }
+                        }),
+                )
+            }
+        };
+        let (offset, count) = query.limit.unwrap_or((0, -1));
+        let count = usize::try_from(count).unwrap_or(usize::MAX);
+        elements
+            .skip(offset)
+            .take(count)
+            .map(|(member, score)| (member.to_vec(), score))
+            .collect()
+    }
+
+    pub fn pop(&mut self, count: usize, max: bool) -> Vec<(Vec<u8>, f64)> {
+        let start = if max {
+            self.list.last()
+        } else {
+            self.list.first()
+        };
+        let popped: Vec<_> = self
+            .list
+            .iter_from(start, max)
+            .take(count)
+            .map(|(member, score)| (member.to_vec(), score))
+            .collect();
+        for (member, _) in &popped {
+            self.remove(member);
+        }
+        popped
+    }
+}
+
+impl<I> From<I> for SortedSetValue
+where
+    I: IntoIterator<Item = (Vec<u8>, f64)>,
+{
+    fn from(value: I) -> Self {
+        let mut sorted_set = Self::default();
+        for (member, score) in value {
+            sorted_set.insert(member, score);
+        }
+        sorted_set
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreBound {
+    pub value: f64,
+    pub exclusive: bool,
+}
+
+impl ScoreBound {
+    fn above_min(&self, score: f64) -> bool {
+        if self.exclusive {
+            score > self.value
+        } else {
+            score >= self.value
+        }
+    }
+
+    fn below_max(&self, score: f64) -> bool {
+        if self.exclusive {
+            score < self.value
+        } else {
+            score <= self.value
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LexBound {
+    Min,
+    Max,
+    Inclusive(Vec<u8>),
+    Exclusive(Vec<u8>),
+}
+
+impl LexBound {
+    fn above_min(&self, member: &[u8]) -> bool {
+        match self {
+            Self::Min => true,
+            Self::Max => false,
+            Self::Inclusive(bound) => member >= bound.as_slice(),
+            Self::Exclusive(bound) => member > bound.as_slice(),
+        }
+    }
+
+    fn below_max(&self, member: &[u8]) -> bool {
+        match self {
+            Self::Min => false,
+            Self::Max => true,
+            Self::Inclusive(bound) => member <= bound.as_slice(),
+            Self::Exclusive(bound) => member < bound.as_slice(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RangeBy {
+    Rank(i64, i64),
+    Score(ScoreBound, ScoreBound),
+    Lex(LexBound, LexBound),
+}
+
+/// Bounds are always stored as `(min, max)`, even for `REV` queries.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RangeQuery {
+    pub by: RangeBy,
+    pub reverse: bool,
+    pub limit: Option<(usize, i64)>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AddFlags {
+    pub nx: bool,
+    pub xx: bool,
+    pub gt: bool,
+    pub lt: bool,
+    pub ch: bool,
+    pub incr: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregate {
+    Sum,
+    Min,
+    Max,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SortedSetCommand {
+    Add {
+        key: Vec<u8>,
+        elements: Vec<(f64, Vec<u8>)>,
+        flags: AddFlags,
+    },
+    IncrBy(Vec<u8>, f64, Vec<u8>),
+    Rem(Vec<u8>, Vec<Vec<u8>>),
+    Card(Vec<u8>),
+    Count(Vec<u8>, ScoreBound, ScoreBound),
+    Score(Vec<u8>, Vec<u8>),
+    Rank {
+        key: Vec<u8>,
+        member: Vec<u8>,
+        reverse: bool,
+        withscore: bool,
+    },
+    Range {
+        key: Vec<u8>,
+        query: RangeQuery,
+        withscores: bool,
+    },
+    RemRange(Vec<u8>, RangeBy),
+    Pop {
+        key: Vec<u8>,
+        count: Option<usize>,
+        max: bool,
+    },
+    Store {
+        destination: Vec<u8>,
+        keys: Vec<Vec<u8>>,
+        weights: Vec<f64>,
+        aggregate: Aggregate,
+        union: bool,
+    },
+    Scan {
+        key: Vec<u8>,
+        scan: ScanArgs,
+    },
+}
+
+fn sorted_set_mut<'a, T: DataStore>(
+    store: &'a mut T,
+    key: &[u8],
+) -> Result<Option<&'a mut SortedSetValue>, AppError> {
+    match store.entry_mut(key) {
+        None => Ok(None),
+        Some(DataStoreEntry {
+            value: DataValue::SortedSet(sorted_set),
+            ..
+        }) => Ok(Some(sorted_set)),
+        Some(_) => Err(AppError::WrongType),
+    }
+}
+
+fn sorted_set_or_create<'a, T: DataStore>(
+    store: &'a mut T,
+    key: &[u8],
+) -> Result<&'a mut SortedSetValue, AppError> {
+    if sorted_set_mut(store, key)?.is_none() {
+        let value = DataValue::SortedSet(SortedSetValue::default());
+        store.insert_entry(key.to_owned(), DataStoreEntry::with_value(value, None));
+    }
+    Ok(sorted_set_mut(store, key)?.expect("sorted set inserted above"))
+}
+
+fn remove_if_empty<T: DataStore>(store: &mut T, key: &[u8]) {
+    if let Ok(Some(sorted_set)) = sorted_set_mut(store, key) {
+        if sorted_set.is_empty() {
+            store.remove(key);
+            store.notify(NotifyFlags::GENERIC, "del", key);
+        }
+    }
+}
+
+pub fn score_reply(score: f64) -> Resp {
+    Resp::BulkString(score.to_string().into_bytes())
+}
+
+fn elements_reply(elements: Vec<(Vec<u8>, f64)>, withscores: bool) -> Resp {
+    let reply = elements
+        .into_iter()
+        .flat_map(|(member, score)| {
+            let mut element = vec![Resp::BulkString(member)];
+            if withscores {
+                element.push(score_reply(score));
+            }
+            element
+        })
+        .collect();
+    Resp::Array(reply)
+}
+
+/// Elements of a sorted set, or of a plain set with every score at 1, for `ZUNIONSTORE`.
+fn weighted_elements<T: DataStore>(
+    store: &mut T,
+    key: &[u8],
+) -> Result<Vec<(Vec<u8>, f64)>, AppError> {
+    match store.entry_mut(key) {
+        None => Ok(vec![]),
+        Some(DataStoreEntry {
+            value: DataValue::SortedSet(sorted_set),
+            ..
+        }) => Ok(sorted_set
+            .iter()
+            .map(|(member, score)| (member.to_vec(), score))
+            .collect()),
+        Some(DataStoreEntry {
+            value: DataValue::Set(set),
+            ..
+        }) => Ok(set
+            .members()
+            .into_iter()
+            .map(|member| (member, 1.0))
+            .collect()),
+        Some(_) => Err(AppError::WrongType),
+    }
+}
+
+fn aggregate(aggregate: Aggregate, current: f64, score: f64) -> f64 {
+    match aggregate {
+        Aggregate::Sum => {
+            let sum = current + score;
+            if sum.is_nan() {
+                0.0
+            } else {
+                sum
+            }
+        }
+        Aggregate::Min => current.min(score),
+        Aggregate::Max => current.max(score),
+    }
+}
+
+fn pop_event(max: bool) -> &'static str {
+    if max {
+        "zpopmax"
+    } else {
+        "zpopmin"
+    }
+}
+
+/// Pops from the first non empty sorted set among `keys`, used by `BZPOPMIN`/`BZPOPMAX`.
+/// Returns `None` while every key is empty so the caller stays blocked.
+pub fn pop_first_available<T: DataStore>(
+    store: &mut T,
+    keys: &[Vec<u8>],
+    max: bool,
+) -> Result<Option<Resp>, AppError> {
+    for key in keys {
+        let Some(sorted_set) = sorted_set_mut(store, key)? else {
+            continue;
+        };
+        let Some((member, score)) = sorted_set.pop(1, max).pop() else {
+            continue;
+        };
+        store.signal_modified(key);
+        store.notify(NotifyFlags::ZSET, pop_event(max), key);
+        remove_if_empty(store, key);
+        let key = Resp::deserialize(key)?;
+        return Ok(Some(Resp::Array(vec![
+            key,
+            Resp::BulkString(member),
+            score_reply(score),
+        ])));
+    }
+    Ok(None)
+}
+
+pub fn apply_sorted_set_command<T: DataStore>(
+    store: &mut T,
+    command: SortedSetCommand,
+) -> Result<Resp, AppError> {
+    match command {
+        SortedSetCommand::Add {
+            key,
+            elements,
+            flags,
+        } => {
+            if flags.xx && sorted_set_mut(store, &key)?.is_none() {
+                return Ok(if flags.incr {
+                    Resp::null_bulk_string()
+                } else {
+                    Resp::Integers(0)
+                });
+            }
+            let sorted_set = sorted_set_or_create(store, &key)?;
+            let (mut added, mut changed, mut last_score) = (0, 0, None);
+            for (score, member) in elements {
+                match sorted_set.score(&member) {
+                    None if flags.xx => last_score = None,
+                    None => {
+                        sorted_set.insert(member, score);
+                        added += 1;
+                        last_score = Some(score);
+                    }
+                    Some(_) if flags.nx => last_score = None,
+                    Some(current) => {
+                        let new = if flags.incr { current + score } else { score };
+                        if new.is_nan() {
+                            return Err(AppError::Generic(
+                                "resulting score is not a number (NaN)".to_owned(),
+                            ));
+                        }
+                        if (flags.gt && new <= current) || (flags.lt && new >= current) {
+                            last_score = None;
+                            continue;
+                        }
+                        if new != current {
+                            sorted_set.insert(member, new);
+                            changed += 1;
+                        }
+                        last_score = Some(new);
+                    }
+                }
+            }
+            if added + changed > 0 {
+                store.signal_modified(&key);
+                let event = if flags.incr { "zincr" } else { "zadd" };
+                store.notify(NotifyFlags::ZSET, event, &key);
+            }
+            remove_if_empty(store, &key);
+            if flags.incr {
+                return Ok(last_score
+                    .map(score_reply)
+                    .unwrap_or(Resp::null_bulk_string()));
+            }
+            Ok(Resp::Integers(if flags.ch {
+                added + changed
+            } else {
+                added
+            }))
+        }
+        SortedSetCommand::IncrBy(key, increment, member) => {
+            let sorted_set = sorted_set_or_create(store, &key)?;
+            let score = sorted_set.score(&member).unwrap_or(0.0) + increment;
+            if score.is_nan() {
+                return Err(AppError::Generic(
+                    "resulting score is not a number (NaN)".to_owned(),
+                ));
+            }
+            sorted_set.insert(member, score);
+            store.signal_modified(&key);
+            store.notify(NotifyFlags::ZSET, "zincr", &key);
+            Ok(score_reply(score))
+        }
+        SortedSetCommand::Rem(key, members) => {
+            let Some(sorted_set) = sorted_set_mut(store, &key)? else {
+                return Ok(Resp::Integers(0));
+            };
+            let removed = members
+                .iter()
+                .filter(|member| sorted_set.remove(member))
+                .count();
+            if removed > 0 {
+                store.signal_modified(&key);
+                store.notify(NotifyFlags::ZSET, "zrem", &key);
+            }
+            remove_if_empty(store, &key);
+            Ok(Resp::Integers(removed as i64))
+        }
+        SortedSetCommand::Card(key) => {
+            let len = sorted_set_mut(store, &key)?.map_or(0, |sorted_set| sorted_set.len());
+            Ok(Resp::Integers(len as i64))
+        }
+        SortedSetCommand::Count(key, min, max) => {
+            let query = RangeQuery {
+                by: RangeBy::Score(min, max),
+                reverse: false,
+                limit: None,
+            };
+            let count =
+                sorted_set_mut(store, &key)?.map_or(0, |sorted_set| sorted_set.range(&query).len());
+            Ok(Resp::Integers(count as i64))
+        }
+        SortedSetCommand::Score(key, member) => {
+            let score = sorted_set_mut(store, &key)?.and_then(|set| set.score(&member));
+            Ok(score.map(score_reply).unwrap_or(Resp::null_bulk_string()))
+        }
+        SortedSetCommand::Rank {
+            key,
+            member,
+            reverse,
+            withscore,
+        } => {
+            let Some(sorted_set) = sorted_set_mut(store, &key)? else {
+                return Ok(Resp::null_bulk_string());
+            };
+            let (Some(rank), Some(score)) =
+                (sorted_set.rank(&member, reverse), sorted_set.score(&member))
+            else {
+                return Ok(Resp::null_bulk_string());
+            };
+            if withscore {
+                return Ok(Resp::Array(vec![
+                    Resp::Integers(rank as i64),
+                    score_reply(score),
+                ]));
+            }
+            Ok(Resp::Integers(rank as i64))
+        }
+        SortedSetCommand::Range {
+            key,
+            query,
+            withscores,
+        } => {
+            let elements = sorted_set_mut(store, &key)?
+                .map(|sorted_set| sorted_set.range(&query))
+                .unwrap_or_default();
+            Ok(elements_reply(elements, withscores))
+        }
+        SortedSetCommand::RemRange(key, by) => {
+            let Some(sorted_set) = sorted_set_mut(store, &key)? else {
+                return Ok(Resp::Integers(0));
+            };
+            let event = match by {
+                RangeBy::Rank(..) => "zremrangebyrank",
+                RangeBy::Score(..) => "zremrangebyscore",
+                RangeBy::Lex(..) => "zremrangebylex",
+            };
+            let query = RangeQuery {
+                by,
+                reverse: false,
+                limit: None,
+            };
+            let elements = sorted_set.range(&query);
+            for (member, _) in &elements {
+                sorted_set.remove(member);
+            }
+            if !elements.is_empty() {
+                store.signal_modified(&key);
+                store.notify(NotifyFlags::ZSET, event, &key);
+            }
+            remove_if_empty(store, &key);
+            Ok(Resp::Integers(elements.len() as i64))
+        }
+        SortedSetCommand::Pop { key, count, max } => {
+            let Some(sorted_set) = sorted_set_mut(store, &key)? else {
+                return Ok(Resp::Array(vec![]));
+            };
+            let popped = sorted_set.pop(count.unwrap_or(1), max);
+            if !popped.is_empty() {
+                store.signal_modified(&key);
+                store.notify(NotifyFlags::ZSET, pop_event(max), &key);
+            }
+            remove_if_empty(store, &key);
+            Ok(elements_reply(popped, true))
+        }
+        SortedSetCommand::Store {
+            destination,
+            keys,
+            weights,
+            aggregate: method,
+            union,
+        } => {
+            let mut result: Option<HashMap<Vec<u8>, f64>> = None;
+            for (index, key) in keys.iter().enumerate() {
+                let weight = weights.get(index).copied().unwrap_or(1.0);
+                let elements = weighted_elements(store, key)?
+                    .into_iter()
+                    .map(|(member, score)| {
+                        let weighted = score * weight;
+                        (member, if weighted.is_nan() { 0.0 } else { weighted })
+                    });
+                result = Some(match result {
+                    None => elements.collect(),
+                    Some(mut current) if union => {
+                        for (member, score) in elements {
+                            current
+                                .entry(member)
+                                .and_modify(|existing| {
+                                    *existing = aggregate(method, *existing, score)
+                                })
+                                .or_insert(score);
+                        }
+                        current
+                    }
+                    Some(current) => {
+                        let elements: HashMap<_, _> = elements.collect();
+                        current
+                            .into_iter()
+                            .filter_map(|(member, existing)| {
+                                let score = *elements.get(&member)?;
+                                Some((member, aggregate(method, existing, score)))
+                            })
+                            .collect()
+                    }
+                });
+            }
+            let result = result.unwrap_or_default();
+            let len = result.len();
+            let existed = store.remove(&destination).is_some();
+            if len > 0 {
+                let value = DataValue::SortedSet(result.into());
+                store.insert_entry(destination.clone(), DataStoreEntry::with_value(value, None));
+                let event = if union { "zunionstore" } else { "zinterstore" };
+                store.notify(NotifyFlags::ZSET, event, &destination);
+            } else if existed {
+                store.notify(NotifyFlags::GENERIC, "del", &destination);
+            }
+            Ok(Resp::Integers(len as i64))
+        }
+        SortedSetCommand::Scan { key, scan } => {
+            let Some(sorted_set) = sorted_set_mut(store, &key)? else {
+                return Ok(scan_reply(0, vec![]));
+            };
+            let (cursor, elements) = sorted_set.scan(scan.cursor, scan.count);
+            let elements = elements
+                .into_iter()
+                .filter(|(member, _)| {
+                    scan.pattern
+                        .as_ref()
+                        .is_none_or(|pattern| glob_match(pattern, member, false))
+                })
+                .flat_map(|(member, score)| {
+                    [Resp::BulkString(member.to_owned()), score_reply(score)]
+                })
+                .collect();
+            Ok(scan_reply(cursor, elements))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data_management::hash_table_store::HashTableDataStore;
+
+    fn bound(value: f64) -> ScoreBound {
+        ScoreBound {
+            value,
+            exclusive: false,
+        }
+    }
+
+    fn store_with_leaderboard() -> HashTableDataStore {
+        let mut store = HashTableDataStore::default();
+        let elements = [(1.0, "alice"), (2.0, "bob"), (3.0, "carol"), (4.0, "dave")]
+            .into_iter()
+            .map(|(score, member)| (score, member.as_bytes().to_vec()))
+            .collect();
+        apply_sorted_set_command(
+            &mut store,
+            SortedSetCommand::Add {
+                key: b"board".to_vec(),
+                elements,
+                flags: AddFlags::default(),
+            },
+        )
+        .unwrap();
+        store
+    }
+
+    fn range(store: &mut HashTableDataStore, query: RangeQuery) -> Resp {
+        apply_sorted_set_command(
+            store,
+            SortedSetCommand::Range {
+                key: b"board".to_vec(),
+                query,
+                withscores: false,
+            },
+        )
+        .unwrap()
+    }
+
+    fn members(members: &[&str]) -> Resp {
+        Resp::Array(
+            members
+                .iter()
+                .map(|member| Resp::bulk_string_from_str(member))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn should_apply_add_flags() {
+        let mut store = store_with_leaderboard();
+        let add = |score: f64, member: &str, flags| SortedSetCommand::Add {
+            key: b"board".to_vec(),
+            elements: vec![(score, member.as_bytes().to_vec())],
+            flags,
+        };
+        let nx = AddFlags {
+            nx: true,
+            ..Default::default()
+        };
+        let result = apply_sorted_set_command(&mut store, add(10.0, "alice", nx));
+        assert_eq!(result.unwrap(), Resp::Integers(0));
+        let gt_ch = AddFlags {
+            gt: true,
+            ch: true,
+            ..Default::default()
+        };
+        let result = apply_sorted_set_command(&mut store, add(0.5, "bob", gt_ch));
+        assert_eq!(result.unwrap(), Resp::Integers(0));
+        let result = apply_sorted_set_command(&mut store, add(5.0, "bob", gt_ch));
+        assert_eq!(result.unwrap(), Resp::Integers(1));
+        let incr = AddFlags {
+            incr: true,
+            ..Default::default()
+        };
+        let result = apply_sorted_set_command(&mut store, add(1.5, "alice", incr));
+        assert_eq!(result.unwrap(), Resp::bulk_string_from_str("2.5"));
+    }
+
+    #[test]
+    fn should_range_by_rank() {
+        let mut store = store_with_leaderboard();
+        let query = |reverse| RangeQuery {
+            by: RangeBy::Rank(0, -2),
+            reverse,
+            limit: None,
+        };
+        assert_eq!(
+            range(&mut store, query(false)),
+            members(&["alice", "bob", "carol"])
+        );
+        assert_eq!(
+            range(&mut store, query(true)),
+            members(&["dave", "carol", "bob"])
+        );
+    }
+
+    #[test]
+    fn should_range_by_score_with_limit() {
+        let mut store = store_with_leaderboard();
+        let query = RangeQuery {
+            by: RangeBy::Score(
+                ScoreBound {
+                    value: 1.0,
+                    exclusive: true,
+                },
+                bound(f64::INFINITY),
+            ),
+            reverse: true,
+            limit: Some((1, 2)),
+        };
+        assert_eq!(range(&mut store, query), members(&["carol", "bob"]));
+    }
+
+    #[test]
+    fn should_range_by_lex() {
+        let mut store = store_with_leaderboard();
+        let query = RangeQuery {
+            by: RangeBy::Lex(
+                LexBound::Inclusive(b"b".to_vec()),
+                LexBound::Exclusive(b"d".to_vec()),
+            ),
+            reverse: false,
+            limit: None,
+        };
+        assert_eq!(range(&mut store, query), members(&["bob", "carol"]));
+    }
+
+    #[test]
+    fn should_rank_and_remove_range() {
+        let mut store = store_with_leaderboard();
+        let rank = apply_sorted_set_command(
+            &mut store,
+            SortedSetCommand::Rank {
+                key: b"board".to_vec(),
+                member: b"carol".to_vec(),
+                reverse: true,
+                withscore: false,
+            },
+        );
+        assert_eq!(rank.unwrap(), Resp::Integers(1));
+        let removed = apply_sorted_set_command(
+            &mut store,
+            SortedSetCommand::RemRange(b"board".to_vec(), RangeBy::Score(bound(2.0), bound(3.0))),
+        );
+        assert_eq!(removed.unwrap(), Resp::Integers(2));
+        let card = apply_sorted_set_command(&mut store, SortedSetCommand::Card(b"board".to_vec()));
+        assert_eq!(card.unwrap(), Resp::Integers(2));
+    }
+
+    #[test]
+    fn should_pop_min_and_max() {
+        let mut store = store_with_leaderboard();
+        let popped = apply_sorted_set_command(
+            &mut store,
+            SortedSetCommand::Pop {
+                key: b"board".to_vec(),
+                count: Some(2),
+                max: true,
+            },
+        );
+        assert_eq!(
+            popped.unwrap(),
+            Resp::Array(vec![
+                Resp::bulk_string_from_str("dave"),
+                Resp::bulk_string_from_str("4"),
+                Resp::bulk_string_from_str("carol"),
+                Resp::bulk_string_from_str("3"),
+            ])
+        );
+        let popped = pop_first_available(&mut store, &[b"empty".to_vec()], false);
+        assert!(popped.unwrap().is_none());
+    }
+
+    #[test]
+    fn should_store_weighted_union_and_intersection() {
+        let mut store = store_with_leaderboard();
+        apply_sorted_set_command(
+            &mut store,
+            SortedSetCommand::Add {
+                key: b"other".to_vec(),
+                elements: vec![(10.0, b"alice".to_vec()), (5.0, b"erin".to_vec())],
+                flags: AddFlags::default(),
+            },
+        )
+        .unwrap();
+        let store_command = |union, aggregate| SortedSetCommand::Store {
+            destination: b"dest".to_vec(),
+            keys: vec![b"board".to_vec(), b"other".to_vec()],
+            weights: vec![2.0, 1.0],
+            aggregate,
+            union,
+        };
+        let result = apply_sorted_set_command(&mut store, store_command(true, Aggregate::Sum));
+        assert_eq!(result.unwrap(), Resp::Integers(5));
+        let result = apply_sorted_set_command(&mut store, store_command(false, Aggregate::Max));
+        assert_eq!(result.unwrap(), Resp::Integers(1));
+        let score = apply_sorted_set_command(
+            &mut store,
+            SortedSetCommand::Score(b"dest".to_vec(), b"alice".to_vec()),
+        );
+        assert_eq!(score.unwrap(), Resp::bulk_string_from_str("10"));
+    }
+}
@@ -0,0 +1,549 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    ops::Bound,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{errors::AppError, resp::Resp};
+
+use super::{
+    consumer_group::{apply_consumer_group_command, ConsumerGroup, ConsumerGroupCommand},
+    datastore::{DataStore, DataStoreEntry, DataValue},
+    notification::NotifyFlags,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StreamId {
+    pub ms: u64,
+    pub seq: u64,
+}
+
+impl StreamId {
+    pub const MIN: Self = Self { ms: 0, seq: 0 };
+    pub const MAX: Self = Self {
+        ms: u64::MAX,
+        seq: u64::MAX,
+    };
+
+    pub fn new(ms: u64, seq: u64) -> Self {
+        Self { ms, seq }
+    }
+
+    /// Parses `ms-seq`, or a bare `ms` whose sequence defaults to `default_seq`.
+    pub fn parse(bytes: &[u8], default_seq: u64) -> Option<Self> {
+        let id = std::str::from_utf8(bytes).ok()?;
+        match id.split_once('-') {
+            Some((ms, seq)) => Some(Self::new(ms.parse().ok()?, seq.parse().ok()?)),
+            None => Some(Self::new(id.parse().ok()?, default_seq)),
+        }
+    }
+
+    pub fn next(self) -> Option<Self> {
+        match self.seq.checked_add(1) {
+            Some(seq) => Some(Self::new(self.ms, seq)),
+            None => Some(Self::new(self.ms.checked_add(1)?, 0)),
+        }
+    }
+
+    pub fn previous(self) -> Option<Self> {
+        match self.seq.checked_sub(1) {
+            Some(seq) => Some(Self::new(self.ms, seq)),
+            None => Some(Self::new(self.ms.checked_sub(1)?, u64::MAX)),
+        }
+    }
+
+    pub fn reply(&self) -> Resp {
+        Resp::BulkString(self.to_string().into_bytes())
+    }
+}
+
+impl Display for StreamId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+/// ID argument of `XADD`: `*`, `ms-*` or a full `ms-seq`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdSpec {
+    Auto,
+    Partial(u64),
+    Explicit(StreamId),
+}
+
+/// ID argument of `XREAD`, `$` being resolved to the last ID when the command is received.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadFrom {
+    Id(StreamId),
+    Last,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrimStrategy {
+    MaxLen(usize),
+    MinId(StreamId),
+}
+
+/// `MAXLEN | MINID [= | ~] threshold [LIMIT count]`. Approximate trimming is done
+/// exactly, which Redis allows, `limit` only capping the number of evicted entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Trim {
+    pub strategy: TrimStrategy,
+    pub limit: Option<usize>,
+}
+
+pub type StreamFields = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// `XREAD` keys along with the ID to read after.
+pub type ReadStreams = Vec<(Vec<u8>, ReadFrom)>;
+
+/// Entries are kept in a `BTreeMap` ordered by ID, consumer groups living alongside them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StreamValue {
+    pub(super) entries: BTreeMap<StreamId, StreamFields>,
+    pub(super) groups: BTreeMap<Vec<u8>, ConsumerGroup>,
+    pub(super) last_id: StreamId,
+    pub(super) max_deleted_id: StreamId,
+    pub(super) entries_added: u64,
+}
+
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+impl StreamValue {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn last_id(&self) -> StreamId {
+        self.last_id
+    }
+
+    pub fn max_deleted_id(&self) -> StreamId {
+        self.max_deleted_id
+    }
+
+    pub fn entries_added(&self) -> u64 {
+        self.entries_added
+    }
+
+    pub fn get(&self, id: &StreamId) -> Option<&StreamFields> {
+        self.entries.get(id)
+    }
+
+    pub fn first_entry(&self) -> Option<(&StreamId, &StreamFields)> {
+        self.entries.first_key_value()
+    }
+
+    pub fn last_entry(&self) -> Option<(&StreamId, &StreamFields)> {
+        self.entries.last_key_value()
+    }
+
+    /// Number of entries added up to `id` when it can be known without scanning, used to
+    /// seed a consumer group's `entries-read` counter.
+    pub fn entries_read_at(&self, id: StreamId) -> Option<u64> {
+        if id >= self.last_id {
+            return Some(self.entries_added);
+        }
+        let before_first = self.first_entry().is_some_and(|(first, _)| id < *first);
+        (before_first && self.max_deleted_id == StreamId::MIN).then_some(0)
+    }
+
+    fn next_id(&self, spec: IdSpec) -> Result<StreamId, AppError> {
+        let last = self.last_id;
+        match spec {
+            IdSpec::Auto => {
+                let ms = now_ms();
+                if ms > last.ms {
+                    Ok(StreamId::new(ms, 0))
+                } else {
+                    last.next().ok_or(AppError::StreamIdTooSmall)
+                }
+            }
+            IdSpec::Partial(ms) if ms > last.ms => Ok(StreamId::new(ms, 0)),
+            IdSpec::Partial(ms) if ms == last.ms => {
+                let seq = last.seq.checked_add(1).ok_or(AppError::StreamIdTooSmall)?;
+                Ok(StreamId::new(ms, seq))
+            }
+            IdSpec::Partial(_) => Err(AppError::StreamIdTooSmall),
+            IdSpec::Explicit(StreamId::MIN) => Err(AppError::StreamIdZero),
+            IdSpec::Explicit(id) if id <= last => Err(AppError::StreamIdTooSmall),
+            IdSpec::Explicit(id) => Ok(id),
+        }
+    }
+
+    pub fn add(&mut self, spec: IdSpec, fields: StreamFields) -> Result<StreamId, AppError> {
+        let id = self.next_id(spec)?;
+        self.entries.insert(id, fields);
+        self.last_id = id;
+        self.entries_added += 1;
+        Ok(id)
+    }
+
+    pub fn remove(&mut self, id: &StreamId) -> bool {
+        let removed = self.entries.remove(id).is_some();
+        if removed {
+            self.max_deleted_id = self.max_deleted_id.max(*id);
+        }
+        removed
+    }
+
+    /// Evicts the oldest entries, returning how many were removed.
+    pub fn trim(&mut self, trim: &Trim) -> usize {
+        let limit = trim.limit.unwrap_or(usize::MAX);
+        let mut removed = 0;
+        while removed < limit {
+            let Some(&id) = self.entries.keys().next() else {
+                break;
+            };
+            let evict = match trim.strategy {
+                TrimStrategy::MaxLen(max_len) => self.entries.len() > max_len,
+                TrimStrategy::MinId(min_id) => id < min_id,
+            };
+            if !evict {
+                break;
+            }
+            self.remove(&id);
+            removed += 1;
+        }
+        removed
+    }
+
+    pub fn range(
+        &self,
+        start: StreamId,
+        end: StreamId,
+        count: Option<usize>,
+        reverse: bool,
+    ) -> Vec<(StreamId, &StreamFields)> {
+        if start > end {
+            return vec![];
+        }
+        let range = self.entries.range(start..=end);
+        let count = count.unwrap_or(usize::MAX);
+        let entries: Box<dyn Iterator<Item = _>> = if reverse {
+            Box::new(range.rev())
+        } else {
+            Box::new(range)
+        };
+        entries
+            .take(count)
+            .map(|(id, fields)| (*id, fields))
+            .collect()
+    }
+
+    /// Entries with an ID strictly greater than `id`.
+    pub fn after(&self, id: StreamId, count: Option<usize>) -> Vec<(StreamId, &StreamFields)> {
+        self.entries
+            .range((Bound::Excluded(id), Bound::Unbounded))
+            .take(count.unwrap_or(usize::MAX))
+            .map(|(id, fields)| (*id, fields))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamCommand {
+    Add {
+        key: Vec<u8>,
+        id: IdSpec,
+        fields: StreamFields,
+        trim: Option<Trim>,
+        nomkstream: bool,
+    },
+    Range {
+        key: Vec<u8>,
+        start: StreamId,
+        end: StreamId,
+        count: Option<usize>,
+        reverse: bool,
+    },
+    Len(Vec<u8>),
+    Trim(Vec<u8>, Trim),
+    Del(Vec<u8>, Vec<StreamId>),
+    Read {
+        streams: ReadStreams,
+        count: Option<usize>,
+    },
+    Group(ConsumerGroupCommand),
+}
+
+pub fn stream_mut<'a, T: DataStore>(
+    store: &'a mut T,
+    key: &[u8],
+) -> Result<Option<&'a mut StreamValue>, AppError> {
+    match store.entry_mut(key) {
+        None => Ok(None),
+        Some(DataStoreEntry {
+            value: DataValue::Stream(stream),
+            ..
+        }) => Ok(Some(stream)),
+        Some(_) => Err(AppError::WrongType),
+    }
+}
+
+pub fn entry_reply(id: StreamId, fields: &StreamFields) -> Resp {
+    let fields = fields
+        .iter()
+        .flat_map(|(field, value)| {
+            [
+                Resp::BulkString(field.to_owned()),
+                Resp::BulkString(value.to_owned()),
+            ]
+        })
+        .collect();
+    Resp::Array(vec![id.reply(), Resp::Array(fields)])
+}
+
+pub fn entries_reply(entries: Vec<(StreamId, &StreamFields)>) -> Resp {
+    Resp::Array(
+        entries
+            .into_iter()
+            .map(|(id, fields)| entry_reply(id, fields))
+            .collect(),
+    )
+}
+
+/// Resolves `$` to the current last ID of each stream so that a blocked `XREAD`
+/// only wakes up for entries added after it was issued.
+pub fn resolve_last_ids<T: DataStore>(
+    store: &mut T,
+    streams: &mut [(Vec<u8>, ReadFrom)],
+) -> Result<(), AppError> {
+    for (key, from) in streams.iter_mut() {
+        if *from == ReadFrom::Last {
+            let last_id = stream_mut(store, key)?.map_or(StreamId::MIN, |stream| stream.last_id());
+            *from = ReadFrom::Id(last_id);
+        }
+    }
+    Ok(())
+}
+
+/// `XREAD` reply, `None` when none of the streams has new entries.
+pub fn read_streams<T: DataStore>(
+    store: &mut T,
+    streams: &[(Vec<u8>, ReadFrom)],
+    count: Option<usize>,
+) -> Result<Option<Resp>, AppError> {
+    let mut reply = vec![];
+    for (key, from) in streams {
+        let Some(stream) = stream_mut(store, key)? else {
+            continue;
+        };
+        let after = match from {
+            ReadFrom::Id(id) => *id,
+            ReadFrom::Last => stream.last_id(),
+        };
+        let entries = stream.after(after, count);
+        if !entries.is_empty() {
+            reply.push(Resp::Array(vec![
+                Resp::deserialize(key)?,
+                entries_reply(entries),
+            ]));
+        }
+    }
+    Ok((!reply.is_empty()).then_some(Resp::Array(reply)))
+}
+
+fn notify_add<T: DataStore>(store: &mut T, key: &[u8], trimmed: bool) {
+    store.notify(NotifyFlags::STREAM, "xadd", key);
+    if trimmed {
+        store.notify(NotifyFlags::STREAM, "xtrim", key);
+    }
+}
+
+pub fn apply_stream_command<T: DataStore>(
+    store: &mut T,
+    command: StreamCommand,
+) -> Result<Resp, AppError> {
+    match command {
+        StreamCommand::Add {
+            key,
+            id,
+            fields,
+            trim,
+            nomkstream,
+        } => {
+            if stream_mut(store, &key)?.is_none() {
+                if nomkstream {
+                    return Ok(Resp::null_bulk_string());
+                }
+                let mut stream = StreamValue::default();
+                let id = stream.add(id, fields)?;
+                let trimmed = trim.is_some_and(|trim| stream.trim(&trim) > 0);
+                let value = DataValue::Stream(stream);
+                store.insert_entry(key.clone(), DataStoreEntry::with_value(value, None));
+                notify_add(store, &key, trimmed);
+                return Ok(id.reply());
+            }
+            let stream = stream_mut(store, &key)?.expect("stream checked above");
+            let id = stream.add(id, fields)?;
+            let trimmed = trim.is_some_and(|trim| stream.trim(&trim) > 0);
+            store.signal_modified(&key);
+            notify_add(store, &key, trimmed);
+            Ok(id.reply())
+        }
+        StreamCommand::Range {
+            key,
+            start,
+            end,
+            count,
+            reverse,
+        } => {
+            let reply = stream_mut(store, &key)?
+                .map(|stream| entries_reply(stream.range(start, end, count, reverse)))
+                .unwrap_or(Resp::Array(vec![]));
+            Ok(reply)
+        }
+        StreamCommand::Len(key) => {
+            let len = stream_mut(store, &key)?.map_or(0, |stream| stream.len());
+            Ok(Resp::Integers(len as i64))
+        }
+        StreamCommand::Trim(key, trim) => {
+            let removed = stream_mut(store, &key)?.map_or(0, |stream| stream.trim(&trim));
+            if removed > 0 {
+                store.signal_modified(&key);
+                store.notify(NotifyFlags::STREAM, "xtrim", &key);
+            }
+            Ok(Resp::Integers(removed as i64))
+        }
+        StreamCommand::Del(key, ids) => {
+            let removed = stream_mut(store, &key)?.map_or(0, |stream| {
+                ids.iter().filter(|id| stream.remove(id)).count()
+            });
+            if removed > 0 {
+                store.signal_modified(&key);
+                store.notify(NotifyFlags::STREAM, "xdel", &key);
+            }
+            Ok(Resp::Integers(removed as i64))
+        }
+        StreamCommand::Read { streams, count } => {
+            Ok(read_streams(store, &streams, count)?.unwrap_or(Resp::NullArray))
+        }
+        StreamCommand::Group(command) => apply_consumer_group_command(store, command),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data_management::hash_table_store::HashTableDataStore;
+
+    fn fields(field: &str, value: &str) -> StreamFields {
+        vec![(field.as_bytes().to_vec(), value.as_bytes().to_vec())]
+    }
+
+    fn key() -> Vec<u8> {
+        Resp::bulk_string_from_str("events").serialize().unwrap()
+    }
+
+    fn add(store: &mut HashTableDataStore, id: IdSpec) -> Result<Resp, AppError> {
+        apply_stream_command(
+            store,
+            StreamCommand::Add {
+                key: key(),
+                id,
+                fields: fields("type", "click"),
+                trim: None,
+                nomkstream: false,
+            },
+        )
+    }
+
+    #[test]
+    fn should_generate_monotonic_ids() {
+        let mut stream = StreamValue::default();
+        assert_eq!(
+            stream.add(IdSpec::Partial(0), fields("a", "1")).unwrap(),
+            StreamId::new(0, 1)
+        );
+        assert_eq!(
+            stream.add(IdSpec::Partial(5), fields("a", "2")).unwrap(),
+            StreamId::new(5, 0)
+        );
+        assert_eq!(
+            stream.add(IdSpec::Partial(5), fields("a", "3")).unwrap(),
+            StreamId::new(5, 1)
+        );
+        let auto = stream.add(IdSpec::Auto, fields("a", "4")).unwrap();
+        assert!(auto > StreamId::new(5, 1));
+    }
+
+    #[test]
+    fn should_reject_ids_not_greater_than_top_item() {
+        let mut store = HashTableDataStore::default();
+        assert!(add(&mut store, IdSpec::Explicit(StreamId::new(2, 3))).is_ok());
+        let result = add(&mut store, IdSpec::Explicit(StreamId::new(2, 3)));
+        assert!(matches!(result, Err(AppError::StreamIdTooSmall)));
+        let result = add(&mut store, IdSpec::Partial(1));
+        assert!(matches!(result, Err(AppError::StreamIdTooSmall)));
+        let result = StreamValue::default().add(IdSpec::Explicit(StreamId::MIN), fields("a", "1"));
+        assert!(matches!(result, Err(AppError::StreamIdZero)));
+    }
+
+    #[test]
+    fn should_trim_by_max_len_and_min_id() {
+        let mut stream = StreamValue::default();
+        for ms in 1..=5 {
+            stream.add(IdSpec::Partial(ms), fields("n", "v")).unwrap();
+        }
+        let max_len = Trim {
+            strategy: TrimStrategy::MaxLen(3),
+            limit: None,
+        };
+        assert_eq!(stream.trim(&max_len), 2);
+        let min_id = Trim {
+            strategy: TrimStrategy::MinId(StreamId::new(5, 0)),
+            limit: Some(1),
+        };
+        assert_eq!(stream.trim(&min_id), 1);
+        assert_eq!(stream.first_entry().unwrap().0, &StreamId::new(4, 0));
+        assert_eq!(stream.max_deleted_id(), StreamId::new(3, 0));
+    }
+
+    #[test]
+    fn should_range_in_both_directions() {
+        let mut stream = StreamValue::default();
+        for ms in 1..=4 {
+            stream.add(IdSpec::Partial(ms), fields("n", "v")).unwrap();
+        }
+        let ids = |entries: Vec<(StreamId, &StreamFields)>| -> Vec<u64> {
+            entries.into_iter().map(|(id, _)| id.ms).collect()
+        };
+        let start = StreamId::new(2, 0);
+        assert_eq!(
+            ids(stream.range(start, StreamId::MAX, None, false)),
+            [2, 3, 4]
+        );
+        assert_eq!(
+            ids(stream.range(start, StreamId::MAX, Some(2), true)),
+            [4, 3]
+        );
+        assert_eq!(ids(stream.after(start, None)), [3, 4]);
+    }
+
+    #[test]
+    fn should_read_only_streams_with_new_entries() {
+        let mut store = HashTableDataStore::default();
+        add(&mut store, IdSpec::Explicit(StreamId::new(1, 0))).unwrap();
+        let mut streams = vec![(key(), ReadFrom::Last)];
+        resolve_last_ids(&mut store, &mut streams).unwrap();
+        assert_eq!(streams[0].1, ReadFrom::Id(StreamId::new(1, 0)));
+        assert!(read_streams(&mut store, &streams, None).unwrap().is_none());
+        add(&mut store, IdSpec::Explicit(StreamId::new(2, 0))).unwrap();
+        let reply = read_streams(&mut store, &streams, None).unwrap().unwrap();
+        let Resp::Array(streams) = reply else {
+            panic!()
+        };
+        assert_eq!(streams.len(), 1);
+    }
+}
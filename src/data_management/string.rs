@@ -0,0 +1,300 @@
+use std::time::SystemTime;
+
+use crate::{errors::AppError, helpers::number::parse_float, resp::Resp};
+
+use super::{
+    datastore::{DataStore, DataStoreEntry, DataValue},
+    notification::NotifyFlags,
+};
+
+/// Largest string value accepted, like Redis' default `proto-max-bulk-len`.
+const MAX_STRING_LENGTH: usize = 512 * 1024 * 1024;
+
+/// Expiry change requested by `GETEX`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpiryUpdate {
+    At(SystemTime),
+    Persist,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StringCommand {
+    IncrBy(Vec<u8>, i64),
+    IncrByFloat(Vec<u8>, f64),
+    Append(Vec<u8>, Vec<u8>),
+    StrLen(Vec<u8>),
+    GetRange {
+        key: Vec<u8>,
+        start: i64,
+        end: i64,
+    },
+    SetRange {
+        key: Vec<u8>,
+        offset: usize,
+        value: Vec<u8>,
+    },
+    GetDel(Vec<u8>),
+    GetEx {
+        key: Vec<u8>,
+        update: Option<ExpiryUpdate>,
+    },
+}
+
+/// Raw bytes of the string stored at `key`, values being kept serialized as bulk strings.
+fn string_value<T: DataStore>(store: &mut T, key: &[u8]) -> Result<Option<Vec<u8>>, AppError> {
+    match store.entry_mut(key) {
+        None => Ok(None),
+        Some(DataStoreEntry {
+            value: DataValue::String(data),
+            ..
+        }) => match Resp::deserialize(data)? {
+            Resp::BulkString(bytes) => Ok(Some(bytes)),
+            _ => Err(AppError::WrongType),
+        },
+        Some(_) => Err(AppError::WrongType),
+    }
+}
+
+/// Overwrites the string stored at `key`, keeping its TTL like every in place edit does.
+fn set_string<T: DataStore>(store: &mut T, key: &[u8], bytes: Vec<u8>) -> Result<(), AppError> {
+    let data = Resp::BulkString(bytes).serialize()?;
+    match store.entry_mut(key) {
+        Some(entry) => {
+            entry.value = DataValue::String(data);
+            entry.bump_version();
+        }
+        None => store.insert(key.to_owned(), data, None),
+    }
+    Ok(())
+}
+
+fn bulk(value: Option<Vec<u8>>) -> Resp {
+    value.map_or(Resp::null_bulk_string(), Resp::BulkString)
+}
+
+pub fn apply_string_command<T: DataStore>(
+    store: &mut T,
+    command: StringCommand,
+) -> Result<Resp, AppError> {
+    match command {
+        StringCommand::IncrBy(key, increment) => {
+            let current = match string_value(store, &key)? {
+                Some(value) => std::str::from_utf8(&value)
+                    .ok()
+                    .and_then(|value| value.parse::<i64>().ok())
+                    .ok_or(AppError::NotAnInteger)?,
+                None => 0,
+            };
+            let new = current.checked_add(increment).ok_or(AppError::Overflow)?;
+            set_string(store, &key, new.to_string().into_bytes())?;
+            store.notify(NotifyFlags::STRING, "incrby", &key);
+            Ok(Resp::Integers(new))
+        }
+        StringCommand::IncrByFloat(key, increment) => {
+            let current = match string_value(store, &key)? {
+                Some(value) => parse_float(&value).ok_or(AppError::NotAFloat)?,
+                None => 0.0,
+            };
+            let new = current + increment;
+            if !new.is_finite() {
+                return Err(AppError::NanOrInfinity);
+            }
+            let new = new.to_string().into_bytes();
+            set_string(store, &key, new.clone())?;
+            store.notify(NotifyFlags::STRING, "incrbyfloat", &key);
+            Ok(Resp::BulkString(new))
+        }
+        StringCommand::Append(key, suffix) => {
+            let mut value = string_value(store, &key)?.unwrap_or_default();
+            if value.len() + suffix.len() > MAX_STRING_LENGTH {
+                return Err(AppError::StringTooLong);
+            }
+            value.extend(suffix);
+            let len = value.len();
+            set_string(store, &key, value)?;
+            store.notify(NotifyFlags::STRING, "append", &key);
+            Ok(Resp::Integers(len as i64))
+        }
+        StringCommand::StrLen(key) => {
+            let len = string_value(store, &key)?.map_or(0, |value| value.len());
+            Ok(Resp::Integers(len as i64))
+        }
+        StringCommand::GetRange { key, start, end } => {
+            let value = string_value(store, &key)?.unwrap_or_default();
+            let len = value.len() as i64;
+            if start < 0 && end < 0 && start > end {
+                return Ok(Resp::BulkString(vec![]));
+            }
+            let start = if start < 0 { len + start } else { start }.max(0);
+            let end = if end < 0 { len + end } else { end }.max(0).min(len - 1);
+            if len == 0 || start > end {
+                return Ok(Resp::BulkString(vec![]));
+            }
+            Ok(Resp::BulkString(
+                value[start as usize..=end as usize].to_vec(),
+            ))
+        }
+        StringCommand::SetRange { key, offset, value } => {
+            let current = string_value(store, &key)?;
+            if value.is_empty() {
+                return Ok(Resp::Integers(current.map_or(0, |value| value.len()) as i64));
+            }
+            if offset + value.len() > MAX_STRING_LENGTH {
+                return Err(AppError::StringTooLong);
+            }
+            let mut current = current.unwrap_or_default();
+            if current.len() < offset + value.len() {
+                current.resize(offset + value.len(), 0);
+            }
+            current[offset..offset + value.len()].copy_from_slice(&value);
+            let len = current.len();
+            set_string(store, &key, current)?;
+            store.notify(NotifyFlags::STRING, "setrange", &key);
+            Ok(Resp::Integers(len as i64))
+        }
+        StringCommand::GetDel(key) => {
+            let value = string_value(store, &key)?;
+            if value.is_some() {
+                store.remove(&key);
+                store.notify(NotifyFlags::GENERIC, "del", &key);
+            }
+            Ok(bulk(value))
+        }
+        StringCommand::GetEx { key, update } => {
+            let value = string_value(store, &key)?;
+            let event = match (update, store.entry_mut(&key)) {
+                (Some(ExpiryUpdate::At(at)), Some(_)) if at <= SystemTime::now() => {
+                    store.remove(&key);
+                    Some("del")
+                }
+                (Some(ExpiryUpdate::At(at)), Some(entry)) => {
+                    entry.set_expiry(Some(at));
+                    entry.bump_version();
+                    store.signal_volatile(&key);
+                    Some("expire")
+                }
+                (Some(ExpiryUpdate::Persist), Some(entry)) => {
+                    entry.set_expiry(None);
+                    entry.bump_version();
+                    Some("persist")
+                }
+                _ => None,
+            };
+            if let Some(event) = event {
+                store.notify(NotifyFlags::GENERIC, event, &key);
+            }
+            Ok(bulk(value))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::data_management::hash_table_store::HashTableDataStore;
+
+    fn key() -> Vec<u8> {
+        Resp::bulk_string_from_str("counter").serialize().unwrap()
+    }
+
+    fn store_with(value: &str, expiry: Option<Duration>) -> HashTableDataStore {
+        let mut store = HashTableDataStore::default();
+        let data = Resp::bulk_string_from_str(value).serialize().unwrap();
+        store.insert(key(), data, expiry);
+        store
+    }
+
+    #[test]
+    fn should_increment_and_keep_ttl() {
+        let mut store = store_with("41", Some(Duration::from_secs(100)));
+        let result = apply_string_command(&mut store, StringCommand::IncrBy(key(), 1));
+        assert_eq!(result.unwrap(), Resp::Integers(42));
+        assert!(store.entry_mut(&key()).unwrap().expiry().is_some());
+        let result = apply_string_command(&mut store, StringCommand::IncrByFloat(key(), 0.5));
+        assert_eq!(result.unwrap(), Resp::bulk_string_from_str("42.5"));
+    }
+
+    #[test]
+    fn should_reject_non_integer_and_overflowing_values() {
+        let mut store = store_with("9223372036854775807", None);
+        let result = apply_string_command(&mut store, StringCommand::IncrBy(key(), 1));
+        assert!(matches!(result, Err(AppError::Overflow)));
+        let mut store = store_with("12a", None);
+        let result = apply_string_command(&mut store, StringCommand::IncrBy(key(), 1));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "ERR value is not an integer or out of range"
+        );
+    }
+
+    #[test]
+    fn should_get_range_with_negative_indexes() {
+        let mut store = store_with("This is a string", None);
+        let range = |start, end| StringCommand::GetRange {
+            key: key(),
+            start,
+            end,
+        };
+        let result = apply_string_command(&mut store, range(-3, -1));
+        assert_eq!(result.unwrap(), Resp::bulk_string_from_str("ing"));
+        let result = apply_string_command(&mut store, range(10, 100));
+        assert_eq!(result.unwrap(), Resp::bulk_string_from_str("string"));
+        let result = apply_string_command(&mut store, range(5, 3));
+        assert_eq!(result.unwrap(), Resp::bulk_string_from_str(""));
+    }
+
+    #[test]
+    fn should_keep_crlfs_and_empty_values() {
+        let mut store = store_with("a\r\nb", None);
+        let command = StringCommand::Append(key(), b"x\r\ny".to_vec());
+        let result = apply_string_command(&mut store, command);
+        assert_eq!(result.unwrap(), Resp::Integers(8));
+        let value = string_value(&mut store, &key()).unwrap();
+        assert_eq!(value, Some(b"a\r\nbx\r\ny".to_vec()));
+
+        let mut store = store_with("", None);
+        let result = apply_string_command(&mut store, StringCommand::StrLen(key()));
+        assert_eq!(result.unwrap(), Resp::Integers(0));
+        let command = StringCommand::GetRange {
+            key: key(),
+            start: 5,
+            end: 10,
+        };
+        let result = apply_string_command(&mut store, command).unwrap();
+        assert_eq!(result.serialize().unwrap(), b"$0\r\n\r\n");
+    }
+
+    #[test]
+    fn should_pad_set_range_with_zero_bytes() {
+        let mut store = HashTableDataStore::default();
+        let command = StringCommand::SetRange {
+            key: key(),
+            offset: 2,
+            value: b"ab".to_vec(),
+        };
+        let result = apply_string_command(&mut store, command);
+        assert_eq!(result.unwrap(), Resp::Integers(4));
+        let value = string_value(&mut store, &key()).unwrap();
+        assert_eq!(value, Some(b"\0\0ab".to_vec()));
+    }
+
+    #[test]
+    fn should_update_expiry_on_getex() {
+        let mut store = store_with("v", Some(Duration::from_secs(100)));
+        let persist = StringCommand::GetEx {
+            key: key(),
+            update: Some(ExpiryUpdate::Persist),
+        };
+        let result = apply_string_command(&mut store, persist);
+        assert_eq!(result.unwrap(), Resp::bulk_string_from_str("v"));
+        assert!(store.entry_mut(&key()).unwrap().expiry().is_none());
+        let expire = StringCommand::GetEx {
+            key: key(),
+            update: Some(ExpiryUpdate::At(SystemTime::UNIX_EPOCH)),
+        };
+        apply_string_command(&mut store, expire).unwrap();
+        assert!(store.entry_mut(&key()).is_none());
+    }
+}
@@ -0,0 +1,348 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{errors::AppError, resp::Resp};
+
+use super::{
+    datastore::key_name,
+    hash::HashCommand,
+    keyspace::KeyspaceCommand,
+    message::DataRequest,
+    pubsub::{PubSubHub, PushSender},
+    sorted_set::SortedSetCommand,
+    stream::StreamCommand,
+    string::StringCommand,
+    unordered_set::UnorderedSetCommand,
+};
+
+/// Channel RESP2 clients get invalidations on, through the client they redirect to.
+pub const INVALIDATE_CHANNEL: &str = "__redis__:invalidate";
+
+/// Which reads are remembered outside of `BCAST` mode, `CLIENT CACHING` picking them in
+/// `OPTIN` and `OPTOUT` modes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TrackingMode {
+    #[default]
+    Always,
+    OptIn,
+    OptOut,
+}
+
+/// Options of `CLIENT TRACKING ON`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrackingOptions {
+    pub redirect: Option<u64>,
+    pub bcast: bool,
+    pub prefixes: Vec<Vec<u8>>,
+    pub mode: TrackingMode,
+    pub noloop: bool,
+}
+
+impl TrackingOptions {
+    /// Whether the keys read by the next command are remembered, given the `CLIENT
+    /// CACHING` answer sent before it.
+    pub fn remembers(&self, caching: Option<bool>) -> bool {
+        match self.mode {
+            _ if self.bcast => false,
+            TrackingMode::Always => true,
+            TrackingMode::OptIn => caching == Some(true),
+            TrackingMode::OptOut => caching != Some(false),
+        }
+    }
+}
+
+/// Carried by the requests of a tracking client, so that the worker remembers the keys
+/// they read and knows who wrote the keys it invalidates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrackingContext {
+    pub client: u64,
+    pub remember: bool,
+}
+
+#[derive(Debug)]
+pub enum TrackingCommand {
+    On {
+        client: PushSender,
+        /// Whether the client takes RESP3 pushes, otherwise it needs a redirection.
+        resp3: bool,
+        options: TrackingOptions,
+    },
+    Off(u64),
+}
+
+#[derive(Debug)]
+struct TrackingClient {
+    push: PushSender,
+    resp3: bool,
+    options: TrackingOptions,
+}
+
+/// Keys read by tracking clients, owned by the data worker. Like Redis' table it ignores
+/// logical databases, and a key is forgotten once its readers were told it changed.
+#[derive(Debug, Default)]
+pub struct TrackingTable {
+    clients: HashMap<u64, TrackingClient>,
+    keys: HashMap<Vec<u8>, HashSet<u64>>,
+}
+
+impl TrackingTable {
+    pub fn remember(&mut self, client: u64, keys: Vec<Vec<u8>>) {
+        if !self.clients.contains_key(&client) {
+            return;
+        }
+        for key in keys {
+            self.keys.entry(key).or_default().insert(client);
+        }
+    }
+
+    /// Tells the clients that read `keys`, or that follow prefixes of them, that they
+    /// changed, `origin` being the client whose command changed them.
+    pub fn invalidate(&mut self, hub: &PubSubHub, keys: &[Vec<u8>], origin: Option<u64>) {
+        if self.clients.is_empty() {
+            return;
+        }
+        let mut invalidated: HashMap<u64, Vec<Vec<u8>>> = HashMap::new();
+        for key in keys {
+            let readers = self.keys.remove(key).unwrap_or_default();
+            let name = key_name(key);
+            for (id, client) in &self.clients {
+                let follows = match client.options.bcast {
+                    true => {
+                        client.options.prefixes.is_empty()
+                            || client.options.prefixes.iter().any(|p| name.starts_with(p))
+                    }
+                    false => readers.contains(id),
+                };
+                let looped = client.options.noloop && origin == Some(*id);
+                if follows && !looped {
+                    let names = invalidated.entry(*id).or_default();
+                    if !names.contains(&name) {
+                        names.push(name.clone());
+                    }
+                }
+            }
+        }
+        for (id, names) in invalidated {
+            let names = Resp::Array(names.into_iter().map(Resp::BulkString).collect());
+            self.send(hub, id, names);
+        }
+    }
+
+    /// Invalidates everything after a flush, with a null key like Redis.
+    pub fn flush(&mut self, hub: &PubSubHub) {
+        self.keys.clear();
+        let ids: Vec<u64> = self.clients.keys().copied().collect();
+        for id in ids {
+            self.send(hub, id, Resp::NullArray);
+        }
+    }
+
+    fn send(&mut self, hub: &PubSubHub, id: u64, keys: Resp) {
+        let Some(client) = self.clients.get(&id) else {
+            return;
+        };
+        let delivered = match client.options.redirect {
+            Some(target) => match hub.subscriber(target) {
+                Some(target) => {
+                    target.push(Resp::Array(vec![
+                        Resp::bulk_string_from_str("message"),
+                        Resp::bulk_string_from_str(INVALIDATE_CHANNEL),
+                        keys,
+                    ]));
+                    !client.push.is_closed()
+                }
+                None if client.resp3 => client.push.push(Resp::Array(vec![
+                    Resp::bulk_string_from_str("tracking-redir-broken"),
+                    Resp::Integers(target as i64),
+                ])),
+                None => !client.push.is_closed(),
+            },
+            None if client.resp3 => client.push.push(Resp::Array(vec![
+                Resp::bulk_string_from_str("invalidate"),
+                keys,
+            ])),
+            // A RESP2 connection has no way to take them without redirecting.
+            None => !client.push.is_closed(),
+        };
+        if !delivered {
+            self.clients.remove(&id);
+        }
+    }
+}
+
+pub fn apply_tracking_command(
+    table: &mut TrackingTable,
+    hub: &PubSubHub,
+    command: TrackingCommand,
+) -> Result<Resp, AppError> {
+    match command {
+        TrackingCommand::On {
+            client,
+            resp3,
+            options,
+        } => {
+            if let Some(target) = options.redirect {
+                if target != client.id() && hub.subscriber(target).is_none() {
+                    return Err(AppError::Generic(
+                        "The client ID you want redirect to does not exist".to_owned(),
+                    ));
+                }
+            }
+            let id = client.id();
+            let client = TrackingClient {
+                push: client,
+                resp3,
+                options,
+            };
+            table.clients.insert(id, client);
+        }
+        TrackingCommand::Off(id) => {
+            table.clients.remove(&id);
+            table.keys.retain(|_, readers| {
+                readers.remove(&id);
+                !readers.is_empty()
+            });
+        }
+    }
+    Ok(Resp::simple_string_from_str("OK"))
+}
+
+/// Keys a request reads, remembered for the tracking client that sent it. Only read-only
+/// commands count, like in Redis.
+pub fn read_keys(request: &DataRequest) -> Vec<Vec<u8>> {
+    match request {
+        DataRequest::Get(message) => vec![message.key.clone()],
+        DataRequest::Keyspace(message) => match &message.command {
+            KeyspaceCommand::MGet(keys) | KeyspaceCommand::Exists(keys) => keys.clone(),
+            KeyspaceCommand::Dump(key) | KeyspaceCommand::Object(_, key) => vec![key.clone()],
+            _ => vec![],
+        },
+        DataRequest::String(message) => match &message.command {
+            StringCommand::StrLen(key) | StringCommand::GetRange { key, .. } => vec![key.clone()],
+            _ => vec![],
+        },
+        DataRequest::Hash(message) => match message.command {
+            HashCommand::Get(_)
+            | HashCommand::MGet(_)
+            | HashCommand::GetAll
+            | HashCommand::Keys
+            | HashCommand::Vals
+            | HashCommand::Len
+            | HashCommand::Exists(_)
+            | HashCommand::StrLen(_)
+            | HashCommand::Scan { .. }
+            | HashCommand::Ttl { .. } => vec![message.key.clone()],
+            _ => vec![],
+        },
+        DataRequest::UnorderedSet(message) => match &message.command {
+            UnorderedSetCommand::Members(key)
+            | UnorderedSetCommand::IsMember(key, _)
+            | UnorderedSetCommand::MIsMember(key, _)
+            | UnorderedSetCommand::Card(key)
+            | UnorderedSetCommand::RandMember(key, _)
+            | UnorderedSetCommand::Scan { key, .. } => vec![key.clone()],
+            UnorderedSetCommand::Combine(_, keys) | UnorderedSetCommand::InterCard { keys, .. } => {
+                keys.clone()
+            }
+            _ => vec![],
+        },
+        DataRequest::SortedSet(message) => match &message.command {
+            SortedSetCommand::Card(key)
+            | SortedSetCommand::Count(key, ..)
+            | SortedSetCommand::Score(key, _)
+            | SortedSetCommand::Rank { key, .. }
+            | SortedSetCommand::Range { key, .. }
+            | SortedSetCommand::Scan { key, .. } => vec![key.clone()],
+            _ => vec![],
+        },
+        DataRequest::Stream(message) => match &message.command {
+            StreamCommand::Range { key, .. } | StreamCommand::Len(key) => vec![key.clone()],
+            StreamCommand::Read { streams, .. } => {
+                streams.iter().map(|(key, _)| key.clone()).collect()
+            }
+            _ => vec![],
+        },
+        _ => vec![],
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        commands::test_helpers::key,
+        data_management::pubsub::{
+            apply_pubsub_command, push_channel, OutputBufferLimits, PubSubCommand, SubscriptionKind,
+        },
+        resp::Resp,
+    };
+
+    use super::*;
+
+    #[test]
+    fn should_invalidate_read_keys_once_through_redirection() {
+        let mut hub = PubSubHub::default();
+        let (target, mut target_pushes) = push_channel(1, OutputBufferLimits::default());
+        let subscribe = PubSubCommand::Subscribe {
+            kind: SubscriptionKind::Channel,
+            client: target,
+            names: vec![INVALIDATE_CHANNEL.as_bytes().to_vec()],
+        };
+        apply_pubsub_command(&mut hub, subscribe).unwrap();
+        target_pushes.try_recv().unwrap();
+
+        let mut table = TrackingTable::default();
+        let (client, _pushes) = push_channel(2, OutputBufferLimits::default());
+        let options = TrackingOptions {
+            redirect: Some(1),
+            ..Default::default()
+        };
+        let on = TrackingCommand::On {
+            client,
+            resp3: false,
+            options,
+        };
+        apply_tracking_command(&mut table, &hub, on).unwrap();
+        table.remember(2, vec![key("a"), key("b")]);
+
+        table.invalidate(&hub, &[key("a"), key("c")], None);
+        table.invalidate(&hub, &[key("a")], None);
+        assert_eq!(
+            target_pushes.try_recv().unwrap(),
+            Resp::Array(vec![
+                Resp::bulk_string_from_str("message"),
+                Resp::bulk_string_from_str(INVALIDATE_CHANNEL),
+                Resp::Array(vec![Resp::bulk_string_from_str("a")]),
+            ])
+        );
+        assert!(target_pushes.try_recv().is_none());
+    }
+
+    #[test]
+    fn should_broadcast_prefixes_to_resp3_clients_but_their_own_writes() {
+        let hub = PubSubHub::default();
+        let mut table = TrackingTable::default();
+        let (client, mut pushes) = push_channel(1, OutputBufferLimits::default());
+        let options = TrackingOptions {
+            bcast: true,
+            prefixes: vec![b"user:".to_vec()],
+            noloop: true,
+            ..Default::default()
+        };
+        let on = TrackingCommand::On {
+            client,
+            resp3: true,
+            options,
+        };
+        apply_tracking_command(&mut table, &hub, on).unwrap();
+
+        table.invalidate(&hub, &[key("user:1"), key("other")], None);
+        table.invalidate(&hub, &[key("user:2")], Some(1));
+        assert_eq!(
+            pushes.try_recv().unwrap(),
+            Resp::Array(vec![
+                Resp::bulk_string_from_str("invalidate"),
+                Resp::Array(vec![Resp::bulk_string_from_str("user:1")]),
+            ])
+        );
+        assert!(pushes.try_recv().is_none());
+    }
+}
@@ -0,0 +1,588 @@
+use std::collections::HashSet;
+
+use rand::seq::{IteratorRandom, SliceRandom};
+
+use crate::{errors::AppError, helpers::glob::glob_match, resp::Resp};
+
+use super::{
+    datastore::{DataStore, DataStoreEntry, DataValue},
+    dict::{scan_reply, Dict, ScanArgs},
+    notification::NotifyFlags,
+};
+
+/// Same default as Redis' `set-max-intset-entries`.
+const SET_MAX_INTSET_ENTRIES: usize = 512;
+
+/// Sets made only of integers are kept as a sorted `Vec<i64>` (Redis' intset) and
+/// converted to a hash table as soon as a non integer member is added or the set grows.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SetValue {
+    IntSet(Vec<i64>),
+    HashTable(Dict<Vec<u8>, ()>),
+}
+
+impl Default for SetValue {
+    fn default() -> Self {
+        Self::IntSet(vec![])
+    }
+}
+
+/// Only canonical integers (no sign prefix, no leading zeros) can live in an intset
+/// since the member has to be rebuilt byte for byte.
+fn as_int(member: &[u8]) -> Option<i64> {
+    let int = std::str::from_utf8(member).ok()?.parse::<i64>().ok()?;
+    (int.to_string().as_bytes() == member).then_some(int)
+}
+
+impl SetValue {
+    pub fn encoding(&self) -> &'static str {
+        match self {
+            Self::IntSet(_) => "intset",
+            Self::HashTable(_) => "hashtable",
+        }
+    }
+
+    fn convert_to_hash_table(&mut self) {
+        if let Self::IntSet(ints) = self {
+            let members = ints
+                .iter()
+                .map(|int| (int.to_string().into_bytes(), ()))
+                .collect();
+            *self = Self::HashTable(members);
+        }
+    }
+
+    /// Returns true when `member` was not already part of the set.
+    pub fn insert(&mut self, member: Vec<u8>) -> bool {
+        if let Self::IntSet(ints) = self {
+            match as_int(&member) {
+                Some(int) => match ints.binary_search(&int) {
+                    Ok(_) => return false,
+                    Err(position) if ints.len() < SET_MAX_INTSET_ENTRIES => {
+                        ints.insert(position, int);
+                        return true;
+                    }
+                    Err(_) => self.convert_to_hash_table(),
+                },
+                None => self.convert_to_hash_table(),
+            }
+        }
+        match self {
+            Self::HashTable(members) => members.insert(member, ()).is_none(),
+            Self::IntSet(_) => unreachable!("intset converted above"),
+        }
+    }
+
+    pub fn contains(&self, member: &[u8]) -> bool {
+        match self {
+            Self::IntSet(ints) => {
+                as_int(member).is_some_and(|int| ints.binary_search(&int).is_ok())
+            }
+            Self::HashTable(members) => members.contains_key(member),
+        }
+    }
+
+    pub fn remove(&mut self, member: &[u8]) -> bool {
+        match self {
+            Self::IntSet(ints) => match as_int(member).map(|int| ints.binary_search(&int)) {
+                Some(Ok(position)) => {
+                    ints.remove(position);
+                    true
+                }
+                _ => false,
+            },
+            Self::HashTable(members) => members.remove(member).is_some(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Self::IntSet(ints) => ints.len(),
+            Self::HashTable(members) => members.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn members(&self) -> Vec<Vec<u8>> {
+        match self {
+            Self::IntSet(ints) => ints
+                .iter()
+                .map(|int| int.to_string().into_bytes())
+                .collect(),
+            Self::HashTable(members) => members.keys().cloned().collect(),
+        }
+    }
+
+    /// Intsets are small enough to be returned in a single call, like Redis does.
+    pub fn scan(&self, cursor: u64, count: usize) -> (u64, Vec<Vec<u8>>) {
+        match self {
+            Self::IntSet(_) => (0, self.members()),
+            Self::HashTable(members) => {
+                let (cursor, members) = members.scan(cursor, count);
+                let members = members.into_iter().map(|(member, _)| member.clone());
+                (cursor, members.collect())
+            }
+        }
+    }
+}
+
+impl<I> From<I> for SetValue
+where
+    I: IntoIterator<Item = Vec<u8>>,
+{
+    fn from(value: I) -> Self {
+        let mut set = Self::default();
+        for member in value {
+            set.insert(member);
+        }
+        set
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetOperation {
+    Inter,
+    Union,
+    Diff,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnorderedSetCommand {
+    Add(Vec<u8>, Vec<Vec<u8>>),
+    Rem(Vec<u8>, Vec<Vec<u8>>),
+    Members(Vec<u8>),
+    IsMember(Vec<u8>, Vec<u8>),
+    MIsMember(Vec<u8>, Vec<Vec<u8>>),
+    Card(Vec<u8>),
+    Combine(SetOperation, Vec<Vec<u8>>),
+    Store {
+        operation: SetOperation,
+        destination: Vec<u8>,
+        keys: Vec<Vec<u8>>,
+    },
+    InterCard {
+        keys: Vec<Vec<u8>>,
+        limit: usize,
+    },
+    RandMember(Vec<u8>, Option<i64>),
+    Pop(Vec<u8>, Option<usize>),
+    Move {
+        source: Vec<u8>,
+        destination: Vec<u8>,
+        member: Vec<u8>,
+    },
+    Scan {
+        key: Vec<u8>,
+        scan: ScanArgs,
+    },
+}
+
+fn set_mut<'a, T: DataStore>(
+    store: &'a mut T,
+    key: &[u8],
+) -> Result<Option<&'a mut SetValue>, AppError> {
+    match store.entry_mut(key) {
+        None => Ok(None),
+        Some(DataStoreEntry {
+            value: DataValue::Set(set),
+            ..
+        }) => Ok(Some(set)),
+        Some(_) => Err(AppError::WrongType),
+    }
+}
+
+fn set_or_create<'a, T: DataStore>(
+    store: &'a mut T,
+    key: &[u8],
+) -> Result<&'a mut SetValue, AppError> {
+    if set_mut(store, key)?.is_none() {
+        let entry = DataStoreEntry::with_value(DataValue::Set(SetValue::default()), None);
+        store.insert_entry(key.to_owned(), entry);
+    }
+    Ok(set_mut(store, key)?.expect("set inserted above"))
+}
+
+fn remove_if_empty<T: DataStore>(store: &mut T, key: &[u8]) {
+    if let Ok(Some(set)) = set_mut(store, key) {
+        if set.is_empty() {
+            store.remove(key);
+            store.notify(NotifyFlags::GENERIC, "del", key);
+        }
+    }
+}
+
+fn combine<T: DataStore>(
+    store: &mut T,
+    operation: SetOperation,
+    keys: &[Vec<u8>],
+) -> Result<HashSet<Vec<u8>>, AppError> {
+    let mut sets = Vec::with_capacity(keys.len());
+    for key in keys {
+        let members: HashSet<Vec<u8>> = set_mut(store, key)?
+            .map(|set| set.members().into_iter().collect())
+            .unwrap_or_default();
+        sets.push(members);
+    }
+    let mut sets = sets.into_iter();
+    let first = sets.next().unwrap_or_default();
+    Ok(sets.fold(first, |acc, set| match operation {
+        SetOperation::Inter => acc.intersection(&set).cloned().collect(),
+        SetOperation::Union => acc.union(&set).cloned().collect(),
+        SetOperation::Diff => acc.difference(&set).cloned().collect(),
+    }))
+}
+
+fn members_reply(members: impl IntoIterator<Item = Vec<u8>>) -> Resp {
+    Resp::Array(members.into_iter().map(Resp::BulkString).collect())
+}
+
+pub fn apply_unordered_set_command<T: DataStore>(
+    store: &mut T,
+    command: UnorderedSetCommand,
+) -> Result<Resp, AppError> {
+    match command {
+        UnorderedSetCommand::Add(key, members) => {
+            let set = set_or_create(store, &key)?;
+            let added = members
+                .into_iter()
+                .filter(|member| set.insert(member.to_owned()))
+                .count();
+            if added > 0 {
+                store.signal_modified(&key);
+                store.notify(NotifyFlags::SET, "sadd", &key);
+            }
+            Ok(Resp::Integers(added as i64))
+        }
+        UnorderedSetCommand::Rem(key, members) => {
+            let Some(set) = set_mut(store, &key)? else {
+                return Ok(Resp::Integers(0));
+            };
+            let removed = members.iter().filter(|member| set.remove(member)).count();
+            if removed > 0 {
+                store.signal_modified(&key);
+                store.notify(NotifyFlags::SET, "srem", &key);
+            }
+            remove_if_empty(store, &key);
+            Ok(Resp::Integers(removed as i64))
+        }
+        UnorderedSetCommand::Members(key) => {
+            let members = set_mut(store, &key)?
+                .map(|set| set.members())
+                .unwrap_or_default();
+            Ok(members_reply(members))
+        }
+        UnorderedSetCommand::IsMember(key, member) => {
+            let found = set_mut(store, &key)?.is_some_and(|set| set.contains(&member));
+            Ok(Resp::Integers(found as i64))
+        }
+        UnorderedSetCommand::MIsMember(key, members) => {
+            let set = set_mut(store, &key)?;
+            let found = members
+                .iter()
+                .map(|member| {
+                    let found = set.as_ref().is_some_and(|set| set.contains(member));
+                    Resp::Integers(found as i64)
+                })
+                .collect();
+            Ok(Resp::Array(found))
+        }
+        UnorderedSetCommand::Card(key) => {
+            let len = set_mut(store, &key)?.map(|set| set.len()).unwrap_or(0);
+            Ok(Resp::Integers(len as i64))
+        }
+        UnorderedSetCommand::Combine(operation, keys) => {
+            Ok(members_reply(combine(store, operation, &keys)?))
+        }
+        UnorderedSetCommand::Store {
+            operation,
+            destination,
+            keys,
+        } => {
+            let result = combine(store, operation, &keys)?;
+            let len = result.len();
+            let existed = store.remove(&destination).is_some();
+            if len > 0 {
+                let entry = DataStoreEntry::with_value(DataValue::Set(result.into()), None);
+                store.insert_entry(destination.clone(), entry);
+                let event = match operation {
+                    SetOperation::Inter => "sinterstore",
+                    SetOperation::Union => "sunionstore",
+                    SetOperation::Diff => "sdiffstore",
+                };
+                store.notify(NotifyFlags::SET, event, &destination);
+            } else if existed {
+                store.notify(NotifyFlags::GENERIC, "del", &destination);
+            }
+            Ok(Resp::Integers(len as i64))
+        }
+        UnorderedSetCommand::InterCard { keys, limit } => {
+            let len = combine(store, SetOperation::Inter, &keys)?.len();
+            let len = if limit > 0 { len.min(limit) } else { len };
+            Ok(Resp::Integers(len as i64))
+        }
+        UnorderedSetCommand::RandMember(key, count) => {
+            let members = set_mut(store, &key)?
+                .map(|set| set.members())
+                .unwrap_or_default();
+            let mut rng = rand::thread_rng();
+            match count {
+                None => Ok(members
+                    .choose(&mut rng)
+                    .map(|member| Resp::BulkString(member.to_owned()))
+                    .unwrap_or(Resp::null_bulk_string())),
+                Some(count) if count >= 0 => Ok(members_reply(
+                    members
+                        .into_iter()
+                        .choose_multiple(&mut rng, count as usize),
+                )),
+                Some(_) if members.is_empty() => Ok(Resp::Array(vec![])),
+                Some(count) => Ok(members_reply(
+                    (0..count.unsigned_abs())
+                        .filter_map(|_| members.choose(&mut rng).cloned())
+                        .collect::<Vec<_>>(),
+                )),
+            }
+        }
+        UnorderedSetCommand::Pop(key, count) => {
+            let Some(set) = set_mut(store, &key)? else {
+                return Ok(match count {
+                    Some(_) => Resp::Array(vec![]),
+                    None => Resp::null_bulk_string(),
+                });
+            };
+            let popped = set
+                .members()
+                .into_iter()
+                .choose_multiple(&mut rand::thread_rng(), count.unwrap_or(1));
+            for member in &popped {
+                set.remove(member);
+            }
+            if !popped.is_empty() {
+                store.signal_modified(&key);
+                store.notify(NotifyFlags::SET, "spop", &key);
+            }
+            remove_if_empty(store, &key);
+            Ok(match count {
+                Some(_) => members_reply(popped),
+                None => popped
+                    .into_iter()
+                    .next()
+                    .map(Resp::BulkString)
+                    .unwrap_or(Resp::null_bulk_string()),
+            })
+        }
+        UnorderedSetCommand::Move {
+            source,
+            destination,
+            member,
+        } => {
+            set_mut(store, &destination)?;
+            let moved = match set_mut(store, &source)? {
+                Some(set) => set.remove(&member),
+                None => false,
+            };
+            if moved {
+                store.signal_modified(&source);
+                store.notify(NotifyFlags::SET, "srem", &source);
+                remove_if_empty(store, &source);
+                if set_or_create(store, &destination)?.insert(member) {
+                    store.signal_modified(&destination);
+                    store.notify(NotifyFlags::SET, "sadd", &destination);
+                }
+            }
+            Ok(Resp::Integers(moved as i64))
+        }
+        UnorderedSetCommand::Scan { key, scan } => {
+            let (cursor, members) = set_mut(store, &key)?
+                .map(|set| set.scan(scan.cursor, scan.count))
+                .unwrap_or_default();
+            let members = members
+                .into_iter()
+                .filter(|member| {
+                    scan.pattern
+                        .as_ref()
+                        .is_none_or(|pattern| glob_match(pattern, member, false))
+                })
+                .map(Resp::BulkString)
+                .collect();
+            Ok(scan_reply(cursor, members))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data_management::hash_table_store::HashTableDataStore;
+
+    fn members(members: &[&str]) -> Vec<Vec<u8>> {
+        members
+            .iter()
+            .map(|member| member.as_bytes().to_vec())
+            .collect()
+    }
+
+    fn sorted(reply: Resp) -> Vec<Resp> {
+        match reply {
+            Resp::Array(mut items) => {
+                items.sort_by_key(|item| format!("{item:?}"));
+                items
+            }
+            other => panic!("expected array got {other:?}"),
+        }
+    }
+
+    fn store_with_sets() -> HashTableDataStore {
+        let mut store = HashTableDataStore::default();
+        let add =
+            |key: &[u8], values: &[&str]| UnorderedSetCommand::Add(key.to_vec(), members(values));
+        apply_unordered_set_command(&mut store, add(b"a", &["1", "2", "3"])).unwrap();
+        apply_unordered_set_command(&mut store, add(b"b", &["2", "3", "x"])).unwrap();
+        store
+    }
+
+    #[test]
+    fn should_keep_small_integer_sets_as_intset() {
+        let mut set = SetValue::from(members(&["3", "1", "2"]));
+        assert_eq!(set, SetValue::IntSet(vec![1, 2, 3]));
+        set.insert(b"01".to_vec());
+        assert_eq!(set.encoding(), "hashtable");
+        assert!(set.contains(b"01") && set.contains(b"2"));
+    }
+
+    #[test]
+    fn should_convert_intset_when_growing_past_the_limit() {
+        let set = SetValue::from((0..=SET_MAX_INTSET_ENTRIES).map(|i| i.to_string().into_bytes()));
+        assert_eq!(set.encoding(), "hashtable");
+        assert_eq!(set.len(), SET_MAX_INTSET_ENTRIES + 1);
+    }
+
+    #[test]
+    fn should_add_and_remove_members() {
+        let mut store = store_with_sets();
+        let result = apply_unordered_set_command(
+            &mut store,
+            UnorderedSetCommand::Add(b"a".to_vec(), members(&["3", "4"])),
+        );
+        assert_eq!(result.unwrap(), Resp::Integers(1));
+        let result = apply_unordered_set_command(
+            &mut store,
+            UnorderedSetCommand::Rem(b"b".to_vec(), members(&["2", "3", "x"])),
+        );
+        assert_eq!(result.unwrap(), Resp::Integers(3));
+        assert!(store.entry_mut(b"b").is_none());
+    }
+
+    #[test]
+    fn should_combine_sets() {
+        let mut store = store_with_sets();
+        let keys = vec![b"a".to_vec(), b"b".to_vec()];
+        let inter = apply_unordered_set_command(
+            &mut store,
+            UnorderedSetCommand::Combine(SetOperation::Inter, keys.clone()),
+        );
+        assert_eq!(
+            sorted(inter.unwrap()),
+            vec![
+                Resp::bulk_string_from_str("2"),
+                Resp::bulk_string_from_str("3")
+            ]
+        );
+        let diff = apply_unordered_set_command(
+            &mut store,
+            UnorderedSetCommand::Combine(SetOperation::Diff, keys.clone()),
+        );
+        assert_eq!(sorted(diff.unwrap()), vec![Resp::bulk_string_from_str("1")]);
+        let union = apply_unordered_set_command(
+            &mut store,
+            UnorderedSetCommand::InterCard { keys, limit: 1 },
+        );
+        assert_eq!(union.unwrap(), Resp::Integers(1));
+    }
+
+    #[test]
+    fn should_store_combination_result() {
+        let mut store = store_with_sets();
+        store.insert(b"dest".to_vec(), b"string".to_vec(), None);
+        let result = apply_unordered_set_command(
+            &mut store,
+            UnorderedSetCommand::Store {
+                operation: SetOperation::Union,
+                destination: b"dest".to_vec(),
+                keys: vec![b"a".to_vec(), b"b".to_vec()],
+            },
+        );
+        assert_eq!(result.unwrap(), Resp::Integers(4));
+        let card =
+            apply_unordered_set_command(&mut store, UnorderedSetCommand::Card(b"dest".to_vec()));
+        assert_eq!(card.unwrap(), Resp::Integers(4));
+    }
+
+    #[test]
+    fn should_return_repeated_members_for_negative_count() {
+        let mut store = HashTableDataStore::default();
+        apply_unordered_set_command(
+            &mut store,
+            UnorderedSetCommand::Add(b"a".to_vec(), members(&["x"])),
+        )
+        .unwrap();
+        let result = apply_unordered_set_command(
+            &mut store,
+            UnorderedSetCommand::RandMember(b"a".to_vec(), Some(-3)),
+        );
+        assert_eq!(
+            result.unwrap(),
+            Resp::Array(vec![Resp::bulk_string_from_str("x"); 3])
+        );
+    }
+
+    #[test]
+    fn should_pop_members() {
+        let mut store = store_with_sets();
+        let result = apply_unordered_set_command(
+            &mut store,
+            UnorderedSetCommand::Pop(b"a".to_vec(), Some(5)),
+        );
+        assert_eq!(sorted(result.unwrap()).len(), 3);
+        assert!(store.entry_mut(b"a").is_none());
+    }
+
+    #[test]
+    fn should_move_member() {
+        let mut store = store_with_sets();
+        let result = apply_unordered_set_command(
+            &mut store,
+            UnorderedSetCommand::Move {
+                source: b"b".to_vec(),
+                destination: b"c".to_vec(),
+                member: b"x".to_vec(),
+            },
+        );
+        assert_eq!(result.unwrap(), Resp::Integers(1));
+        let result = apply_unordered_set_command(
+            &mut store,
+            UnorderedSetCommand::IsMember(b"c".to_vec(), b"x".to_vec()),
+        );
+        assert_eq!(result.unwrap(), Resp::Integers(1));
+    }
+
+    #[test]
+    fn should_reply_wrong_type_for_non_set_keys() {
+        let mut store = store_with_sets();
+        store.insert(b"string".to_vec(), b"value".to_vec(), None);
+        let result = apply_unordered_set_command(
+            &mut store,
+            UnorderedSetCommand::Combine(
+                SetOperation::Union,
+                vec![b"a".to_vec(), b"string".to_vec()],
+            ),
+        );
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            AppError::WrongType.to_string()
+        );
+    }
+}
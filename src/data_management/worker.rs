@@ -1,12 +1,46 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    collections::VecDeque,
+    future::Future,
+    path::Path,
+    pin::pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
 
-use tokio::{sync::mpsc, task::JoinHandle};
+use futures::task::noop_waker_ref;
+use tokio::{
+    runtime::{Handle, RuntimeFlavor},
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+    time::Instant,
+};
 
-use crate::{errors::AppError, resp::Resp};
+use crate::{
+    commands::{command_registry::CommandRegistry, session::Session},
+    errors::AppError,
+    resp::Resp,
+};
 
 use super::{
-    datastore::DataStore,
-    message::{DataChannelMessage, ResponseChannelMessage},
+    blocking::BlockedClient,
+    database::{apply_database_command, DatabaseCommand},
+    datastore::{DataStore, DataStoreEntry, DataValue},
+    function::{FunctionCommand, FunctionLibraries},
+    hash::apply_hash_command,
+    keyspace::apply_keyspace_command,
+    message::{
+        DataChannelMessage, DataRequest, ExecMessage, ResponseChannelMessage, ShutdownMessage,
+    },
+    notification::{self, NotifyFlags},
+    pubsub::{apply_pubsub_command, PubSubHub},
+    rdb,
+    scripting::{Script, ScriptCommand, ScriptEngine, ScriptMonitor},
+    sorted_set::apply_sorted_set_command,
+    stream::apply_stream_command,
+    string::apply_string_command,
+    tracking::{apply_tracking_command, read_keys, TrackingTable},
+    unordered_set::apply_unordered_set_command,
 };
 
 #[derive(Debug)]
@@ -14,86 +48,537 @@ pub struct DataManager<T>
 where
     T: DataStore,
 {
-    data_store: T,
+    databases: Vec<T>,
     data_receiver: mpsc::Receiver<DataChannelMessage>,
     cleanup_intervall: Arc<Duration>,
+    blocked: VecDeque<BlockedClient>,
+    pubsub: PubSubHub,
+    tracking: TrackingTable,
+    notify_flags: NotifyFlags,
+    /// Commands scripts send through `redis.call`, absent until `with_commands`.
+    commands: Option<Arc<CommandRegistry>>,
+    /// Only taken out while a script runs, its commands needing the rest of the worker.
+    scripts: Option<ScriptEngine>,
+    /// Taken out while a function runs, like `scripts`.
+    functions: Option<FunctionLibraries>,
+    /// Running script, shared with the connections replying `-BUSY` meanwhile.
+    script_monitor: Arc<ScriptMonitor>,
+    /// Changes made to the dataset, telling whether a script wrote.
+    dirty: u64,
 }
 
+/// Commands scripts may not send, Redis' `noscript` ones.
+const SCRIPT_DENIED_COMMANDS: [&str; 20] = [
+    "MULTI",
+    "EXEC",
+    "DISCARD",
+    "WATCH",
+    "UNWATCH",
+    "SUBSCRIBE",
+    "UNSUBSCRIBE",
+    "PSUBSCRIBE",
+    "PUNSUBSCRIBE",
+    "SSUBSCRIBE",
+    "SUNSUBSCRIBE",
+    "EVAL",
+    "EVALSHA",
+    "SCRIPT",
+    "FCALL",
+    "FCALL_RO",
+    "FUNCTION",
+    "CLIENT",
+    "HELLO",
+    "SHUTDOWN",
+];
+
 impl<T> DataManager<T>
 where
     T: DataStore,
 {
+    /// `databases` holds every logical database, messages addressing them by index, and
+    /// `notify_flags` selects the keyspace events published to subscribers.
     pub fn new(
         data_receiver: mpsc::Receiver<DataChannelMessage>,
-        data_store: Option<T>,
+        mut databases: Vec<T>,
         cleanup_intervall: Option<Duration>,
+        notify_flags: NotifyFlags,
     ) -> Self {
+        // Loading a dataset is not something subscribers are told about.
+        databases.iter_mut().for_each(|store| {
+            store.take_events();
+        });
         Self {
-            data_store: data_store.unwrap_or_default(),
+            databases,
             data_receiver,
             cleanup_intervall: match cleanup_intervall {
                 Some(duration) => duration,
                 None => Duration::from_secs(60 * 60),
             }
             .into(),
+            blocked: VecDeque::new(),
+            pubsub: PubSubHub::default(),
+            tracking: TrackingTable::default(),
+            notify_flags,
+            commands: None,
+            scripts: Some(ScriptEngine::new()),
+            functions: Some(FunctionLibraries::new()),
+            script_monitor: Arc::default(),
+            dirty: 0,
         }
     }
 
+    /// Lets scripts run `commands`, the registry the connections use.
+    pub fn with_commands(mut self, commands: Arc<CommandRegistry>) -> Self {
+        self.commands = Some(commands);
+        self
+    }
+
+    /// Shares the running script with `script_monitor`'s other holders.
+    pub fn with_script_monitor(mut self, script_monitor: Arc<ScriptMonitor>) -> Self {
+        self.script_monitor = script_monitor;
+        self
+    }
+
+    /// Loads function libraries read from an RDB file, logging those that fail to.
+    pub fn with_libraries(mut self, libraries: Vec<Vec<u8>>) -> Self {
+        let functions = self.functions.get_or_insert_with(FunctionLibraries::new);
+        for code in libraries {
+            if let Err(err) = functions.load(code, false) {
+                log::error!("Could not load function library: {err}");
+            }
+        }
+        self
+    }
+
+    #[cfg(test)]
     fn worker(
         data_receiver: mpsc::Receiver<DataChannelMessage>,
         data_store: Option<T>,
         cleanup_intervall: Option<Duration>,
     ) -> JoinHandle<()> {
-        let manager = Self::new(data_receiver, data_store, cleanup_intervall);
+        let databases = vec![data_store.unwrap_or_default(), T::default()];
+        let manager = Self::new(
+            data_receiver,
+            databases,
+            cleanup_intervall,
+            NotifyFlags::default(),
+        );
         manager.run()
     }
-    //pub fn cleanup(&mut self) -> JoinHandle<()> {
-    //    let cleanup_intervall = self.cleanup_intervall.clone();
-    //    let store = self.data_store.clone();
-    //    tokio::spawn(async move {
-    //        loop {
-    //            store.lock().await.clean();
-    //            tokio::time::sleep(*cleanup_intervall).await;
-    //        }
-    //    })
-    //}
 
     pub fn run(mut self) -> JoinHandle<()> {
         tokio::spawn(async move {
-            while let Some(message) = self.data_receiver.recv().await {
-                match message {
-                    DataChannelMessage::Set(message) => {
-                        self.data_store.insert(message.key, message.value, None);
-
-                        match message
-                            .sender
-                            .send(ResponseChannelMessage(Resp::simple_string_from_str("OK")))
-                        {
-                            Ok(_) => (),
-                            Err(err) => log::error!("Could not reply: {:?}", err.0),
+            let mut cleanup = tokio::time::interval(*self.cleanup_intervall);
+            cleanup.tick().await;
+            loop {
+                let deadline = self
+                    .blocked
+                    .iter()
+                    .filter_map(|client| client.deadline)
+                    .min();
+                tokio::select! {
+                    message = self.data_receiver.recv() => match message {
+                        Some(DataChannelMessage {
+                            request: DataRequest::Shutdown(message),
+                            ..
+                        }) => {
+                            if self.shutdown(message) {
+                                break;
+                            }
                         }
-                    }
-                    DataChannelMessage::Get(message) => {
-                        let response = match self.data_store.get(message.key) {
-                            Some(data) => Resp::deserialize(&data),
-                            None => Ok(Resp::null_bulk_string()),
-                        };
-
-                        let response = match response {
-                            Ok(data) => data,
-                            Err(err) => AppError::from(err).into(),
-                        };
-
-                        message
-                            .sender
-                            .send(ResponseChannelMessage(response))
-                            .unwrap();
-                    }
-                    _ => todo!(),
+                        Some(message) => {
+                            self.handle_message(message);
+                            self.serve_blocked();
+                        }
+                        None => break,
+                    },
+                    _ = cleanup.tick() => self.databases.iter_mut().for_each(T::clean),
+                    _ = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now)),
+                        if deadline.is_some() => self.expire_blocked(),
                 }
+                self.publish_events(None);
             }
         })
     }
+
+    /// Applies `message`, then invalidates the keys it changed before remembering those it
+    /// read so that a tracking client is not told about its own read.
+    fn handle_message(&mut self, message: DataChannelMessage) {
+        let DataChannelMessage {
+            db,
+            request,
+            tracking,
+        } = message;
+        let read = tracking
+            .filter(|tracking| tracking.remember)
+            .map(|tracking| (tracking.client, read_keys(&request)));
+        self.apply(db, request);
+        self.publish_events(tracking.map(|tracking| tracking.client));
+        if let Some((client, keys)) = read {
+            self.tracking.remember(client, keys);
+        }
+    }
+
+    fn apply(&mut self, db: usize, request: DataRequest) {
+        match request {
+            DataRequest::Set(message) => {
+                let store = &mut self.databases[db];
+                store.insert(message.key.clone(), message.value, message.expiry);
+                store.notify(NotifyFlags::STRING, "set", &message.key);
+                if message.expiry.is_some() {
+                    store.notify(NotifyFlags::GENERIC, "expire", &message.key);
+                }
+                reply(message.sender, Ok(Resp::simple_string_from_str("OK")));
+            }
+            DataRequest::Get(message) => {
+                let response = match self.databases[db].entry_mut(&message.key) {
+                    Some(DataStoreEntry {
+                        value: DataValue::String(data),
+                        ..
+                    }) => Resp::deserialize(data).map_err(AppError::from),
+                    Some(_) => Err(AppError::WrongType),
+                    None => Ok(Resp::null_bulk_string()),
+                };
+                reply(message.sender, response);
+            }
+            DataRequest::Keyspace(message) => {
+                let response = apply_keyspace_command(&mut self.databases[db], message.command);
+                reply(message.sender, response);
+            }
+            DataRequest::String(message) => {
+                let response = apply_string_command(&mut self.databases[db], message.command);
+                reply(message.sender, response);
+            }
+            DataRequest::Hash(message) => {
+                let response =
+                    apply_hash_command(&mut self.databases[db], &message.key, message.command);
+                reply(message.sender, response);
+            }
+            DataRequest::UnorderedSet(message) => {
+                let response =
+                    apply_unordered_set_command(&mut self.databases[db], message.command);
+                reply(message.sender, response);
+            }
+            DataRequest::SortedSet(message) => {
+                let response = apply_sorted_set_command(&mut self.databases[db], message.command);
+                reply(message.sender, response);
+            }
+            DataRequest::Stream(message) => {
+                let response = apply_stream_command(&mut self.databases[db], message.command);
+                reply(message.sender, response);
+            }
+            DataRequest::Blocking(message) => {
+                let store = &mut self.databases[db];
+                let mut command = message.command;
+                let served = command
+                    .prepare(store)
+                    .and_then(|_| command.try_serve(store));
+                match served {
+                    Ok(Some(response)) => reply(message.sender, Ok(response)),
+                    Ok(None) => self.blocked.push_back(BlockedClient::new(
+                        db,
+                        command,
+                        message.timeout,
+                        message.sender,
+                    )),
+                    Err(err) => reply(message.sender, Err(err)),
+                }
+            }
+            DataRequest::Database(message) => {
+                let flushes = matches!(
+                    message.command,
+                    DatabaseCommand::FlushDb { .. }
+                        | DatabaseCommand::FlushAll { .. }
+                        | DatabaseCommand::SwapDb(..)
+                );
+                let response = apply_database_command(&mut self.databases, db, message.command);
+                if flushes && response.is_ok() {
+                    self.dirty += 1;
+                    self.tracking.flush(&self.pubsub);
+                }
+                reply(message.sender, response);
+            }
+            DataRequest::PubSub(message) => {
+                let response = apply_pubsub_command(&mut self.pubsub, message.command);
+                reply(message.sender, response);
+            }
+            DataRequest::Watch(message) => {
+                let versions = message
+                    .keys
+                    .iter()
+                    .map(|key| Resp::Integers(self.databases[db].version(key) as i64))
+                    .collect();
+                reply(message.sender, Ok(Resp::Array(versions)));
+            }
+            DataRequest::Exec(message) => self.exec(message),
+            DataRequest::Tracking(message) => {
+                let response =
+                    apply_tracking_command(&mut self.tracking, &self.pubsub, message.command);
+                reply(message.sender, response);
+            }
+            DataRequest::Script(message) => {
+                let response = off_runtime(|| self.script(db, message.user, message.command));
+                reply(message.sender, response);
+            }
+            DataRequest::Function(message) => {
+                let response = off_runtime(|| self.function(db, message.user, message.command));
+                reply(message.sender, response);
+            }
+            // Only ever sent by the event loop, straight to the worker loop.
+            DataRequest::Shutdown(message) => reply(
+                message.sender,
+                Err(AppError::Generic("SHUTDOWN is not allowed here".to_owned())),
+            ),
+        }
+    }
+
+    /// Saves the dataset if the message asks to, telling whether the worker may stop: it
+    /// keeps serving when the save failed.
+    fn shutdown(&mut self, message: ShutdownMessage) -> bool {
+        let saved = match &message.save {
+            Some(path) => self.save(path),
+            None => Ok(()),
+        };
+        let stop = saved.is_ok();
+        reply(
+            message.sender,
+            saved.map(|_| Resp::simple_string_from_str("OK")),
+        );
+        stop
+    }
+
+    fn save(&self, path: &Path) -> Result<(), AppError> {
+        let libraries = self.functions.iter().flat_map(FunctionLibraries::codes);
+        rdb::save_file(path, &self.databases, libraries)?;
+        log::info!("DB saved on disk");
+        Ok(())
+    }
+
+    fn script(
+        &mut self,
+        db: usize,
+        user: String,
+        command: ScriptCommand,
+    ) -> Result<Resp, AppError> {
+        let Some(mut scripts) = self.scripts.take() else {
+            return Err(AppError::Generic("scripting is not available".to_owned()));
+        };
+        let response = match command {
+            ScriptCommand::Load(body) => scripts
+                .load(&body)
+                .map(|sha| Resp::BulkString(sha.into_bytes())),
+            ScriptCommand::Exists(shas) => Ok(Resp::Array(
+                shas.iter()
+                    .map(|sha| Resp::Integers(scripts.exists(sha) as i64))
+                    .collect(),
+            )),
+            ScriptCommand::Flush => {
+                scripts.flush();
+                Ok(Resp::simple_string_from_str("OK"))
+            }
+            ScriptCommand::Eval { script, keys, args } => {
+                let sha = match script {
+                    Script::Body(body) => scripts.load(&body),
+                    Script::Sha(sha) => Ok(sha),
+                };
+                let (sender, mut receiver) = mpsc::channel(1);
+                let mut session = Session::for_script(db, user, sender);
+                let monitor = self.script_monitor.clone();
+                sha.and_then(|sha| {
+                    scripts.run(&sha, keys, args, &monitor, |args| {
+                        self.script_call(&mut session, &mut receiver, args)
+                    })
+                })
+            }
+        };
+        self.scripts = Some(scripts);
+        response
+    }
+
+    fn function(
+        &mut self,
+        db: usize,
+        user: String,
+        command: FunctionCommand,
+    ) -> Result<Resp, AppError> {
+        let Some(mut functions) = self.functions.take() else {
+            return Err(AppError::Generic("functions are not available".to_owned()));
+        };
+        let ok = || Resp::simple_string_from_str("OK");
+        let response = match command {
+            FunctionCommand::Load { code, replace } => functions
+                .load(code, replace)
+                .map(|name| Resp::BulkString(name.into_bytes())),
+            FunctionCommand::List { pattern, with_code } => {
+                Ok(functions.list(pattern.as_deref(), with_code))
+            }
+            FunctionCommand::Delete(name) => functions.delete(&name).map(|_| ok()),
+            FunctionCommand::Flush => {
+                functions.flush();
+                Ok(ok())
+            }
+            FunctionCommand::Dump => Ok(Resp::BulkString(functions.dump())),
+            FunctionCommand::Restore { payload, policy } => {
+                functions.restore(&payload, policy).map(|_| ok())
+            }
+            FunctionCommand::Call {
+                name,
+                keys,
+                args,
+                read_only,
+            } => {
+                let (sender, mut receiver) = mpsc::channel(1);
+                let mut session = Session::for_script(db, user, sender);
+                let monitor = self.script_monitor.clone();
+                functions.call(&name, keys, args, read_only, &monitor, |args| {
+                    self.script_call(&mut session, &mut receiver, args)
+                })
+            }
+        };
+        self.functions = Some(functions);
+        response
+    }
+
+    /// Runs a command sent by a script. Its handler is polled right here, the requests it
+    /// sends through the script `session` being applied in between polls, so it never
+    /// waits on the worker's own channel.
+    fn script_call(
+        &mut self,
+        session: &mut Session,
+        receiver: &mut mpsc::Receiver<DataChannelMessage>,
+        args: Vec<Vec<u8>>,
+    ) -> Resp {
+        let Some(commands) = self.commands.clone() else {
+            return AppError::Generic("scripting is not available".to_owned()).into();
+        };
+        let name = String::from_utf8_lossy(&args[0]).to_uppercase();
+        if SCRIPT_DENIED_COMMANDS.contains(&name.as_str()) {
+            return not_allowed_from_script();
+        }
+        let args: Vec<Resp> = args[1..].iter().cloned().map(Resp::BulkString).collect();
+        let mut reply = pin!(async {
+            match args.is_empty() {
+                true => commands.no_args_command(session, &name).await,
+                false => commands.command_with_args(session, &name, &args).await,
+            }
+        });
+        let mut context = Context::from_waker(noop_waker_ref());
+        let dirty = self.dirty;
+        loop {
+            if let Poll::Ready(reply) = reply.as_mut().poll(&mut context) {
+                if self.dirty != dirty {
+                    self.script_monitor.wrote();
+                }
+                return reply.unwrap_or_else(Into::into);
+            }
+            let mut applied = false;
+            while let Ok(message) = receiver.try_recv() {
+                self.run_queued(message);
+                applied = true;
+            }
+            // Waiting on anything but the worker would never end.
+            if !applied {
+                return not_allowed_from_script();
+            }
+        }
+    }
+
+    /// Runs a transaction without letting other messages in, blocking commands replying
+    /// right away as if they timed out.
+    fn exec(&mut self, message: ExecMessage) {
+        let ExecMessage {
+            watched,
+            requests,
+            sender,
+        } = message;
+        let touched = watched
+            .iter()
+            .any(|watched| self.databases[watched.db].version(&watched.key) != watched.version);
+        if touched {
+            return reply(sender, Ok(Resp::NullArray));
+        }
+        for message in requests {
+            self.run_queued(message);
+        }
+        reply(sender, Ok(Resp::simple_string_from_str("OK")));
+    }
+
+    /// Runs a request of a transaction or a script, blocking commands replying right away
+    /// as if they timed out.
+    fn run_queued(&mut self, message: DataChannelMessage) {
+        match message.request {
+            DataRequest::Blocking(blocking) => {
+                let store = &mut self.databases[message.db];
+                let mut command = blocking.command;
+                let response = command
+                    .prepare(store)
+                    .and_then(|_| command.try_serve(store))
+                    .map(|served| served.unwrap_or(Resp::NullArray));
+                reply(blocking.sender, response);
+            }
+            _ => self.handle_message(message),
+        }
+    }
+
+    /// Invalidates the keys changed since the last turn for tracking clients, then
+    /// publishes their keyspace events. `origin` is the client that changed them, if any.
+    fn publish_events(&mut self, origin: Option<u64>) {
+        for (db, store) in self.databases.iter_mut().enumerate() {
+            let events = store.take_events();
+            if events.is_empty() {
+                continue;
+            }
+            self.dirty += events.len() as u64;
+            let keys: Vec<_> = events.iter().map(|event| event.key.clone()).collect();
+            self.tracking.invalidate(&self.pubsub, &keys, origin);
+            notification::publish_events(&mut self.pubsub, self.notify_flags, db, events);
+        }
+    }
+
+    /// Serves blocked clients in FIFO order now that the keyspace may have changed.
+    fn serve_blocked(&mut self) {
+        for client in std::mem::take(&mut self.blocked) {
+            if client.sender.is_closed() {
+                continue;
+            }
+            match client.command.try_serve(&mut self.databases[client.db]) {
+                Ok(Some(response)) => reply(client.sender, Ok(response)),
+                Ok(None) => self.blocked.push_back(client),
+                Err(err) => reply(client.sender, Err(err)),
+            }
+        }
+    }
+
+    fn expire_blocked(&mut self) {
+        let now = Instant::now();
+        for client in std::mem::take(&mut self.blocked) {
+            if client.timed_out(now) {
+                reply(client.sender, Ok(Resp::NullArray));
+            } else {
+                self.blocked.push_back(client);
+            }
+        }
+    }
+}
+
+/// Runs a script, which may take long, after moving the other tasks of this thread to
+/// another one when the runtime has others.
+fn off_runtime<R>(run: impl FnOnce() -> R) -> R {
+    match Handle::try_current().map(|handle| handle.runtime_flavor()) {
+        Ok(RuntimeFlavor::MultiThread) => tokio::task::block_in_place(run),
+        _ => run(),
+    }
+}
+
+fn not_allowed_from_script() -> Resp {
+    AppError::Generic("This Redis command is not allowed from script".to_owned()).into()
+}
+
+fn reply(sender: oneshot::Sender<ResponseChannelMessage>, response: Result<Resp, AppError>) {
+    let response = response.unwrap_or_else(Into::into);
+    if let Err(err) = sender.send(ResponseChannelMessage(response)) {
+        log::error!("Could not reply: {:?}", err.0)
+    }
 }
 
 #[cfg(test)]
@@ -103,9 +588,14 @@ mod test {
 
     use super::*;
     use crate::data_management::{
-        datastore::DataStoreEntry,
+        blocking::BlockingCommand,
+        hash::{HashCommand, HashValue},
         hash_table_store::HashTableDataStore,
-        message::{GetMessage, SetMessage},
+        message::{
+            BlockingMessage, GetMessage, HashMessage, PubSubMessage, SetMessage, SortedSetMessage,
+        },
+        pubsub::{push_channel, OutputBufferLimits, PubSubCommand, SubscriptionKind},
+        sorted_set::{AddFlags, SortedSetCommand},
     };
 
     #[tokio::test]
@@ -119,7 +609,7 @@ mod test {
 
         let message = SetMessage::new(key, value, response_sender, None);
         data_sender
-            .send(DataChannelMessage::Set(message))
+            .send(DataChannelMessage::new(0, DataRequest::Set(message)))
             .await
             .unwrap();
 
@@ -141,7 +631,7 @@ mod test {
         DataManager::worker(data_receiver, Some(default), None);
         let message = GetMessage::new(key, response_sender);
         data_sender
-            .send(DataChannelMessage::Get(message))
+            .send(DataChannelMessage::new(0, DataRequest::Get(message)))
             .await
             .unwrap();
 
@@ -159,7 +649,7 @@ mod test {
         DataManager::<HashTableDataStore>::worker(data_receiver, None, None);
         let message = GetMessage::new(key, response_sender);
         data_sender
-            .send(DataChannelMessage::Get(message))
+            .send(DataChannelMessage::new(0, DataRequest::Get(message)))
             .await
             .unwrap();
 
@@ -182,11 +672,159 @@ mod test {
         DataManager::worker(data_receiver, Some(data_store), None);
         let message = GetMessage::new(key, response_sender);
         data_sender
-            .send(DataChannelMessage::Get(message))
+            .send(DataChannelMessage::new(0, DataRequest::Get(message)))
             .await
             .unwrap();
 
         let res = response_receiver.await.unwrap();
         assert_eq!(res.0.serialize().unwrap(), EXPECT.as_bytes())
     }
+
+    #[tokio::test]
+    async fn should_reply_wrong_type_on_get_of_hash() {
+        let key = Resp::bulk_string_from_str("hello").serialize().unwrap();
+        let hash = HashValue::from([(b"field".to_vec(), b"value".to_vec())]);
+        let entry = DataStoreEntry::with_value(DataValue::Hash(hash), None);
+        let data_store = HashTableDataStore::from([(key.clone(), entry)]);
+        let (data_sender, data_receiver) = mpsc::channel::<DataChannelMessage>(1000);
+        let (response_sender, response_receiver) = tokio::sync::oneshot::channel();
+
+        DataManager::worker(data_receiver, Some(data_store), None);
+        let message = GetMessage::new(key, response_sender);
+        data_sender
+            .send(DataChannelMessage::new(0, DataRequest::Get(message)))
+            .await
+            .unwrap();
+
+        let res = response_receiver.await.unwrap();
+        assert_eq!(res.0, AppError::WrongType.into())
+    }
+
+    #[tokio::test]
+    async fn should_apply_hash_command() {
+        let key = Resp::bulk_string_from_str("hello").serialize().unwrap();
+        let (data_sender, data_receiver) = mpsc::channel::<DataChannelMessage>(1000);
+        let (response_sender, response_receiver) = tokio::sync::oneshot::channel();
+
+        DataManager::<HashTableDataStore>::worker(data_receiver, None, None);
+        let command = HashCommand::Set(vec![(b"field".to_vec(), b"value".to_vec())]);
+        let message = HashMessage::new(key, command, response_sender);
+        data_sender
+            .send(DataChannelMessage::new(0, DataRequest::Hash(message)))
+            .await
+            .unwrap();
+
+        let res = response_receiver.await.unwrap();
+        assert_eq!(res.0, Resp::Integers(1))
+    }
+
+    #[tokio::test]
+    async fn should_serve_blocked_client_once_key_is_filled() {
+        let key = Resp::bulk_string_from_str("board").serialize().unwrap();
+        let (data_sender, data_receiver) = mpsc::channel::<DataChannelMessage>(1000);
+        let (blocked_sender, blocked_receiver) = tokio::sync::oneshot::channel();
+        let (response_sender, response_receiver) = tokio::sync::oneshot::channel();
+
+        DataManager::<HashTableDataStore>::worker(data_receiver, None, None);
+        let command = BlockingCommand::ZPop {
+            keys: vec![key.clone()],
+            max: false,
+        };
+        let message = BlockingMessage::new(command, None, blocked_sender);
+        data_sender
+            .send(DataChannelMessage::new(0, DataRequest::Blocking(message)))
+            .await
+            .unwrap();
+        let command = SortedSetCommand::Add {
+            key,
+            elements: vec![(1.0, b"alice".to_vec())],
+            flags: AddFlags::default(),
+        };
+        let message = SortedSetMessage::new(command, response_sender);
+        data_sender
+            .send(DataChannelMessage::new(0, DataRequest::SortedSet(message)))
+            .await
+            .unwrap();
+
+        assert_eq!(response_receiver.await.unwrap().0, Resp::Integers(1));
+        let expect = Resp::Array(vec![
+            Resp::bulk_string_from_str("board"),
+            Resp::bulk_string_from_str("alice"),
+            Resp::bulk_string_from_str("1"),
+        ]);
+        assert_eq!(blocked_receiver.await.unwrap().0, expect)
+    }
+
+    #[tokio::test]
+    async fn should_reply_null_array_when_blocking_times_out() {
+        let key = Resp::bulk_string_from_str("board").serialize().unwrap();
+        let (data_sender, data_receiver) = mpsc::channel::<DataChannelMessage>(1000);
+        let (response_sender, response_receiver) = tokio::sync::oneshot::channel();
+
+        DataManager::<HashTableDataStore>::worker(data_receiver, None, None);
+        let command = BlockingCommand::ZPop {
+            keys: vec![key],
+            max: true,
+        };
+        let timeout = Some(Duration::from_millis(10));
+        let message = BlockingMessage::new(command, timeout, response_sender);
+        data_sender
+            .send(DataChannelMessage::new(0, DataRequest::Blocking(message)))
+            .await
+            .unwrap();
+
+        let res = response_receiver.await.unwrap();
+        assert_eq!(res.0, Resp::NullArray)
+    }
+
+    #[tokio::test]
+    async fn should_publish_keyevents_from_active_expiry() {
+        let (data_sender, data_receiver) = mpsc::channel::<DataChannelMessage>(1000);
+        let flags = NotifyFlags::parse("Egx").unwrap();
+        let databases = vec![HashTableDataStore::default()];
+        DataManager::new(
+            data_receiver,
+            databases,
+            Some(Duration::from_millis(5)),
+            flags,
+        )
+        .run();
+
+        let (client, mut pushes) = push_channel(1, OutputBufferLimits::default());
+        let (response_sender, response_receiver) = tokio::sync::oneshot::channel();
+        let command = PubSubCommand::Subscribe {
+            kind: SubscriptionKind::Pattern,
+            client,
+            names: vec![b"__keyevent@0__:*".to_vec()],
+        };
+        let message = PubSubMessage::new(command, response_sender);
+        data_sender
+            .send(DataChannelMessage::new(0, DataRequest::PubSub(message)))
+            .await
+            .unwrap();
+        response_receiver.await.unwrap();
+
+        let key = Resp::bulk_string_from_str("hello").serialize().unwrap();
+        let value = Resp::bulk_string_from_str("world").serialize().unwrap();
+        let (response_sender, response_receiver) = tokio::sync::oneshot::channel();
+        let message = SetMessage::new(key, value, response_sender, Some(Duration::from_millis(1)));
+        data_sender
+            .send(DataChannelMessage::new(0, DataRequest::Set(message)))
+            .await
+            .unwrap();
+        response_receiver.await.unwrap();
+
+        pushes.recv().await.unwrap();
+        let event = |event: &str| {
+            Resp::Array(vec![
+                Resp::bulk_string_from_str("pmessage"),
+                Resp::bulk_string_from_str("__keyevent@0__:*"),
+                Resp::bulk_string_from_str(&format!("__keyevent@0__:{event}")),
+                Resp::bulk_string_from_str("hello"),
+            ])
+        };
+        assert_eq!(pushes.recv().await.unwrap(), event("expire"));
+        let expired = tokio::time::timeout(Duration::from_secs(1), pushes.recv()).await;
+        assert_eq!(expired.unwrap().unwrap(), event("expired"));
+    }
 }
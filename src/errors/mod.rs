@@ -16,6 +16,8 @@ pub enum AppError {
     UnknownCommand(String),
     #[error("ERR wrong number of arguments for '{0}' expected {1} command got {2}")]
     InvalidArgLength(String, String, String),
+    #[error("ERR wrong number of arguments for '{0}' command")]
+    WrongArity(String),
     #[error(transparent)]
     SocketError(#[from] io::Error),
     #[error(transparent)]
@@ -32,6 +34,72 @@ pub enum AppError {
     InvalidExpiry(#[from] ParseIntError),
     #[error(transparent)]
     InvalidUtf8(#[from] Utf8Error),
+    #[error("WRONGTYPE Operation against a key holding the wrong kind of value")]
+    WrongType,
+    #[error("ERR syntax error")]
+    Syntax,
+    #[error("ERR value is not an integer or out of range")]
+    NotAnInteger,
+    #[error("ERR value is not a valid float")]
+    NotAFloat,
+    #[error("ERR increment or decrement would overflow")]
+    Overflow,
+    #[error("ERR increment would produce NaN or Infinity")]
+    NanOrInfinity,
+    #[error("ERR hash value is not an integer")]
+    HashValueNotInteger,
+    #[error("ERR hash value is not a float")]
+    HashValueNotFloat,
+    #[error("ERR string exceeds maximum allowed size (proto-max-bulk-len)")]
+    StringTooLong,
+    #[error("ERR invalid expire time in '{0}' command")]
+    InvalidExpireTime(String),
+    #[error("ERR Invalid stream ID specified as stream command argument")]
+    InvalidStreamId,
+    #[error("ERR The ID specified in XADD is equal or smaller than the target stream top item")]
+    StreamIdTooSmall,
+    #[error("ERR The ID specified in XADD must be greater than 0-0")]
+    StreamIdZero,
+    #[error("NOGROUP No such key '{0}' or consumer group '{1}'")]
+    NoGroup(String, String),
+    #[error("BUSYGROUP Consumer Group name already exists")]
+    BusyGroup,
+    #[error("BUSYKEY Target key name already exists.")]
+    BusyKey,
+    #[error("ERR no such key")]
+    NoSuchKey,
+    #[error("EXECABORT Transaction discarded because of previous errors.")]
+    ExecAbort,
+    #[error("NOPROTO unsupported protocol version")]
+    NoProto,
+    #[error("NOSCRIPT No matching script. Please use EVAL.")]
+    NoScript,
+    #[error("BUSY Redis is busy running a script. You can only call {0} or SHUTDOWN NOSAVE.")]
+    Busy(&'static str),
+    #[error("NOTBUSY No scripts in execution right now.")]
+    NotBusy,
+    #[error("UNKILLABLE Sorry the script already executed write commands against the dataset. You can either wait the script termination or kill the server in a hard way using the SHUTDOWN NOSAVE command.")]
+    Unkillable,
+    #[error("ERR Script killed by user with {0}...")]
+    ScriptKilled(&'static str),
+    #[error("NOAUTH Authentication required.")]
+    NoAuth,
+    #[error("NOAUTH HELLO must be called with the client already authenticated, otherwise the HELLO <proto> AUTH <user> <pass> option can be used to authenticate the client and select the RESP protocol version at the same time")]
+    HelloNoAuth,
+    #[error("WRONGPASS invalid username-password pair or user is disabled.")]
+    WrongPass,
+    #[error("ERR max number of clients reached")]
+    MaxClients,
+    #[error("Failed to configure TLS: {0}")]
+    TlsConfig(String),
+    #[error("NOPERM User {0} has no permissions to run the '{1}' command")]
+    NoPermCommand(String, String),
+    #[error("NOPERM No permissions to access a key")]
+    NoPermKey,
+    #[error("NOPERM No permissions to access a channel")]
+    NoPermChannel,
+    #[error("ERR {0}")]
+    Generic(String),
 }
 
 impl From<AppError> for Resp {
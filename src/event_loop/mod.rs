@@ -1,32 +1,118 @@
-use std::sync::Arc;
+mod tls;
+
+use std::{
+    fs, io,
+    net::SocketAddr,
+    os::unix::fs::PermissionsExt,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt},
-    net::TcpListener,
-    sync::Notify,
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::{TcpListener, TcpStream, UnixListener, UnixStream},
+    sync::{mpsc, watch, Notify, OwnedRwLockWriteGuard, RwLock},
+    task::JoinSet,
 };
+use tokio_rustls::TlsAcceptor;
+
+use tls::TlsSettings;
 
 use crate::{
+    acl::{
+        command_table::{blocking, command_name, may_write},
+        Acl, NO_AUTH_COMMANDS,
+    },
+    clients::{ClientInfo, ClientTable},
     commands::{
+        acl::{AclCommandHandler, ACL_COMMAND_NAMES},
+        auth::{AuthCommand, AUTH_COMMAND_NAME},
+        client::{
+            ClientCommandHandler, ClientConnectionCommandHandler, CLIENT_COMMAND_NAMES,
+            CLIENT_CONNECTION_COMMAND_NAMES,
+        },
         command_registry::CommandRegistry,
+        consumer_group::{ConsumerGroupCommandHandler, CONSUMER_GROUP_COMMAND_NAMES},
+        database::{DatabaseCommandHandler, DATABASE_COMMAND_NAMES},
         echo::{EchoCommand, ECHO_COMMAND_NAME},
+        function::{FunctionCommandHandler, FUNCTION_COMMAND_NAMES},
         get::{GetCommandHandler, GET_COMMAND_NAME},
         get_config::{GetConfigCommandHandler, GET_CONFIG_COMMAND_NAME},
+        hash::{HashCommandHandler, HASH_COMMAND_NAMES},
+        hello::{HelloCommand, HELLO_COMMAND_NAME},
+        keyspace::{KeyspaceCommandHandler, KEYSPACE_COMMAND_NAMES},
         ping::PingCommand,
+        pubsub::{PubSubCommandHandler, PUBSUB_COMMAND_NAMES},
+        scripting::{
+            ScriptKillCommandHandler, ScriptingCommandHandler, SCRIPTING_COMMAND_NAMES,
+            SCRIPT_KILL_COMMAND_NAMES,
+        },
+        session::{ClientAddress, Session},
         set::{SetCommandHandler, SET_COMMAND_NAME},
+        shutdown::{ShutdownCommand, SHUTDOWN_COMMAND_NAME},
+        sorted_set::{SortedSetCommandHandler, SORTED_SET_COMMAND_NAMES},
+        stream::{StreamCommandHandler, STREAM_COMMAND_NAMES},
+        string::{StringCommandHandler, STRING_COMMAND_NAMES},
+        transaction::{TransactionCommandHandler, TRANSACTION_COMMAND_NAMES},
+        unordered_set::{UnorderedSetCommandHandler, UNORDERED_SET_COMMAND_NAMES},
     },
     config::AppConfig,
-    data_management::message::DataChannelMessage,
+    data_management::{
+        message::{send_message, DataChannelMessage, DataRequest, ShutdownMessage},
+        pubsub::{push_channel, OutputBufferLimits},
+        scripting::{ScriptKind, ScriptMonitor},
+    },
     errors::AppError,
     resp::Resp,
+    shutdown::{SaveMode, ShutdownRequest},
 };
 
+/// Source of the ids identifying connections.
+static NEXT_CLIENT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// How long a shutdown waits for in-flight commands before closing their connections.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[derive(Debug)]
 pub struct EventLoop {
     port: i32,
     host: String,
-    command_registry: Arc<CommandRegistry>,
-    data_sender: Arc<tokio::sync::mpsc::Sender<DataChannelMessage>>,
+    tls: Option<TlsSettings>,
+    unixsocket: Option<UnixSocket>,
+    connections: Connections,
+    data_sender: Arc<mpsc::Sender<DataChannelMessage>>,
+    /// RDB file saved on shutdown, unless `SHUTDOWN NOSAVE`.
+    rdb_path: Option<PathBuf>,
+    shutdown: mpsc::Sender<ShutdownRequest>,
+    shutdown_requests: mpsc::Receiver<ShutdownRequest>,
+    /// Tells the connections to close once done with their in-flight commands.
+    closing: watch::Sender<bool>,
+}
+
+/// `unixsocket` connections are also accepted on, with its `unixsocketperm`.
+#[derive(Debug)]
+struct UnixSocket {
+    path: PathBuf,
+    perm: Option<u32>,
+}
+
+impl UnixSocket {
+    /// Listens on the socket, replacing the file a previous run may have left behind.
+    fn bind(&self) -> io::Result<UnixListener> {
+        match fs::remove_file(&self.path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+            _ => (),
+        }
+        let listener = UnixListener::bind(&self.path)?;
+        if let Some(perm) = self.perm {
+            fs::set_permissions(&self.path, fs::Permissions::from_mode(perm))?;
+        }
+        Ok(listener)
+    }
 }
 
 impl EventLoop {
@@ -36,7 +122,8 @@ impl EventLoop {
         data_sender: Arc<tokio::sync::mpsc::Sender<DataChannelMessage>>,
         config: &AppConfig,
     ) -> Self {
-        let mut command_registry = CommandRegistry::new();
+        let acl = Arc::new(Acl::from_config(config));
+        let mut command_registry = CommandRegistry::new().with_acl(acl.clone());
         command_registry.register(
             GET_CONFIG_COMMAND_NAME,
             Box::new(GetConfigCommandHandler::new(config.into())),
@@ -49,23 +136,181 @@ impl EventLoop {
             SET_COMMAND_NAME,
             Box::new(SetCommandHandler::new(data_sender.clone())),
         );
+        for name in KEYSPACE_COMMAND_NAMES {
+            command_registry.register(
+                name,
+                Box::new(KeyspaceCommandHandler::new(name, data_sender.clone())),
+            );
+        }
+        for name in DATABASE_COMMAND_NAMES {
+            command_registry.register(
+                name,
+                Box::new(DatabaseCommandHandler::new(
+                    name,
+                    data_sender.clone(),
+                    config.databases(),
+                )),
+            );
+        }
+        for name in STRING_COMMAND_NAMES {
+            command_registry.register(
+                name,
+                Box::new(StringCommandHandler::new(name, data_sender.clone())),
+            );
+        }
+        for name in HASH_COMMAND_NAMES {
+            command_registry.register(
+                name,
+                Box::new(HashCommandHandler::new(name, data_sender.clone())),
+            );
+        }
+        for name in UNORDERED_SET_COMMAND_NAMES {
+            command_registry.register(
+                name,
+                Box::new(UnorderedSetCommandHandler::new(name, data_sender.clone())),
+            );
+        }
+        for name in SORTED_SET_COMMAND_NAMES {
+            command_registry.register(
+                name,
+                Box::new(SortedSetCommandHandler::new(name, data_sender.clone())),
+            );
+        }
+        for name in STREAM_COMMAND_NAMES {
+            command_registry.register(
+                name,
+                Box::new(StreamCommandHandler::new(name, data_sender.clone())),
+            );
+        }
+        for name in CONSUMER_GROUP_COMMAND_NAMES {
+            command_registry.register(
+                name,
+                Box::new(ConsumerGroupCommandHandler::new(name, data_sender.clone())),
+            );
+        }
+        for name in PUBSUB_COMMAND_NAMES {
+            command_registry.register(
+                name,
+                Box::new(PubSubCommandHandler::new(name, data_sender.clone())),
+            );
+        }
+        for name in TRANSACTION_COMMAND_NAMES {
+            command_registry.register(
+                name,
+                Box::new(TransactionCommandHandler::new(name, data_sender.clone())),
+            );
+        }
+        for name in CLIENT_COMMAND_NAMES {
+            command_registry.register(
+                name,
+                Box::new(ClientCommandHandler::new(name, data_sender.clone())),
+            );
+        }
+        for name in SCRIPTING_COMMAND_NAMES {
+            command_registry.register(
+                name,
+                Box::new(ScriptingCommandHandler::new(name, data_sender.clone())),
+            );
+        }
+        for name in FUNCTION_COMMAND_NAMES {
+            command_registry.register(
+                name,
+                Box::new(FunctionCommandHandler::new(name, data_sender.clone())),
+            );
+        }
+        let clients = Arc::<ClientTable>::default();
+        for name in CLIENT_CONNECTION_COMMAND_NAMES {
+            command_registry.register(
+                name,
+                Box::new(ClientConnectionCommandHandler::new(name, clients.clone())),
+            );
+        }
+        let script_monitor = Arc::<ScriptMonitor>::default();
+        for name in SCRIPT_KILL_COMMAND_NAMES {
+            command_registry.register(
+                name,
+                Box::new(ScriptKillCommandHandler::new(name, script_monitor.clone())),
+            );
+        }
+        command_registry.register(AUTH_COMMAND_NAME, Box::new(AuthCommand::new(acl.clone())));
+        command_registry.register(HELLO_COMMAND_NAME, Box::new(HelloCommand::new(acl.clone())));
+        for name in ACL_COMMAND_NAMES {
+            command_registry.register(
+                name,
+                Box::new(AclCommandHandler::new(name, acl.clone(), clients.clone())),
+            );
+        }
+        let (shutdown, shutdown_requests) = mpsc::channel(16);
+        command_registry.register(
+            SHUTDOWN_COMMAND_NAME,
+            Box::new(ShutdownCommand::new(shutdown.clone())),
+        );
+        let (closing, closing_receiver) = watch::channel(false);
         command_registry.register(ECHO_COMMAND_NAME, Box::new(EchoCommand::new()));
         command_registry.register("PING", Box::new(PingCommand));
 
         Self {
             port,
             host,
-            command_registry: command_registry.into(),
+            tls: TlsSettings::from_config(config),
+            unixsocket: config.unixsocket.clone().map(|path| UnixSocket {
+                path,
+                perm: config.unixsocketperm,
+            }),
+            connections: Connections {
+                command_registry: command_registry.into(),
+                script_monitor,
+                busy_reply_threshold: config.busy_reply_threshold(),
+                acl,
+                clients,
+                maxclients: config.maxclients(),
+                output_buffer_limits: config.client_output_buffer_limit(),
+                query_buffer_limit: config.client_query_buffer_limit(),
+                closing: closing_receiver,
+                running: Arc::default(),
+            },
             data_sender,
+            rdb_path: config.rdb_path(),
+            shutdown,
+            shutdown_requests,
+            closing,
         }
     }
+    /// Registry the connections dispatch to, also used by scripts.
+    pub fn command_registry(&self) -> Arc<CommandRegistry> {
+        self.connections.command_registry.clone()
+    }
+
+    /// Script run by the worker, which the connections reply `-BUSY` about.
+    pub fn script_monitor(&self) -> Arc<ScriptMonitor> {
+        self.connections.script_monitor.clone()
+    }
+
+    /// Where shutdowns are requested from outside of the connections, like on signals.
+    pub fn shutdown_requests(&self) -> mpsc::Sender<ShutdownRequest> {
+        self.shutdown.clone()
+    }
+
     pub fn address(&self) -> String {
         format!("{}:{}", self.host, self.port)
     }
 
-    pub async fn run(&self, notify: Option<&Notify>) -> Result<(), AppError> {
-        let addr = self.address();
-        let listener = TcpListener::bind(&addr).await?;
+    /// Serves connections until a shutdown stops the server.
+    pub async fn run(mut self, notify: Option<&Notify>) -> Result<(), AppError> {
+        // Like in Redis, port 0 leaves only the TLS and Unix socket listeners.
+        let listener = match self.port {
+            0 => None,
+            _ => Some(TcpListener::bind(self.address()).await?),
+        };
+        let unix_listener = self.unixsocket.as_ref().map(UnixSocket::bind).transpose()?;
+        let tls_listener = match &self.tls {
+            Some(tls) => {
+                let acceptor = tls.acceptor()?;
+                let listener = TcpListener::bind(format!("{}:{}", self.host, tls.port)).await?;
+                Some((listener, acceptor))
+            }
+            None => None,
+        };
 
         if let Some(notify) = notify {
             notify.notify_one();
@@ -73,101 +318,458 @@ impl EventLoop {
 
         log::info!("Rust redis is up");
 
+        let mut tasks = JoinSet::new();
         loop {
-            match listener.accept().await {
-                Ok((mut stream, _)) => {
-                    let command_registry = self.command_registry.clone();
-                    tokio::spawn(async move {
-                        log::info!("Incoming request");
-
-                        loop {
-                            let mut buf = vec![0u8; 1024];
-
-                            match stream.read(&mut buf).await {
-                                Ok(0) => {
-                                    //let _ = stream.write_all(b"NO COMMAND WAS SENDED").await;
-                                    break;
-                                }
-                                Ok(size) => {
-                                    let commands = match parse_commands(&buf[..size]) {
-                                        Ok(commands) => commands,
-                                        Err(err) => {
-                                            let err = Into::<Resp>::into(err);
-
-                                            if let Ok(serialized) = err.serialize() {
-                                                let _ = stream.write_all(&serialized).await;
-                                            } else {
-                                                log::error!("Unable to serialize error")
-                                            }
-                                            continue;
-                                        }
-                                    };
-                                    for command in commands {
-                                        match command {
-                                            Resp::Array(command_with_args) => {
-                                                if let Resp::BulkString(command) =
-                                                    &command_with_args[0]
-                                                {
-                                                    let command = command_as_str(&command).unwrap();
-
-                                                    let result = if command_with_args.len() == 1 {
-                                                        command_registry
-                                                            .no_args_command(command)
-                                                            .await
-                                                    } else {
-                                                        command_registry
-                                                            .command_with_args(
-                                                                command,
-                                                                &command_with_args[1..],
-                                                            )
-                                                            .await
-                                                    }
-                                                    .map_err(|err| Into::<Resp>::into(err));
-                                                    let response = match result {
-                                                        Ok(res) => res.serialize(),
-                                                        Err(err) => err.serialize(),
-                                                    };
-
-                                                    if let Ok(response) = response {
-                                                        match stream.write_all(&response).await {
-                                                            Ok(_) => (),
-                                                            Err(err) => {
-                                                                log::error!("{}", err.to_string());
-                                                                break;
-                                                            }
-                                                        }
-                                                    } else if let Err(err) = response {
-                                                        log::error!("{}", err.to_string());
-                                                        break;
-                                                    }
-                                                }
-                                            }
-                                            _ => todo!(),
-                                        }
-                                    }
-                                }
-                                Err(err) => {
-                                    log::error!("{:?}", err.to_string());
-                                    break;
+            let request = tokio::select! {
+                accepted = accept_tcp(listener.as_ref()) => match accepted {
+                    Ok((stream, peer)) => {
+                        let local = stream.local_addr().unwrap_or(peer);
+                        let address = ClientAddress::Tcp { peer, local };
+                        tasks.spawn(self.connections.clone().serve(stream, address));
+                        continue;
+                    }
+                    Err(err) => {
+                        log::error!("{:?}", err.to_string());
+                        continue;
+                    }
+                },
+                accepted = accept_tls(tls_listener.as_ref()) => match accepted {
+                    Ok((stream, peer, acceptor)) => {
+                        let local = stream.local_addr().unwrap_or(peer);
+                        let address = ClientAddress::Tcp { peer, local };
+                        let connections = self.connections.clone();
+                        // Handshakes happen in the connection tasks not to hold the others.
+                        tasks.spawn(async move {
+                            match acceptor.accept(stream).await {
+                                Ok(stream) => connections.serve(stream, address).await,
+                                Err(err) => log::warn!("TLS handshake with {peer} failed: {err}"),
+                            }
+                        });
+                        continue;
+                    }
+                    Err(err) => {
+                        log::error!("{:?}", err.to_string());
+                        continue;
+                    }
+                },
+                accepted = accept_unix(unix_listener.as_ref()) => match accepted {
+                    Ok(stream) => {
+                        let path = self.unixsocket.as_ref().map(|socket| socket.path.clone());
+                        let address = ClientAddress::Unix(path.unwrap_or_default());
+                        tasks.spawn(self.connections.clone().serve(stream, address));
+                        continue;
+                    }
+                    Err(err) => {
+                        log::error!("{:?}", err.to_string());
+                        continue;
+                    }
+                },
+                Some(_) = tasks.join_next() => continue,
+                Some(request) = self.shutdown_requests.recv() => request,
+            };
+            if self.shut_down(request, &mut tasks).await {
+                break;
+            }
+        }
+        if let Some(socket) = &self.unixsocket {
+            let _ = fs::remove_file(&socket.path);
+        }
+        log::info!("Redis is now ready to exit, bye bye...");
+        Ok(())
+    }
+
+    /// Stops accepting connections and waits for the commands they run to be answered,
+    /// holding back the others, then stops the data worker after saving the dataset if
+    /// asked to and closes the connections. There are no replicas to notify nor append
+    /// only file to flush. Tells whether the server stopped: when the save failed it goes
+    /// on with its clients still connected, unless the shutdown is forced, and `SHUTDOWN`
+    /// gets an error.
+    async fn shut_down(&self, request: ShutdownRequest, tasks: &mut JoinSet<()>) -> bool {
+        log::warn!("User requested shutdown...");
+        let ShutdownRequest { save, force, reply } = request;
+        let stopped = match self.save_path(save, force) {
+            Some(path) => {
+                // A script keeps its command running and the worker busy until it stops.
+                self.connections.script_monitor.abort();
+                let drained = self.drain_commands().await;
+                let stopped = self.stop_data_worker(path, force).await;
+                if stopped {
+                    // Commands held back are not run, their connections closing instead.
+                    self.closing.send_replace(true);
+                }
+                drop(drained);
+                stopped
+            }
+            None => false,
+        };
+        if let Some(reply) = reply {
+            let result = match stopped {
+                true => Ok(()),
+                false => Err(AppError::Generic(
+                    "Errors trying to SHUTDOWN. Check logs.".to_owned(),
+                )),
+            };
+            let _ = reply.send(result);
+        }
+        if !stopped {
+            log::error!("Errors trying to shut down the server, check the logs");
+            return false;
+        }
+        let drained = async { while tasks.join_next().await.is_some() {} };
+        if tokio::time::timeout(SHUTDOWN_TIMEOUT, drained)
+            .await
+            .is_err()
+        {
+            log::warn!("Closing the connections still busy after {SHUTDOWN_TIMEOUT:?}");
+            tasks.abort_all();
+        }
+        true
+    }
+
+    /// File the dataset is saved to as `save` says, `None` when it has to be saved but
+    /// cannot be, unless `force`d.
+    fn save_path(&self, save: SaveMode, force: bool) -> Option<Option<PathBuf>> {
+        match save {
+            SaveMode::Configured => Some(self.rdb_path.clone()),
+            SaveMode::Save if self.rdb_path.is_none() => {
+                log::error!("Cannot save the DB on shutdown without dir and dbfilename");
+                force.then_some(None)
+            }
+            SaveMode::Save => Some(self.rdb_path.clone()),
+            SaveMode::NoSave => Some(None),
+        }
+    }
+
+    /// Waits for the commands being run to be answered, holding back the others for as
+    /// long as the returned guard lives. Commands still running after `SHUTDOWN_TIMEOUT`
+    /// are not waited for.
+    async fn drain_commands(&self) -> Option<OwnedRwLockWriteGuard<()>> {
+        let running = self.connections.running.clone().write_owned();
+        let drained = tokio::time::timeout(SHUTDOWN_TIMEOUT, running).await.ok();
+        if drained.is_none() {
+            log::warn!("Shutting down with commands still running after {SHUTDOWN_TIMEOUT:?}");
+        }
+        drained
+    }
+
+    /// Asks the data worker to save the dataset to `path` if any and stop, telling whether
+    /// it did. A failed save keeps it serving, unless `force`d.
+    async fn stop_data_worker(&self, path: Option<PathBuf>, force: bool) -> bool {
+        let stop = |save| {
+            send_message(&self.data_sender, 0, |sender| {
+                DataRequest::Shutdown(ShutdownMessage::new(save, sender))
+            })
+        };
+        match stop(path).await {
+            Ok(Resp::SimpleError(err)) => {
+                log::error!(
+                    "Error trying to save the DB: {}",
+                    String::from_utf8_lossy(&err)
+                );
+                if !force {
+                    return false;
+                }
+                if let Err(err) = stop(None).await {
+                    log::error!("{err}");
+                }
+            }
+            Ok(_) => (),
+            Err(err) => log::error!("{err}"),
+        }
+        true
+    }
+}
+
+/// Resolves once the server shuts down, without keeping `closing` borrowed.
+async fn shutting_down(closing: &mut watch::Receiver<bool>) {
+    let _ = closing.wait_for(|closing| *closing).await;
+}
+
+/// Next connection of the plain TCP listener, never resolving without one.
+async fn accept_tcp(listener: Option<&TcpListener>) -> io::Result<(TcpStream, SocketAddr)> {
+    match listener {
+        Some(listener) => listener.accept().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Next connection of the TLS listener, along with the acceptor to handshake it with,
+/// never resolving without one.
+async fn accept_tls(
+    listener: Option<&(TcpListener, TlsAcceptor)>,
+) -> io::Result<(TcpStream, SocketAddr, TlsAcceptor)> {
+    match listener {
+        Some((listener, acceptor)) => {
+            let (stream, peer) = listener.accept().await?;
+            Ok((stream, peer, acceptor.clone()))
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Next connection of the Unix socket listener, never resolving without one.
+async fn accept_unix(listener: Option<&UnixListener>) -> io::Result<UnixStream> {
+    match listener {
+        Some(listener) => listener.accept().await.map(|(stream, _)| stream),
+        None => std::future::pending().await,
+    }
+}
+
+/// State shared by the connection tasks, whatever stream they are served on.
+#[derive(Debug, Clone)]
+struct Connections {
+    command_registry: Arc<CommandRegistry>,
+    script_monitor: Arc<ScriptMonitor>,
+    busy_reply_threshold: Duration,
+    /// Users, telling whether connections must authenticate before sending commands.
+    acl: Arc<Acl>,
+    clients: Arc<ClientTable>,
+    maxclients: usize,
+    output_buffer_limits: OutputBufferLimits,
+    /// Bytes of a command a client may send before the connection is closed.
+    query_buffer_limit: usize,
+    /// Read by the commands being run, and written by a shutdown to wait for them and
+    /// hold back the others.
+    running: Arc<RwLock<()>>,
+    closing: watch::Receiver<bool>,
+}
+
+impl Connections {
+    /// Reads commands from `stream` and writes their replies, until the client disconnects.
+    async fn serve<S>(self, mut stream: S, address: ClientAddress)
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+    {
+        let Self {
+            command_registry,
+            script_monitor,
+            busy_reply_threshold,
+            acl,
+            clients,
+            maxclients,
+            output_buffer_limits,
+            query_buffer_limit,
+            mut closing,
+            running,
+        } = self;
+        log::info!("Incoming request");
+        let id = NEXT_CLIENT_ID.fetch_add(1, Ordering::Relaxed);
+        let (push, mut pushes) = push_channel(id, output_buffer_limits);
+        let mut session = Session::with_push(push);
+        session.set_authenticated(acl.default_authenticated());
+        session.set_address(address);
+        let connected = match clients.connect(&session, maxclients) {
+            Ok(connected) => connected,
+            Err(err) => {
+                let _ = write_resp(&mut stream, err.into()).await;
+                return;
+            }
+        };
+
+        let mut query = Vec::new();
+        'connection: loop {
+            let mut buf = vec![0u8; 1024];
+
+            let read = tokio::select! {
+                read = stream.read(&mut buf) => read,
+                _ = connected.kill().notified() => break,
+                _ = shutting_down(&mut closing) => break,
+                pushed = pushes.recv() => {
+                    let Some(message) = pushed else {
+                        log::warn!("Disconnecting client {id} over its output buffer limit");
+                        break;
+                    };
+                    let message = push_frame(&session, message);
+                    if let Err(err) = write_resp(&mut stream, message).await {
+                        log::error!("{}", err.to_string());
+                        break;
+                    }
+                    continue;
+                }
+            };
+            match read {
+                Ok(0) => {
+                    //let _ = stream.write_all(b"NO COMMAND WAS SENDED").await;
+                    break;
+                }
+                Ok(size) => {
+                    query.extend_from_slice(&buf[..size]);
+                    let commands = match take_commands(&mut query) {
+                        Ok(commands) => commands,
+                        Err(err) => {
+                            query.clear();
+                            let err = Into::<Resp>::into(err);
+
+                            if let Ok(serialized) = err.serialize() {
+                                let _ = stream.write_all(&serialized).await;
+                            } else {
+                                log::error!("Unable to serialize error")
+                            }
+                            continue;
+                        }
+                    };
+                    if query.len() > query_buffer_limit {
+                        log::warn!("Closing client {id} that reached max query buffer length");
+                        break;
+                    }
+                    for command in commands {
+                        let (command, args) = match parse_command(&command) {
+                            Ok(parsed) => parsed,
+                            Err(err) => {
+                                if let Err(err) = write_resp(&mut stream, err.into()).await {
+                                    log::error!("{}", err.to_string());
+                                    break 'connection;
                                 }
-                            };
+                                continue;
+                            }
+                        };
+                        let name = command_name(command, args);
+                        let shutdown = name == "shutdown";
+                        // `CLIENT` commands are never held back, so that a paused
+                        // server can be unpaused.
+                        if !name.starts_with("client|") {
+                            clients.wait_unpaused(may_write(&name)).await;
+                        }
+                        // Blocking commands would hold a shutdown up until they time out.
+                        let _running = match shutdown || blocking(&name) {
+                            true => None,
+                            false => tokio::select! {
+                                biased;
+                                _ = shutting_down(&mut closing) => break 'connection,
+                                running = running.read() => Some(running),
+                            },
+                        };
+                        session.start_command(name);
+
+                        let busy = script_monitor.busy(busy_reply_threshold);
+                        let refused = refusal(&session, busy, command, args);
+                        let result = if let Some(err) = refused {
+                            Err(err)
+                        } else if args.is_empty() {
+                            command_registry
+                                .no_args_command(&mut session, command)
+                                .await
+                        } else {
+                            command_registry
+                                .command_with_args(&mut session, command, args)
+                                .await
+                        }
+                        .map_err(Into::<Resp>::into);
+                        // Pushed replies, like `SUBSCRIBE`
+                        // confirmations, go before the next one.
+                        while let Some(message) = pushes.try_recv() {
+                            let message = push_frame(&session, message);
+                            if let Err(err) = write_resp(&mut stream, message).await {
+                                log::error!("{}", err.to_string());
+                                break 'connection;
+                            }
+                        }
+                        if pushes.overflowed() || shutdown && result.is_ok() {
+                            break 'connection;
+                        }
+                        pushes.set_pubsub(session.subscribed());
+                        clients.update(
+                            ClientInfo::new(&session).with_buffers(query.len(), pushes.queued()),
+                        );
+                        let suppressed = session.reply_suppressed();
+                        if suppressed || session.take_reply_pushed() && result.is_ok() {
+                            continue;
+                        }
+                        let response = match result {
+                            Ok(res) => res.serialize(),
+                            Err(err) => err.serialize(),
+                        };
+
+                        if let Ok(response) = response {
+                            // Counted while being written, like pushed messages are.
+                            if !pushes.reply_queued(response.len()) {
+                                log::warn!(
+                                    "Disconnecting client {id} over its output buffer limit"
+                                );
+                                break 'connection;
+                            }
+                            let written = stream.write_all(&response).await;
+                            pushes.reply_written(response.len());
+                            if let Err(err) = written {
+                                log::error!("{}", err.to_string());
+                                break;
+                            }
+                        } else if let Err(err) = response {
+                            log::error!("{}", err.to_string());
+                            break;
                         }
-                    });
+                    }
+                }
+                Err(err) => {
+                    log::error!("{:?}", err.to_string());
+                    break;
                 }
-                Err(err) => log::error!("{:?}", err.to_string()),
             };
         }
     }
 }
 
-pub fn parse_commands(input: &[u8]) -> Result<Vec<Resp>, AppError> {
+/// Name and arguments of a command, sent as an array of bulk strings.
+fn parse_command(command: &Resp) -> Result<(&str, &[Resp]), AppError> {
+    match command {
+        Resp::Array(items) => match items.split_first() {
+            Some((Resp::BulkString(name), args)) => Ok((command_as_str(name)?, args)),
+            Some(_) => Err(AppError::InvalidArgType("bulk string".to_owned())),
+            None => Err(AppError::InvalidCommand("empty array".to_owned())),
+        },
+        _ => Err(AppError::InvalidArgType("array".to_owned())),
+    }
+}
+
+/// Error replied instead of running `command`: to clients that did not authenticate yet,
+/// and while a script is `busy` to any command but the one killing it and
+/// `SHUTDOWN NOSAVE`.
+fn refusal(
+    session: &Session,
+    busy: Option<ScriptKind>,
+    command: &str,
+    args: &[Resp],
+) -> Option<AppError> {
+    let name = command.to_uppercase();
+    if !session.authenticated() && !NO_AUTH_COMMANDS.contains(&name.as_str()) {
+        return Some(AppError::NoAuth);
+    }
+    let killing = matches!(args.first(), Some(Resp::BulkString(subcommand))
+        if subcommand.eq_ignore_ascii_case(b"KILL"));
+    let nosave = args
+        .iter()
+        .any(|arg| matches!(arg, Resp::BulkString(arg) if arg.eq_ignore_ascii_case(b"NOSAVE")));
+    match busy {
+        Some(_) if killing && ["SCRIPT", "FUNCTION"].contains(&name.as_str()) => None,
+        Some(_) if nosave && name == "SHUTDOWN" => None,
+        Some(kind) => Some(AppError::Busy(kind.kill_command())),
+        None => None,
+    }
+}
+
+/// Pushed messages are arrays in RESP2 and out of band pushes in RESP3.
+fn push_frame(session: &Session, message: Resp) -> Resp {
+    match message {
+        Resp::Array(items) if session.resp3() => Resp::Push(items),
+        message => message,
+    }
+}
+
+async fn write_resp(stream: &mut (impl AsyncWrite + Unpin), resp: Resp) -> Result<(), AppError> {
+    stream.write_all(&resp.serialize()?).await?;
+    Ok(())
+}
+
+/// Takes the commands fully received from the start of `query`, leaving the rest of it to
+/// be completed by the next reads.
+pub fn take_commands(query: &mut Vec<u8>) -> Result<Vec<Resp>, AppError> {
     let mut parsed = 0;
     let mut commands = Vec::new();
-    while parsed < input.len() {
-        let cur = Resp::deserialize(&input[parsed..])?;
-        parsed += cur.size();
-        commands.push(cur);
+    while let Some(len) = Resp::frame_len(&query[parsed..])? {
+        commands.push(Resp::deserialize(&query[parsed..parsed + len])?);
+        parsed += len;
     }
+    query.drain(..parsed);
     Ok(commands)
 }
 
@@ -0,0 +1,74 @@
+use std::{path::PathBuf, sync::Arc};
+
+use rustls::{
+    pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+    server::WebPkiClientVerifier,
+    RootCertStore, ServerConfig,
+};
+use tokio_rustls::TlsAcceptor;
+
+use crate::{
+    config::{AppConfig, TlsAuthClients},
+    errors::AppError,
+};
+
+/// `tls-port` connections are accepted on, with the certificates they are secured by.
+#[derive(Debug)]
+pub struct TlsSettings {
+    pub port: u16,
+    cert_file: Option<PathBuf>,
+    key_file: Option<PathBuf>,
+    ca_cert_file: Option<PathBuf>,
+    auth_clients: TlsAuthClients,
+}
+
+impl TlsSettings {
+    /// Settings of the TLS listener, when `tls-port` is set to anything but 0.
+    pub fn from_config(config: &AppConfig) -> Option<Self> {
+        Some(Self {
+            port: config.tls_port.filter(|port| *port != 0)?,
+            cert_file: config.tls_cert_file.clone(),
+            key_file: config.tls_key_file.clone(),
+            ca_cert_file: config.tls_ca_cert_file.clone(),
+            auth_clients: config.tls_auth_clients(),
+        })
+    }
+
+    /// Loads the certificates, failing when a file needed by `tls-auth-clients` is missing.
+    pub fn acceptor(&self) -> Result<TlsAcceptor, AppError> {
+        let (Some(cert_file), Some(key_file)) = (&self.cert_file, &self.key_file) else {
+            return Err(tls_error("tls-cert-file and tls-key-file are required"));
+        };
+        let certs = CertificateDer::pem_file_iter(cert_file)
+            .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+            .map_err(tls_error)?;
+        let key = PrivateKeyDer::from_pem_file(key_file).map_err(tls_error)?;
+        let builder = ServerConfig::builder();
+        let builder = match self.auth_clients {
+            TlsAuthClients::No => builder.with_no_client_auth(),
+            auth_clients => {
+                let Some(ca_cert_file) = &self.ca_cert_file else {
+                    return Err(tls_error(
+                        "tls-ca-cert-file is required to authenticate clients",
+                    ));
+                };
+                let mut roots = RootCertStore::empty();
+                for cert in CertificateDer::pem_file_iter(ca_cert_file).map_err(tls_error)? {
+                    roots.add(cert.map_err(tls_error)?).map_err(tls_error)?;
+                }
+                let verifier = WebPkiClientVerifier::builder(roots.into());
+                let verifier = match auth_clients {
+                    TlsAuthClients::Optional => verifier.allow_unauthenticated(),
+                    _ => verifier,
+                };
+                builder.with_client_cert_verifier(verifier.build().map_err(tls_error)?)
+            }
+        };
+        let config = builder.with_single_cert(certs, key).map_err(tls_error)?;
+        Ok(TlsAcceptor::from(Arc::new(config)))
+    }
+}
+
+fn tls_error(err: impl ToString) -> AppError {
+    AppError::TlsConfig(err.to_string())
+}
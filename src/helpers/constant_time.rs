@@ -0,0 +1,24 @@
+use super::sha256::sha256;
+
+/// Compares secrets in a time that depends on neither their content nor their length,
+/// both being hashed to digests of the same size first, like Redis does for passwords.
+pub fn constant_time_eq(left: &[u8], right: &[u8]) -> bool {
+    let (left, right) = (sha256(left), sha256(right));
+    left.iter()
+        .zip(right.iter())
+        .fold(0, |diff, (left, right)| diff | (left ^ right))
+        == 0
+}
+
+#[cfg(test)]
+mod test {
+    use super::constant_time_eq;
+
+    #[test]
+    fn should_compare_secrets() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret!"));
+        assert!(constant_time_eq(b"", b""));
+    }
+}
@@ -0,0 +1,62 @@
+/// CRC-16/XMODEM as used by Redis Cluster to map keys to hash slots: polynomial 0x1021,
+/// zero initial value, no reflection and no final xor.
+const POLY: u16 = 0x1021;
+
+/// Number of hash slots keys and shard channels are spread over.
+pub const CLUSTER_SLOTS: u16 = 16384;
+
+const TABLE: [u16; 256] = {
+    let mut table = [0; 256];
+    let mut byte = 0;
+    while byte < 256 {
+        let mut crc = (byte as u16) << 8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ POLY
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[byte] = crc;
+        byte += 1;
+    }
+    table
+};
+
+pub fn crc16(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0, |crc, byte| {
+        TABLE[((crc >> 8) ^ *byte as u16) as usize & 0xff] ^ (crc << 8)
+    })
+}
+
+/// Slot of `key`, only hashing the part between the first `{` and the next `}` when it
+/// is not empty so that related keys can be kept together.
+pub fn key_hash_slot(key: &[u8]) -> u16 {
+    let tag = key.iter().position(|byte| *byte == b'{').and_then(|start| {
+        let end = key[start + 1..].iter().position(|byte| *byte == b'}')?;
+        Some(&key[start + 1..start + 1 + end]).filter(|tag| !tag.is_empty())
+    });
+    crc16(tag.unwrap_or(key)) % CLUSTER_SLOTS
+}
+
+#[cfg(test)]
+mod test {
+    use super::{crc16, key_hash_slot};
+
+    #[test]
+    fn should_match_redis_check_value() {
+        assert_eq!(crc16(b"123456789"), 0x31c3);
+    }
+
+    #[test]
+    fn should_hash_only_the_tag() {
+        assert_eq!(key_hash_slot(b"foo"), 12182);
+        assert_eq!(
+            key_hash_slot(b"{user1000}.following"),
+            key_hash_slot(b"user1000")
+        );
+        assert_eq!(key_hash_slot(b"foo{}{bar}"), crc16(b"foo{}{bar}") % 16384);
+    }
+}
@@ -0,0 +1,45 @@
+/// Decompresses an LZF block, the compression Redis applies to long RDB strings.
+/// Returns `None` on a corrupted block or when the output is not `len` bytes long.
+pub fn lzf_decompress(input: &[u8], len: usize) -> Option<Vec<u8>> {
+    let mut output = Vec::with_capacity(len);
+    let mut position = 0;
+    while position < input.len() {
+        let control = input[position] as usize;
+        position += 1;
+        if control < 32 {
+            let literal = input.get(position..position + control + 1)?;
+            output.extend_from_slice(literal);
+            position += control + 1;
+            continue;
+        }
+        let mut length = control >> 5;
+        if length == 7 {
+            length += *input.get(position)? as usize;
+            position += 1;
+        }
+        let offset = ((control & 0x1f) << 8) + *input.get(position)? as usize + 1;
+        position += 1;
+        let start = output.len().checked_sub(offset)?;
+        // Back references may overlap the bytes they produce.
+        for index in start..start + length + 2 {
+            output.push(output[index]);
+        }
+    }
+    (output.len() == len).then_some(output)
+}
+
+#[cfg(test)]
+mod test {
+    use super::lzf_decompress;
+
+    #[test]
+    fn should_expand_literals_and_back_references() {
+        // "abc" as a literal run followed by a reference copying it three more times.
+        let compressed = [2, b'a', b'b', b'c', 0xe0, 0x00, 0x02];
+        assert_eq!(
+            lzf_decompress(&compressed, 12).unwrap(),
+            b"abcabcabcabc".to_vec()
+        );
+        assert!(lzf_decompress(&compressed, 11).is_none());
+    }
+}
@@ -1 +1,8 @@
+pub mod constant_time;
+pub mod crc16;
+pub mod crc64;
+pub mod glob;
+pub mod lzf;
 pub mod r#macro;
+pub mod number;
+pub mod sha256;
@@ -0,0 +1,40 @@
+pub fn parse_float(bytes: &[u8]) -> Option<f64> {
+    let float = std::str::from_utf8(bytes).ok()?.parse::<f64>().ok()?;
+    (!float.is_nan()).then_some(float)
+}
+
+/// Parses a memory amount like Redis configuration does: bytes, or a number followed by
+/// `k`, `m`, `g` for powers of 1000 and `kb`, `mb`, `gb` for powers of 1024.
+pub fn parse_memory(value: &str) -> Option<usize> {
+    let value = value.to_lowercase();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let multiplier: usize = match unit {
+        "" | "b" => 1,
+        "k" => 1000,
+        "kb" => 1024,
+        "m" => 1000 * 1000,
+        "mb" => 1024 * 1024,
+        "g" => 1000 * 1000 * 1000,
+        "gb" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+    number.parse::<usize>().ok()?.checked_mul(multiplier)
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_memory;
+
+    #[test]
+    fn should_parse_memory_units() {
+        assert_eq!(parse_memory("100"), Some(100));
+        assert_eq!(parse_memory("2k"), Some(2000));
+        assert_eq!(parse_memory("32mb"), Some(32 * 1024 * 1024));
+        assert_eq!(parse_memory("1GB"), Some(1024 * 1024 * 1024));
+        assert_eq!(parse_memory("mb"), None);
+        assert_eq!(parse_memory("3tb"), None);
+    }
+}
@@ -1,3 +1,5 @@
+mod acl;
+mod clients;
 pub mod commands;
 mod config;
 mod data_management;
@@ -5,46 +7,106 @@ pub mod errors;
 mod event_loop;
 pub mod helpers;
 mod resp;
+mod shutdown;
 
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use clap::Parser;
 use config::AppConfig;
 use data_management::{
-    datastore::DataStore, hash_table_store::HashTableDataStore, message::DataChannelMessage,
+    datastore::DataStore,
+    hash_table_store::HashTableDataStore,
+    message::DataChannelMessage,
+    notification::NotifyFlags,
+    rdb::{self, Snapshot},
     worker::DataManager,
 };
 use errors::AppError;
 use event_loop::EventLoop;
+use shutdown::ShutdownRequest;
 use tokio::sync::{mpsc, Notify};
 
+/// Active expiry period when `expired` events are published, so that they follow the
+/// expiry time closely instead of waiting for the hourly cleanup.
+const EXPIRED_EVENTS_CLEANUP_INTERVAL: Duration = Duration::from_millis(100);
+
 pub struct App<T>
 where
     T: DataStore,
 {
     event_loop: EventLoop,
     data_manager: DataManager<T>,
-    config: Arc<AppConfig>,
 }
 
 impl<T> App<T>
 where
     T: DataStore,
 {
+    /// `data_store` seeds database 0, otherwise databases are loaded from the configured
+    /// RDB file when it exists.
     pub fn new(port: i32, host: String, data_store: Option<T>, config: Arc<AppConfig>) -> Self {
         let (data_sender, data_receiver) = mpsc::channel::<DataChannelMessage>(1000);
         let event_loop = EventLoop::new(port, host, data_sender.into(), &config);
-        let data_manager = DataManager::new(data_receiver, data_store, None);
+        let notify_flags = config.notify_keyspace_events();
+        let cleanup_intervall = notify_flags
+            .enabled(NotifyFlags::EXPIRED)
+            .then_some(EXPIRED_EVENTS_CLEANUP_INTERVAL);
+        let snapshot = Self::snapshot(data_store, &config);
+        let data_manager = DataManager::new(
+            data_receiver,
+            snapshot.databases,
+            cleanup_intervall,
+            notify_flags,
+        )
+        .with_commands(event_loop.command_registry())
+        .with_script_monitor(event_loop.script_monitor())
+        .with_libraries(snapshot.libraries);
         Self {
             event_loop,
             data_manager,
-            config,
         }
     }
 
+    /// Server listening where `config` tells, on `port` and `bind`.
+    pub fn from_config(data_store: Option<T>, config: Arc<AppConfig>) -> Self {
+        let (port, host) = (config.port().into(), config.bind().to_owned());
+        Self::new(port, host, data_store, config)
+    }
+
+    fn snapshot(data_store: Option<T>, config: &AppConfig) -> Snapshot<T> {
+        let empty = || Snapshot {
+            databases: vec![],
+            libraries: vec![],
+        };
+        let mut snapshot = match config.rdb_path().filter(|path| path.exists()) {
+            Some(path) => rdb::load_file(&path, config.databases()).unwrap_or_else(|err| {
+                log::error!("Could not load {}: {err}", path.display());
+                empty()
+            }),
+            None => empty(),
+        };
+        snapshot
+            .databases
+            .resize_with(config.databases(), T::default);
+        if let Some(data_store) = data_store {
+            snapshot.databases[0] = data_store;
+        }
+        snapshot
+    }
+
+    /// Where shutdowns are requested from outside of the connections.
+    pub fn shutdown_requests(&self) -> mpsc::Sender<ShutdownRequest> {
+        self.event_loop.shutdown_requests()
+    }
+
+    /// Serves until shut down, returning once the data worker stopped.
     pub async fn run(self, notif: Option<&Notify>) -> Result<(), AppError> {
-        self.data_manager.run();
-        self.event_loop.run(notif).await
+        let data_manager = self.data_manager.run();
+        self.event_loop.run(notif).await?;
+        if let Err(err) = data_manager.await {
+            log::error!("Data worker failed: {err}");
+        }
+        Ok(())
     }
 }
 
@@ -52,40 +114,78 @@ where
 async fn main() -> Result<(), AppError> {
     env_logger::init();
     let config = AppConfig::try_parse().unwrap();
-    let runner = App::<HashTableDataStore>::new(6379, "127.0.0.1".to_string(), None, config.into());
+    let runner = App::<HashTableDataStore>::from_config(None, config.into());
+    let requests = runner.shutdown_requests();
+    tokio::spawn(async move {
+        match shutdown::signaled().await {
+            Ok(request) => {
+                let _ = requests.send(request).await;
+            }
+            Err(err) => return log::error!("Could not listen to signals: {err}"),
+        }
+        // Like Redis, a second signal does not wait for the shutdown to end.
+        if shutdown::signaled().await.is_ok() {
+            log::warn!("You insist... exiting now.");
+            std::process::exit(1);
+        }
+    });
     runner.run(None).await
 }
 #[cfg(test)]
 mod test {
-    use std::{sync::Arc, time::Duration, usize};
+    use std::{os::unix::fs::PermissionsExt, sync::Arc, time::Duration};
 
     use super::*;
     use data_management::datastore::DataStoreEntry;
     use futures::future::join_all;
     use resp::Resp;
     use tokio::{
-        io::{AsyncReadExt, AsyncWriteExt},
-        net::TcpStream,
+        io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+        net::{TcpStream, UnixStream},
+        task::JoinHandle,
     };
 
-    async fn setup(data: Option<HashTableDataStore>, config: AppConfig) -> TcpStream {
-        let host = "127.0.0.1".to_string();
-        let port = 6379;
+    /// Port nothing listens on, for a server to bind.
+    fn free_port() -> u16 {
+        let ephemeral = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        ephemeral.local_addr().unwrap().port()
+    }
+
+    /// Runs `app`, returning once it listens along with the task running it.
+    async fn run(app: App<HashTableDataStore>) -> JoinHandle<Result<(), AppError>> {
         let notif = Arc::new(Notify::new());
-        let notif2 = notif.clone();
-        tokio::spawn(async move {
-            let runner = App::new(port, host, data, config.into());
-            let _ = runner.run(Some(&notif2)).await;
-        });
-        notif.notified().await;
-        TcpStream::connect("127.0.0.1:6379").await.unwrap()
-    }
-    async fn send_request(stream: &mut TcpStream, req: impl AsRef<[u8]>) -> Vec<u8> {
+        let ready = notif.clone();
+        let mut task = tokio::spawn(async move { app.run(Some(&ready)).await });
+        tokio::select! {
+            _ = notif.notified() => task,
+            stopped = &mut task => panic!("server did not start: {stopped:?}"),
+        }
+    }
+
+    /// Starts a server on a free port, returning its address.
+    async fn start(
+        data: Option<HashTableDataStore>,
+        config: AppConfig,
+    ) -> (String, JoinHandle<Result<(), AppError>>) {
+        let port = free_port();
+        let host = "127.0.0.1".to_owned();
+        let app = run(App::new(port.into(), host, data, config.into())).await;
+        (format!("127.0.0.1:{port}"), app)
+    }
+
+    async fn setup(data: Option<HashTableDataStore>, config: AppConfig) -> TcpStream {
+        let (address, _) = start(data, config).await;
+        TcpStream::connect(address).await.unwrap()
+    }
+    async fn send_request(
+        stream: &mut (impl AsyncRead + AsyncWrite + Unpin),
+        req: impl AsRef<[u8]>,
+    ) -> Vec<u8> {
         stream.write_all(req.as_ref()).await.unwrap();
         let mut buf = vec![0u8; 1024];
         let size = stream.read(&mut buf).await.unwrap();
         buf.resize(size, 0u8);
-        return buf;
+        buf
     }
     #[tokio::test]
     async fn should_reply_to_ping() {
@@ -116,25 +216,17 @@ mod test {
         const INPUT: &str = "*1\r\n$4\r\nPING\r\n";
         const EXPECT: &str = "+PONG\r\n";
         const CLIENTS: usize = 6;
-        tokio::spawn(async move {
-            let runner = App::<HashTableDataStore>::new(
-                6379,
-                "0.0.0.0".to_owned(),
-                None,
-                AppConfig::default().into(),
-            );
-            let _ = runner.run(None).await;
-        });
-        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+        let (address, _) = start(None, AppConfig::default()).await;
         let mut client_handles = Vec::with_capacity(CLIENTS);
         for client_id in 0..CLIENTS {
+            let address = address.clone();
             let handle = tokio::spawn(async move {
-                let mut stream = TcpStream::connect("127.0.0.1:6379").await.unwrap();
+                let mut stream = TcpStream::connect(address).await.unwrap();
                 stream.write_all(INPUT.as_bytes()).await.unwrap();
                 let mut buf = Vec::with_capacity(1024);
                 stream.shutdown().await.unwrap();
                 let size = stream.read_to_end(&mut buf).await.unwrap();
-                return (buf[..size].to_vec(), client_id);
+                (buf[..size].to_vec(), client_id)
             });
             client_handles.push(handle);
         }
@@ -193,6 +285,23 @@ mod test {
         assert_eq!(res, EXPECT)
     }
 
+    #[tokio::test]
+    async fn should_keep_crlfs_and_empty_strings_in_values() {
+        const SET_CRLF: &str = "*3\r\n$3\r\nSET\r\n$4\r\ncrlf\r\n$4\r\na\r\nb\r\n";
+        const GET_CRLF: &str = "*2\r\n$3\r\nGET\r\n$4\r\ncrlf\r\n";
+        const SET_EMPTY: &str = "*3\r\n$3\r\nSET\r\n$5\r\nempty\r\n$0\r\n\r\n";
+        const GET_EMPTY: &str = "*2\r\n$3\r\nGET\r\n$5\r\nempty\r\n";
+        let mut stream = setup(None, AppConfig::default()).await;
+
+        assert_eq!(&send_request(&mut stream, SET_CRLF).await, b"+OK\r\n");
+        assert_eq!(
+            &send_request(&mut stream, GET_CRLF).await,
+            b"$4\r\na\r\nb\r\n"
+        );
+        assert_eq!(&send_request(&mut stream, SET_EMPTY).await, b"+OK\r\n");
+        assert_eq!(&send_request(&mut stream, GET_EMPTY).await, b"$0\r\n\r\n");
+    }
+
     #[tokio::test]
     async fn shoudl_reply_null_bulk_string_if_no_data() {
         const INPUT: &str = "*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n";
@@ -265,6 +374,383 @@ mod test {
         let res = std::str::from_utf8(&res).unwrap();
         assert_eq!(res, EXPECT)
     }
+    #[tokio::test]
+    async fn should_set_and_get_hash_field() {
+        const SET: &str = "*4\r\n$4\r\nHSET\r\n$7\r\nsession\r\n$4\r\nuser\r\n$5\r\nalice\r\n";
+        const GET: &str = "*3\r\n$4\r\nHGET\r\n$7\r\nsession\r\n$4\r\nuser\r\n";
+        let mut stream = setup(None, AppConfig::default()).await;
+
+        let res = send_request(&mut stream, SET).await;
+        assert_eq!(std::str::from_utf8(&res).unwrap(), ":1\r\n");
+        let res = send_request(&mut stream, GET).await;
+        assert_eq!(std::str::from_utf8(&res).unwrap(), "$5\r\nalice\r\n");
+    }
+    #[tokio::test]
+    async fn should_isolate_selected_databases() {
+        const SELECT: &str = "*2\r\n$6\r\nSELECT\r\n$1\r\n1\r\n";
+        const SET: &str = "*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$5\r\nworld\r\n";
+        const GET: &str = "*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n";
+        let (address, _) = start(None, AppConfig::default()).await;
+        let mut stream = TcpStream::connect(&address).await.unwrap();
+
+        let res = send_request(&mut stream, SELECT).await;
+        assert_eq!(std::str::from_utf8(&res).unwrap(), "+OK\r\n");
+        send_request(&mut stream, SET).await;
+        let mut other = TcpStream::connect(&address).await.unwrap();
+        let res = send_request(&mut other, GET).await;
+        assert_eq!(std::str::from_utf8(&res).unwrap(), "$-1\r\n");
+        let res = send_request(&mut stream, GET).await;
+        assert_eq!(std::str::from_utf8(&res).unwrap(), "$5\r\nworld\r\n");
+    }
+    #[tokio::test]
+    async fn should_run_transaction_unless_watched_key_changed() {
+        const WATCH: &str = "*2\r\n$5\r\nWATCH\r\n$5\r\nhello\r\n";
+        const MULTI: &str = "*1\r\n$5\r\nMULTI\r\n";
+        const EXEC: &str = "*1\r\n$4\r\nEXEC\r\n";
+        const SET: &str = "*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$5\r\nworld\r\n";
+        const GET: &str = "*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n";
+        let (address, _) = start(None, AppConfig::default()).await;
+        let mut stream = TcpStream::connect(&address).await.unwrap();
+
+        for (request, expected) in [
+            (MULTI, "+OK\r\n"),
+            (SET, "+QUEUED\r\n"),
+            (GET, "+QUEUED\r\n"),
+        ] {
+            let res = send_request(&mut stream, request).await;
+            assert_eq!(std::str::from_utf8(&res).unwrap(), expected);
+        }
+        let res = send_request(&mut stream, EXEC).await;
+        assert_eq!(
+            std::str::from_utf8(&res).unwrap(),
+            "*2\r\n+OK\r\n$5\r\nworld\r\n"
+        );
+
+        send_request(&mut stream, WATCH).await;
+        let mut other = TcpStream::connect(&address).await.unwrap();
+        send_request(&mut other, SET).await;
+        send_request(&mut stream, MULTI).await;
+        send_request(&mut stream, GET).await;
+        let res = send_request(&mut stream, EXEC).await;
+        assert_eq!(std::str::from_utf8(&res).unwrap(), "*-1\r\n");
+    }
+    #[tokio::test]
+    async fn should_run_cached_script_calling_commands() {
+        const LOAD: &str = "*3\r\n$6\r\nSCRIPT\r\n$4\r\nLOAD\r\n$69\r\nredis.call('SET', KEYS[1], ARGV[1]) return redis.call('GET', KEYS[1])\r\n";
+        const SHA: &str = "2b20e84ba26251fc86a627f03be6dd614b31bac8";
+        let mut stream = setup(None, AppConfig::default()).await;
+
+        let res = send_request(&mut stream, LOAD).await;
+        assert_eq!(
+            std::str::from_utf8(&res).unwrap(),
+            format!("$40\r\n{SHA}\r\n")
+        );
+        let evalsha = format!(
+            "*5\r\n$7\r\nEVALSHA\r\n$40\r\n{SHA}\r\n$1\r\n1\r\n$6\r\nscript\r\n$3\r\nlua\r\n"
+        );
+        let res = send_request(&mut stream, &evalsha).await;
+        assert_eq!(std::str::from_utf8(&res).unwrap(), "$3\r\nlua\r\n");
+        let evalsha = evalsha.replace(SHA, &"0".repeat(40));
+        let res = send_request(&mut stream, evalsha).await;
+        assert_eq!(
+            std::str::from_utf8(&res).unwrap(),
+            "-NOSCRIPT No matching script. Please use EVAL.\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn should_serve_clients_on_unix_socket() {
+        const INFO: &str = "*2\r\n$6\r\nCLIENT\r\n$4\r\nINFO\r\n";
+        let path = std::env::temp_dir().join(format!("redis-{}.sock", std::process::id()));
+        let config = AppConfig::parse_from([
+            "config",
+            "--unixsocket",
+            path.to_str().unwrap(),
+            "--unixsocketperm",
+            "700",
+        ]);
+        run(App::new(0, "127.0.0.1".to_owned(), None, config.into())).await;
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+        let mut stream = UnixStream::connect(&path).await.unwrap();
+        let res = send_request(&mut stream, "*1\r\n$4\r\nPING\r\n").await;
+        assert_eq!(&res, b"+PONG\r\n");
+        let res = send_request(&mut stream, INFO).await;
+        let info = String::from_utf8(res).unwrap();
+        let socket = format!("addr={0}:0 laddr={0}:0 ", path.display());
+        assert!(info.contains(&socket), "{info}");
+        assert!(info.contains(" flags=U "), "{info}");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_reject_clients_over_maxclients() {
+        let path = std::env::temp_dir().join(format!("redis-max-{}.sock", std::process::id()));
+        let config = AppConfig::parse_from([
+            "config",
+            "--unixsocket",
+            path.to_str().unwrap(),
+            "--maxclients",
+            "1",
+        ]);
+        run(App::new(0, "127.0.0.1".to_owned(), None, config.into())).await;
+
+        let mut first = UnixStream::connect(&path).await.unwrap();
+        first.write_all(b"*1\r\n$4\r\nPI").await.unwrap();
+        let res = send_request(&mut first, "NG\r\n").await;
+        assert_eq!(&res, b"+PONG\r\n");
+        let mut second = UnixStream::connect(&path).await.unwrap();
+        let res = send_request(&mut second, "*1\r\n$4\r\nPING\r\n").await;
+        assert_eq!(&res, b"-ERR max number of clients reached\r\n");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_reply_error_to_empty_commands_and_free_their_slot() {
+        let path = std::env::temp_dir().join(format!("redis-empty-{}.sock", std::process::id()));
+        let config = AppConfig::parse_from([
+            "config",
+            "--unixsocket",
+            path.to_str().unwrap(),
+            "--maxclients",
+            "1",
+        ]);
+        run(App::new(0, "127.0.0.1".to_owned(), None, config.into())).await;
+
+        let mut first = UnixStream::connect(&path).await.unwrap();
+        let res = send_request(&mut first, "*0\r\n").await;
+        assert_eq!(&res, b"-ERR invalid command 'empty array'\r\n");
+        let res = send_request(&mut first, "*1\r\n$4\r\nPING\r\n").await;
+        assert_eq!(&res, b"+PONG\r\n");
+        drop(first);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let mut second = UnixStream::connect(&path).await.unwrap();
+        let res = send_request(&mut second, "*1\r\n$4\r\nPING\r\n").await;
+        assert_eq!(&res, b"+PONG\r\n");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_save_and_exit_on_shutdown() {
+        const SET: &str = "*3\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$3\r\nbar\r\n";
+        let dir = std::env::temp_dir().join(format!("redis-shutdown-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = AppConfig::parse_from([
+            "config",
+            "--dir",
+            dir.to_str().unwrap(),
+            "--dbfilename",
+            "dump.rdb",
+        ]);
+        let databases = config.databases();
+        let (address, app) = start(None, config).await;
+
+        let mut stream = TcpStream::connect(&address).await.unwrap();
+        assert_eq!(&send_request(&mut stream, SET).await, b"+OK\r\n");
+        let mut idle = TcpStream::connect(&address).await.unwrap();
+        let res = send_request(&mut idle, "*1\r\n$4\r\nPING\r\n").await;
+        assert_eq!(&res, b"+PONG\r\n");
+        let res = send_request(&mut stream, "*1\r\n$8\r\nSHUTDOWN\r\n").await;
+        assert!(res.is_empty());
+
+        let stopped = tokio::time::timeout(Duration::from_secs(5), app).await;
+        assert!(stopped.unwrap().unwrap().is_ok());
+        assert_eq!(idle.read(&mut [0; 16]).await.unwrap(), 0);
+        assert!(TcpStream::connect(&address).await.is_err());
+        let snapshot = rdb::load_file::<HashTableDataStore>(&dir.join("dump.rdb"), databases);
+        let key = Resp::bulk_string_from_str("foo").serialize().unwrap();
+        assert!(snapshot.unwrap().databases[0].entry(&key).is_some());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn should_answer_in_flight_commands_before_shutting_down() {
+        let dir = std::env::temp_dir().join(format!("redis-drain-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = AppConfig::parse_from([
+            "config",
+            "--dir",
+            dir.to_str().unwrap(),
+            "--dbfilename",
+            "dump.rdb",
+        ]);
+        let databases = config.databases();
+        let (address, app) = start(None, config).await;
+
+        let sets: String = (0..10000)
+            .map(|i| {
+                let key = format!("k{i}");
+                format!("*3\r\n$3\r\nSET\r\n${}\r\n{key}\r\n$1\r\nv\r\n", key.len())
+            })
+            .collect();
+        let (mut replies, mut writes) = TcpStream::connect(&address).await.unwrap().into_split();
+        // Closed by the shutdown before every command is sent.
+        tokio::spawn(async move { writes.write_all(sets.as_bytes()).await });
+        let mut stream = TcpStream::connect(&address).await.unwrap();
+        let res = send_request(&mut stream, "*1\r\n$8\r\nSHUTDOWN\r\n").await;
+        assert!(res.is_empty());
+        let stopped = tokio::time::timeout(Duration::from_secs(5), app).await;
+        assert!(stopped.unwrap().unwrap().is_ok());
+
+        let mut answered = Vec::new();
+        let mut buf = [0; 4096];
+        while let Ok(size @ 1..) = replies.read(&mut buf).await {
+            answered.extend_from_slice(&buf[..size]);
+        }
+        let answered = String::from_utf8(answered).unwrap();
+        assert!(answered
+            .split_terminator("\r\n")
+            .all(|reply| reply == "+OK"));
+        let snapshot = rdb::load_file::<HashTableDataStore>(&dir.join("dump.rdb"), databases);
+        let saved = snapshot.unwrap().databases[0].len();
+        assert!(saved >= answered.matches("+OK").count());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_keep_clients_connected_when_shutdown_cannot_save() {
+        const PING: &str = "*1\r\n$4\r\nPING\r\n";
+        const FAILED: &[u8] = b"-ERR Errors trying to SHUTDOWN. Check logs.\r\n";
+        let dir = std::env::temp_dir().join(format!("redis-missing-{}", std::process::id()));
+        let config = AppConfig::parse_from([
+            "config",
+            "--dir",
+            dir.to_str().unwrap(),
+            "--dbfilename",
+            "dump.rdb",
+        ]);
+        let (address, _) = start(None, config).await;
+
+        let mut stream = TcpStream::connect(&address).await.unwrap();
+        let mut idle = TcpStream::connect(&address).await.unwrap();
+        assert_eq!(&send_request(&mut idle, PING).await, b"+PONG\r\n");
+        let res = send_request(&mut stream, "*1\r\n$8\r\nSHUTDOWN\r\n").await;
+        assert_eq!(&res, FAILED);
+        assert_eq!(&send_request(&mut idle, PING).await, b"+PONG\r\n");
+        assert_eq!(&send_request(&mut stream, PING).await, b"+PONG\r\n");
+    }
+
+    #[tokio::test]
+    async fn should_reply_error_to_shutdown_save_without_rdb_file() {
+        let mut stream = setup(None, AppConfig::default()).await;
+        let res = send_request(&mut stream, "*2\r\n$8\r\nSHUTDOWN\r\n$4\r\nSAVE\r\n").await;
+        assert_eq!(&res, b"-ERR Errors trying to SHUTDOWN. Check logs.\r\n");
+        let res = send_request(&mut stream, "*1\r\n$4\r\nPING\r\n").await;
+        assert_eq!(&res, b"+PONG\r\n");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn should_shut_down_nosave_while_an_unkillable_script_runs() {
+        const EVAL: &str =
+            "*3\r\n$4\r\nEVAL\r\n$43\r\nredis.call('SET','k','v') while true do end\r\n$1\r\n0\r\n";
+        const SCRIPT_KILL: &str = "*2\r\n$6\r\nSCRIPT\r\n$4\r\nKILL\r\n";
+        const SHUTDOWN: &str = "*1\r\n$8\r\nSHUTDOWN\r\n";
+        const SHUTDOWN_NOSAVE: &str = "*2\r\n$8\r\nSHUTDOWN\r\n$6\r\nNOSAVE\r\n";
+        let config = AppConfig::parse_from(["config", "--busy-reply-threshold", "10"]);
+        let (address, app) = start(None, config).await;
+
+        let mut script = TcpStream::connect(&address).await.unwrap();
+        script.write_all(EVAL.as_bytes()).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let mut stream = TcpStream::connect(&address).await.unwrap();
+        let res = send_request(&mut stream, SCRIPT_KILL).await;
+        assert!(res.starts_with(b"-UNKILLABLE"));
+        let res = send_request(&mut stream, SHUTDOWN).await;
+        assert!(res.starts_with(b"-BUSY"));
+        let res = send_request(&mut stream, SHUTDOWN_NOSAVE).await;
+        assert!(res.is_empty());
+
+        let stopped = tokio::time::timeout(Duration::from_secs(5), app).await;
+        assert!(stopped.unwrap().unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn should_serve_tls_only_clients_with_a_certificate() {
+        use rcgen::{BasicConstraints, CertificateParams, ExtendedKeyUsagePurpose, IsCa, KeyPair};
+        use rustls::{
+            pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer, ServerName},
+            ClientConfig, RootCertStore,
+        };
+        use tokio_rustls::TlsConnector;
+
+        let dir = std::env::temp_dir().join(format!("redis-tls-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca = ca_params.self_signed(&ca_key).unwrap();
+        let server_key = KeyPair::generate().unwrap();
+        let server = CertificateParams::new(vec!["localhost".to_owned()])
+            .unwrap()
+            .signed_by(&server_key, &ca, &ca_key)
+            .unwrap();
+        let client_key = KeyPair::generate().unwrap();
+        let mut client_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        client_params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth];
+        let client = client_params.signed_by(&client_key, &ca, &ca_key).unwrap();
+        let files = [
+            ("ca.crt", ca.pem()),
+            ("redis.crt", server.pem()),
+            ("redis.key", server_key.serialize_pem()),
+        ];
+        for (name, pem) in &files {
+            std::fs::write(dir.join(name), pem).unwrap();
+        }
+
+        let port = free_port();
+        let path = |name: &str| dir.join(name).to_str().unwrap().to_owned();
+        let config = AppConfig::parse_from([
+            "config".to_owned(),
+            // No plain TCP port, TLS only.
+            "--port".to_owned(),
+            "0".to_owned(),
+            "--tls-port".to_owned(),
+            port.to_string(),
+            "--tls-cert-file".to_owned(),
+            path("redis.crt"),
+            "--tls-key-file".to_owned(),
+            path("redis.key"),
+            "--tls-ca-cert-file".to_owned(),
+            path("ca.crt"),
+        ]);
+        run(App::from_config(None, config.into())).await;
+
+        let mut roots = RootCertStore::empty();
+        roots.add(ca.der().clone()).unwrap();
+        let connect = |config: ClientConfig| async move {
+            let stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+                .await
+                .unwrap();
+            let server_name = ServerName::try_from("localhost").unwrap();
+            TlsConnector::from(Arc::new(config))
+                .connect(server_name, stream)
+                .await
+        };
+        let client_cert = CertificateDer::from_pem_slice(client.pem().as_bytes()).unwrap();
+        let client_key = PrivateKeyDer::from_pem_slice(client_key.serialize_pem().as_bytes());
+        let config = ClientConfig::builder()
+            .with_root_certificates(roots.clone())
+            .with_client_auth_cert(vec![client_cert], client_key.unwrap())
+            .unwrap();
+        let mut stream = connect(config).await.unwrap();
+        let res = send_request(&mut stream, "*1\r\n$4\r\nPING\r\n").await;
+        assert_eq!(&res, b"+PONG\r\n");
+
+        let config = ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let rejected = match connect(config).await {
+            Err(_) => true,
+            Ok(mut stream) => {
+                let _ = stream.write_all(b"*1\r\n$4\r\nPING\r\n").await;
+                matches!(stream.read(&mut [0; 16]).await, Err(_) | Ok(0))
+            }
+        };
+        assert!(rejected);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
     //#[ignore = "not complete"]
     //#[test]
     //fn should_reply_error_if_invalid_utf8_command() {
@@ -3,4 +3,6 @@ pub const BULK_STRING_PREFIX: u8 = 36_u8; // $
 pub const ARRAY_PREFIX: u8 = 42_u8; // *
 pub const SIMPLE_ERROR_PREFIX: u8 = 45_u8; // -
 pub const INTEGERS_PREFIX: u8 = 58_u8; // :
+pub const MAP_PREFIX: u8 = 37_u8; // %
+pub const PUSH_PREFIX: u8 = 62_u8; // >
 pub const CRLF_BYTES: &[u8] = b"\r\n";
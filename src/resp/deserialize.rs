@@ -1,7 +1,7 @@
 use crate::{errors::resp::DeserializeError, resp::r#const::INTEGERS_PREFIX};
 
 use super::{
-    helpers::{check_prefix, find_crlf, is_valid_utf8, parse_resp_item_len},
+    helpers::{check_prefix, find_crlf, is_valid_utf8, parse_bulk_string_len, parse_resp_item_len},
     r#const::{
         ARRAY_PREFIX, BULK_STRING_PREFIX, CRLF_BYTES, SIMPLE_ERROR_PREFIX, SIMPLE_STRING_PREFIX,
     },
@@ -9,7 +9,7 @@ use super::{
 };
 
 pub(super) fn deserialize_simple_string(simple_string: &[u8]) -> Result<Resp, DeserializeError> {
-    check_prefix(&simple_string, SIMPLE_STRING_PREFIX)?;
+    check_prefix(simple_string, SIMPLE_STRING_PREFIX)?;
     let crlf = find_crlf(simple_string)?;
     let simple_string = &simple_string[1..crlf];
     is_valid_utf8(simple_string)?;
@@ -24,13 +24,19 @@ pub(super) fn deserialize_simple_error(simple_error: &[u8]) -> Result<Resp, Dese
     Ok(Resp::SimpleError(simple_error.to_owned()))
 }
 
+/// Reads as many bytes as the header announces, so that bulk strings may hold CRLFs.
 pub(super) fn deserialize_bulk_string(bulk_string: &[u8]) -> Result<Resp, DeserializeError> {
     check_prefix(bulk_string, BULK_STRING_PREFIX)?;
     let crlf_pos = find_crlf(bulk_string)?;
-    let bulk_start = crlf_pos + 2;
+    let Some(len) = parse_bulk_string_len(&bulk_string[1..crlf_pos])? else {
+        return Ok(Resp::Null);
+    };
+    let bulk_start = crlf_pos + CRLF_BYTES.len();
     let bulk_string = &bulk_string[bulk_start..];
-    let crlf_pos = find_crlf(bulk_string)?;
-    Ok(Resp::BulkString(bulk_string[..crlf_pos].to_owned()))
+    if bulk_string.get(len..len + CRLF_BYTES.len()) != Some(CRLF_BYTES) {
+        return Err(DeserializeError::InvalidCRLF);
+    }
+    Ok(Resp::BulkString(bulk_string[..len].to_owned()))
 }
 
 pub(super) fn deserialize_array(arr: &[u8]) -> Result<Resp, DeserializeError> {
@@ -44,14 +50,9 @@ pub(super) fn deserialize_array(arr: &[u8]) -> Result<Resp, DeserializeError> {
         let current = &arr[current_pos..];
         match current[0] {
             BULK_STRING_PREFIX => {
-                let item_first_crlf_pos = find_crlf(current)?;
-                let item_len = parse_resp_item_len(&current[1..item_first_crlf_pos])?;
-                let header_len = item_len.to_string().len() + 1;
-                let item_len = item_len + (crlf_len * 2);
-                let item = &current[..item_len + header_len];
-                let de_item = Resp::deserialize(item)?;
-                buf.push(de_item);
-                current_pos += item.len();
+                let item = Resp::deserialize(current)?;
+                current_pos += item.size();
+                buf.push(item);
             }
             SIMPLE_STRING_PREFIX => {
                 let item_crlf_pos = find_crlf(current)?;
@@ -198,6 +199,26 @@ mod test {
         assert_eq!(result.unwrap(), Resp::Integers(EXPECT))
     }
 
+    #[test]
+    fn should_deserialize_bulk_strings_by_their_length() {
+        let result = deserialize_bulk_string(b"$4\r\na\r\nb\r\n").unwrap();
+        assert_eq!(result, Resp::BulkString(b"a\r\nb".to_vec()));
+        let result = deserialize_bulk_string(b"$0\r\n\r\n").unwrap();
+        assert_eq!(result, Resp::BulkString(vec![]));
+        assert_eq!(deserialize_bulk_string(b"$-1\r\n").unwrap(), Resp::Null);
+        assert_eq!(
+            deserialize_bulk_string(b"$3\r\nab\r\n").unwrap_err(),
+            DeserializeError::InvalidCRLF
+        );
+        let result = deserialize_array(b"*3\r\n$3\r\nSET\r\n$1\r\nk\r\n$4\r\nx\r\ny\r\n");
+        let expect = vec![
+            Resp::bulk_string_from_str("SET"),
+            Resp::bulk_string_from_str("k"),
+            Resp::bulk_string_from_str("x\r\ny"),
+        ];
+        assert_eq!(result.unwrap(), Resp::Array(expect));
+    }
+
     #[test]
     fn should_deserialize_bulk_string_with_space() {
         const INPUT: &str = "$10\r\nCONFIG GET\r\n";
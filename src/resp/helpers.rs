@@ -1,6 +1,9 @@
 use crate::errors::resp::DeserializeError;
 
-use super::r#const::CRLF_BYTES;
+use super::r#const::{
+    ARRAY_PREFIX, BULK_STRING_PREFIX, CRLF_BYTES, INTEGERS_PREFIX, SIMPLE_ERROR_PREFIX,
+    SIMPLE_STRING_PREFIX,
+};
 
 pub(super) fn find_crlf(data: &[u8]) -> Result<usize, DeserializeError> {
     data.windows(2)
@@ -14,6 +17,46 @@ pub(super) fn parse_resp_item_len(input: &[u8]) -> Result<usize, DeserializeErro
         .map_err(|_| DeserializeError::InvalidLength)
 }
 
+/// Length announced by a bulk string header, `None` for the `-1` of a null one.
+pub(super) fn parse_bulk_string_len(input: &[u8]) -> Result<Option<usize>, DeserializeError> {
+    match input {
+        b"-1" => Ok(None),
+        _ => parse_resp_item_len(input).map(Some),
+    }
+}
+
+/// Length of the frame at the start of `input`, `None` while it is not fully received.
+pub(super) fn frame_len(input: &[u8]) -> Result<Option<usize>, DeserializeError> {
+    let Some(&prefix) = input.first() else {
+        return Ok(None);
+    };
+    let Ok(line_end) = find_crlf(input) else {
+        return Ok(None);
+    };
+    let header_len = line_end + CRLF_BYTES.len();
+    match prefix {
+        SIMPLE_STRING_PREFIX | SIMPLE_ERROR_PREFIX | INTEGERS_PREFIX => Ok(Some(header_len)),
+        BULK_STRING_PREFIX => match parse_bulk_string_len(&input[1..line_end])? {
+            Some(len) => {
+                let len = header_len + len + CRLF_BYTES.len();
+                Ok((input.len() >= len).then_some(len))
+            }
+            None => Ok(Some(header_len)),
+        },
+        ARRAY_PREFIX => {
+            let mut len = header_len;
+            for _ in 0..parse_resp_item_len(&input[1..line_end])? {
+                match frame_len(&input[len..])? {
+                    Some(item_len) => len += item_len,
+                    None => return Ok(None),
+                }
+            }
+            Ok(Some(len))
+        }
+        _ => Err(DeserializeError::InvalidPrefix),
+    }
+}
+
 pub(super) fn check_prefix(input: &[u8], prefix: u8) -> Result<(), DeserializeError> {
     if input[0] == prefix {
         return Ok(());
@@ -47,6 +90,24 @@ mod test {
         )
     }
 
+    #[test]
+    fn should_find_complete_frames() {
+        const ARRAY: &[u8] = b"*2\r\n$4\r\nECHO\r\n$5\r\nhello\r\n";
+        assert_eq!(frame_len(INPUT).unwrap(), Some(INPUT.len()));
+        assert_eq!(frame_len(ARRAY).unwrap(), Some(ARRAY.len()));
+        assert_eq!(
+            frame_len(&[ARRAY, b"*1"].concat()).unwrap(),
+            Some(ARRAY.len())
+        );
+        for len in 0..ARRAY.len() {
+            assert_eq!(frame_len(&ARRAY[..len]).unwrap(), None);
+        }
+        assert_eq!(
+            frame_len(b"?\r\n").unwrap_err(),
+            DeserializeError::InvalidPrefix
+        );
+    }
+
     #[test]
     fn should_parse_length() {
         assert_eq!(parse_resp_item_len(&INPUT[1..2]).unwrap(), 5)
@@ -7,8 +7,8 @@ use r#const::{
     SIMPLE_STRING_PREFIX,
 };
 use serialize::{
-    serialize_array, serialize_bulk_string, serialize_integer, serialize_simple_error,
-    serialize_simple_string,
+    serialize_array, serialize_bulk_string, serialize_integer, serialize_map, serialize_null_array,
+    serialize_null_bulk_string, serialize_push, serialize_simple_error, serialize_simple_string,
 };
 
 use crate::{
@@ -26,18 +26,29 @@ pub enum Resp {
     SimpleString(Vec<u8>),
     SimpleError(Vec<u8>),
     BulkString(Vec<u8>),
+    /// Null bulk string, telling a missing value apart from an empty one.
+    Null,
     Array(Vec<Resp>),
     Integers(i64),
+    NullArray,
+    /// RESP3 map, only sent to connections that switched protocol with `HELLO 3`.
+    Map(Vec<(Resp, Resp)>),
+    /// RESP3 out of band message, like pub/sub messages and invalidations.
+    Push(Vec<Resp>),
 }
 
 impl Resp {
     pub fn serialize(self) -> Result<Vec<u8>, SerializeError> {
         match self {
             Resp::BulkString(bulk) => serialize_bulk_string(&bulk),
+            Resp::Null => Ok(serialize_null_bulk_string()),
             Resp::SimpleString(simple) => serialize_simple_string(&simple),
             Resp::Array(array) => serialize_array(array),
             Resp::SimpleError(error) => serialize_simple_error(&error),
             Resp::Integers(int) => serialize_integer(int),
+            Resp::NullArray => Ok(serialize_null_array()),
+            Resp::Map(map) => serialize_map(map),
+            Resp::Push(push) => serialize_push(push),
         }
     }
 
@@ -52,8 +63,13 @@ impl Resp {
         }
     }
 
+    /// Length of the frame at the start of `input`, `None` while more of it has to be read.
+    pub fn frame_len(input: &[u8]) -> Result<Option<usize>, DeserializeError> {
+        helpers::frame_len(input)
+    }
+
     pub fn null_bulk_string() -> Self {
-        Self::bulk_string_from_str("")
+        Self::Null
     }
 
     pub fn bulk_string_from_str(value: &str) -> Self {
@@ -71,18 +87,14 @@ impl Resp {
     pub fn as_str(&self) -> Result<&str, ()> {
         match self {
             Resp::BulkString(bulk) => Ok(std::str::from_utf8(bulk).unwrap()),
-            Resp::SimpleString(simple_string) => Ok(std::str::from_utf8(&simple_string).unwrap()),
+            Resp::SimpleString(simple_string) => Ok(std::str::from_utf8(simple_string).unwrap()),
             //Resp::Integers(int) => Ok(std::str::from_utf8(int.to_string().as_bytes()).unwrap()),
             _ => Err(()),
         }
     }
 
     pub fn is_bulk_string(&self) -> bool {
-        if let Self::BulkString(_) = self {
-            true
-        } else {
-            false
-        }
+        matches!(self, Self::BulkString(_))
     }
     pub fn size(&self) -> usize {
         match self {
@@ -93,7 +105,7 @@ impl Resp {
                 let len = string.len();
                 1 + len + len.to_string().len() + (CRLF_BYTES.len() * 2)
             }
-            Self::Array(arr) => {
+            Self::Array(arr) | Self::Push(arr) => {
                 let len = arr.len().to_string().len();
                 arr.iter()
                     .fold(1 + len + (CRLF_BYTES.len()), |mut acc, cur| {
@@ -106,6 +118,11 @@ impl Resp {
                 let len = int.to_string().len();
                 header_len + len + CRLF_BYTES.len()
             }
+            Self::Null | Self::NullArray => 3 + CRLF_BYTES.len(),
+            Self::Map(map) => map.iter().fold(
+                1 + map.len().to_string().len() + CRLF_BYTES.len(),
+                |acc, (key, value)| acc + key.size() + value.size(),
+            ),
         }
     }
 }
@@ -2,8 +2,8 @@ use crate::{errors::resp::SerializeError, ternary_expr};
 
 use super::{
     r#const::{
-        ARRAY_PREFIX, BULK_STRING_PREFIX, CRLF_BYTES, INTEGERS_PREFIX, SIMPLE_ERROR_PREFIX,
-        SIMPLE_STRING_PREFIX,
+        ARRAY_PREFIX, BULK_STRING_PREFIX, CRLF_BYTES, INTEGERS_PREFIX, MAP_PREFIX, PUSH_PREFIX,
+        SIMPLE_ERROR_PREFIX, SIMPLE_STRING_PREFIX,
     },
     Resp,
 };
@@ -22,23 +22,50 @@ pub(super) fn serialize_simple_string(simple_string: &[u8]) -> Result<Vec<u8>, S
 
 pub(super) fn serialize_bulk_string(bulk_string: &[u8]) -> Result<Vec<u8>, SerializeError> {
     let length = bulk_string.len();
-    let length_string = ternary_expr!(length == 0, "-1".to_owned(), length.to_string());
+    let length_string = length.to_string();
     let mut buf = Vec::with_capacity(length + (CRLF_BYTES.len() * 2) + length_string.len() + 1);
     buf.push(BULK_STRING_PREFIX);
     buf.extend_from_slice(length_string.as_bytes());
     buf.extend_from_slice(CRLF_BYTES);
-    if length > 0 {
-        buf.extend_from_slice(bulk_string);
-        buf.extend_from_slice(CRLF_BYTES);
-    }
+    buf.extend_from_slice(bulk_string);
+    buf.extend_from_slice(CRLF_BYTES);
     Ok(buf)
 }
 
+pub(super) fn serialize_null_bulk_string() -> Vec<u8> {
+    let mut buf = Vec::with_capacity(3 + CRLF_BYTES.len());
+    buf.push(BULK_STRING_PREFIX);
+    buf.extend_from_slice(b"-1");
+    buf.extend_from_slice(CRLF_BYTES);
+    buf
+}
+
 pub(super) fn serialize_array(input: Vec<Resp>) -> Result<Vec<u8>, SerializeError> {
+    serialize_aggregate(ARRAY_PREFIX, input)
+}
+
+/// RESP3 out of band data, shaped like an array.
+pub(super) fn serialize_push(input: Vec<Resp>) -> Result<Vec<u8>, SerializeError> {
+    serialize_aggregate(PUSH_PREFIX, input)
+}
+
+pub(super) fn serialize_map(input: Vec<(Resp, Resp)>) -> Result<Vec<u8>, SerializeError> {
+    let mut buf = Vec::new();
+    buf.push(MAP_PREFIX);
+    buf.extend_from_slice(input.len().to_string().as_bytes());
+    buf.extend_from_slice(CRLF_BYTES);
+    for (key, value) in input {
+        buf.extend(key.serialize()?);
+        buf.extend(value.serialize()?);
+    }
+    Ok(buf)
+}
+
+fn serialize_aggregate(prefix: u8, input: Vec<Resp>) -> Result<Vec<u8>, SerializeError> {
     let mut buf = Vec::new();
     let length = input.len();
     let length_string = length.to_string();
-    buf.push(ARRAY_PREFIX);
+    buf.push(prefix);
     buf.extend_from_slice(length_string.as_bytes());
     buf.extend_from_slice(CRLF_BYTES);
     for val in input {
@@ -47,6 +74,14 @@ pub(super) fn serialize_array(input: Vec<Resp>) -> Result<Vec<u8>, SerializeErro
     }
     Ok(buf)
 }
+pub(super) fn serialize_null_array() -> Vec<u8> {
+    let mut buf = Vec::with_capacity(3 + CRLF_BYTES.len());
+    buf.push(ARRAY_PREFIX);
+    buf.extend_from_slice(b"-1");
+    buf.extend_from_slice(CRLF_BYTES);
+    buf
+}
+
 pub(super) fn serialize_simple_error(error: &[u8]) -> Result<Vec<u8>, SerializeError> {
     if std::str::from_utf8(error).is_err() {
         return Err(SerializeError::InvaliUtf8);
@@ -123,9 +158,41 @@ mod test {
     }
     #[test]
     fn shoudl_serialize_null_bulk_string() {
-        const INPUT: &str = "";
         const EXPECT: &str = "$-1\r\n";
-        let result = Resp::bulk_string_from_str(INPUT).serialize().unwrap();
+        let result = Resp::null_bulk_string().serialize().unwrap();
+        assert_eq!(result, EXPECT.as_bytes())
+    }
+
+    #[test]
+    fn should_serialize_empty_bulk_string() {
+        const EXPECT: &str = "$0\r\n\r\n";
+        let result = Resp::bulk_string_from_str("").serialize().unwrap();
+        assert_eq!(result, EXPECT.as_bytes())
+    }
+
+    #[test]
+    fn should_serialize_null_array() {
+        const EXPECT: &str = "*-1\r\n";
+        let result = Resp::NullArray.serialize().unwrap();
         assert_eq!(result, EXPECT.as_bytes())
     }
+
+    #[test]
+    fn should_serialize_push_and_map() {
+        let push = Resp::Push(vec![
+            Resp::bulk_string_from_str("invalidate"),
+            Resp::NullArray,
+        ]);
+        assert_eq!(push.size(), 26);
+        assert_eq!(
+            push.serialize().unwrap(),
+            b">2\r\n$10\r\ninvalidate\r\n*-1\r\n"
+        );
+        let map = Resp::Map(vec![(
+            Resp::bulk_string_from_str("proto"),
+            Resp::Integers(3),
+        )]);
+        assert_eq!(map.size(), 19);
+        assert_eq!(map.serialize().unwrap(), b"%1\r\n$5\r\nproto\r\n:3\r\n");
+    }
 }
@@ -0,0 +1,39 @@
+use std::io;
+
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::oneshot,
+};
+
+use crate::errors::AppError;
+
+/// Whether the dataset is saved before exiting, `SHUTDOWN [NOSAVE|SAVE]`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SaveMode {
+    /// Saves when an RDB file is configured.
+    #[default]
+    Configured,
+    Save,
+    NoSave,
+}
+
+/// Shutdown asked by `SHUTDOWN` or a signal, which the event loop carries out.
+#[derive(Debug, Default)]
+pub struct ShutdownRequest {
+    pub save: SaveMode,
+    /// Exits even when the dataset could not be saved.
+    pub force: bool,
+    /// Told whether the server is going down, for `SHUTDOWN` to reply when it is not.
+    pub reply: Option<oneshot::Sender<Result<(), AppError>>>,
+}
+
+/// Waits for `SIGTERM` or `SIGINT`, which shut down like a plain `SHUTDOWN`.
+pub async fn signaled() -> io::Result<ShutdownRequest> {
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    tokio::select! {
+        _ = terminate.recv() => log::info!("Received SIGTERM scheduling shutdown..."),
+        _ = interrupt.recv() => log::info!("Received SIGINT scheduling shutdown..."),
+    }
+    Ok(ShutdownRequest::default())
+}